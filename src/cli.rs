@@ -0,0 +1,140 @@
+use crate::config::Config;
+use crate::events::events::{FetchOptions, Result};
+use chrono::prelude::*;
+
+const USAGE: &str = "Usage: cigale [--date YYYY-MM-DD] [--format text|json] [--source <name>]
+
+Without parameters, starts the graphical interface.
+With parameters, prints the events of the day (default: today) and exits.";
+
+#[derive(PartialEq, Debug)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(PartialEq, Debug)]
+struct CliOptions {
+    day: Date<Local>,
+    format: OutputFormat,
+    source: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<CliOptions> {
+    let mut options = CliOptions {
+        day: Local::today(),
+        format: OutputFormat::Text,
+        source: None,
+    };
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        let mut value = || it.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--date" => {
+                let date = NaiveDate::parse_from_str(value()?, "%Y-%m-%d")?;
+                options.day = Local
+                    .from_local_date(&date)
+                    .single()
+                    .ok_or("Invalid date")?;
+            }
+            "--format" => {
+                options.format = match value()?.as_str() {
+                    "text" => OutputFormat::Text,
+                    "json" => OutputFormat::Json,
+                    f => return Err(format!("Unknown format: {}", f).into()),
+                }
+            }
+            "--source" => options.source = Some(value()?.to_string()),
+            _ => return Err(format!("Unknown parameter: {}", arg).into()),
+        }
+    }
+    Ok(options)
+}
+
+/// whether the user configured an event source with that name
+fn has_event_source(config: &Config, source: &str) -> bool {
+    crate::events::events::get_event_providers()
+        .iter()
+        .any(|ep| ep.get_config_names(config).iter().any(|n| *n == source))
+}
+
+fn run(options: CliOptions) -> Result<bool> {
+    let config = Config::read_config_file()?;
+    if let Some(source) = &options.source {
+        if !has_event_source(&config, source) {
+            return Err(format!("Unknown event source: {}", source).into());
+        }
+    }
+    let fetched = crate::events::events::get_events_from_sources(
+        config,
+        &FetchOptions::default(),
+        options.day,
+        options.source.as_deref(),
+    );
+    match options.format {
+        OutputFormat::Text => print!("{}", crate::export::to_text(&fetched.events)),
+        OutputFormat::Json => println!("{}", crate::export::to_json(options.day, &fetched.events)?),
+    }
+    for err in &fetched.errors {
+        eprintln!("{}", err.to_string_with_hint());
+    }
+    Ok(fetched.errors.is_empty())
+}
+
+/// if we got command-line parameters, run in headless mode,
+/// and return the process exit code.
+/// returns None if the GUI should be started.
+pub fn run_if_requested() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        return None;
+    }
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return Some(0);
+    }
+    let result = parse_args(&args).and_then(run);
+    Some(match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            2
+        }
+    })
+}
+
+#[test]
+fn it_parses_command_line_arguments() {
+    let args: Vec<String> = vec![
+        "--date",
+        "2020-02-25",
+        "--format",
+        "json",
+        "--source",
+        "work",
+    ]
+    .into_iter()
+    .map(|s| s.to_string())
+    .collect();
+    assert_eq!(
+        CliOptions {
+            day: Local.ymd(2020, 2, 25),
+            format: OutputFormat::Json,
+            source: Some("work".to_string()),
+        },
+        parse_args(&args).unwrap()
+    );
+    assert!(parse_args(&["--format".to_string(), "xml".to_string()]).is_err());
+}
+
+#[test]
+fn it_finds_event_sources_by_name() {
+    let mut config = Config::default_config();
+    config.rss_feed.insert(
+        "work".to_string(),
+        crate::events::rssfeed::RssFeedConfig { feed_urls: vec![] },
+    );
+    assert!(has_event_source(&config, "work"));
+    assert!(!has_event_source(&config, "work-old"));
+}
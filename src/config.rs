@@ -1,4 +1,5 @@
-use crate::events::events::{EventProvider, Result};
+use crate::events::events::{EventProvider, FetchOptions, Result};
+use crate::events::http::CacheValidators;
 use chrono::prelude::*;
 use gtk::prelude::*;
 use regex::Regex;
@@ -10,8 +11,17 @@ use std::io::{Read, Write};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use std::*;
 
+/// stored in the config file in place of secrets which
+/// live in the system keyring
+pub const SECRET_IN_KEYRING: &str = "<stored in the system keyring>";
+
+/// overrides the folder holding the config file and the cache,
+/// for instance to run isolated profiles
+const CONFIG_DIR_ENV_VAR: &str = "CIGALE_CONFIG_DIR";
+
 #[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
 pub enum PrevNextDaySkipWeekends {
     Skip,
@@ -23,12 +33,102 @@ impl Default for PrevNextDaySkipWeekends {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+impl Default for LogLevel {
+    fn default() -> Self {
+        LogLevel::Error
+    }
+}
+/// how the rows of the event list are displayed
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+pub enum EventRowsLayout {
+    // provider name and details under the icon and the time
+    Comfortable,
+    // a single line per event, the rest in a tooltip
+    Compact,
+}
+impl Default for EventRowsLayout {
+    fn default() -> Self {
+        EventRowsLayout::Comfortable
+    }
+}
+
+impl LogLevel {
+    pub fn level_filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Config {
     #[serde(default)] // prefer_dark_theme was added later, after 0.4.0
     pub prefer_dark_theme: bool,
     #[serde(default)] // was added later, after 0.4.0
     pub prev_next_day_skip_weekends: PrevNextDaySkipWeekends,
+    #[serde(default = "Config::default_cache_ttl_secs")] // was added later, after 0.5.3
+    pub cache_ttl_secs: u64,
+    #[serde(default = "Config::default_restore_last_viewed_date")] // was added later, after 0.5.3
+    pub restore_last_viewed_date: bool,
+    #[serde(default)] // was added later, after 0.5.3
+    pub group_related_events: bool,
+    #[serde(default = "Config::default_group_related_events_window_mins")]
+    // was added later, after 0.5.3
+    pub group_related_events_window_mins: u32,
+    // collapse the same event reported by several providers
+    #[serde(default)] // was added later, after 0.5.3
+    pub merge_events_across_sources: bool,
+    #[serde(default)] // was added later, after 0.5.3
+    pub notify_new_events: bool,
+    #[serde(default = "Config::default_notify_poll_interval_mins")]
+    // was added later, after 0.5.3
+    pub notify_poll_interval_mins: u32,
+    // event source names in the order chosen by the user.
+    // the names are unique across providers.
+    #[serde(default)] // was added later, after 0.5.3
+    pub sources_order: Vec<String>,
+    #[serde(
+        default = "Config::default_week_starts_on",
+        deserialize_with = "Config::deserialize_week_starts_on"
+    )] // was added later, after 0.5.3
+    pub week_starts_on: Weekday,
+    // on startup, remove cache files not written to since that many days.
+    // 0 means never remove them.
+    #[serde(default)] // was added later, after 0.5.3
+    pub cache_eviction_days: u32,
+    // on startup, fetch today's events in the background, so that
+    // they're ready when the user switches to today
+    #[serde(default)] // was added later, after 0.5.3
+    pub warm_today_cache_on_startup: bool,
+    // how many event sources we fetch from at the same time, for all
+    // the days we fetch. 1 fetches the event sources one after the other.
+    #[serde(default = "Config::default_max_concurrent_fetches")]
+    // was added later, after 0.5.3
+    pub max_concurrent_fetches: u32,
+    // level of our logs, in addition to what RUST_LOG enables
+    #[serde(default)] // was added later, after 0.5.3
+    pub log_level: LogLevel,
+    #[serde(default)] // was added later, after 0.5.3
+    pub event_rows_layout: EventRowsLayout,
+    // separators between the hours in the event list
+    #[serde(default)] // was added later, after 0.5.3
+    pub group_events_by_hour: bool,
+    // chrono format string for the displayed day
+    #[serde(
+        default = "Config::default_date_display_format",
+        deserialize_with = "Config::deserialize_date_display_format"
+    )] // was added later, after 0.5.3
+    pub date_display_format: String,
     pub git: HashMap<String, crate::events::git::GitConfig>,
     pub email: HashMap<String, crate::events::email::EmailConfig>,
     pub ical: HashMap<String, crate::events::ical::IcalConfig>,
@@ -37,6 +137,63 @@ pub struct Config {
     pub gitlab: HashMap<String, crate::events::gitlab::GitlabConfig>,
     #[serde(default)] // stackexchange was added later, after 0.4.0
     pub stackexchange: HashMap<String, crate::events::stackexchange::StackExchangeConfig>,
+    #[serde(default)] // github was added later, after 0.5.3
+    pub github: HashMap<String, crate::events::github::GitHubConfig>,
+    #[serde(default)] // jira was added later, after 0.5.3
+    pub jira: HashMap<String, crate::events::jira::JiraConfig>,
+    #[serde(default)] // imap was added later, after 0.5.3
+    pub imap: HashMap<String, crate::events::imap::ImapConfig>,
+    #[serde(default)] // hg was added later, after 0.5.3
+    pub hg: HashMap<String, crate::events::hg::HgConfig>,
+    #[serde(default)] // rss_feed was added later, after 0.5.3
+    pub rss_feed: HashMap<String, crate::events::rssfeed::RssFeedConfig>,
+    #[serde(default)] // gitea was added later, after 0.5.3
+    pub gitea: HashMap<String, crate::events::gitea::GiteaConfig>,
+    #[serde(default)] // svn was added later, after 0.5.3
+    pub svn: HashMap<String, crate::events::svn::SvnConfig>,
+    #[serde(default)] // slack was added later, after 0.5.3
+    pub slack: HashMap<String, crate::events::slack::SlackConfig>,
+    #[serde(default)] // trello was added later, after 0.5.3
+    pub trello: HashMap<String, crate::events::trello::TrelloConfig>,
+    #[serde(default)] // jenkins was added later, after 0.5.3
+    pub jenkins: HashMap<String, crate::events::jenkins::JenkinsConfig>,
+    #[serde(default)] // gerrit was added later, after 0.5.3
+    pub gerrit: HashMap<String, crate::events::gerrit::GerritConfig>,
+    #[serde(default)] // exchange was added later, after 0.5.3
+    pub exchange: HashMap<String, crate::events::exchange::ExchangeConfig>,
+    #[serde(default)] // toggl was added later, after 0.5.3
+    pub toggl: HashMap<String, crate::events::toggl::TogglConfig>,
+    #[serde(default)] // asana was added later, after 0.5.3
+    pub asana: HashMap<String, crate::events::asana::AsanaConfig>,
+    #[serde(default)] // phabricator was added later, after 0.5.3
+    pub phabricator: HashMap<String, crate::events::phabricator::PhabricatorConfig>,
+    #[serde(default)] // harvest was added later, after 0.5.3
+    pub harvest: HashMap<String, crate::events::harvest::HarvestConfig>,
+    #[serde(default)] // mattermost was added later, after 0.5.3
+    pub mattermost: HashMap<String, crate::events::mattermost::MattermostConfig>,
+    #[serde(default)] // google_calendar was added later, after 0.5.3
+    pub google_calendar: HashMap<String, crate::events::googlecalendar::GoogleCalendarConfig>,
+    #[serde(default)] // custom_json was added later, after 0.5.3
+    pub custom_json: HashMap<String, crate::events::customjson::CustomJsonConfig>,
+    #[serde(default)] // bitbucket was added later, after 0.5.3
+    pub bitbucket: HashMap<String, crate::events::bitbucket::BitbucketConfig>,
+    #[serde(default)] // mock was added later, after 0.5.3
+    pub mock: HashMap<String, crate::events::mock::MockConfig>,
+    #[serde(default)] // todoist was added later, after 0.5.3
+    pub todoist: HashMap<String, crate::events::todoist::TodoistConfig>,
+    #[serde(default)] // youtrack was added later, after 0.5.3
+    pub youtrack: HashMap<String, crate::events::youtrack::YouTrackConfig>,
+    #[serde(default)] // onedev was added later, after 0.5.3
+    pub onedev: HashMap<String, crate::events::onedev::OneDevConfig>,
+    // maximum requests per minute by host, shared by all the event sources.
+    // must stay after the simple values for the toml serialization.
+    #[serde(default = "Config::default_host_rate_limits")] // was added later, after 0.5.3
+    pub host_rate_limits: HashMap<String, u32>,
+    // the event sources which the user disabled for now, for instance
+    // while offline, by provider name. we don't fetch from them, but keep
+    // their settings. must stay after the simple values for the toml serialization.
+    #[serde(default)] // was added later, after 0.5.3
+    pub disabled_sources: HashMap<String, Vec<String>>,
 }
 
 impl Config {
@@ -53,12 +210,182 @@ impl Config {
             redmine: HashMap::new(),
             gitlab: HashMap::new(),
             stackexchange: HashMap::new(),
+            github: HashMap::new(),
+            jira: HashMap::new(),
+            imap: HashMap::new(),
+            hg: HashMap::new(),
+            rss_feed: HashMap::new(),
+            gitea: HashMap::new(),
+            svn: HashMap::new(),
+            slack: HashMap::new(),
+            trello: HashMap::new(),
+            jenkins: HashMap::new(),
+            gerrit: HashMap::new(),
+            exchange: HashMap::new(),
+            toggl: HashMap::new(),
+            asana: HashMap::new(),
+            phabricator: HashMap::new(),
+            harvest: HashMap::new(),
+            mattermost: HashMap::new(),
+            google_calendar: HashMap::new(),
+            custom_json: HashMap::new(),
+            bitbucket: HashMap::new(),
+            mock: HashMap::new(),
+            todoist: HashMap::new(),
+            youtrack: HashMap::new(),
+            onedev: HashMap::new(),
+            host_rate_limits: Self::default_host_rate_limits(),
+            disabled_sources: HashMap::new(),
             prefer_dark_theme: false,
             prev_next_day_skip_weekends: PrevNextDaySkipWeekends::Skip,
+            cache_ttl_secs: Self::default_cache_ttl_secs(),
+            restore_last_viewed_date: Self::default_restore_last_viewed_date(),
+            group_related_events: false,
+            group_related_events_window_mins: Self::default_group_related_events_window_mins(),
+            merge_events_across_sources: false,
+            notify_new_events: false,
+            notify_poll_interval_mins: Self::default_notify_poll_interval_mins(),
+            sources_order: vec![],
+            week_starts_on: Self::default_week_starts_on(),
+            cache_eviction_days: 0,
+            warm_today_cache_on_startup: false,
+            max_concurrent_fetches: Self::default_max_concurrent_fetches(),
+            log_level: LogLevel::Error,
+            event_rows_layout: EventRowsLayout::Comfortable,
+            group_events_by_hour: false,
+            date_display_format: Self::default_date_display_format(),
+        }
+    }
+
+    fn default_cache_ttl_secs() -> u64 {
+        600
+    }
+
+    fn default_group_related_events_window_mins() -> u32 {
+        15
+    }
+
+    fn default_notify_poll_interval_mins() -> u32 {
+        10
+    }
+
+    fn default_week_starts_on() -> Weekday {
+        Weekday::Mon
+    }
+
+    // don't fail reading the whole config for an invalid day name
+    fn deserialize_week_starts_on<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Weekday, D::Error> {
+        let day = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(day.parse().unwrap_or_else(|_| {
+            log::warn!("Invalid week_starts_on: {}, using Monday", day);
+            Self::default_week_starts_on()
+        }))
+    }
+
+    pub fn is_source_enabled(&self, provider_name: &str, config_name: &str) -> bool {
+        !self
+            .disabled_sources
+            .get(provider_name)
+            .map_or(false, |names| names.iter().any(|n| n == config_name))
+    }
+
+    pub fn set_source_enabled(&mut self, provider_name: &str, config_name: &str, enabled: bool) {
+        let names = self
+            .disabled_sources
+            .entry(provider_name.to_string())
+            .or_insert_with(Vec::new);
+        names.retain(|n| n != config_name);
+        if !enabled {
+            names.push(config_name.to_string());
+        }
+        if names.is_empty() {
+            self.disabled_sources.remove(provider_name);
+        }
+    }
+
+    /// forget the position and the disabled state of a removed event source
+    pub fn forget_event_source(&mut self, provider_name: &str, config_name: &str) {
+        self.sources_order.retain(|n| n != config_name);
+        self.set_source_enabled(provider_name, config_name, true);
+    }
+
+    /// a renamed event source keeps its position and its disabled state
+    pub fn rename_event_source(&mut self, provider_name: &str, old_name: &str, new_name: &str) {
+        for n in self.sources_order.iter_mut() {
+            if n == old_name {
+                *n = new_name.to_string();
+            }
+        }
+        if !self.is_source_enabled(provider_name, old_name) {
+            self.set_source_enabled(provider_name, old_name, true);
+            self.set_source_enabled(provider_name, new_name, false);
         }
     }
 
-    fn read_config_file() -> Result<Config> {
+    // under the documented limits, for the users with several
+    // sources on the same service
+    fn default_host_rate_limits() -> HashMap<String, u32> {
+        vec![
+            // 5000 requests/hour with a token
+            ("api.github.com", 80),
+            // search.messages is tier 2: 20 requests/minute
+            ("slack.com", 20),
+            // 30 requests/second, but 10000 a day
+            ("api.stackexchange.com", 300),
+        ]
+        .into_iter()
+        .map(|(host, per_minute)| (host.to_string(), per_minute))
+        .collect()
+    }
+
+    fn default_max_concurrent_fetches() -> u32 {
+        4
+    }
+
+    fn default_date_display_format() -> String {
+        "%A, %Y-%m-%d".to_string()
+    }
+
+    // don't fail reading the whole config for an invalid format
+    fn deserialize_date_display_format<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<String, D::Error> {
+        let format = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Ok(match Self::validate_date_format(&format) {
+            Ok(()) => format,
+            Err(e) => {
+                log::warn!("Invalid date_display_format: {}, using the default", e);
+                Self::default_date_display_format()
+            }
+        })
+    }
+
+    /// chrono panics when displaying a date with an invalid format
+    pub fn validate_date_format(format: &str) -> std::result::Result<(), String> {
+        if format.trim().is_empty() {
+            return Err("The date format can't be empty".to_string());
+        }
+        if chrono::format::StrftimeItems::new(format).any(|i| i == chrono::format::Item::Error) {
+            return Err(format!("Invalid date format: {}", format));
+        }
+        Ok(())
+    }
+
+    /// the first day of the week containing `day`
+    pub fn week_start(&self, day: Date<Local>) -> Date<Local> {
+        let days_since_week_start = (7 + day.weekday().num_days_from_monday()
+            - self.week_starts_on.num_days_from_monday())
+            % 7;
+        day - chrono::Duration::days(days_since_week_start as i64)
+    }
+
+    fn default_restore_last_viewed_date() -> bool {
+        true
+    }
+
+    pub fn read_config_file() -> Result<Config> {
         let config_file = Self::config_path()?;
         if !config_file.is_file() {
             return Ok(Self::default_config());
@@ -126,17 +453,171 @@ impl Config {
         Ok(())
     }
 
+    fn config_folder_path(env_override: Option<ffi::OsString>) -> PathBuf {
+        match env_override.filter(|d| !d.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::home_dir()
+                .expect("Can't find your home folder?")
+                .join(".cigale"),
+        }
+    }
+
     pub fn config_folder() -> Result<PathBuf> {
-        let home_dir = dirs::home_dir().expect("Can't find your home folder?");
-        let config_folder = home_dir.join(".cigale");
+        let config_folder = Self::config_folder_path(env::var_os(CONFIG_DIR_ENV_VAR));
         if !config_folder.is_dir() {
-            fs::create_dir(&config_folder)?;
+            fs::create_dir_all(&config_folder)?;
             // we potentially put passwords in the config file...
             Self::set_private_folder(&config_folder)?;
         }
         Ok(config_folder)
     }
 
+    /// last viewed date handling
+    /// we store it in a separate file and not in the config file,
+    /// because it changes all the time and we don't want to
+    /// rewrite the config file on every day change.
+
+    fn last_viewed_date_path() -> Result<PathBuf> {
+        Ok(Self::config_folder()?.join("last_viewed_date"))
+    }
+
+    pub fn save_last_viewed_date(day: Date<Local>) {
+        if let Err(e) = Self::last_viewed_date_path()
+            .and_then(|p| Ok(fs::write(p, day.format("%Y-%m-%d").to_string())?))
+        {
+            log::warn!("Failed saving the last viewed date: {}", e);
+        }
+    }
+
+    fn parse_last_viewed_date(contents: &str, today: Date<Local>) -> Option<Date<Local>> {
+        NaiveDate::parse_from_str(contents.trim(), "%Y-%m-%d")
+            .ok()
+            .and_then(|d| Local.from_local_date(&d).single())
+            // a date in the future makes no sense, the clock was probably wrong
+            .filter(|d| *d <= today)
+    }
+
+    /// the day to display when starting the app
+    pub fn initial_day(&self) -> Date<Local> {
+        let default_day = Local::today().pred();
+        if !self.restore_last_viewed_date {
+            return default_day;
+        }
+        Self::last_viewed_date_path()
+            .ok()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .and_then(|c| Self::parse_last_viewed_date(&c, Local::today()))
+            .unwrap_or(default_day)
+    }
+
+    /// secrets handling
+
+    fn get_keyring_service(event_provider: &dyn EventProvider, config_name: &str) -> String {
+        format!(
+            "cigale:{}:{}",
+            event_provider.name().to_lowercase(),
+            config_name
+        )
+    }
+
+    /// store a secret (password...) in the system keyring.
+    /// returns the value to save in the config file instead of the secret.
+    /// falls back to storing the secret in plaintext in the config file
+    /// if there is no secret service available.
+    /// if the keyring already has that secret, it's left alone.
+    pub fn store_secret(
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+        username: &str,
+        secret: String,
+    ) -> String {
+        let service = Self::get_keyring_service(event_provider, config_name);
+        let keyring = keyring::Keyring::new(&service, username);
+        if keyring.get_password().ok().as_ref() == Some(&secret) {
+            return SECRET_IN_KEYRING.to_string();
+        }
+        match keyring.set_password(&secret) {
+            Ok(_) => SECRET_IN_KEYRING.to_string(),
+            Err(e) => {
+                log::warn!(
+                    "Can't store the secret for {} in the keyring, will store it in plaintext: {}",
+                    service,
+                    e
+                );
+                secret
+            }
+        }
+    }
+
+    /// read a secret which was stored through store_secret(),
+    /// passing the value which was saved in the config file.
+    pub fn read_secret(
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+        username: &str,
+        config_value: &str,
+    ) -> Result<String> {
+        if config_value != SECRET_IN_KEYRING {
+            // plaintext fallback
+            return Ok(config_value.to_string());
+        }
+        let service = Self::get_keyring_service(event_provider, config_name);
+        keyring::Keyring::new(&service, username)
+            .get_password()
+            .map_err(|e| {
+                format!(
+                    "Can't read the secret for {} from the keyring: {}",
+                    service, e
+                )
+                .into()
+            })
+    }
+
+    /// after an event source was edited and its secret stored again:
+    /// remove the previous secret from the keyring, unless the new
+    /// secret went to the same keyring entry.
+    pub fn remove_replaced_secret(
+        event_provider: &dyn EventProvider,
+        old_config_name: &str,
+        old_username: &str,
+        old_config_value: &str,
+        config_name: &str,
+        username: &str,
+        config_value: &str,
+    ) {
+        if old_config_name == config_name
+            && old_username == username
+            && config_value == SECRET_IN_KEYRING
+        {
+            return;
+        }
+        Self::remove_secret(
+            event_provider,
+            old_config_name,
+            old_username,
+            old_config_value,
+        );
+    }
+
+    pub fn remove_secret(
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+        username: &str,
+        config_value: &str,
+    ) {
+        if config_value != SECRET_IN_KEYRING {
+            return;
+        }
+        let service = Self::get_keyring_service(event_provider, config_name);
+        if let Err(e) = keyring::Keyring::new(&service, username).delete_password() {
+            log::warn!(
+                "Can't remove the secret for {} from the keyring: {}",
+                service,
+                e
+            );
+        }
+    }
+
     /// cache handling
 
     fn get_cache_path(event_provider: &dyn EventProvider, config_name: &str) -> Result<PathBuf> {
@@ -155,10 +636,24 @@ impl Config {
         re.replace_all(str, "_")
     }
 
+    /// how old can the cache be for the events of that day.
+    /// events from past days don't change anymore, so for these
+    /// the cache is valid as long as it was written after the day ended.
+    pub fn cache_max_age(&self, day: Date<Local>) -> Option<chrono::Duration> {
+        if day >= Local::today() {
+            Some(chrono::Duration::seconds(self.cache_ttl_secs as i64))
+        } else {
+            None
+        }
+    }
+
+    /// the cache is valid if it was written after `date`, or
+    /// if it is younger than `max_age`.
     pub fn get_cached_contents(
         event_provider: &dyn EventProvider,
         config_name: &str,
         date: &DateTime<Local>,
+        max_age: Option<chrono::Duration>,
     ) -> Result<Option<String>> {
         let cache_file = Self::get_cache_path(event_provider, config_name)?;
         if !cache_file.exists() {
@@ -166,7 +661,10 @@ impl Config {
         }
         let metadata = std::fs::metadata(&cache_file)?;
         let file_date: DateTime<Local> = DateTime::from(metadata.modified()?);
-        if file_date >= *date {
+        let is_fresh = max_age
+            .map(|age| file_date >= Local::now() - age)
+            .unwrap_or(false);
+        if file_date >= *date || is_fresh {
             let mut contents = String::new();
             File::open(cache_file)?.read_to_string(&mut contents)?;
             Ok(Some(contents))
@@ -180,6 +678,26 @@ impl Config {
         }
     }
 
+    /// get the cached contents for the events of that day,
+    /// honoring the cache TTL and the bypass_cache option
+    pub fn get_cached_day_contents(
+        &self,
+        options: &FetchOptions,
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Option<String>> {
+        if options.bypass_cache {
+            return Ok(None);
+        }
+        Self::get_cached_contents(
+            event_provider,
+            config_name,
+            &(day.and_hms(0, 0, 0) + chrono::Duration::days(1)),
+            self.cache_max_age(day),
+        )
+    }
+
     pub fn write_to_cache(
         event_provider: &dyn EventProvider,
         config_name: &str,
@@ -189,6 +707,149 @@ impl Config {
         file.write_all(contents.as_bytes())?;
         Ok(())
     }
+
+    fn validators_cache_key(config_name: &str) -> String {
+        format!("{}_validators", config_name)
+    }
+
+    /// write to the cache, with the validators the server sent us for
+    /// these contents, so that we can ask it later whether they changed
+    pub fn write_to_cache_with_validators(
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+        contents: &str,
+        validators: &CacheValidators,
+    ) -> Result<()> {
+        Self::write_to_cache(event_provider, config_name, contents)?;
+        let validators_path =
+            Self::get_cache_path(event_provider, &Self::validators_cache_key(config_name))?;
+        if validators.is_empty() {
+            if validators_path.exists() {
+                fs::remove_file(validators_path)?;
+            }
+            Ok(())
+        } else {
+            Ok(fs::write(
+                validators_path,
+                serde_json::to_string(validators)?,
+            )?)
+        }
+    }
+
+    /// the cached contents however old they are, with their validators.
+    /// None if we don't have validators for them.
+    pub fn get_cached_contents_with_validators(
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+    ) -> Result<Option<(String, CacheValidators)>> {
+        let cache_file = Self::get_cache_path(event_provider, config_name)?;
+        let validators_file =
+            Self::get_cache_path(event_provider, &Self::validators_cache_key(config_name))?;
+        if !cache_file.exists() || !validators_file.exists() {
+            return Ok(None);
+        }
+        let validators = serde_json::from_str(&fs::read_to_string(validators_file)?)?;
+        Ok(Some((fs::read_to_string(cache_file)?, validators)))
+    }
+
+    /// the cache files live next to the config file,
+    /// they're recognized by their extension.
+    pub fn cache_dir() -> Result<PathBuf> {
+        Self::config_folder()
+    }
+
+    fn cache_files(cache_dir: &Path) -> Result<Vec<(PathBuf, fs::Metadata)>> {
+        let mut result = vec![];
+        for entry in fs::read_dir(cache_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("cache") {
+                let metadata = fs::metadata(&path)?;
+                if metadata.is_file() {
+                    result.push((path, metadata));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn find_day_cache_file(
+        cache_dir: &Path,
+        provider_name: &str,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Option<PathBuf>> {
+        // the day files are named <config>_<day>, the other
+        // providers use a single <config> file for all the days
+        let single_file = format!(
+            "{}_{}",
+            provider_name,
+            Self::sanitize_for_filename(config_name)
+        );
+        let day_file = format!("{}_{}", single_file, day.format("%Y%m%d"));
+        let cache_files: Vec<PathBuf> = Self::cache_files(cache_dir)?
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        let find_file = |stem: &str| {
+            cache_files
+                .iter()
+                .find(|path| path.file_stem().and_then(|s| s.to_str()) == Some(stem))
+                .cloned()
+        };
+        Ok(find_file(&day_file).or_else(|| find_file(&single_file)))
+    }
+
+    /// the cache file of that event source for that day, if any, for
+    /// troubleshooting. Most providers cache each day in its own file,
+    /// some use a single file for all the days.
+    pub fn day_cache_file(
+        event_provider: &dyn EventProvider,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Option<PathBuf>> {
+        Self::find_day_cache_file(&Self::cache_dir()?, event_provider.name(), config_name, day)
+    }
+
+    /// total size of the cache files, in bytes
+    pub fn cache_size() -> Result<u64> {
+        Ok(Self::cache_files(&Self::cache_dir()?)?
+            .iter()
+            .map(|(_, metadata)| metadata.len())
+            .sum())
+    }
+
+    /// remove the cache files which were last written before `older_than`,
+    /// or all of them if it's None. Returns the number of bytes freed.
+    fn remove_cache_files(cache_dir: &Path, older_than: Option<SystemTime>) -> Result<u64> {
+        let mut freed = 0;
+        for (path, metadata) in Self::cache_files(cache_dir)? {
+            if older_than.map_or(true, |t| metadata.modified().map_or(false, |m| m < t)) {
+                fs::remove_file(&path)?;
+                freed += metadata.len();
+            }
+        }
+        Ok(freed)
+    }
+
+    /// remove all the cache files, for all the event providers.
+    /// Returns the number of bytes freed.
+    pub fn clear_cache() -> Result<u64> {
+        Self::remove_cache_files(&Self::cache_dir()?, None)
+    }
+
+    /// remove the cache files which were not written to for
+    /// `cache_eviction_days`, if that setting is enabled.
+    /// Returns the number of bytes freed.
+    pub fn evict_old_cache_files(&self) -> Result<u64> {
+        if self.cache_eviction_days == 0 {
+            return Ok(0);
+        }
+        let max_age = time::Duration::from_secs(u64::from(self.cache_eviction_days) * 24 * 3600);
+        match SystemTime::now().checked_sub(max_age) {
+            Some(older_than) => Self::remove_cache_files(&Self::cache_dir()?, Some(older_than)),
+            None => Ok(0),
+        }
+    }
 }
 
 #[test]
@@ -202,3 +863,155 @@ fn it_properly_escapes_filenames() {
         Config::sanitize_for_filename("simPle N!()12č>/\\*3amée")
     );
 }
+
+#[test]
+fn it_only_uses_the_cache_ttl_for_today() {
+    let config = Config::default_config();
+    assert_eq!(
+        Some(chrono::Duration::seconds(600)),
+        config.cache_max_age(Local::today())
+    );
+    assert_eq!(
+        None,
+        config.cache_max_age(Local::today() - chrono::Duration::days(1))
+    );
+}
+
+#[test]
+fn it_computes_the_week_start() {
+    let mut config = Config::default_config();
+    // 2020-02-25 is a tuesday
+    assert_eq!(
+        Local.ymd(2020, 2, 24),
+        config.week_start(Local.ymd(2020, 2, 25))
+    );
+    config.week_starts_on = Weekday::Sun;
+    assert_eq!(
+        Local.ymd(2020, 2, 23),
+        config.week_start(Local.ymd(2020, 2, 25))
+    );
+    assert_eq!(
+        Local.ymd(2020, 2, 23),
+        config.week_start(Local.ymd(2020, 2, 23))
+    );
+}
+
+#[test]
+fn it_falls_back_to_monday_for_invalid_week_starts() {
+    #[derive(serde_derive::Deserialize)]
+    struct WeekConfig {
+        #[serde(deserialize_with = "Config::deserialize_week_starts_on")]
+        week_starts_on: Weekday,
+    }
+    let parse = |s: &str| toml::from_str::<WeekConfig>(s).unwrap().week_starts_on;
+    assert_eq!(Weekday::Sun, parse("week_starts_on = \"Sun\""));
+    assert_eq!(Weekday::Mon, parse("week_starts_on = \"Someday\""));
+}
+
+#[test]
+fn it_falls_back_for_invalid_last_viewed_dates() {
+    let today = Local.ymd(2020, 2, 25);
+    assert_eq!(
+        Some(Local.ymd(2020, 2, 20)),
+        Config::parse_last_viewed_date("2020-02-20\n", today)
+    );
+    assert_eq!(None, Config::parse_last_viewed_date("2020-02-26", today));
+    assert_eq!(None, Config::parse_last_viewed_date("garbage", today));
+}
+
+#[test]
+fn it_honors_the_config_dir_override() {
+    assert_eq!(
+        PathBuf::from("/tmp/cigale-profile"),
+        Config::config_folder_path(Some("/tmp/cigale-profile".into()))
+    );
+    assert!(Config::config_folder_path(Some("".into())).ends_with(".cigale"));
+    assert!(Config::config_folder_path(None).ends_with(".cigale"));
+}
+
+#[test]
+fn it_removes_only_cache_files() {
+    let dir = env::temp_dir().join(format!("cigale-cache-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "prefer_dark_theme = true").unwrap();
+    fs::write(dir.join("Git_work.cache"), "0123456789").unwrap();
+    fs::write(dir.join("Redmine_work.cache"), "01234").unwrap();
+    let in_the_past = SystemTime::now() - time::Duration::from_secs(3600);
+    assert_eq!(
+        0,
+        Config::remove_cache_files(&dir, Some(in_the_past)).unwrap()
+    );
+    assert_eq!(15, Config::remove_cache_files(&dir, None).unwrap());
+    assert!(dir.join("config.toml").exists());
+    assert!(!dir.join("Git_work.cache").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn it_finds_the_cache_file_of_a_day() {
+    let dir = env::temp_dir().join(format!("cigale-day-cache-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for file in &[
+        "Git_work_20200225.cache",
+        "Git_work_20200226.cache",
+        "Git_workshop_20200225.cache",
+        "Git_work_old_20200225.cache",
+        "Git_work_old_20200227.cache",
+        "Ical_work.cache",
+        "Ical_work_validators.cache",
+    ] {
+        fs::write(dir.join(file), "").unwrap();
+    }
+    let day = Local.ymd(2020, 2, 25);
+    assert_eq!(
+        Some(dir.join("Git_work_20200225.cache")),
+        Config::find_day_cache_file(&dir, "Git", "work", day).unwrap()
+    );
+    // "work" must not pick the files of "work-old"
+    assert_eq!(
+        None,
+        Config::find_day_cache_file(&dir, "Git", "work", Local.ymd(2020, 2, 27)).unwrap()
+    );
+    assert_eq!(
+        Some(dir.join("Git_work_old_20200225.cache")),
+        Config::find_day_cache_file(&dir, "Git", "work-old", day).unwrap()
+    );
+    assert_eq!(
+        Some(dir.join("Ical_work.cache")),
+        Config::find_day_cache_file(&dir, "Ical", "work", day).unwrap()
+    );
+    assert_eq!(
+        None,
+        Config::find_day_cache_file(&dir, "Redmine", "work", day).unwrap()
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn it_validates_date_formats() {
+    assert_eq!(Ok(()), Config::validate_date_format("%Y-%m-%d (%a)"));
+    assert_eq!(Ok(()), Config::validate_date_format("%A, %B %-d"));
+    assert!(Config::validate_date_format("%Y-%m-%").is_err());
+    assert!(Config::validate_date_format("%Q").is_err());
+    assert!(Config::validate_date_format(" ").is_err());
+}
+
+#[test]
+fn it_tracks_disabled_sources_by_provider() {
+    let mut config = Config::default_config();
+    config.set_source_enabled("Redmine", "work", false);
+    assert!(!config.is_source_enabled("Redmine", "work"));
+    assert!(config.is_source_enabled("Git", "work"));
+    config.rename_event_source("Redmine", "work", "office");
+    assert!(config.is_source_enabled("Redmine", "work"));
+    assert!(!config.is_source_enabled("Redmine", "office"));
+    config.forget_event_source("Redmine", "office");
+    assert!(config.is_source_enabled("Redmine", "office"));
+    assert!(config.disabled_sources.is_empty());
+    // serialized after the simple values
+    config.set_source_enabled("Git", "work", false);
+    let toml = toml::to_string_pretty(&config).unwrap();
+    let read_config = toml::from_str::<Config>(&toml).unwrap();
+    assert_eq!(config.disabled_sources, read_config.disabled_sources);
+    assert!(!read_config.is_source_enabled("Git", "work"));
+}
@@ -0,0 +1,381 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://developers.asana.com/docs/get-multiple-tasks
+const API_URL: &str = "https://app.asana.com/api/1.0";
+// the maximum page size for asana
+const PAGE_LIMIT: &str = "100";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AsanaConfig {
+    pub personal_access_token: String,
+    pub workspace_gid: String,
+}
+
+pub struct Asana;
+const PERSONAL_ACCESS_TOKEN_KEY: &str = "Personal access token";
+const WORKSPACE_GID_KEY: &str = "Workspace gid";
+
+#[derive(Deserialize, Debug)]
+struct AsanaData<T> {
+    data: T,
+}
+
+#[derive(Deserialize, Debug)]
+struct AsanaPage<T> {
+    data: Vec<T>,
+    next_page: Option<AsanaNextPage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AsanaNextPage {
+    offset: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct AsanaUser {
+    gid: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct AsanaTask {
+    gid: String,
+    name: String,
+    completed_at: Option<DateTime<Local>>,
+    permalink_url: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct AsanaStory {
+    created_at: DateTime<Local>,
+    created_by: Option<AsanaUser>,
+    resource_subtype: String,
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct AsanaComment {
+    task: AsanaTask,
+    story: AsanaStory,
+}
+
+/// what we keep in the cache
+#[derive(Deserialize, Serialize, Debug)]
+struct AsanaDayActivity {
+    completed_tasks: Vec<AsanaTask>,
+    comments: Vec<AsanaComment>,
+}
+
+impl Asana {
+    fn asana_get<T: DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        asana_config: &AsanaConfig,
+        url_path: &str,
+        params: &[(&str, String)],
+    ) -> Result<T> {
+        let json = http::send_with_retry(|| {
+            client
+                .get(&format!("{}{}", API_URL, url_path))
+                .bearer_auth(&asana_config.personal_access_token)
+                .query(params)
+        })?
+        .text()?;
+        Ok(serde_json::from_str::<T>(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?)
+    }
+
+    /// asana uses cursor-based pagination: each page gives
+    /// us the offset to pass to get the next one.
+    fn asana_get_all<T: DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        asana_config: &AsanaConfig,
+        url_path: &str,
+        params: &[(&str, String)],
+    ) -> Result<Vec<T>> {
+        let mut result = vec![];
+        let mut offset: Option<String> = None;
+        loop {
+            let mut page_params = params.to_vec();
+            page_params.push(("limit", PAGE_LIMIT.to_string()));
+            if let Some(o) = offset {
+                page_params.push(("offset", o));
+            }
+            let page: AsanaPage<T> = Self::asana_get(client, asana_config, url_path, &page_params)?;
+            result.extend(page.data);
+            match page.next_page {
+                Some(next) => offset = Some(next.offset),
+                None => return Ok(result),
+            }
+        }
+    }
+
+    fn is_on_day(date: &DateTime<Local>, day: Date<Local>) -> bool {
+        date.date() == day
+    }
+
+    fn is_comment_by(story: &AsanaStory, user: &AsanaUser, day: Date<Local>) -> bool {
+        story.resource_subtype == "comment_added"
+            && story.created_by.as_ref().map(|u| &u.gid) == Some(&user.gid)
+            && Self::is_on_day(&story.created_at, day)
+    }
+
+    fn fetch_activity(
+        config_name: &str,
+        asana_config: &AsanaConfig,
+        day: Date<Local>,
+    ) -> Result<AsanaDayActivity> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let me: AsanaData<AsanaUser> = Self::asana_get(
+            &client,
+            asana_config,
+            "/users/me",
+            &[("opt_fields", "gid".to_string())],
+        )?;
+        // the API doesn't let us filter on the commenter, so we look
+        // at the tasks assigned to us, which were touched since that day
+        let tasks: Vec<AsanaTask> = Self::asana_get_all(
+            &client,
+            asana_config,
+            "/tasks",
+            &[
+                ("assignee", "me".to_string()),
+                ("workspace", asana_config.workspace_gid.clone()),
+                ("modified_since", day.and_hms(0, 0, 0).to_rfc3339()),
+                (
+                    "opt_fields",
+                    "gid,name,completed_at,permalink_url".to_string(),
+                ),
+            ],
+        )?;
+        let mut comments = vec![];
+        for task in &tasks {
+            let stories: Vec<AsanaStory> = Self::asana_get_all(
+                &client,
+                asana_config,
+                &format!("/tasks/{}/stories", task.gid),
+                &[(
+                    "opt_fields",
+                    "created_at,created_by.gid,resource_subtype,text".to_string(),
+                )],
+            )?;
+            comments.extend(
+                stories
+                    .into_iter()
+                    .filter(|s| Self::is_comment_by(s, &me.data, day))
+                    .map(|story| AsanaComment {
+                        task: task.clone(),
+                        story,
+                    }),
+            );
+        }
+        let activity = AsanaDayActivity {
+            completed_tasks: tasks
+                .into_iter()
+                .filter(|t| t.completed_at.map_or(false, |c| Self::is_on_day(&c, day)))
+                .collect(),
+            comments,
+        };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Asana, &cache_key, &serde_json::to_string(&activity)?)?;
+        Ok(activity)
+    }
+
+    fn task_event(
+        task: &AsanaTask,
+        time: NaiveTime,
+        details: Option<&str>,
+        extra_details: &str,
+    ) -> Event {
+        let body = details
+            .into_iter()
+            .chain(task.permalink_url.as_deref())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let event = Event::new(
+            "Asana",
+            Icon::CHECK_SQUARE,
+            time,
+            task.name.clone(),
+            task.name.clone(),
+            EventBody::PlainText(body),
+            Some(extra_details.to_string()),
+        );
+        match &task.permalink_url {
+            Some(url) => event.with_url(url),
+            None => event,
+        }
+    }
+
+    fn activity_to_events(activity: &AsanaDayActivity) -> Vec<Event> {
+        let completed = activity.completed_tasks.iter().filter_map(|t| {
+            t.completed_at
+                .map(|c| Self::task_event(t, c.time(), None, "Task completed"))
+        });
+        let comments = activity.comments.iter().map(|c| {
+            Self::task_event(
+                &c.task,
+                c.story.created_at.time(),
+                c.story.text.as_deref(),
+                "Task comment",
+            )
+        });
+        let mut result: Vec<Event> = completed.chain(comments).collect();
+        result.sort_by_key(|e| e.event_time);
+        result
+    }
+}
+
+impl EventProvider for Asana {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (PERSONAL_ACCESS_TOKEN_KEY, ConfigType::Password),
+            (WORKSPACE_GID_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Asana"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CHECK_SQUARE
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.asana.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                PERSONAL_ACCESS_TOKEN_KEY,
+                config.asana[config_name].personal_access_token.to_string(),
+            ),
+            (
+                WORKSPACE_GID_KEY,
+                config.asana[config_name].workspace_gid.to_string(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.asana.insert(
+            config_name,
+            AsanaConfig {
+                personal_access_token: config_values.remove(PERSONAL_ACCESS_TOKEN_KEY).unwrap(),
+                workspace_gid: config_values
+                    .remove(WORKSPACE_GID_KEY)
+                    .unwrap()
+                    .trim()
+                    .to_string(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.asana.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let asana_config = &config.asana[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let activity = match config
+            .get_cached_day_contents(options, &Asana, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<AsanaDayActivity>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_activity(config_name, asana_config, day)?,
+        };
+        Ok(Self::activity_to_events(&activity))
+    }
+}
+
+#[test]
+fn it_converts_asana_activity_to_events() {
+    let tasks_json = r#"{"data": [
+        {"gid": "1", "name": "Write the specs", "completed_at": "2020-02-25T10:00:00.000Z",
+         "permalink_url": "https://app.asana.com/0/0/1"},
+        {"gid": "2", "name": "Review the design", "completed_at": null,
+         "permalink_url": "https://app.asana.com/0/0/2"}
+    ], "next_page": {"offset": "abc", "path": "/tasks?offset=abc", "uri": ""}}"#;
+    let page = serde_json::from_str::<AsanaPage<AsanaTask>>(tasks_json).unwrap();
+    assert_eq!("abc", page.next_page.unwrap().offset);
+    let stories_json = r#"{"data": [
+        {"created_at": "2020-02-25T09:00:00.000Z", "created_by": {"gid": "42"},
+         "resource_subtype": "comment_added", "text": "looks good"},
+        {"created_at": "2020-02-25T09:30:00.000Z", "created_by": {"gid": "43"},
+         "resource_subtype": "comment_added", "text": "not mine"},
+        {"created_at": "2020-02-25T10:00:00.000Z", "created_by": {"gid": "42"},
+         "resource_subtype": "assigned", "text": "assigned to me"}
+    ], "next_page": null}"#;
+    let stories = serde_json::from_str::<AsanaPage<AsanaStory>>(stories_json).unwrap();
+    assert!(stories.next_page.is_none());
+    let me = AsanaUser {
+        gid: "42".to_string(),
+    };
+    let day = stories.data[0].created_at.date();
+    let activity = AsanaDayActivity {
+        completed_tasks: vec![page.data[0].clone()],
+        comments: stories
+            .data
+            .into_iter()
+            .filter(|s| Asana::is_comment_by(s, &me, day))
+            .map(|story| AsanaComment {
+                task: page.data[1].clone(),
+                story,
+            })
+            .collect(),
+    };
+    let events = Asana::activity_to_events(&activity);
+    assert_eq!(2, events.len());
+    assert_eq!("Review the design", events[0].event_contents_header);
+    assert_eq!(
+        EventBody::PlainText("looks good\n\nhttps://app.asana.com/0/0/2".to_string()),
+        events[0].event_contents_body
+    );
+    assert_eq!(
+        Some("Task comment".to_string()),
+        events[0].event_extra_details
+    );
+    assert_eq!("Write the specs", events[1].event_contents_header);
+    assert_eq!(
+        Some("https://app.asana.com/0/0/1".to_string()),
+        events[1].event_url
+    );
+}
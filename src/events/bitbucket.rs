@@ -0,0 +1,780 @@
+use super::events::{
+    day_bounds, ConfigType, Event, EventBody, EventProvider, FetchOptions, Result,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Bitbucket Cloud and Bitbucket Server (Data Center) have unrelated APIs:
+// https://developer.atlassian.com/cloud/bitbucket/rest/
+// https://docs.atlassian.com/bitbucket-server/rest/latest/bitbucket-rest.html
+// Neither has a user activity feed, so we look at the pull requests
+// we authored (and their comments), and at the commits of the
+// repositories we recently worked on.
+const CLOUD_API_URL: &str = "https://api.bitbucket.org/2.0";
+// we follow the pages until we go back before the day, but not forever
+const MAX_PAGES: usize = 5;
+const MAX_REPOS: usize = 20;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BitbucketConfig {
+    // None => Bitbucket Cloud
+    pub server_url: Option<String>,
+    pub username: String,
+    pub app_password: String, // config::SECRET_IN_KEYRING if stored in the keyring
+}
+
+pub struct Bitbucket;
+const SERVER_URL_KEY: &str = "Server URL (empty for Bitbucket Cloud)";
+const USERNAME_KEY: &str = "Username";
+const APP_PASSWORD_KEY: &str = "App password";
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+enum ActivityKind {
+    Commit,
+    PullRequest,
+    Comment,
+}
+
+/// what we keep in the cache: the activity of the day,
+/// the same for both flavors of Bitbucket
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct BitbucketActivity {
+    kind: ActivityKind,
+    time: DateTime<Local>,
+    repo: String,
+    title: String,
+    body: String,
+    url: String,
+}
+
+// Bitbucket Cloud
+
+#[derive(Deserialize, Debug)]
+struct CloudPage<T> {
+    values: Vec<T>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudUser {
+    // missing for deleted users
+    #[serde(default)]
+    account_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudLink {
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudLinks {
+    html: Option<CloudLink>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudRepository {
+    full_name: String,
+    updated_on: DateTime<Local>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudCommitAuthor {
+    user: Option<CloudUser>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudCommit {
+    hash: String,
+    date: DateTime<Local>,
+    message: String,
+    author: CloudCommitAuthor,
+    links: CloudLinks,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudPrDestination {
+    repository: CloudPrRepository,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudPrRepository {
+    full_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudPullRequest {
+    id: u64,
+    title: String,
+    description: Option<String>,
+    created_on: DateTime<Local>,
+    updated_on: DateTime<Local>,
+    destination: CloudPrDestination,
+    links: CloudLinks,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudContent {
+    #[serde(default)]
+    raw: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CloudComment {
+    content: CloudContent,
+    created_on: DateTime<Local>,
+    user: CloudUser,
+    links: CloudLinks,
+}
+
+// Bitbucket Server
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerPage<T> {
+    values: Vec<T>,
+    is_last_page: bool,
+    next_page_start: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ServerProject {
+    key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ServerRepository {
+    slug: String,
+    project: ServerProject,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerCommitAuthor {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerCommit {
+    id: String,
+    message: String,
+    // milliseconds since the epoch
+    author_timestamp: i64,
+    author: ServerCommitAuthor,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerRef {
+    repository: ServerRepository,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerPullRequest {
+    id: u64,
+    title: String,
+    description: Option<String>,
+    created_date: i64,
+    updated_date: i64,
+    to_ref: ServerRef,
+}
+
+#[derive(Deserialize, Debug)]
+struct ServerUser {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ServerComment {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ServerPrActivity {
+    action: String,
+    created_date: i64,
+    user: ServerUser,
+    comment: Option<ServerComment>,
+}
+
+impl Bitbucket {
+    fn config_values(
+        bitbucket_config: &BitbucketConfig,
+        app_password: String,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                bitbucket_config
+                    .server_url
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (USERNAME_KEY, bitbucket_config.username.to_string()),
+            (APP_PASSWORD_KEY, app_password),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_app_password(config_name: &str, bitbucket_config: &BitbucketConfig) -> Result<String> {
+        Config::read_secret(
+            &Bitbucket,
+            config_name,
+            &bitbucket_config.username,
+            &bitbucket_config.app_password,
+        )
+    }
+
+    fn parse_millis(millis: i64) -> DateTime<Local> {
+        Utc.timestamp_millis(millis).with_timezone(&Local)
+    }
+
+    fn first_line(text: &str) -> String {
+        text.lines().next().unwrap_or("").to_string()
+    }
+
+    fn get_json<T: DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        bitbucket_config: &BitbucketConfig,
+        password: &str,
+        url: &str,
+    ) -> Result<T> {
+        let json = http::send_with_retry(|| {
+            client
+                .get(url)
+                .basic_auth(&bitbucket_config.username, Some(password))
+        })?
+        .text()?;
+        Ok(serde_json::from_str::<T>(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?)
+    }
+
+    /// follow the 'next' links while `keep_going` accepts the last item
+    fn cloud_get_pages<T: DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        bitbucket_config: &BitbucketConfig,
+        password: &str,
+        url: &str,
+        keep_going: impl Fn(&T) -> bool,
+    ) -> Result<Vec<T>> {
+        let mut result = vec![];
+        let mut next = Some(url.to_string());
+        for _ in 0..MAX_PAGES {
+            let page_url = match next {
+                Some(u) => u,
+                None => break,
+            };
+            let page: CloudPage<T> = Self::get_json(client, bitbucket_config, password, &page_url)?;
+            next = page.next;
+            result.extend(page.values);
+            if !result.last().map_or(false, &keep_going) {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    fn server_get_pages<T: DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        bitbucket_config: &BitbucketConfig,
+        password: &str,
+        url: &str,
+        keep_going: impl Fn(&T) -> bool,
+    ) -> Result<Vec<T>> {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let mut result = vec![];
+        let mut start = 0;
+        for _ in 0..MAX_PAGES {
+            let page: ServerPage<T> = Self::get_json(
+                client,
+                bitbucket_config,
+                password,
+                &format!("{}{}start={}", url, separator, start),
+            )?;
+            result.extend(page.values);
+            match page.next_page_start {
+                Some(s) if !page.is_last_page && result.last().map_or(false, &keep_going) => {
+                    start = s
+                }
+                _ => break,
+            }
+        }
+        Ok(result)
+    }
+
+    fn fetch_cloud_activity(
+        client: &reqwest::blocking::Client,
+        bitbucket_config: &BitbucketConfig,
+        password: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<BitbucketActivity>> {
+        let (day_start, next_day_start) = day_bounds(day);
+        let is_on_day = |t: &DateTime<Local>| *t >= day_start && *t < next_day_start;
+        let me: CloudUser = Self::get_json(
+            client,
+            bitbucket_config,
+            password,
+            &format!("{}/user", CLOUD_API_URL),
+        )?;
+        let mut result = vec![];
+
+        let prs: Vec<CloudPullRequest> = Self::cloud_get_pages(
+            client,
+            bitbucket_config,
+            password,
+            &format!(
+                "{}/pullrequests/{}?state=OPEN&state=MERGED&state=DECLINED&state=SUPERSEDED&sort=-updated_on",
+                CLOUD_API_URL, me.account_id
+            ),
+            |pr: &CloudPullRequest| pr.updated_on >= day_start,
+        )?;
+        for pr in prs.iter().filter(|pr| pr.updated_on >= day_start) {
+            let pr_url = pr.links.html.as_ref().map(|l| l.href.clone());
+            if is_on_day(&pr.created_on) {
+                result.push(BitbucketActivity {
+                    kind: ActivityKind::PullRequest,
+                    time: pr.created_on,
+                    repo: pr.destination.repository.full_name.clone(),
+                    title: format!("PR #{}: {}", pr.id, pr.title),
+                    body: pr.description.clone().unwrap_or_default(),
+                    url: pr_url.clone().unwrap_or_default(),
+                });
+            }
+            let comments: Vec<CloudComment> = Self::cloud_get_pages(
+                client,
+                bitbucket_config,
+                password,
+                &format!(
+                    "{}/repositories/{}/pullrequests/{}/comments",
+                    CLOUD_API_URL, pr.destination.repository.full_name, pr.id
+                ),
+                |_| true,
+            )?;
+            result.extend(
+                comments
+                    .into_iter()
+                    .filter(|c| c.user.account_id == me.account_id && is_on_day(&c.created_on))
+                    .map(|c| BitbucketActivity {
+                        kind: ActivityKind::Comment,
+                        time: c.created_on,
+                        repo: pr.destination.repository.full_name.clone(),
+                        title: format!("PR #{}: {}", pr.id, pr.title),
+                        body: c.content.raw,
+                        url: c
+                            .links
+                            .html
+                            .map(|l| l.href)
+                            .or_else(|| pr_url.clone())
+                            .unwrap_or_default(),
+                    }),
+            );
+        }
+
+        let repos: Vec<CloudRepository> = Self::cloud_get_pages(
+            client,
+            bitbucket_config,
+            password,
+            &format!(
+                "{}/repositories?role=contributor&sort=-updated_on",
+                CLOUD_API_URL
+            ),
+            |r: &CloudRepository| r.updated_on >= day_start,
+        )?;
+        for repo in repos
+            .iter()
+            .filter(|r| r.updated_on >= day_start)
+            .take(MAX_REPOS)
+        {
+            let commits: Vec<CloudCommit> = Self::cloud_get_pages(
+                client,
+                bitbucket_config,
+                password,
+                &format!("{}/repositories/{}/commits", CLOUD_API_URL, repo.full_name),
+                |c: &CloudCommit| c.date >= day_start,
+            )?;
+            result.extend(
+                commits
+                    .into_iter()
+                    .filter(|c| {
+                        is_on_day(&c.date)
+                            && c.author.user.as_ref().map(|u| &u.account_id) == Some(&me.account_id)
+                    })
+                    .map(|c| BitbucketActivity {
+                        kind: ActivityKind::Commit,
+                        time: c.date,
+                        repo: repo.full_name.clone(),
+                        title: Self::first_line(&c.message),
+                        body: format!("{}\n\n{}", c.hash, c.message),
+                        url: c.links.html.map(|l| l.href).unwrap_or_default(),
+                    }),
+            );
+        }
+        Ok(result)
+    }
+
+    fn fetch_server_activity(
+        client: &reqwest::blocking::Client,
+        bitbucket_config: &BitbucketConfig,
+        server_url: &str,
+        password: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<BitbucketActivity>> {
+        let (day_start, next_day_start) = day_bounds(day);
+        let is_on_day = |t: &DateTime<Local>| *t >= day_start && *t < next_day_start;
+        let api_url = format!("{}/rest/api/1.0", server_url);
+        let mut result = vec![];
+
+        let prs: Vec<ServerPullRequest> = Self::server_get_pages(
+            client,
+            bitbucket_config,
+            password,
+            &format!(
+                "{}/dashboard/pull-requests?role=AUTHOR&state=ALL&order=NEWEST",
+                api_url
+            ),
+            |pr: &ServerPullRequest| Self::parse_millis(pr.updated_date) >= day_start,
+        )?;
+        for pr in prs
+            .iter()
+            .filter(|pr| Self::parse_millis(pr.updated_date) >= day_start)
+        {
+            let repo = &pr.to_ref.repository;
+            let repo_name = format!("{}/{}", repo.project.key, repo.slug);
+            let pr_url = format!(
+                "{}/projects/{}/repos/{}/pull-requests/{}",
+                server_url, repo.project.key, repo.slug, pr.id
+            );
+            let created = Self::parse_millis(pr.created_date);
+            if is_on_day(&created) {
+                result.push(BitbucketActivity {
+                    kind: ActivityKind::PullRequest,
+                    time: created,
+                    repo: repo_name.clone(),
+                    title: format!("PR #{}: {}", pr.id, pr.title),
+                    body: pr.description.clone().unwrap_or_default(),
+                    url: pr_url.clone(),
+                });
+            }
+            let activities: Vec<ServerPrActivity> = Self::server_get_pages(
+                client,
+                bitbucket_config,
+                password,
+                &format!(
+                    "{}/projects/{}/repos/{}/pull-requests/{}/activities",
+                    api_url, repo.project.key, repo.slug, pr.id
+                ),
+                |a: &ServerPrActivity| Self::parse_millis(a.created_date) >= day_start,
+            )?;
+            result.extend(
+                activities
+                    .into_iter()
+                    .filter(|a| {
+                        a.action == "COMMENTED"
+                            && a.user.name == bitbucket_config.username
+                            && is_on_day(&Self::parse_millis(a.created_date))
+                    })
+                    .map(|a| BitbucketActivity {
+                        kind: ActivityKind::Comment,
+                        time: Self::parse_millis(a.created_date),
+                        repo: repo_name.clone(),
+                        title: format!("PR #{}: {}", pr.id, pr.title),
+                        body: a.comment.map(|c| c.text).unwrap_or_default(),
+                        url: pr_url.clone(),
+                    }),
+            );
+        }
+
+        let repos: Vec<ServerRepository> = Self::server_get_pages(
+            client,
+            bitbucket_config,
+            password,
+            &format!("{}/profile/recent/repos", api_url),
+            |_| false,
+        )?;
+        for repo in repos.iter().take(MAX_REPOS) {
+            let commits: Vec<ServerCommit> = Self::server_get_pages(
+                client,
+                bitbucket_config,
+                password,
+                &format!(
+                    "{}/projects/{}/repos/{}/commits?limit=100",
+                    api_url, repo.project.key, repo.slug
+                ),
+                |c: &ServerCommit| Self::parse_millis(c.author_timestamp) >= day_start,
+            )?;
+            result.extend(
+                commits
+                    .into_iter()
+                    .filter(|c| {
+                        c.author.name == bitbucket_config.username
+                            && is_on_day(&Self::parse_millis(c.author_timestamp))
+                    })
+                    .map(|c| BitbucketActivity {
+                        kind: ActivityKind::Commit,
+                        time: Self::parse_millis(c.author_timestamp),
+                        repo: format!("{}/{}", repo.project.key, repo.slug),
+                        title: Self::first_line(&c.message),
+                        body: format!("{}\n\n{}", c.id, c.message),
+                        url: format!(
+                            "{}/projects/{}/repos/{}/commits/{}",
+                            server_url, repo.project.key, repo.slug, c.id
+                        ),
+                    }),
+            );
+        }
+        Ok(result)
+    }
+
+    fn fetch_activity(
+        config_name: &str,
+        bitbucket_config: &BitbucketConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<BitbucketActivity>> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let password = Self::read_app_password(config_name, bitbucket_config)?;
+        let activity = match &bitbucket_config.server_url {
+            Some(server_url) => {
+                Self::fetch_server_activity(&client, bitbucket_config, server_url, &password, day)?
+            }
+            None => Self::fetch_cloud_activity(&client, bitbucket_config, &password, day)?,
+        };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Bitbucket, &cache_key, &serde_json::to_string(&activity)?)?;
+        Ok(activity)
+    }
+
+    fn to_event(activity: &BitbucketActivity) -> Event {
+        let (icon, kind_desc) = match activity.kind {
+            ActivityKind::Commit => (Icon::CODE_BRANCH, "commit"),
+            ActivityKind::PullRequest => (Icon::TASKS, "pull request"),
+            ActivityKind::Comment => (Icon::COMMENT_DOTS, "comment"),
+        };
+        let event = Event::new(
+            "Bitbucket",
+            icon,
+            activity.time.time(),
+            activity.title.clone(),
+            format!("{}: {}", activity.repo, activity.title),
+            EventBody::PlainText(activity.body.clone()),
+            Some(kind_desc.to_string()),
+        )
+        .with_category(kind_desc);
+        if activity.url.is_empty() {
+            event
+        } else {
+            event.with_url(&activity.url)
+        }
+    }
+}
+
+impl EventProvider for Bitbucket {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (APP_PASSWORD_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Bitbucket"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.bitbucket.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            SERVER_URL_KEY if !value.trim().is_empty() => reqwest::Url::parse(value.trim())
+                .map(|_| ())
+                .map_err(|e| format!("Invalid URL: {}", e)),
+            USERNAME_KEY if value.trim().is_empty() => Err("The username is required".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let bitbucket_config = &config.bitbucket[config_name];
+        Self::config_values(
+            bitbucket_config,
+            Self::read_app_password(config_name, bitbucket_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.bitbucket[config_name], "".to_string())
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let username = config_values
+            .remove(USERNAME_KEY)
+            .unwrap()
+            .trim()
+            .to_string();
+        let app_password = Config::store_secret(
+            &Bitbucket,
+            &config_name,
+            &username,
+            config_values.remove(APP_PASSWORD_KEY).unwrap(),
+        );
+        config.bitbucket.insert(
+            config_name,
+            BitbucketConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .map(|u| u.trim().trim_end_matches('/').to_string())
+                    .filter(|u| !u.is_empty()),
+                username,
+                app_password,
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.bitbucket.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.bitbucket[&config_name];
+            Config::remove_replaced_secret(
+                &Bitbucket,
+                old_config_name,
+                &old.username,
+                &old.app_password,
+                &config_name,
+                &new.username,
+                &new.app_password,
+            );
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(bitbucket_config) = config.bitbucket.remove(&config_name) {
+            Config::remove_secret(
+                &Bitbucket,
+                &config_name,
+                &bitbucket_config.username,
+                &bitbucket_config.app_password,
+            );
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let bitbucket_config = &config.bitbucket[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let activity = match config
+            .get_cached_day_contents(options, &Bitbucket, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<BitbucketActivity>>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_activity(config_name, bitbucket_config, day)?,
+        };
+        let mut result: Vec<Event> = activity.iter().map(Self::to_event).collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_parses_bitbucket_server_pages() {
+    let json = r#"{
+        "size": 1, "limit": 25, "isLastPage": false, "start": 0, "nextPageStart": 25,
+        "values": [{
+            "id": "abcdef0123",
+            "message": "Fix the build\n\nthe details",
+            "authorTimestamp": 1582646495123,
+            "author": {"name": "jdoe", "emailAddress": "jdoe@example.com"}
+        }]
+    }"#;
+    let page = serde_json::from_str::<ServerPage<ServerCommit>>(json).unwrap();
+    assert!(!page.is_last_page);
+    assert_eq!(Some(25), page.next_page_start);
+    assert_eq!("jdoe", page.values[0].author.name);
+    assert_eq!(
+        Utc.ymd(2020, 2, 25).and_hms_milli(16, 1, 35, 123),
+        Bitbucket::parse_millis(page.values[0].author_timestamp)
+    );
+}
+
+#[test]
+fn it_converts_bitbucket_activity_to_events() {
+    let activity = BitbucketActivity {
+        kind: ActivityKind::Comment,
+        time: Local.ymd(2020, 2, 25).and_hms(10, 15, 0),
+        repo: "team/cigale".to_string(),
+        title: "PR #3: Add Bitbucket".to_string(),
+        body: "looks good".to_string(),
+        url: "https://bitbucket.org/team/cigale/pull-requests/3#comment-1".to_string(),
+    };
+    let event = Bitbucket::to_event(&activity);
+    assert_eq!(Icon::COMMENT_DOTS, event.event_type_icon);
+    assert_eq!(NaiveTime::from_hms(10, 15, 0), event.event_time);
+    assert_eq!(
+        "team/cigale: PR #3: Add Bitbucket",
+        event.event_contents_header
+    );
+    assert_eq!(Some("comment".to_string()), event.event_category);
+    assert_eq!(
+        Some("https://bitbucket.org/team/cigale/pull-requests/3#comment-1".to_string()),
+        event.event_url
+    );
+}
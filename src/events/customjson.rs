@@ -0,0 +1,390 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// fetch any JSON endpoint, and extract the events through JSONPath
+// expressions. Each expression must select one value per event,
+// for instance $.entries[*].title: the n-th time goes with the n-th title.
+// We support the JSONPath subset: $, .key, ['key'], [n], [*] and .*
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CustomJsonConfig {
+    pub url: String,
+    // for instance "Authorization: Bearer xxx"
+    pub auth_header: Option<String>,
+    pub time_jsonpath: String,
+    pub title_jsonpath: String,
+    pub body_jsonpath: String, // empty => no body
+}
+
+pub struct CustomJson;
+const URL_KEY: &str = "URL ({date} is replaced by the day)";
+const AUTH_HEADER_KEY: &str = "Authentication header (optional)";
+const TIME_JSONPATH_KEY: &str = "Time JSONPath";
+const TITLE_JSONPATH_KEY: &str = "Title JSONPath";
+const BODY_JSONPATH_KEY: &str = "Body JSONPath (optional)";
+
+#[derive(PartialEq, Debug)]
+enum JsonPathStep {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+impl CustomJson {
+    fn parse_jsonpath(path: &str) -> std::result::Result<Vec<JsonPathStep>, String> {
+        let path = path.trim();
+        let mut rest = path
+            .strip_prefix('$')
+            .ok_or_else(|| format!("The JSONPath must start with $: {}", path))?;
+        let mut steps = vec![];
+        while !rest.is_empty() {
+            if let Some(r) = rest.strip_prefix(".*") {
+                steps.push(JsonPathStep::Wildcard);
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix('.') {
+                let end = r.find(|c| c == '.' || c == '[').unwrap_or(r.len());
+                if end == 0 {
+                    return Err(format!("Missing key after . in {}", path));
+                }
+                steps.push(JsonPathStep::Key(r[..end].to_string()));
+                rest = &r[end..];
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let end = r
+                    .find(']')
+                    .ok_or_else(|| format!("Missing ] in {}", path))?;
+                let contents = r[..end].trim();
+                steps.push(if contents == "*" {
+                    JsonPathStep::Wildcard
+                } else if let Ok(idx) = contents.parse::<usize>() {
+                    JsonPathStep::Index(idx)
+                } else if contents.len() >= 2
+                    && (contents.starts_with('\'') && contents.ends_with('\'')
+                        || contents.starts_with('"') && contents.ends_with('"'))
+                {
+                    JsonPathStep::Key(contents[1..contents.len() - 1].to_string())
+                } else {
+                    return Err(format!("Unsupported JSONPath selector [{}]", contents));
+                });
+                rest = &r[end + 1..];
+            } else {
+                return Err(format!("Unexpected JSONPath syntax: {}", rest));
+            }
+        }
+        Ok(steps)
+    }
+
+    fn select<'a>(root: &'a Value, steps: &[JsonPathStep]) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for step in steps {
+            current = current
+                .into_iter()
+                .flat_map(|v| -> Vec<&Value> {
+                    match (step, v) {
+                        (JsonPathStep::Key(k), _) => v.get(k.as_str()).into_iter().collect(),
+                        (JsonPathStep::Index(i), _) => v.get(*i).into_iter().collect(),
+                        (JsonPathStep::Wildcard, Value::Array(a)) => a.iter().collect(),
+                        (JsonPathStep::Wildcard, Value::Object(o)) => o.values().collect(),
+                        (JsonPathStep::Wildcard, _) => vec![],
+                    }
+                })
+                .collect();
+        }
+        current
+    }
+
+    fn value_to_string(value: &Value) -> String {
+        match value {
+            Value::String(s) => s.clone(),
+            Value::Null => "".to_string(),
+            v => v.to_string(),
+        }
+    }
+
+    /// unix timestamps (seconds or milliseconds), RFC3339, local date-times,
+    /// or times only. Returns the date if we know it.
+    fn parse_time(value: &Value) -> Option<(Option<Date<Local>>, NaiveTime)> {
+        let with_date = |dt: DateTime<Local>| Some((Some(dt.date()), dt.time()));
+        match value {
+            Value::Number(n) => {
+                let ts = n.as_i64()?;
+                if ts > 100_000_000_000 {
+                    with_date(Local.timestamp_millis(ts))
+                } else {
+                    with_date(Local.timestamp(ts, 0))
+                }
+            }
+            Value::String(s) => {
+                let s = s.trim();
+                if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+                    return with_date(dt.with_timezone(&Local));
+                }
+                for format in &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+                    if let Ok(dt) = NaiveDateTime::parse_from_str(s, format) {
+                        return Local
+                            .from_local_datetime(&dt)
+                            .earliest()
+                            .and_then(with_date);
+                    }
+                }
+                ["%H:%M:%S", "%H:%M"]
+                    .iter()
+                    .find_map(|f| NaiveTime::parse_from_str(s, f).ok())
+                    .map(|t| (None, t))
+            }
+            _ => None,
+        }
+    }
+
+    fn fetch_json(
+        config_name: &str,
+        custom_config: &CustomJsonConfig,
+        day: Date<Local>,
+    ) -> Result<String> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let url = custom_config
+            .url
+            .replace("{date}", &day.format("%Y-%m-%d").to_string());
+        let auth_header = custom_config
+            .auth_header
+            .as_ref()
+            .and_then(|h| h.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()));
+        let json = http::send_with_retry(|| {
+            let request = client.get(&url);
+            match &auth_header {
+                Some((name, value)) => request.header(name.as_str(), value.as_str()),
+                None => request,
+            }
+        })?
+        .text()?;
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&CustomJson, &cache_key, &json)?;
+        Ok(json)
+    }
+
+    fn parse_events(
+        custom_config: &CustomJsonConfig,
+        json: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let root = serde_json::from_str::<Value>(json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+        let times = Self::select(&root, &Self::parse_jsonpath(&custom_config.time_jsonpath)?);
+        let titles = Self::select(&root, &Self::parse_jsonpath(&custom_config.title_jsonpath)?);
+        let bodies = if custom_config.body_jsonpath.trim().is_empty() {
+            vec![]
+        } else {
+            Self::select(&root, &Self::parse_jsonpath(&custom_config.body_jsonpath)?)
+        };
+        if times.len() != titles.len() {
+            return Err(format!(
+                "The JSONPaths selected {} times but {} titles",
+                times.len(),
+                titles.len()
+            )
+            .into());
+        }
+        let mut result = vec![];
+        for (idx, (time_value, title_value)) in times.iter().zip(titles.iter()).enumerate() {
+            let (date, time) = Self::parse_time(time_value)
+                .ok_or_else(|| format!("Can't parse the time {}", time_value))?;
+            if date.map_or(false, |d| d != day) {
+                continue;
+            }
+            let title = Self::value_to_string(title_value);
+            let body = bodies
+                .get(idx)
+                .map(|b| Self::value_to_string(b))
+                .unwrap_or_else(|| "".to_string());
+            result.push(Event::new(
+                "Custom",
+                Icon::COG,
+                time,
+                title.clone(),
+                title,
+                EventBody::PlainText(body),
+                None,
+            ));
+        }
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+impl EventProvider for CustomJson {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (URL_KEY, ConfigType::Text("")),
+            (AUTH_HEADER_KEY, ConfigType::Text("")),
+            (TIME_JSONPATH_KEY, ConfigType::Text("$[*].time")),
+            (TITLE_JSONPATH_KEY, ConfigType::Text("$[*].title")),
+            (BODY_JSONPATH_KEY, ConfigType::Text("$[*].body")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "CustomJson"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::COG
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.custom_json.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            URL_KEY => reqwest::Url::parse(&value.trim().replace("{date}", "2020-01-01"))
+                .map(|_| ())
+                .map_err(|e| format!("Invalid URL: {}", e)),
+            AUTH_HEADER_KEY if !value.trim().is_empty() && !value.contains(':') => {
+                Err("The header must look like 'Name: value'".to_string())
+            }
+            TIME_JSONPATH_KEY | TITLE_JSONPATH_KEY => Self::parse_jsonpath(value).map(|_| ()),
+            BODY_JSONPATH_KEY if !value.trim().is_empty() => {
+                Self::parse_jsonpath(value).map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let custom_config = &config.custom_json[config_name];
+        vec![
+            (URL_KEY, custom_config.url.to_string()),
+            (
+                AUTH_HEADER_KEY,
+                custom_config
+                    .auth_header
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (TIME_JSONPATH_KEY, custom_config.time_jsonpath.to_string()),
+            (TITLE_JSONPATH_KEY, custom_config.title_jsonpath.to_string()),
+            (BODY_JSONPATH_KEY, custom_config.body_jsonpath.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.custom_json.insert(
+            config_name,
+            CustomJsonConfig {
+                url: config_values.remove(URL_KEY).unwrap().trim().to_string(),
+                auth_header: config_values
+                    .remove(AUTH_HEADER_KEY)
+                    .map(|h| h.trim().to_string())
+                    .filter(|h| !h.is_empty()),
+                time_jsonpath: config_values.remove(TIME_JSONPATH_KEY).unwrap(),
+                title_jsonpath: config_values.remove(TITLE_JSONPATH_KEY).unwrap(),
+                body_jsonpath: config_values.remove(BODY_JSONPATH_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.custom_json.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let custom_config = &config.custom_json[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let json = match config.get_cached_day_contents(options, &CustomJson, &cache_key, day)? {
+            Some(j) => j,
+            None => Self::fetch_json(config_name, custom_config, day)?,
+        };
+        Self::parse_events(custom_config, &json, day)
+    }
+}
+
+#[test]
+fn it_parses_jsonpaths() {
+    assert_eq!(
+        vec![
+            JsonPathStep::Key("data".to_string()),
+            JsonPathStep::Wildcard,
+            JsonPathStep::Key("the title".to_string()),
+            JsonPathStep::Index(0),
+        ],
+        CustomJson::parse_jsonpath("$.data[*]['the title'][0]").unwrap()
+    );
+    assert_eq!(
+        Vec::<JsonPathStep>::new(),
+        CustomJson::parse_jsonpath("$").unwrap()
+    );
+    assert!(CustomJson::parse_jsonpath("data.title").is_err());
+    assert!(CustomJson::parse_jsonpath("$.data[").is_err());
+    assert!(CustomJson::parse_jsonpath("$.data[?(@.x)]").is_err());
+}
+
+#[test]
+fn it_extracts_custom_json_events() {
+    let custom_config = CustomJsonConfig {
+        url: "https://example.com/{date}".to_string(),
+        auth_header: None,
+        time_jsonpath: "$.entries[*].at".to_string(),
+        title_jsonpath: "$.entries[*].title".to_string(),
+        body_jsonpath: "$.entries[*].details.text".to_string(),
+    };
+    let json = r#"{"entries": [
+        {"at": "14:00", "title": "deploy", "details": {"text": "v2"}},
+        {"at": "2020-02-25 09:30:00", "title": "standup", "details": {"text": null}},
+        {"at": "2020-02-24 09:30:00", "title": "yesterday", "details": {"text": ""}}
+    ]}"#;
+    let events = CustomJson::parse_events(&custom_config, json, Local.ymd(2020, 2, 25)).unwrap();
+    assert_eq!(
+        vec![
+            (NaiveTime::from_hms(9, 30, 0), "standup", "".to_string()),
+            (NaiveTime::from_hms(14, 0, 0), "deploy", "v2".to_string())
+        ],
+        events
+            .iter()
+            .map(|e| (
+                e.event_time,
+                e.event_info.as_str(),
+                e.event_contents_body.as_plain_text()
+            ))
+            .collect::<Vec<_>>()
+    );
+}
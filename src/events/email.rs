@@ -1,4 +1,4 @@
-use super::events::{ConfigType, Event, EventBody, EventProvider, Result};
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
@@ -97,7 +97,7 @@ impl Email {
         Ok(cur_buf)
     }
 
-    fn get_header_val(headers: &[mailparse::MailHeader], header_name: &str) -> Option<String> {
+    pub fn get_header_val(headers: &[mailparse::MailHeader], header_name: &str) -> Option<String> {
         headers
             .iter()
             // TODO change to Result::contains when it stabilizes
@@ -105,7 +105,7 @@ impl Email {
             .map(|h| h.get_value())
     }
 
-    fn parse_email_headers_date(headers: &[mailparse::MailHeader]) -> Option<DateTime<Local>> {
+    pub fn parse_email_headers_date(headers: &[mailparse::MailHeader]) -> Option<DateTime<Local>> {
         Email::get_header_val(headers, "Date").and_then(|d_str| Email::parse_email_date(&d_str))
     }
 
@@ -309,6 +309,7 @@ impl EventProvider for Email {
     fn get_events(
         &self,
         config: &Config,
+        _options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>> {
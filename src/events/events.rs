@@ -1,16 +1,44 @@
+use super::asana::Asana;
+use super::bitbucket::Bitbucket;
+use super::customjson::CustomJson;
 use super::email::Email;
+use super::exchange::Exchange;
+use super::gerrit::Gerrit;
 use super::git::Git;
+use super::gitea::Gitea;
+use super::github::GitHub;
 use super::gitlab::Gitlab;
+use super::googlecalendar::GoogleCalendar;
+use super::harvest::Harvest;
+use super::hg::Hg;
 use super::ical::Ical;
+use super::imap::Imap;
+use super::jenkins::Jenkins;
+use super::jira::Jira;
+use super::mattermost::Mattermost;
+use super::mock::Mock;
+use super::onedev::OneDev;
+use super::phabricator::Phabricator;
 use super::redmine::Redmine;
+use super::rssfeed::RssFeed;
+use super::slack::Slack;
 use super::stackexchange::StackExchange;
+use super::svn::Svn;
+use super::todoist::Todoist;
+use super::toggl::Toggl;
+use super::trello::Trello;
+use super::youtrack::YouTrack;
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use serde_derive::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -19,7 +47,10 @@ pub enum ConfigType {
     Password,
     File,
     Folder,
+    // the values come from EventProvider::field_values
     Combo,
+    // the default value. stored as "true" or "false"
+    Checkbox(bool),
 }
 
 pub type Result<T> = std::result::Result<T, Box<dyn Error + Sync + Send>>;
@@ -37,6 +68,17 @@ pub trait EventProvider: Sync {
         config_name: &str,
     ) -> HashMap<&'static str, String>;
 
+    /// like get_config_values, but with the secrets left empty: to display
+    /// the event sources without reading the keyring. providers which keep
+    /// secrets in the keyring override it.
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        self.get_config_values(config, config_name)
+    }
+
     fn add_config_values(
         &self,
         config: &mut Config,
@@ -44,6 +86,20 @@ pub trait EventProvider: Sync {
         config_values: HashMap<&'static str, String>,
     );
 
+    /// replace the configuration `old_config_name` by these values, maybe
+    /// under a new name. providers which keep secrets in the keyring
+    /// override it, to keep the keyring entry of an unchanged secret.
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        self.remove_config(config, old_config_name.to_string());
+        self.add_config_values(config, config_name, config_values);
+    }
+
     fn field_values(
         &self,
         cur_values: &HashMap<&'static str, String>,
@@ -52,31 +108,198 @@ pub trait EventProvider: Sync {
 
     fn remove_config(&self, config: &mut Config, config_name: String);
 
+    /// check the configuration values entered by the user, for instance
+    /// by connecting to the server. Providers which can't cheaply
+    /// validate their configuration accept everything.
+    fn validate_config(&self, _config_values: &HashMap<&'static str, String>) -> Result<()> {
+        Ok(())
+    }
+
+    /// check a single field while the user is typing, without side-effects.
+    /// the add event source dialog won't let the user save while
+    /// a field is invalid.
+    fn validate_field(
+        &self,
+        _field_name: &'static str,
+        _value: &str,
+    ) -> std::result::Result<(), String> {
+        Ok(())
+    }
+
     fn name(&self) -> &'static str;
 
     fn default_icon(&self) -> Icon;
 
+    /// the local files a configuration reads its events from.
+    /// we reload the displayed day when they change.
+    fn watched_paths(&self, _config: &Config, _config_name: &str) -> Vec<PathBuf> {
+        vec![]
+    }
+
     fn get_events(
         &self,
         config: &Config,
+        options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>>;
+
+    /// providers which can cheaply get only the newest events
+    /// override get_events_since and return true here
+    fn supports_incremental_fetch(&self) -> bool {
+        false
+    }
+
+    /// the events of the day of `since`, which happened at or after `since`
+    fn get_events_since(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        since: DateTime<Local>,
+    ) -> Result<Vec<Event>> {
+        Ok(self
+            .get_events(config, options, config_name, since.date())?
+            .into_iter()
+            .filter(|e| e.event_time >= since.time())
+            .collect())
+    }
 }
 
 pub fn get_event_providers() -> Vec<Box<dyn EventProvider>> {
     vec![
         Box::new(Git),
+        Box::new(Hg),
+        Box::new(Svn),
         Box::new(Email),
+        Box::new(Imap),
         Box::new(Ical),
+        Box::new(Exchange),
+        Box::new(GoogleCalendar),
         Box::new(Redmine),
         Box::new(Gitlab),
         Box::new(StackExchange),
+        Box::new(GitHub),
+        Box::new(Gitea),
+        Box::new(OneDev),
+        Box::new(Bitbucket),
+        Box::new(Gerrit),
+        Box::new(Phabricator),
+        Box::new(Jira),
+        Box::new(YouTrack),
+        Box::new(Toggl),
+        Box::new(Harvest),
+        Box::new(Trello),
+        Box::new(Asana),
+        Box::new(Todoist),
+        Box::new(Jenkins),
+        Box::new(RssFeed),
+        Box::new(Slack),
+        Box::new(Mattermost),
+        Box::new(CustomJson),
+        Box::new(Mock),
     ]
 }
 
+/// the local files of all the configured event sources
+pub fn get_watched_paths(config: &Config) -> Vec<PathBuf> {
+    get_event_providers()
+        .iter()
+        .flat_map(|ep| {
+            ep.get_config_names(config)
+                .into_iter()
+                .flat_map(|cfg_name| ep.watched_paths(config, cfg_name))
+                .collect::<Vec<_>>()
+        })
+        .filter(|p| p.exists())
+        .collect()
+}
+
+/// what went wrong while fetching events, when the provider knows.
+/// boxed like all our errors, the UI finds it back through ErrorKind.
+#[derive(Debug)]
+pub enum EventError {
+    // the server rejected our credentials
+    Auth(String),
+    Network(reqwest::Error),
+    // we didn't understand what the server sent us
+    Parse(String),
+    // the event source settings are incomplete or invalid
+    Config(String),
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventError::Auth(msg) | EventError::Parse(msg) | EventError::Config(msg) => {
+                write!(f, "{}", msg)
+            }
+            EventError::Network(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for EventError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EventError::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// the kind of a provider error, so that the UI can suggest a fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Auth,
+    Network,
+    Parse,
+    Config,
+    Other,
+}
+
+impl ErrorKind {
+    /// the kind the provider gave through EventError, otherwise
+    /// we guess it from the type of the error
+    pub fn of(err: &(dyn Error + 'static)) -> ErrorKind {
+        if let Some(e) = err.downcast_ref::<EventError>() {
+            return match e {
+                EventError::Auth(_) => ErrorKind::Auth,
+                EventError::Network(_) => ErrorKind::Network,
+                EventError::Parse(_) => ErrorKind::Parse,
+                EventError::Config(_) => ErrorKind::Config,
+            };
+        }
+        if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+            return match e.status() {
+                Some(reqwest::StatusCode::UNAUTHORIZED) | Some(reqwest::StatusCode::FORBIDDEN) => {
+                    ErrorKind::Auth
+                }
+                Some(s) if s.is_server_error() => ErrorKind::Network,
+                Some(_) => ErrorKind::Other,
+                None if e.is_decode() => ErrorKind::Parse,
+                None => ErrorKind::Network,
+            };
+        }
+        if err.is::<serde_json::Error>() {
+            return ErrorKind::Parse;
+        }
+        ErrorKind::Other
+    }
+
+    /// what the user can do about it
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            ErrorKind::Auth => Some("check your credentials"),
+            ErrorKind::Network => Some("check your connection and retry"),
+            ErrorKind::Config => Some("check the event source settings"),
+            ErrorKind::Parse | ErrorKind::Other => None,
+        }
+    }
+}
+
 #[derive(Debug)]
-struct ProviderError {
+pub struct ProviderError {
     pub provider_name: &'static str,
     pub config_name: String,
     pub err: Box<dyn Error + Send + Sync>,
@@ -86,7 +309,7 @@ impl fmt::Display for ProviderError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} - {}: {}",
+            "{} ({}): {}",
             self.provider_name, self.config_name, self.err
         )
     }
@@ -96,7 +319,7 @@ impl Error for ProviderError {}
 
 /// lets us know from which event source the error came
 impl ProviderError {
-    fn new(
+    pub fn new(
         provider_name: &'static str,
         config_name: String,
         err: Box<dyn Error + Send + Sync>,
@@ -107,85 +330,658 @@ impl ProviderError {
             err,
         }
     }
+
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::of(&*self.err)
+    }
+
+    /// the error, with what the user can do about it
+    pub fn to_string_with_hint(&self) -> String {
+        match self.kind().hint() {
+            Some(hint) => format!("{} ({})", self, hint),
+            None => self.to_string(),
+        }
+    }
 }
 
-pub fn get_all_events(config: Config, day: Date<Local>) -> Result<Vec<Event>> {
-    let start = Instant::now();
-    let eps = get_event_providers();
-    let configs_to_fetch: Vec<(&Box<dyn EventProvider>, &String)> = eps
+/// total of the durations of the events, formatted like "Total: 2h 30m"
+pub fn format_total_duration(events: &[&Event]) -> String {
+    let total = events
         .iter()
-        .flat_map(|ep| {
-            ep.get_config_names(&config)
+        .filter_map(|e| e.event_duration)
+        .fold(chrono::Duration::zero(), |a, b| a + b);
+    format!(
+        "Total: {}h {}m",
+        total.num_hours(),
+        total.num_minutes() % 60
+    )
+}
+
+/// for a range of events selected in the list: how many, when, how long
+pub fn format_range_summary(events: &[&Event]) -> String {
+    let span = match (events.first(), events.last()) {
+        (Some(first), Some(last)) => format!(
+            ", {}–{}",
+            first.event_time.format("%H:%M"),
+            (last.event_time + last.event_duration.unwrap_or_else(chrono::Duration::zero))
+                .format("%H:%M")
+        ),
+        _ => "".to_string(),
+    };
+    format!(
+        "{} event(s){}\n{}",
+        events.len(),
+        span,
+        format_total_duration(events)
+    )
+}
+
+/// API-based event sources report their timestamps in UTC
+pub fn to_local(utc: DateTime<Utc>) -> DateTime<Local> {
+    utc.with_timezone(&Local)
+}
+
+/// parse a timezone override: "UTC", or an offset to UTC
+/// like "+02:00" or "-0530"
+pub fn parse_timezone(timezone: &str) -> Result<FixedOffset> {
+    let timezone = timezone.trim();
+    if timezone.eq_ignore_ascii_case("utc") || timezone == "Z" {
+        return Ok(FixedOffset::east(0));
+    }
+    let invalid = || {
+        format!(
+            "Invalid timezone {}, expected for instance +02:00",
+            timezone
+        )
+    };
+    let re = regex::Regex::new(r"^([+-])(\d{2}):?(\d{2})$").unwrap();
+    let caps = re.captures(timezone).ok_or_else(invalid)?;
+    let secs = caps[2].parse::<i32>()? * 3600 + caps[3].parse::<i32>()? * 60;
+    let offset = if &caps[1] == "-" {
+        FixedOffset::west_opt(secs)
+    } else {
+        FixedOffset::east_opt(secs)
+    };
+    Ok(offset.ok_or_else(invalid)?)
+}
+
+/// some servers claim to report UTC timestamps while they're really
+/// in another timezone. `timezone` is then that real timezone.
+pub fn to_local_in_timezone(
+    timestamp: DateTime<Utc>,
+    timezone: Option<&str>,
+) -> Result<DateTime<Local>> {
+    Ok(match timezone {
+        Some(tz) => to_local(
+            timestamp - chrono::Duration::seconds(parse_timezone(tz)?.local_minus_utc() as i64),
+        ),
+        None => to_local(timestamp),
+    })
+}
+
+/// the [start, end) range of the day, to filter timestamps on.
+/// comparing timestamps and not naive dates makes sure that
+/// an event at 23:30 UTC lands on the right local day.
+pub fn day_bounds(day: Date<Local>) -> (DateTime<Local>, DateTime<Local>) {
+    (day.and_hms(0, 0, 0), day.succ().and_hms(0, 0, 0))
+}
+
+/// collapse events from the same provider with the same title
+/// (for instance the same redmine issue), happening within
+/// `window` of each other, into a single event.
+/// the events must be sorted by time.
+pub fn group_related_events(events: Vec<Event>, window: chrono::Duration) -> Vec<Event> {
+    // the group events, and the time of the latest event in each group
+    let mut groups: Vec<(Event, NaiveTime)> = vec![];
+    for event in events {
+        let group = groups.iter_mut().rev().find(|(g, last_time)| {
+            g.event_type_desc == event.event_type_desc
+                && g.event_contents_header == event.event_contents_header
+                && event.event_time - *last_time <= window
+        });
+        match group {
+            Some((g, last_time)) => {
+                *last_time = event.event_time;
+                g.event_contents_body =
+                    EventBody::merge(&g.event_contents_body, &event.event_contents_body);
+                g.event_duration = match (g.event_duration, event.event_duration) {
+                    (Some(a), Some(b)) => Some(a + b),
+                    (a, b) => a.or(b),
+                };
+            }
+            None => {
+                let time = event.event_time;
+                groups.push((event, time));
+            }
+        }
+    }
+    groups.into_iter().map(|(g, _)| g).collect()
+}
+
+// providers round timestamps differently (some to the minute, some
+// report the time the server indexed the event)
+const SAME_EVENT_TOLERANCE_SECS: i64 = 60;
+
+/// whether two events from different providers are the same event: same
+/// dedup key if both have one, otherwise close timestamps and same title.
+fn is_same_event(e1: &Event, e2: &Event) -> bool {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    // chrono 0.4.19 has no Duration::abs
+    let tolerance = chrono::Duration::seconds(SAME_EVENT_TOLERANCE_SECS);
+    let time_diff = e1.event_time - e2.event_time;
+    e1.event_type_desc != e2.event_type_desc
+        && match (&e1.dedup_key, &e2.dedup_key) {
+            (Some(k1), Some(k2)) => k1 == k2,
+            _ => {
+                -tolerance <= time_diff
+                    && time_diff <= tolerance
+                    && normalize(&e1.event_info) == normalize(&e2.event_info)
+            }
+        }
+}
+
+/// keep the event with the richer body, completing it with the other one
+fn merge_same_event(e1: Event, e2: Event) -> Event {
+    let (kept, other) = if e2.event_contents_body.as_plain_text().len()
+        > e1.event_contents_body.as_plain_text().len()
+    {
+        (e2, e1)
+    } else {
+        (e1, e2)
+    };
+    Event {
+        event_extra_details: kept.event_extra_details.or(other.event_extra_details),
+        event_duration: kept.event_duration.or(other.event_duration),
+        event_category: kept.event_category.or(other.event_category),
+        event_url: kept.event_url.or(other.event_url),
+        dedup_key: kept.dedup_key.or(other.dedup_key),
+        ..kept
+    }
+}
+
+/// collapse the events reported by several providers, for instance
+/// a commit seen both by git and as a redmine changeset, into one event.
+pub fn merge_events_across_sources(events: Vec<Event>) -> Vec<Event> {
+    let mut result: Vec<Event> = vec![];
+    for event in events {
+        match result.iter().position(|e| is_same_event(e, &event)) {
+            Some(idx) => {
+                let existing = result.remove(idx);
+                result.insert(idx, merge_same_event(existing, event));
+            }
+            None => result.push(event),
+        }
+    }
+    result
+}
+
+/// every available event provider, with the number of
+/// event sources configured for it
+pub fn event_providers_usage(config: &Config) -> Vec<(&'static str, usize)> {
+    get_event_providers()
+        .iter()
+        .map(|ep| (ep.name(), ep.get_config_names(config).len()))
+        .collect()
+}
+
+/// all the configured event sources, in the order chosen by the user.
+/// sources which were never reordered come last, by provider then by name.
+pub fn ordered_event_sources<'a>(
+    eps: &'a [Box<dyn EventProvider>],
+    config: &'a Config,
+) -> Vec<(&'a Box<dyn EventProvider>, &'a String)> {
+    let mut sources: Vec<(usize, &Box<dyn EventProvider>, &String)> = eps
+        .iter()
+        .enumerate()
+        .flat_map(|(ep_idx, ep)| {
+            ep.get_config_names(config)
                 .into_iter()
-                .map(move |cfg_name| (ep, cfg_name))
+                .map(move |cfg_name| (ep_idx, ep, cfg_name))
         })
         .collect();
+    sources.sort_by_key(|(ep_idx, _, cfg_name)| {
+        (
+            config
+                .sources_order
+                .iter()
+                .position(|n| n == *cfg_name)
+                .unwrap_or(usize::MAX),
+            *ep_idx,
+            (*cfg_name).clone(),
+        )
+    });
+    sources
+        .into_iter()
+        .map(|(_, ep, cfg_name)| (ep, cfg_name))
+        .collect()
+}
 
-    // use rayon's par_iter to fetch in parallel from multiple
-    // event sources -- it's not CPU bound, but some sources
-    // go to the network and parallelization helps a lot.
-    // maybe I should force the size of the rayon's thread pool:
-    // https://docs.rs/rayon/1.3.0/rayon/struct.ThreadPoolBuilder.html#method.build_global
-    // because I think currently rayon will tie it to the number
-    // of cores of the machine, but in our case it's really independent
-    // as the tasks are IO-bound. Possibly I should enforce let's say
-    // 3 threads always. But for now I'll leave the defaults.
-    let mut events: Vec<Event> = configs_to_fetch
-        .par_iter()
-        .map(|(ep, cfg_name)| {
-            let start_cfg = Instant::now();
-            let result = ep.get_events(&config, cfg_name, day).map_err(|err| {
-                Box::new(ProviderError::new(ep.name(), (*cfg_name).clone(), err))
-                    as Box<dyn std::error::Error + Send + Sync>
-            });
-            log::info!(
-                "Fetched events for {}/{} in {:?}",
-                cfg_name,
-                ep.name(),
-                start_cfg.elapsed()
+/// move an event source one step up or down in the sources order
+pub fn move_event_source(config: &mut Config, source_name: &str, up: bool) {
+    let eps = get_event_providers();
+    let mut names: Vec<String> = ordered_event_sources(&eps, config)
+        .into_iter()
+        .map(|(_, n)| n.clone())
+        .collect();
+    if let Some(idx) = names.iter().position(|n| n == source_name) {
+        let other_idx = if up {
+            idx.checked_sub(1)
+        } else {
+            Some(idx + 1).filter(|i| *i < names.len())
+        };
+        if let Some(other) = other_idx {
+            names.swap(idx, other);
+        }
+    }
+    config.sources_order = names;
+}
+
+/// lets slow event sources tell the UI where they're at,
+/// for instance the activity page they're fetching
+#[derive(Clone, Default)]
+pub struct FetchProgress(Option<Arc<dyn Fn(String) + Send + Sync>>);
+
+impl FetchProgress {
+    pub fn new(report: impl Fn(String) + Send + Sync + 'static) -> FetchProgress {
+        FetchProgress(Some(Arc::new(report)))
+    }
+
+    pub fn report(&self, progress: String) {
+        if let Some(report) = &self.0 {
+            report(progress);
+        }
+    }
+}
+
+impl fmt::Debug for FetchProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FetchProgress({})", self.0.is_some())
+    }
+}
+
+/// how we fetch the events this time, as opposed to the
+/// saved configuration of the event sources
+#[derive(Clone, Default, Debug)]
+pub struct FetchOptions {
+    // the user forced a refresh: don't read the caches
+    pub bypass_cache: bool,
+    // the user waits for the fetch in the GUI, so that event
+    // sources may ask them for a two-factor code
+    pub interactive: bool,
+    pub progress: FetchProgress,
+}
+
+/// the events for a day, merged from all the event sources,
+/// plus the errors for the event sources we couldn't fetch from
+pub struct FetchedEvents {
+    pub events: Vec<Event>,
+    pub errors: Vec<ProviderError>,
+}
+
+/// the events of today we got in the last fetch, so that the next
+/// refresh needs to get only the newer events
+#[derive(Serialize, Deserialize)]
+struct TodayEvents {
+    fetched_at: DateTime<Local>,
+    events: Vec<Event>,
+}
+
+// some events show up a little after their timestamp, for instance
+// while a server indexes them: we refetch the last minutes too
+const INCREMENTAL_FETCH_OVERLAP_MINS: i64 = 10;
+
+fn get_source_events(
+    ep: &dyn EventProvider,
+    config: &Config,
+    options: &FetchOptions,
+    config_name: &str,
+    day: Date<Local>,
+) -> Result<Vec<Event>> {
+    if day != Local::today() || !ep.supports_incremental_fetch() {
+        return ep.get_events(config, options, config_name, day);
+    }
+    let day_start = day.and_hms(0, 0, 0);
+    let cache_key = format!("{}_today_events", config_name);
+    let previous = if options.bypass_cache {
+        None
+    } else {
+        Config::get_cached_contents(ep, &cache_key, &day_start, None)?
+            .and_then(|json| serde_json::from_str::<TodayEvents>(&json).ok())
+            .filter(|t| t.fetched_at.date() == day)
+    };
+    let fetched_at = Local::now();
+    let events = match previous {
+        Some(previous)
+            if config
+                .cache_max_age(day)
+                .map_or(false, |age| previous.fetched_at >= fetched_at - age) =>
+        {
+            return Ok(previous.events);
+        }
+        Some(previous) => {
+            let since = (previous.fetched_at
+                - chrono::Duration::minutes(INCREMENTAL_FETCH_OVERLAP_MINS))
+            .max(day_start);
+            let mut events: Vec<Event> = previous
+                .events
+                .into_iter()
+                .filter(|e| e.event_time < since.time())
+                .collect();
+            events.append(&mut ep.get_events_since(config, options, config_name, since)?);
+            events
+        }
+        None => ep.get_events(config, options, config_name, day)?,
+    };
+    Config::write_to_cache(
+        ep,
+        &cache_key,
+        &serde_json::to_string(&TodayEvents {
+            fetched_at,
+            events: events.clone(),
+        })?,
+    )?;
+    Ok(events)
+}
+
+// the threads fetching from the event sources, across all the
+// fetches in progress (day, week, polling), and their count
+static FETCH_POOL: Mutex<Option<(u32, Arc<rayon::ThreadPool>)>> = Mutex::new(None);
+
+/// the thread pool for `max` concurrent fetches. fetches wait on the
+/// network, so we keep them out of rayon's global pool, which is tied
+/// to the number of cores and which we also use for the week view.
+fn fetch_pool(max: u32) -> Arc<rayon::ThreadPool> {
+    let max = max.max(1);
+    let mut pool = FETCH_POOL.lock().unwrap_or_else(|e| e.into_inner());
+    match &*pool {
+        // the user may have changed the limit since the previous fetch
+        Some((count, p)) if *count == max => p.clone(),
+        _ => {
+            let p = Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(max as usize)
+                    .thread_name(|i| format!("fetch-{}", i))
+                    .build()
+                    .expect("failed to start the fetch threads"),
             );
-            result
-        })
-        .collect::<Result<Vec<Vec<Event>>>>()?
+            *pool = Some((max, p.clone()));
+            p
+        }
+    }
+}
+
+pub fn get_all_events(config: Config, options: &FetchOptions, day: Date<Local>) -> FetchedEvents {
+    get_events_from_sources(config, options, day, None)
+}
+
+/// like get_all_events, but when `source` is given, only fetch from
+/// the event source of that name. the configuration is left untouched.
+pub fn get_events_from_sources(
+    config: Config,
+    options: &FetchOptions,
+    day: Date<Local>,
+    source: Option<&str>,
+) -> FetchedEvents {
+    let start = Instant::now();
+    // the user may have changed the limits since the previous fetch
+    super::ratelimit::set_host_limits(&config.host_rate_limits);
+    let eps = get_event_providers();
+    let configs_to_fetch: Vec<_> = ordered_event_sources(&eps, &config)
         .into_iter()
-        .flatten()
+        .filter(|(ep, cfg_name)| config.is_source_enabled(ep.name(), cfg_name))
+        .filter(|(_, cfg_name)| source.map_or(true, |s| s == cfg_name.as_str()))
         .collect();
+
+    // use rayon's par_iter to fetch in parallel from multiple
+    // event sources -- it's not CPU bound, but some sources
+    // go to the network and parallelization helps a lot.
+    // the tasks are IO-bound, so the size of the pool is the
+    // number of concurrent fetches the user allows, not the number of cores.
+    // an error in one event source must not prevent us from
+    // displaying the events from the other sources.
+    let results: Vec<std::result::Result<Vec<Event>, ProviderError>> =
+        fetch_pool(config.max_concurrent_fetches).install(|| {
+            configs_to_fetch
+                .par_iter()
+                .map(|(ep, cfg_name)| {
+                    let start_cfg = Instant::now();
+                    let result = get_source_events(ep.as_ref(), &config, options, cfg_name, day)
+                        .map_err(|err| ProviderError::new(ep.name(), (*cfg_name).clone(), err));
+                    log::info!(
+                        "Fetched events for {}/{} in {:?}",
+                        cfg_name,
+                        ep.name(),
+                        start_cfg.elapsed()
+                    );
+                    result
+                })
+                .collect()
+        });
+    let mut events = vec![];
+    let mut errors = vec![];
+    for result in results {
+        match result {
+            Ok(mut evts) => events.append(&mut evts),
+            Err(e) => errors.push(e),
+        }
+    }
+    // stable sort: events at the same time keep the event source order
     events.sort_by_key(|e| e.event_time);
     log::info!("Fetched all events for {} in {:?}", day, start.elapsed());
-    Ok(events)
+    FetchedEvents { events, errors }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// fetch the events for several days, in parallel
+pub fn get_all_events_for_days(
+    config: Config,
+    options: &FetchOptions,
+    days: Vec<Date<Local>>,
+) -> Vec<(Date<Local>, FetchedEvents)> {
+    days.into_par_iter()
+        .map(|day| (day, get_all_events(config.clone(), options, day)))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum WordWrapMode {
     WordWrap,
+    // wrap on words, but also within words when they don't
+    // fit in a line, for instance long urls
+    CharWrap,
+    // for instance for code, the body will scroll horizontally
     NoWordWrap,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum EventBody {
     PlainText(String),
     Markup(String, WordWrapMode),
+    // converted to markup for display
+    Markdown(String, WordWrapMode),
 }
 
 impl EventBody {
+    /// true if the body is to be displayed as markup (see as_markup)
     pub fn is_markup(&self) -> bool {
-        matches!(self, EventBody::Markup(_, _))
+        !matches!(self, EventBody::PlainText(_))
     }
 
+    /// the body as the provider gave it to us
     pub fn as_str(&self) -> &str {
         match self {
             EventBody::Markup(str, _) => str,
+            EventBody::Markdown(str, _) => str,
             EventBody::PlainText(str) => str,
         }
     }
 
-    pub fn is_word_wrap(&self) -> bool {
-        matches!(self, EventBody::Markup(_, WordWrapMode::WordWrap))
+    /// the body as pango markup
+    pub fn as_markup(&self) -> Cow<str> {
+        match self {
+            EventBody::Markup(str, _) => Cow::Borrowed(str),
+            EventBody::Markdown(str, _) => Cow::Owned(markdown_to_pango(str)),
+            EventBody::PlainText(str) => Cow::Owned(glib::markup_escape_text(str).to_string()),
+        }
+    }
+
+    /// how the body label should wrap, None for no wrapping
+    pub fn wrap_mode(&self) -> Option<pango::WrapMode> {
+        match self.word_wrap_mode() {
+            WordWrapMode::WordWrap => Some(pango::WrapMode::Word),
+            WordWrapMode::CharWrap => Some(pango::WrapMode::WordChar),
+            WordWrapMode::NoWordWrap => None,
+        }
+    }
+
+    fn word_wrap_mode(&self) -> WordWrapMode {
+        match self {
+            EventBody::PlainText(_) => WordWrapMode::CharWrap,
+            EventBody::Markup(_, mode) => mode.clone(),
+            EventBody::Markdown(_, mode) => mode.clone(),
+        }
+    }
+
+    /// concatenate two bodies, for grouped events
+    fn merge(a: &EventBody, b: &EventBody) -> EventBody {
+        match (a, b) {
+            (EventBody::PlainText(a), EventBody::PlainText(b)) => {
+                EventBody::PlainText(format!("{}\n\n{}", a, b))
+            }
+            _ => {
+                EventBody::Markup(
+                    format!("{}\n\n{}", a.as_markup(), b.as_markup()),
+                    // keep the mode which wraps the most
+                    match (a.word_wrap_mode(), b.word_wrap_mode()) {
+                        (WordWrapMode::CharWrap, _) | (_, WordWrapMode::CharWrap) => {
+                            WordWrapMode::CharWrap
+                        }
+                        (WordWrapMode::WordWrap, _) | (_, WordWrapMode::WordWrap) => {
+                            WordWrapMode::WordWrap
+                        }
+                        _ => WordWrapMode::NoWordWrap,
+                    },
+                )
+            }
+        }
+    }
+
+    /// the visible text of the body, without markup.
+    /// links are rendered as "text (url)".
+    pub fn as_plain_text(&self) -> String {
+        match self {
+            EventBody::PlainText(str) => str.clone(),
+            _ => {
+                let mut result = String::new();
+                Self::append_plain_text(
+                    &mut result,
+                    *scraper::Html::parse_fragment(&self.as_markup()).root_element(),
+                );
+                result
+            }
+        }
+    }
+
+    /// the target of the first link in the body, for instance
+    /// the url of the issue. None for plain text bodies.
+    pub fn first_link(&self) -> Option<String> {
+        match self {
+            EventBody::PlainText(_) => None,
+            _ => {
+                let sel = scraper::Selector::parse("a[href]").unwrap();
+                scraper::Html::parse_fragment(&self.as_markup())
+                    .select(&sel)
+                    .next()
+                    .and_then(|a| a.value().attr("href"))
+                    .map(|href| href.to_string())
+            }
+        }
+    }
+
+    fn append_plain_text(result: &mut String, node: ego_tree::NodeRef<scraper::Node>) {
+        for child in node.children() {
+            match child.value() {
+                scraper::Node::Text(text) => result.push_str(text),
+                scraper::Node::Element(elt) if elt.name() == "a" => {
+                    let mut link_text = String::new();
+                    Self::append_plain_text(&mut link_text, child);
+                    match elt.attr("href") {
+                        Some(href) if href != link_text => {
+                            result.push_str(&format!("{} ({})", link_text, href))
+                        }
+                        _ => result.push_str(&link_text),
+                    }
+                }
+                _ => Self::append_plain_text(result, child),
+            }
+        }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// convert markdown to pango markup. pango supports only a few
+/// tags, so we stick to emphasis, code, links and lists.
+fn markdown_to_pango(markdown: &str) -> String {
+    use pulldown_cmark::{Event as MdEvent, Parser, Tag};
+    let mut result = String::new();
+    // the next number for ordered lists, None for bullet lists
+    let mut lists: Vec<Option<u64>> = vec![];
+    for md_event in Parser::new(markdown) {
+        match md_event {
+            MdEvent::Start(Tag::Strong) | MdEvent::Start(Tag::Heading(_)) => result.push_str("<b>"),
+            MdEvent::End(Tag::Strong) => result.push_str("</b>"),
+            MdEvent::End(Tag::Heading(_)) => result.push_str("</b>\n\n"),
+            MdEvent::Start(Tag::Emphasis) => result.push_str("<i>"),
+            MdEvent::End(Tag::Emphasis) => result.push_str("</i>"),
+            MdEvent::Start(Tag::CodeBlock(_)) => result.push_str("<tt>"),
+            MdEvent::End(Tag::CodeBlock(_)) => result.push_str("</tt>\n"),
+            MdEvent::Start(Tag::Link(_, url, _)) => {
+                result.push_str(&format!("<a href=\"{}\">", glib::markup_escape_text(&url)))
+            }
+            MdEvent::End(Tag::Link(_, _, _)) => result.push_str("</a>"),
+            MdEvent::Start(Tag::List(start)) => lists.push(start),
+            MdEvent::End(Tag::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    result.push('\n');
+                }
+            }
+            MdEvent::Start(Tag::Item) => {
+                // nested lists start within the parent item
+                if !result.is_empty() && !result.ends_with('\n') {
+                    result.push('\n');
+                }
+                result.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        result.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => result.push_str("• "),
+                }
+            }
+            MdEvent::End(Tag::Item) if !result.ends_with('\n') => result.push('\n'),
+            // in lists, the items take care of the line breaks
+            MdEvent::End(Tag::Paragraph) if lists.is_empty() => result.push_str("\n\n"),
+            MdEvent::Code(code) => {
+                result.push_str(&format!("<tt>{}</tt>", glib::markup_escape_text(&code)))
+            }
+            MdEvent::Text(text) | MdEvent::Html(text) => {
+                result.push_str(&glib::markup_escape_text(&text))
+            }
+            MdEvent::SoftBreak | MdEvent::HardBreak => result.push('\n'),
+            _ => {}
+        }
+    }
+    result.trim_end().to_string()
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Event {
+    #[serde(deserialize_with = "deserialize_event_type_desc")]
     pub event_type_desc: &'static str,
     pub event_type_icon: Icon,
     pub event_time: NaiveTime,
@@ -193,6 +989,63 @@ pub struct Event {
     pub event_contents_header: String,
     pub event_contents_body: EventBody,
     pub event_extra_details: Option<String>,
+    // time tracked for that event, for instance a worklog
+    #[serde(default, with = "serde_duration_secs")]
+    pub event_duration: Option<chrono::Duration>,
+    // for instance "commit", "issue", "meeting". displayed color-coded.
+    #[serde(default)]
+    pub event_category: Option<String>,
+    // link to the event in the browser
+    #[serde(default)]
+    pub event_url: Option<String>,
+    // identifies the same event reported by different providers,
+    // for instance "commit:<sha>" for a git commit and a redmine changeset
+    #[serde(default)]
+    pub dedup_key: Option<String>,
+}
+
+// the event types we had to leak, so that we leak each of them only once
+static OTHER_EVENT_TYPES: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+// the event type is nearly always the provider name, so we can
+// point to the static string. We leak the few others, once.
+fn deserialize_event_type_desc<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<&'static str, D::Error> {
+    let desc = <String as serde::Deserialize>::deserialize(deserializer)?;
+    if let Some(name) = get_event_providers()
+        .iter()
+        .map(|ep| ep.name())
+        .find(|name| *name == desc)
+    {
+        return Ok(name);
+    }
+    let mut others = OTHER_EVENT_TYPES.lock().unwrap_or_else(|e| e.into_inner());
+    let others = others.get_or_insert_with(HashSet::new);
+    Ok(match others.get(desc.as_str()) {
+        Some(other) => *other,
+        None => {
+            let other: &'static str = Box::leak(desc.into_boxed_str());
+            others.insert(other);
+            other
+        }
+    })
+}
+
+mod serde_duration_secs {
+    pub fn serialize<S: serde::Serializer>(
+        duration: &Option<chrono::Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&duration.map(|d| d.num_seconds()), serializer)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<chrono::Duration>, D::Error> {
+        let secs: Option<i64> = serde::Deserialize::deserialize(deserializer)?;
+        Ok(secs.map(chrono::Duration::seconds))
+    }
 }
 
 impl Event {
@@ -213,6 +1066,499 @@ impl Event {
             event_contents_header,
             event_contents_body,
             event_extra_details,
+            event_duration: None,
+            event_category: None,
+            event_url: None,
+            dedup_key: None,
+        }
+    }
+
+    pub fn with_duration(self, event_duration: chrono::Duration) -> Event {
+        Event {
+            event_duration: Some(event_duration),
+            ..self
+        }
+    }
+
+    pub fn with_category(self, event_category: &str) -> Event {
+        Event {
+            event_category: Some(event_category.to_string()),
+            ..self
         }
     }
+
+    pub fn with_url(self, event_url: &str) -> Event {
+        Event {
+            event_url: Some(event_url.to_string()),
+            ..self
+        }
+    }
+
+    pub fn with_dedup_key(self, dedup_key: &str) -> Event {
+        Event {
+            dedup_key: Some(dedup_key.to_string()),
+            ..self
+        }
+    }
+
+    /// case-insensitive search in the visible contents of the event.
+    /// `search_lower` must be lowercase.
+    pub fn matches_search(&self, search_lower: &str) -> bool {
+        let contains = |s: &str| s.to_lowercase().contains(search_lower);
+        search_lower.is_empty()
+            || contains(&self.event_info)
+            || contains(&self.event_contents_header)
+            || self.event_extra_details.as_deref().map_or(false, contains)
+            || contains(&self.event_contents_body.as_plain_text())
+    }
+
+    /// the link to open the event in the browser, if any.
+    /// some providers still embed the link in the markup body.
+    pub fn link(&self) -> Option<String> {
+        self.event_url
+            .clone()
+            .or_else(|| self.event_contents_body.first_link())
+    }
+}
+
+/// the events of `current` which were not in `previous`,
+/// for instance to notify the user of new activity.
+pub fn new_events<'a>(previous: &[Event], current: &'a [Event]) -> Vec<&'a Event> {
+    let key = |e: &Event| {
+        (
+            e.event_type_desc,
+            e.event_time,
+            e.event_contents_header.clone(),
+        )
+    };
+    let previous_keys: HashSet<_> = previous.iter().map(key).collect();
+    current
+        .iter()
+        .filter(|e| !previous_keys.contains(&key(e)))
+        .collect()
+}
+
+#[test]
+fn it_moves_event_sources() {
+    let mut config = Config::default_config();
+    for name in &["a", "b", "c"] {
+        config.rss_feed.insert(
+            name.to_string(),
+            super::rssfeed::RssFeedConfig { feed_urls: vec![] },
+        );
+    }
+    let names = |config: &Config| {
+        let eps = get_event_providers();
+        ordered_event_sources(&eps, config)
+            .into_iter()
+            .map(|(_, n)| n.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["a", "b", "c"], names(&config));
+    move_event_source(&mut config, "c", true);
+    assert_eq!(vec!["a", "c", "b"], names(&config));
+    move_event_source(&mut config, "a", true);
+    assert_eq!(vec!["a", "c", "b"], names(&config));
+    move_event_source(&mut config, "a", false);
+    assert_eq!(vec!["c", "a", "b"], names(&config));
+}
+
+#[test]
+fn it_finds_new_events() {
+    let event = |time: NaiveTime, header: &str| {
+        Event::new(
+            "Git",
+            Icon::CODE_BRANCH,
+            time,
+            header.to_string(),
+            header.to_string(),
+            EventBody::PlainText("".to_string()),
+            None,
+        )
+    };
+    let previous = vec![event(NaiveTime::from_hms(9, 0, 0), "fix the build")];
+    let current = vec![
+        event(NaiveTime::from_hms(9, 0, 0), "fix the build"),
+        event(NaiveTime::from_hms(11, 0, 0), "add tests"),
+    ];
+    assert_eq!(
+        vec!["add tests"],
+        new_events(&previous, &current)
+            .iter()
+            .map(|e| e.event_contents_header.as_str())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn it_extracts_the_first_link_of_the_body() {
+    assert_eq!(
+        Some("http://redmine/issues/12".to_string()),
+        EventBody::Markup(
+            "<a href=\"http://redmine/issues/12\">Open</a>\n<a href=\"http://other\">x</a>"
+                .to_string(),
+            WordWrapMode::WordWrap
+        )
+        .first_link()
+    );
+    assert_eq!(
+        None,
+        EventBody::PlainText("http://redmine/issues/12".to_string()).first_link()
+    );
+}
+
+#[test]
+fn it_searches_events_case_insensitively() {
+    let event = Event::new(
+        "Redmine",
+        Icon::TASKS,
+        NaiveTime::from_hms(14, 30, 0),
+        "Issue edited".to_string(),
+        "Crash on startup".to_string(),
+        EventBody::Markup(
+            "<a href=\"http://redmine/issues/1234\">Open</a>".to_string(),
+            WordWrapMode::WordWrap,
+        ),
+        None,
+    );
+    assert!(event.matches_search(""));
+    assert!(event.matches_search("crash"));
+    assert!(event.matches_search("1234"));
+    assert!(!event.matches_search("git"));
+}
+
+#[test]
+fn it_converts_markup_to_plain_text() {
+    assert_eq!(
+        "Open in the browser (http://redmine/issues/12)\n\nfixed & <tested>",
+        EventBody::Markup(
+            "<a href=\"http://redmine/issues/12\">Open in the browser</a>\n\n<b>fixed</b> &amp; &lt;tested&gt;"
+                .to_string(),
+            WordWrapMode::WordWrap
+        )
+        .as_plain_text()
+    );
+    assert_eq!(
+        "<b>as is</b>",
+        EventBody::PlainText("<b>as is</b>".to_string()).as_plain_text()
+    );
+}
+
+#[test]
+fn it_classifies_provider_errors() {
+    let error =
+        |err: Box<dyn Error + Send + Sync>| ProviderError::new("Redmine", "work".to_string(), err);
+    assert_eq!(
+        ErrorKind::Auth,
+        error(Box::new(EventError::Auth(
+            "Redmine login failed".to_string()
+        )))
+        .kind()
+    );
+    assert_eq!(
+        "Redmine (work): Redmine login failed (check your credentials)",
+        error(Box::new(EventError::Auth(
+            "Redmine login failed".to_string()
+        )))
+        .to_string_with_hint()
+    );
+    assert_eq!(
+        ErrorKind::Parse,
+        error(Box::new(serde_json::from_str::<u32>("x").unwrap_err())).kind()
+    );
+    assert_eq!(ErrorKind::Other, error("oops".into()).kind());
+    assert_eq!(
+        "Redmine (work): oops",
+        error("oops".into()).to_string_with_hint()
+    );
+}
+
+#[test]
+fn it_limits_the_concurrent_fetches() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let running = AtomicUsize::new(0);
+    let max_running = AtomicUsize::new(0);
+    fetch_pool(2).install(|| {
+        (0..6).into_par_iter().for_each(|_| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+        })
+    });
+    assert!(max_running.load(Ordering::SeqCst) <= 2);
+    assert_eq!(1, fetch_pool(0).current_num_threads());
+}
+
+#[test]
+fn it_sums_event_durations() {
+    let build_event = |duration: Option<chrono::Duration>| {
+        let event = Event::new(
+            "Jira",
+            Icon::TASKS,
+            NaiveTime::from_hms(14, 30, 0),
+            "info".to_string(),
+            "header".to_string(),
+            EventBody::PlainText("".to_string()),
+            None,
+        );
+        match duration {
+            Some(d) => event.with_duration(d),
+            None => event,
+        }
+    };
+    let events = vec![
+        build_event(Some(chrono::Duration::minutes(90))),
+        build_event(None),
+        build_event(Some(chrono::Duration::minutes(45))),
+    ];
+    assert_eq!(
+        "Total: 2h 15m",
+        format_total_duration(&events.iter().collect::<Vec<_>>())
+    );
+}
+
+#[test]
+fn it_summarizes_a_range_of_events() {
+    let build_event = |h: u32, m: u32, duration_mins: Option<i64>| {
+        let event = Event::new(
+            "Toggl",
+            Icon::CLOCK,
+            NaiveTime::from_hms(h, m, 0),
+            "info".to_string(),
+            "header".to_string(),
+            EventBody::PlainText("".to_string()),
+            None,
+        );
+        match duration_mins {
+            Some(d) => event.with_duration(chrono::Duration::minutes(d)),
+            None => event,
+        }
+    };
+    let events = vec![
+        build_event(9, 0, Some(60)),
+        build_event(10, 15, None),
+        build_event(11, 0, Some(30)),
+    ];
+    assert_eq!(
+        "3 event(s), 09:00–11:30\nTotal: 1h 30m",
+        format_range_summary(&events.iter().collect::<Vec<_>>())
+    );
+}
+
+#[test]
+fn it_groups_related_events() {
+    let build_event = |h: u32, m: u32, title: &str| {
+        Event::new(
+            "Redmine",
+            Icon::TASKS,
+            NaiveTime::from_hms(h, m, 0),
+            title.to_string(),
+            title.to_string(),
+            EventBody::PlainText(format!("{}:{}", h, m)),
+            None,
+        )
+    };
+    let grouped = group_related_events(
+        vec![
+            build_event(10, 0, "Bug #1"),
+            build_event(10, 5, "Bug #2"),
+            build_event(10, 10, "Bug #1"),
+            build_event(10, 20, "Bug #1"),
+            build_event(11, 0, "Bug #1"),
+        ],
+        chrono::Duration::minutes(15),
+    );
+    assert_eq!(
+        vec![
+            (NaiveTime::from_hms(10, 0, 0), "10:0\n\n10:10\n\n10:20"),
+            (NaiveTime::from_hms(10, 5, 0), "10:5"),
+            (NaiveTime::from_hms(11, 0, 0), "11:0"),
+        ],
+        grouped
+            .iter()
+            .map(|e| (e.event_time, e.event_contents_body.as_str()))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn it_merges_the_same_event_across_sources() {
+    let commit = Event::new(
+        "Git",
+        Icon::CODE_BRANCH,
+        NaiveTime::from_hms(10, 15, 42),
+        "Fix the build".to_string(),
+        "Fix the build".to_string(),
+        EventBody::PlainText("master\n\nsrc/main.rs | 2 +-".to_string()),
+        Some("1 file changed".to_string()),
+    )
+    .with_dedup_key("commit:abc123");
+    let changeset = Event::new(
+        "Redmine",
+        Icon::CODE_BRANCH,
+        NaiveTime::from_hms(10, 15, 0),
+        "Revision abc123".to_string(),
+        "Revision abc123".to_string(),
+        EventBody::PlainText("Fix the build".to_string()),
+        None,
+    )
+    .with_url("https://redmine/revisions/abc123")
+    .with_dedup_key("commit:abc123");
+    let build_event = |desc: &'static str, m: u32, title: &str| {
+        Event::new(
+            desc,
+            Icon::TASKS,
+            NaiveTime::from_hms(11, m, 0),
+            title.to_string(),
+            title.to_string(),
+            EventBody::PlainText("".to_string()),
+            None,
+        )
+    };
+    let merged = merge_events_across_sources(vec![
+        commit.clone(),
+        changeset,
+        build_event("Jira", 0, "Release  1.0"),
+        build_event("Gitlab", 0, "release 1.0"),
+        build_event("Jira", 30, "Release 1.0"),
+        build_event("Gitlab", 31, "Release 1.0"),
+        build_event("Gitlab", 32, "Release 1.0"),
+    ]);
+    assert_eq!(
+        vec![
+            ("Git", "Fix the build"),
+            ("Jira", "Release  1.0"),
+            ("Jira", "Release 1.0"),
+            ("Gitlab", "Release 1.0"),
+        ],
+        merged
+            .iter()
+            .map(|e| (e.event_type_desc, e.event_info.as_str()))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(commit.event_time, merged[0].event_time);
+    assert_eq!(
+        Some("https://redmine/revisions/abc123".to_string()),
+        merged[0].event_url
+    );
+}
+
+#[test]
+fn it_formats_provider_errors() {
+    assert_eq!(
+        "Redmine (work): login form not found",
+        ProviderError::new("Redmine", "work".to_string(), "login form not found".into())
+            .to_string()
+    );
+}
+
+#[test]
+fn it_maps_word_wrap_modes() {
+    assert_eq!(
+        Some(pango::WrapMode::WordChar),
+        EventBody::PlainText("".to_string()).wrap_mode()
+    );
+    assert_eq!(
+        Some(pango::WrapMode::Word),
+        EventBody::Markup("".to_string(), WordWrapMode::WordWrap).wrap_mode()
+    );
+    assert_eq!(
+        Some(pango::WrapMode::WordChar),
+        EventBody::Markup("".to_string(), WordWrapMode::CharWrap).wrap_mode()
+    );
+    assert_eq!(
+        None,
+        EventBody::Markup("".to_string(), WordWrapMode::NoWordWrap).wrap_mode()
+    );
+}
+
+#[test]
+fn it_converts_markdown_to_pango() {
+    assert_eq!(
+        "Fix the <b>build</b> with <i>care</i>\n\n• use <tt>cargo &amp; co</tt>\n• see <a href=\"https://example.com?a=1&amp;b=2\">the doc</a>\n  1. nested",
+        markdown_to_pango(
+            "Fix the **build** with _care_\n\n- use `cargo & co`\n- see [the doc](https://example.com?a=1&b=2)\n  1. nested"
+        )
+    );
+}
+
+#[test]
+fn it_gets_the_plain_text_of_markdown_bodies() {
+    let body = EventBody::Markdown(
+        "see [the doc](https://example.com)".to_string(),
+        WordWrapMode::WordWrap,
+    );
+    assert!(body.is_markup());
+    assert_eq!("see the doc (https://example.com)", body.as_plain_text());
+    assert_eq!(Some("https://example.com".to_string()), body.first_link());
+}
+
+#[test]
+fn it_parses_timezone_overrides() {
+    assert_eq!(FixedOffset::east(0), parse_timezone("UTC").unwrap());
+    assert_eq!(
+        FixedOffset::east(2 * 3600),
+        parse_timezone("+02:00").unwrap()
+    );
+    assert_eq!(
+        FixedOffset::west(5 * 3600 + 30 * 60),
+        parse_timezone("-0530").unwrap()
+    );
+    assert!(parse_timezone("Europe/Paris").is_err());
+    assert!(parse_timezone("+99:00").is_err());
+}
+
+#[test]
+fn it_applies_timezone_overrides() {
+    let timestamp = Utc.ymd(2020, 2, 25).and_hms(23, 30, 0);
+    assert_eq!(
+        Utc.ymd(2020, 2, 25).and_hms(21, 30, 0),
+        to_local_in_timezone(timestamp, Some("+02:00")).unwrap()
+    );
+    assert_eq!(timestamp, to_local_in_timezone(timestamp, None).unwrap());
+}
+
+#[test]
+fn it_round_trips_events_through_json() {
+    let events = vec![
+        Event::new(
+            "Redmine",
+            Icon::TASKS,
+            NaiveTime::from_hms(10, 15, 30),
+            "Bug #12".to_string(),
+            "Fix the <login> page".to_string(),
+            EventBody::Markup("<b>done</b>".to_string(), WordWrapMode::CharWrap),
+            Some("Issue".to_string()),
+        )
+        .with_url("https://redmine/issues/12")
+        .with_duration(chrono::Duration::minutes(90)),
+        Event::new(
+            "My custom source",
+            Icon::COMMENT_DOTS,
+            NaiveTime::from_hms(16, 0, 0),
+            "notes".to_string(),
+            "notes".to_string(),
+            EventBody::Markdown("**bold**".to_string(), WordWrapMode::NoWordWrap),
+            None,
+        ),
+    ];
+    let json = serde_json::to_string(&events).unwrap();
+    // icons are serialized by name, not as the icon data
+    assert!(json.contains(&format!("\"{}\"", Icon::TASKS.name())));
+    assert_eq!(events, serde_json::from_str::<Vec<Event>>(&json).unwrap());
+}
+
+#[test]
+fn it_reads_events_without_the_newer_fields() {
+    // as in the caches written by older versions
+    let json = r#"{"event_type_desc": "My old source", "event_type_icon": "tasks-symbolic",
+        "event_time": "10:15:00", "event_info": "Bug #12", "event_contents_header": "Bug #12",
+        "event_contents_body": {"PlainText": "fixed"}, "event_extra_details": null}"#;
+    let event = serde_json::from_str::<Event>(json).unwrap();
+    assert_eq!(None, event.event_duration);
+    assert_eq!(None, event.event_url);
+    // the unknown event type is leaked only once
+    let again = serde_json::from_str::<Event>(json).unwrap();
+    assert!(std::ptr::eq(event.event_type_desc, again.event_type_desc));
 }
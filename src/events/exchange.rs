@@ -0,0 +1,381 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use std::collections::HashMap;
+
+// Exchange Web Services, we use the FindItem operation with a
+// CalendarView: the server expands the recurring appointments for us.
+// https://docs.microsoft.com/en-us/exchange/client-developer/web-service-reference/finditem-operation-calendar-item
+// We only support basic authentication: reqwest doesn't implement NTLM.
+// The on-premise exchange servers typically enable basic auth over https.
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
+pub struct ExchangeConfig {
+    pub server_url: String,
+    pub username: String,
+    pub password: String,
+}
+
+pub struct Exchange;
+const SERVER_URL_KEY: &str = "EWS URL";
+const USERNAME_KEY: &str = "Username";
+const PASSWORD_KEY: &str = "Password";
+
+impl Exchange {
+    fn config_values(
+        exchange_config: &ExchangeConfig,
+        password: String,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (SERVER_URL_KEY, exchange_config.server_url.to_string()),
+            (USERNAME_KEY, exchange_config.username.to_string()),
+            (PASSWORD_KEY, password),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_password(config_name: &str, exchange_config: &ExchangeConfig) -> Result<String> {
+        Config::read_secret(
+            &Exchange,
+            config_name,
+            &exchange_config.username,
+            &exchange_config.password,
+        )
+    }
+
+    fn find_item_request(day_start: DateTime<Local>, next_day_start: DateTime<Local>) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"
+               xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types"
+               xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages">
+  <soap:Header>
+    <t:RequestServerVersion Version="Exchange2010_SP2"/>
+  </soap:Header>
+  <soap:Body>
+    <m:FindItem Traversal="Shallow">
+      <m:ItemShape>
+        <t:BaseShape>IdOnly</t:BaseShape>
+        <t:AdditionalProperties>
+          <t:FieldURI FieldURI="item:Subject"/>
+          <t:FieldURI FieldURI="calendar:Start"/>
+          <t:FieldURI FieldURI="calendar:End"/>
+          <t:FieldURI FieldURI="calendar:IsAllDayEvent"/>
+          <t:FieldURI FieldURI="calendar:Location"/>
+          <t:FieldURI FieldURI="calendar:Organizer"/>
+          <t:FieldURI FieldURI="item:DisplayTo"/>
+          <t:FieldURI FieldURI="item:DisplayCc"/>
+        </t:AdditionalProperties>
+      </m:ItemShape>
+      <m:CalendarView StartDate="{}" EndDate="{}"/>
+      <m:ParentFolderIds>
+        <t:DistinguishedFolderId Id="calendar"/>
+      </m:ParentFolderIds>
+    </m:FindItem>
+  </soap:Body>
+</soap:Envelope>"#,
+            day_start.with_timezone(&Utc).to_rfc3339(),
+            next_day_start.with_timezone(&Utc).to_rfc3339()
+        )
+    }
+
+    fn fetch_calendar_xml(
+        config_name: &str,
+        exchange_config: &ExchangeConfig,
+        day: Date<Local>,
+    ) -> Result<String> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let password = Self::read_password(config_name, exchange_config)?;
+        let day_start = day.and_hms(0, 0, 0);
+        let request_body =
+            Self::find_item_request(day_start, day_start + chrono::Duration::days(1));
+        let xml = http::send_with_retry(|| {
+            client
+                .post(&exchange_config.server_url)
+                .basic_auth(&exchange_config.username, Some(&password))
+                .header("Content-Type", "text/xml; charset=utf-8")
+                .body(request_body.clone())
+        })?
+        .text()?;
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Exchange, &cache_key, &xml)?;
+        Ok(xml)
+    }
+
+    fn child_text<'a>(node: &roxmltree::Node<'a, '_>, tag_name: &str) -> Option<&'a str> {
+        node.children()
+            .find(|c| c.has_tag_name(tag_name))
+            .and_then(|c| c.text())
+    }
+
+    fn parse_date(date_str: Option<&str>) -> Option<DateTime<Local>> {
+        date_str
+            .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+            .map(|d| d.with_timezone(&Local))
+    }
+
+    fn parse_calendar_items(xml: &str) -> Result<Vec<Event>> {
+        let doc = roxmltree::Document::parse(xml)?;
+        // EWS reports errors with a success HTTP status
+        if let Some(error) = doc
+            .descendants()
+            .find(|n| n.has_tag_name("ResponseCode"))
+            .and_then(|n| n.text())
+            .filter(|c| *c != "NoError")
+        {
+            let message = doc
+                .descendants()
+                .find(|n| n.has_tag_name("MessageText"))
+                .and_then(|n| n.text())
+                .unwrap_or("");
+            return Err(format!("Exchange error: {} {}", error, message).into());
+        }
+        Ok(doc
+            .descendants()
+            .filter(|n| n.has_tag_name("CalendarItem"))
+            .filter_map(|item| {
+                let subject = Self::child_text(&item, "Subject").unwrap_or("");
+                let start = Self::parse_date(Self::child_text(&item, "Start"))?;
+                let end = Self::parse_date(Self::child_text(&item, "End"));
+                let all_day = Self::child_text(&item, "IsAllDayEvent") == Some("true");
+                let organizer = item
+                    .children()
+                    .find(|c| c.has_tag_name("Organizer"))
+                    .and_then(|o| o.descendants().find(|c| c.has_tag_name("Name")))
+                    .and_then(|n| n.text());
+                let attendees = [
+                    Self::child_text(&item, "DisplayTo"),
+                    Self::child_text(&item, "DisplayCc"),
+                ]
+                .iter()
+                .flatten()
+                .filter(|a| !a.is_empty())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("; ");
+                let extra_info = if all_day {
+                    Some("All day".to_string())
+                } else {
+                    end.map(|e| {
+                        let duration = e - start;
+                        format!(
+                            "End: {}; duration: {}:{:02}",
+                            e.format("%H:%M"),
+                            duration.num_hours(),
+                            duration.num_minutes() % 60
+                        )
+                    })
+                };
+                let mut body = vec![];
+                if let Some(location) = Self::child_text(&item, "Location") {
+                    body.push(format!("Location: {}", location));
+                }
+                if let Some(organizer) = organizer {
+                    body.push(format!("Organizer: {}", organizer));
+                }
+                if !attendees.is_empty() {
+                    body.push(format!("Attendees: {}", attendees));
+                }
+                let event = Event::new(
+                    "Exchange",
+                    if all_day {
+                        Icon::CALENDAR_DAY
+                    } else {
+                        Icon::CALENDAR_ALT
+                    },
+                    start.time(),
+                    subject.to_string(),
+                    subject.to_string(),
+                    EventBody::PlainText(body.join("\n")),
+                    extra_info,
+                );
+                Some(match end {
+                    Some(e) if !all_day => event.with_duration(e - start),
+                    _ => event,
+                })
+            })
+            .collect())
+    }
+}
+
+impl EventProvider for Exchange {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                ConfigType::Text("https://outlook.office365.com/EWS/Exchange.asmx"),
+            ),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (PASSWORD_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Exchange"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CALENDAR_ALT
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.exchange.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let exchange_config = &config.exchange[config_name];
+        Self::config_values(
+            exchange_config,
+            Self::read_password(config_name, exchange_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.exchange[config_name], "".to_string())
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let username = config_values.remove(USERNAME_KEY).unwrap();
+        let password = Config::store_secret(
+            &Exchange,
+            &config_name,
+            &username,
+            config_values.remove(PASSWORD_KEY).unwrap(),
+        );
+        config.exchange.insert(
+            config_name,
+            ExchangeConfig {
+                server_url: config_values.remove(SERVER_URL_KEY).unwrap(),
+                username,
+                password,
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.exchange.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.exchange[&config_name];
+            Config::remove_replaced_secret(
+                &Exchange,
+                old_config_name,
+                &old.username,
+                &old.password,
+                &config_name,
+                &new.username,
+                &new.password,
+            );
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(exchange_config) = config.exchange.remove(&config_name) {
+            Config::remove_secret(
+                &Exchange,
+                &config_name,
+                &exchange_config.username,
+                &exchange_config.password,
+            );
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let exchange_config = &config.exchange[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let xml = match config.get_cached_day_contents(options, &Exchange, &cache_key, day)? {
+            Some(x) => x,
+            None => Self::fetch_calendar_xml(config_name, exchange_config, day)?,
+        };
+        let mut result = Self::parse_calendar_items(&xml)?;
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_parses_ews_calendar_items() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/">
+  <s:Body>
+    <m:FindItemResponse xmlns:m="http://schemas.microsoft.com/exchange/services/2006/messages"
+                        xmlns:t="http://schemas.microsoft.com/exchange/services/2006/types">
+      <m:ResponseMessages>
+        <m:FindItemResponseMessage ResponseClass="Success">
+          <m:ResponseCode>NoError</m:ResponseCode>
+          <m:RootFolder TotalItemsInView="1" IncludesLastItemInRange="true">
+            <t:Items>
+              <t:CalendarItem>
+                <t:Subject>Sprint planning</t:Subject>
+                <t:Start>2020-02-25T09:00:00Z</t:Start>
+                <t:End>2020-02-25T10:30:00Z</t:End>
+                <t:IsAllDayEvent>false</t:IsAllDayEvent>
+                <t:Location>Room 1</t:Location>
+                <t:Organizer><t:Mailbox><t:Name>Jane Doe</t:Name></t:Mailbox></t:Organizer>
+                <t:DisplayTo>John Doe; Jane Doe</t:DisplayTo>
+              </t:CalendarItem>
+            </t:Items>
+          </m:RootFolder>
+        </m:FindItemResponseMessage>
+      </m:ResponseMessages>
+    </m:FindItemResponse>
+  </s:Body>
+</s:Envelope>"#;
+    let events = Exchange::parse_calendar_items(xml).unwrap();
+    assert_eq!(1, events.len());
+    assert_eq!("Sprint planning", events[0].event_info);
+    assert_eq!(
+        Some(chrono::Duration::minutes(90)),
+        events[0].event_duration
+    );
+    assert_eq!(
+        EventBody::PlainText(
+            "Location: Room 1\nOrganizer: Jane Doe\nAttendees: John Doe; Jane Doe".to_string()
+        ),
+        events[0].event_contents_body
+    );
+}
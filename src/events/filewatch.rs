@@ -0,0 +1,43 @@
+// the local event sources (ical files, git repositories) change
+// without any network involved: we watch their files to reload
+// the displayed day when they change.
+use super::events::Result;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// a rebase touches many files in a row, we want a single reload
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(1);
+
+/// call `on_change` (from a background thread) when one of the `paths`
+/// changes. Folders are not watched recursively.
+/// Stop watching by dropping the watcher.
+/// None if there is nothing to watch.
+pub fn watch_paths(
+    paths: &[PathBuf],
+    on_change: impl Fn() + Send + 'static,
+) -> Result<Option<RecommendedWatcher>> {
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::watcher(sender, DEBOUNCE_DELAY)?;
+    for path in paths {
+        watcher.watch(path, RecursiveMode::NonRecursive)?;
+    }
+    // the loop ends when the watcher gets dropped, along with the sender
+    std::thread::spawn(move || {
+        for event in receiver {
+            match event {
+                // the notices come before the debounced events
+                DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => {}
+                DebouncedEvent::Error(e, path) => {
+                    log::warn!("Error watching {:?}: {}", path, e);
+                }
+                _ => on_change(),
+            }
+        }
+    });
+    Ok(Some(watcher))
+}
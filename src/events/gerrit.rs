@@ -0,0 +1,324 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://gerrit-review.googlesource.com/Documentation/rest-api.html
+// the responses are prefixed with a magic string to prevent
+// cross-site script inclusion, which must be stripped before parsing.
+const XSSI_PREFIX: &str = ")]}'";
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GerritConfig {
+    pub server_url: String,
+    pub username: String,
+    pub http_password: String,
+}
+
+pub struct Gerrit;
+const SERVER_URL_KEY: &str = "Server URL";
+const USERNAME_KEY: &str = "Username";
+const HTTP_PASSWORD_KEY: &str = "HTTP Password";
+
+#[derive(Deserialize, Serialize, Debug)]
+struct GerritAccount {
+    _account_id: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct GerritChange {
+    project: String,
+    subject: String,
+    _number: u64,
+    #[serde(default)]
+    messages: Vec<GerritMessage>,
+    #[serde(default)]
+    _more_changes: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+struct GerritMessage {
+    author: Option<GerritAccount>,
+    date: String, // UTC, "2020-02-25 10:00:00.000000000"
+    message: String,
+}
+
+/// what we keep in the cache
+#[derive(Deserialize, Serialize, Debug)]
+struct GerritDayActivity {
+    account_id: u64,
+    changes: Vec<GerritChange>,
+}
+
+impl Gerrit {
+    fn strip_xssi_prefix(json: &str) -> &str {
+        json.trim_start().trim_start_matches(XSSI_PREFIX)
+    }
+
+    fn gerrit_get(
+        client: &reqwest::blocking::Client,
+        gerrit_config: &GerritConfig,
+        url_path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String> {
+        let text = http::send_with_retry(|| {
+            client
+                .get(&format!("{}/a{}", gerrit_config.server_url, url_path))
+                .basic_auth(&gerrit_config.username, Some(&gerrit_config.http_password))
+                .query(params)
+        })?
+        .text()?;
+        Ok(Self::strip_xssi_prefix(&text).to_string())
+    }
+
+    fn parse_gerrit_date(date: &str) -> Option<DateTime<Local>> {
+        Utc.datetime_from_str(date, "%Y-%m-%d %H:%M:%S%.f")
+            .ok()
+            .map(|d| d.with_timezone(&Local))
+    }
+
+    fn fetch_activity(
+        config_name: &str,
+        gerrit_config: &GerritConfig,
+        day: Date<Local>,
+    ) -> Result<GerritDayActivity> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let account_json = Self::gerrit_get(&client, gerrit_config, "/accounts/self", &[])?;
+        let account = serde_json::from_str::<GerritAccount>(&account_json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, account_json))?;
+        // changes updated since the day we display could have
+        // messages from that day. after: is inclusive.
+        let query = format!(
+            "(owner:self OR reviewer:self) after:\"{}\"",
+            day.format("%Y-%m-%d")
+        );
+        let page_size = PAGE_SIZE.to_string();
+        let mut changes = vec![];
+        loop {
+            let start = changes.len().to_string();
+            let json = Self::gerrit_get(
+                &client,
+                gerrit_config,
+                "/changes/",
+                &[
+                    ("q", query.as_str()),
+                    ("o", "MESSAGES"),
+                    ("n", &page_size),
+                    ("S", &start),
+                ],
+            )?;
+            let mut page = serde_json::from_str::<Vec<GerritChange>>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            let more_changes = page.last().map(|c| c._more_changes).unwrap_or(false);
+            changes.append(&mut page);
+            if !more_changes {
+                break;
+            }
+        }
+        let activity = GerritDayActivity {
+            account_id: account._account_id,
+            changes,
+        };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Gerrit, &cache_key, &serde_json::to_string(&activity)?)?;
+        Ok(activity)
+    }
+
+    fn message_to_event(
+        gerrit_config: &GerritConfig,
+        change: &GerritChange,
+        message: &GerritMessage,
+        date: DateTime<Local>,
+    ) -> Event {
+        let action = message.message.lines().next().unwrap_or("").to_string();
+        let icon = if action.starts_with("Uploaded patch set") {
+            Icon::CODE_BRANCH
+        } else if action.contains("Code-Review+") || action.starts_with("Change has been") {
+            Icon::CHECK_SQUARE
+        } else {
+            Icon::COMMENT_DOTS
+        };
+        Event::new(
+            "Gerrit",
+            icon,
+            date.time(),
+            change.subject.clone(),
+            format!("{}: {}", change.project, change.subject),
+            EventBody::Markup(
+                format!(
+                    "<a href=\"{}/c/{}/+/{}\">Open in the browser</a>\n\n{}",
+                    gerrit_config.server_url,
+                    glib::markup_escape_text(&change.project),
+                    change._number,
+                    glib::markup_escape_text(&message.message)
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(action),
+        )
+    }
+
+    fn activity_to_events(
+        gerrit_config: &GerritConfig,
+        activity: &GerritDayActivity,
+        day: Date<Local>,
+    ) -> Vec<Event> {
+        let mut result = vec![];
+        for change in &activity.changes {
+            for message in &change.messages {
+                let is_mine =
+                    message.author.as_ref().map(|a| a._account_id) == Some(activity.account_id);
+                match Self::parse_gerrit_date(&message.date) {
+                    Some(date) if is_mine && date.date() == day => {
+                        result.push(Self::message_to_event(gerrit_config, change, message, date));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        result.sort_by_key(|e| e.event_time);
+        result
+    }
+}
+
+impl EventProvider for Gerrit {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (HTTP_PASSWORD_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Gerrit"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.gerrit.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                config.gerrit[config_name].server_url.to_string(),
+            ),
+            (
+                USERNAME_KEY,
+                config.gerrit[config_name].username.to_string(),
+            ),
+            (
+                HTTP_PASSWORD_KEY,
+                config.gerrit[config_name].http_password.to_string(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.gerrit.insert(
+            config_name,
+            GerritConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                username: config_values.remove(USERNAME_KEY).unwrap(),
+                http_password: config_values.remove(HTTP_PASSWORD_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.gerrit.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let gerrit_config = &config.gerrit[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let activity = match config
+            .get_cached_day_contents(options, &Gerrit, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<GerritDayActivity>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_activity(config_name, gerrit_config, day)?,
+        };
+        Ok(Self::activity_to_events(gerrit_config, &activity, day))
+    }
+}
+
+#[test]
+fn it_parses_gerrit_changes() {
+    let json = r#")]}'
+[{"project": "cigale", "subject": "Fix the build", "_number": 42,
+  "messages": [
+    {"author": {"_account_id": 1000}, "date": "2020-02-25 12:00:00.000000000",
+     "message": "Patch Set 2: Code-Review+2\n\nLooks good"},
+    {"author": {"_account_id": 1001}, "date": "2020-02-25 12:30:00.000000000",
+     "message": "Patch Set 2: Verified+1"}
+  ]}]"#;
+    let changes =
+        serde_json::from_str::<Vec<GerritChange>>(Gerrit::strip_xssi_prefix(json)).unwrap();
+    let config = GerritConfig {
+        server_url: "https://review.example.com".to_string(),
+        username: "john".to_string(),
+        http_password: "".to_string(),
+    };
+    let activity = GerritDayActivity {
+        account_id: 1000,
+        changes,
+    };
+    let day = Utc
+        .ymd(2020, 2, 25)
+        .and_hms(12, 0, 0)
+        .with_timezone(&Local)
+        .date();
+    let events = Gerrit::activity_to_events(&config, &activity, day);
+    assert_eq!(1, events.len());
+    assert_eq!("cigale: Fix the build", events[0].event_contents_header);
+    assert_eq!(
+        Some("Patch Set 2: Code-Review+2".to_string()),
+        events[0].event_extra_details
+    );
+}
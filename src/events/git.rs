@@ -1,10 +1,13 @@
-use super::events::{ConfigType, Event, EventBody, EventProvider, Result, WordWrapMode};
+use super::events::{
+    ConfigType, Event, EventBody, EventError, EventProvider, FetchOptions, Result, WordWrapMode,
+};
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
 use git2::{Commit, Repository};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 // git2 revwalk
 // https://github.com/rust-lang/git2-rs/blob/master/examples/log.rs
@@ -12,7 +15,9 @@ use std::collections::{HashMap, HashSet};
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
 pub struct GitConfig {
     pub repo_folder: String, // Path
-    pub commit_author: String,
+    #[serde(default)] // was added later, after 0.5.3
+    pub extra_repo_folders: Vec<String>, // Paths
+    pub commit_author: String, // empty => the repo's user.email
 }
 
 impl Git {
@@ -148,6 +153,116 @@ impl Git {
         Ok(github_project_name.map(|n| format!("https://github.com/{}/commit/", n)))
     }
 
+    fn commit_matches_author(c: &Commit, author: &str) -> bool {
+        c.author().name() == Some(author) || c.author().email() == Some(author)
+    }
+
+    fn get_repo_author(repo: &Repository, git_config: &GitConfig) -> Result<String> {
+        if !git_config.commit_author.is_empty() {
+            return Ok(git_config.commit_author.clone());
+        }
+        // no author configured => default to the user.email from the git config
+        Ok(repo
+            .config()?
+            .get_string("user.email")
+            .map_err(|e| format!("No commit author configured and no git user.email: {}", e))?)
+    }
+
+    fn get_repo_folders(git_config: &GitConfig) -> Vec<&str> {
+        std::iter::once(git_config.repo_folder.as_str())
+            .chain(git_config.extra_repo_folders.iter().map(|s| s.as_str()))
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn get_repo_events(
+        config: &Config,
+        git_config: &GitConfig,
+        repo_folder: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let day_start = day.and_hms(0, 0, 0);
+        let next_day_start = day_start + chrono::Duration::days(1);
+        let repo = Repository::open(repo_folder).map_err(|e| {
+            EventError::Config(format!(
+                "Can't open the git repository {}: {}",
+                repo_folder,
+                e.message()
+            ))
+        })?;
+        let commit_author = Self::get_repo_author(&repo, git_config)?;
+        let mut all_commits = HashMap::new();
+        let commit_display_url = Self::get_commit_display_url(&repo, config)?;
+        log::info!("gitlab commit display url: {:?}", commit_display_url);
+        for branch in repo
+            .branches(Some(git2::BranchType::Local))?
+            .filter_map(|b| b.ok())
+        {
+            if let Some(branch_oid) = branch.0.get().target() {
+                let branch_name = branch.0.name().ok().flatten().map(|s| s.to_string());
+                let branch_head = repo.find_commit(branch_oid)?;
+                let branch_head_date = Git::git2_time_to_datetime(branch_head.time());
+                if branch_head_date < day_start {
+                    // early abort: quite a lot faster than starting a useless revwalk
+                    continue;
+                }
+                let mut revwalk = repo.revwalk()?;
+                revwalk.set_sorting(/*git2::Sort::REVERSE |*/ git2::Sort::TIME)?;
+                revwalk.push(branch_oid)?;
+                let mut commits: Vec<Commit> = revwalk
+                    .map(|r| {
+                        let oid = r?;
+                        repo.find_commit(oid)
+                    })
+                    .filter_map(|c| match c {
+                        Ok(commit) => Some(commit),
+                        Err(e) => {
+                            println!("Error walking the revisions {}, skipping", e);
+                            None
+                        }
+                    })
+                    .take_while(|c| {
+                        let commit_date = Git::git2_time_to_datetime(c.time());
+                        commit_date >= day_start
+                    })
+                    .filter(|c| {
+                        let commit_date = Git::git2_time_to_datetime(c.time());
+                        commit_date < next_day_start
+                            && Self::commit_matches_author(c, &commit_author)
+                    })
+                    .collect();
+                commits.reverse();
+                all_commits.insert(branch_name.unwrap_or_else(|| "".to_string()), commits);
+            }
+        }
+        let master_commit_ids: &HashSet<git2::Oid> = &all_commits
+            .get("master")
+            .unwrap_or(&vec![])
+            .iter()
+            .map(|c| c.id())
+            .collect();
+        let mut result = all_commits
+            .iter()
+            .flat_map(|(branch, commits)| {
+                let rrepo = &repo;
+                let cdu = &commit_display_url;
+                commits
+                    .iter()
+                    .filter(move |c| branch == "master" || !master_commit_ids.contains(&c.id()))
+                    .map(move |c| Self::build_event(c, rrepo, branch, cdu))
+            })
+            .collect::<Vec<Event>>();
+        result.sort_by_key(|e| e.event_time); // need to sort for the dedup to work
+        result.dedup_by(|e1, e2| {
+            // deduplicate identical commits seen in different branches
+            // (the body will be different since we put the branch name there)
+            e1.event_time == e2.event_time
+                && e1.event_contents_header == e2.event_contents_header
+                && e1.event_info == e2.event_info
+        });
+        Ok(result)
+    }
+
     fn build_event(
         c: &Commit,
         repo: &Repository,
@@ -190,17 +305,20 @@ impl Git {
             EventBody::Markup(contents, WordWrapMode::NoWordWrap),
             extra_details,
         )
+        .with_dedup_key(&format!("commit:{}", c.id()))
     }
 }
 
 pub struct Git;
 const REPO_FOLDER_KEY: &str = "Repository folder";
+const EXTRA_REPO_FOLDERS_KEY: &str = "Other repository folders";
 const COMMIT_AUTHOR_KEY: &str = "Commit Author";
 
 impl EventProvider for Git {
     fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
         vec![
             (REPO_FOLDER_KEY, ConfigType::Folder),
+            (EXTRA_REPO_FOLDERS_KEY, ConfigType::Text("")),
             (COMMIT_AUTHOR_KEY, ConfigType::Combo),
         ]
     }
@@ -213,6 +331,20 @@ impl EventProvider for Git {
         Icon::CODE_BRANCH
     }
 
+    fn watched_paths(&self, config: &Config, config_name: &str) -> Vec<PathBuf> {
+        // commits, checkouts and rebases all append to the HEAD reflog.
+        // HEAD itself covers repositories with the reflog disabled.
+        // watching the whole .git would reload on every index refresh.
+        Git::get_repo_folders(&config.git[config_name])
+            .into_iter()
+            .flat_map(|folder| {
+                let git_dir = Path::new(folder).join(".git");
+                vec![git_dir.join("HEAD"), git_dir.join("logs").join("HEAD")]
+            })
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
     fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
         config.git.keys().collect()
     }
@@ -270,6 +402,13 @@ impl EventProvider for Git {
                 REPO_FOLDER_KEY,
                 config.git[config_name].repo_folder.to_string(),
             ),
+            (
+                EXTRA_REPO_FOLDERS_KEY,
+                std::env::join_paths(&config.git[config_name].extra_repo_folders)
+                    .ok()
+                    .and_then(|p| p.to_str().map(|s| s.to_string()))
+                    .unwrap_or_else(|| "".to_string()),
+            ),
             (
                 COMMIT_AUTHOR_KEY,
                 config.git[config_name].commit_author.to_string(),
@@ -289,6 +428,15 @@ impl EventProvider for Git {
             config_name,
             GitConfig {
                 repo_folder: config_values.remove(REPO_FOLDER_KEY).unwrap(),
+                // paths separated like in the PATH environment variable
+                extra_repo_folders: std::env::split_paths(
+                    &config_values
+                        .remove(EXTRA_REPO_FOLDERS_KEY)
+                        .unwrap_or_else(|| "".to_string()),
+                )
+                .filter_map(|p| p.to_str().map(|s| s.to_string()))
+                .filter(|s| !s.is_empty())
+                .collect(),
                 commit_author: config_values.remove(COMMIT_AUTHOR_KEY).unwrap(),
             },
         );
@@ -301,83 +449,21 @@ impl EventProvider for Git {
     fn get_events(
         &self,
         config: &Config,
+        _options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>> {
         let git_config = &config.git[config_name];
-        let day_start = day.and_hms(0, 0, 0);
-        let next_day_start = day_start + chrono::Duration::days(1);
-        let repo = Repository::open(&git_config.repo_folder)?;
-        let mut all_commits = HashMap::new();
-        let commit_display_url = Self::get_commit_display_url(&repo, config)?;
-        log::info!("gitlab commit display url: {:?}", commit_display_url);
-        for branch in repo
-            .branches(Some(git2::BranchType::Local))?
-            .filter_map(|b| b.ok())
-        {
-            if let Some(branch_oid) = branch.0.get().target() {
-                let branch_name = branch.0.name().ok().flatten().map(|s| s.to_string());
-                let branch_head = repo.find_commit(branch_oid)?;
-                let branch_head_date = Git::git2_time_to_datetime(branch_head.time());
-                if branch_head_date < day_start {
-                    // early abort: quite a lot faster than starting a useless revwalk
-                    continue;
-                }
-                let mut revwalk = repo.revwalk()?;
-                revwalk.set_sorting(/*git2::Sort::REVERSE |*/ git2::Sort::TIME)?;
-                revwalk.push(branch_oid)?;
-                let mut commits: Vec<Commit> = revwalk
-                    .map(|r| {
-                        let oid = r?;
-                        repo.find_commit(oid)
-                    })
-                    .filter_map(|c| match c {
-                        Ok(commit) => Some(commit),
-                        Err(e) => {
-                            println!("Error walking the revisions {}, skipping", e);
-                            None
-                        }
-                    })
-                    .take_while(|c| {
-                        let commit_date = Git::git2_time_to_datetime(c.time());
-                        commit_date >= day_start
-                    })
-                    .filter(|c| {
-                        let commit_date = Git::git2_time_to_datetime(c.time());
-                        // TODO move to option.contains when it stabilizes https://github.com/rust-lang/rust/issues/62358
-                        commit_date < next_day_start
-                            && c.author().name() == Some(&git_config.commit_author)
-                    })
-                    .collect();
-                commits.reverse();
-                all_commits.insert(branch_name.unwrap_or_else(|| "".to_string()), commits);
-            }
+        let mut result = vec![];
+        for repo_folder in Self::get_repo_folders(git_config) {
+            result.append(&mut Self::get_repo_events(
+                config,
+                git_config,
+                repo_folder,
+                day,
+            )?);
         }
-        let master_commit_ids: &HashSet<git2::Oid> = &all_commits
-            .get("master")
-            .unwrap_or(&vec![])
-            .iter()
-            .map(|c| c.id())
-            .collect();
-        let mut result = all_commits
-            .iter()
-            .flat_map(|(branch, commits)| {
-                let rrepo = &repo;
-                let cdu = &commit_display_url;
-                commits
-                    .iter()
-                    .filter(move |c| branch == "master" || !master_commit_ids.contains(&c.id()))
-                    .map(move |c| Self::build_event(c, rrepo, branch, cdu))
-            })
-            .collect::<Vec<Event>>();
-        result.sort_by_key(|e| e.event_time); // need to sort for the dedup to work
-        result.dedup_by(|e1, e2| {
-            // deduplicate identical commits seen in different branches
-            // (the body will be different since we put the branch name there)
-            e1.event_time == e2.event_time
-                && e1.event_contents_header == e2.event_contents_header
-                && e1.event_info == e2.event_info
-        });
+        result.sort_by_key(|e| e.event_time);
         Ok(result)
     }
 }
@@ -408,6 +494,31 @@ fn it_rejects_a_non_remote_github_url() {
     );
 }
 
+#[test]
+fn it_lists_all_the_repo_folders() {
+    let git_config = GitConfig {
+        repo_folder: "/a".to_string(),
+        extra_repo_folders: vec!["/b".to_string(), "/c".to_string()],
+        commit_author: "".to_string(),
+    };
+    assert_eq!(vec!["/a", "/b", "/c"], Git::get_repo_folders(&git_config));
+}
+
+#[test]
+fn it_watches_only_the_repo_head() {
+    let git_cfg_map = vec![
+        (REPO_FOLDER_KEY, ".".to_string()),
+        (COMMIT_AUTHOR_KEY, "".to_string()),
+    ]
+    .into_iter()
+    .collect();
+    let mut config = Config::default_config();
+    Git.add_config_values(&mut config, "test".to_string(), git_cfg_map);
+    let paths = Git.watched_paths(&config, "test");
+    assert!(!paths.is_empty());
+    assert!(paths.iter().all(|p| p.is_file() && p.ends_with("HEAD")));
+}
+
 #[test]
 fn it_can_get_events_for_the_cigale_repo() {
     let git_cfg_map = vec![
@@ -448,7 +559,12 @@ fn it_can_get_events_for_the_cigale_repo() {
         Some("".to_string()),
     );
     let actual = Git
-        .get_events(&config, "test", Local.ymd(2020, 2, 25))
+        .get_events(
+            &config,
+            &FetchOptions::default(),
+            "test",
+            Local.ymd(2020, 2, 25),
+        )
         .unwrap();
     assert_eq!(2, actual.len());
     assert_eq!(expected_fst, *actual.first().unwrap());
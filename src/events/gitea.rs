@@ -0,0 +1,368 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// gitea (and forgejo, which is a gitea fork) has an API
+// inspired by GitHub's, but the activity feed payloads differ.
+// https://try.gitea.io/api/swagger#/user/userListActivityFeeds
+const PAGE_SIZE: usize = 50;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GiteaConfig {
+    pub server_url: String,
+    pub username: String,
+    pub token: String,
+}
+
+pub struct Gitea;
+const SERVER_URL_KEY: &str = "Server URL";
+const USERNAME_KEY: &str = "Username";
+const TOKEN_KEY: &str = "Access Token";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GiteaActivity {
+    op_type: String,
+    repo: GiteaRepo,
+    ref_name: Option<String>,
+    content: Option<String>,
+    created: DateTime<Local>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GiteaRepo {
+    full_name: String,
+    html_url: String,
+}
+
+// for commit_repo activities, the content is a json document
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct GiteaPushCommits {
+    commits: Vec<GiteaCommit>,
+    #[serde(rename = "CompareURL")]
+    compare_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct GiteaCommit {
+    sha1: String,
+    message: String,
+}
+
+impl Gitea {
+    fn open_in_browser_link(url: &str) -> String {
+        format!(
+            "<a href=\"{}\">Open in browser</a>",
+            glib::markup_escape_text(url)
+        )
+    }
+
+    // for issue & pull request activities, the content
+    // looks like "<index>|<title or comment>"
+    fn parse_index_content(content: &str) -> (&str, &str) {
+        let mut parts = content.splitn(2, '|');
+        let index = parts.next().unwrap_or("");
+        (index, parts.next().unwrap_or(""))
+    }
+
+    fn build_push_event(gitea_config: &GiteaConfig, activity: &GiteaActivity) -> Option<Event> {
+        let push: GiteaPushCommits =
+            serde_json::from_str(activity.content.as_deref().unwrap_or("")).ok()?;
+        let branch = activity
+            .ref_name
+            .as_deref()
+            .map(|r| r.trim_start_matches("refs/heads/"))
+            .unwrap_or("");
+        let url = match &push.compare_url {
+            Some(compare) if !compare.is_empty() => {
+                format!("{}/{}", gitea_config.server_url, compare)
+            }
+            _ => format!("{}/src/branch/{}", activity.repo.html_url, branch),
+        };
+        let title = format!(
+            "Push to {} ({}): {} commit(s)",
+            activity.repo.full_name,
+            branch,
+            push.commits.len()
+        );
+        let body = format!(
+            "{}\n\n{}",
+            Self::open_in_browser_link(&url),
+            push.commits
+                .iter()
+                .map(|c| format!(
+                    "<b>{}</b> {}",
+                    &c.sha1[..7.min(c.sha1.len())],
+                    glib::markup_escape_text(&c.message)
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        Some(Event::new(
+            "Gitea",
+            Icon::CODE_BRANCH,
+            activity.created.time(),
+            push.commits
+                .first()
+                .map(|c| c.message.lines().next().unwrap_or("").to_string())
+                .unwrap_or_else(|| title.clone()),
+            title,
+            EventBody::Markup(body, WordWrapMode::NoWordWrap),
+            Some(format!("Push: {}", branch)),
+        ))
+    }
+
+    fn build_issue_or_pr_event(
+        activity: &GiteaActivity,
+        is_pull_request: bool,
+        action: &str,
+    ) -> Option<Event> {
+        let (index, text) = Self::parse_index_content(activity.content.as_deref()?);
+        let (kind, url) = if is_pull_request {
+            (
+                "Pull Request",
+                format!("{}/pulls/{}", activity.repo.html_url, index),
+            )
+        } else {
+            (
+                "Issue",
+                format!("{}/issues/{}", activity.repo.html_url, index),
+            )
+        };
+        let title = format!("{} {}#{} {}", kind, activity.repo.full_name, index, action);
+        Some(Event::new(
+            "Gitea",
+            match action {
+                "Closed" | "Merged" | "Approved" => Icon::CHECK_SQUARE,
+                _ => Icon::COMMENT_DOTS,
+            },
+            activity.created.time(),
+            if text.is_empty() {
+                title.clone()
+            } else {
+                text.to_string()
+            },
+            title,
+            EventBody::Markup(
+                format!(
+                    "{}\n\n{}",
+                    Self::open_in_browser_link(&url),
+                    glib::markup_escape_text(text)
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(format!("{} {}", kind, action.to_lowercase())),
+        ))
+    }
+
+    fn build_event(gitea_config: &GiteaConfig, activity: &GiteaActivity) -> Option<Event> {
+        match activity.op_type.as_str() {
+            "commit_repo" => Self::build_push_event(gitea_config, activity),
+            "create_issue" => Self::build_issue_or_pr_event(activity, false, "Opened"),
+            "close_issue" => Self::build_issue_or_pr_event(activity, false, "Closed"),
+            "reopen_issue" => Self::build_issue_or_pr_event(activity, false, "Reopened"),
+            "comment_issue" => Self::build_issue_or_pr_event(activity, false, "Comment"),
+            "create_pull_request" => Self::build_issue_or_pr_event(activity, true, "Opened"),
+            "close_pull_request" => Self::build_issue_or_pr_event(activity, true, "Closed"),
+            "reopen_pull_request" => Self::build_issue_or_pr_event(activity, true, "Reopened"),
+            "merge_pull_request" => Self::build_issue_or_pr_event(activity, true, "Merged"),
+            "comment_pull" => Self::build_issue_or_pr_event(activity, true, "Comment"),
+            "approve_pull_request" => Self::build_issue_or_pr_event(activity, true, "Approved"),
+            "reject_pull_request" => {
+                Self::build_issue_or_pr_event(activity, true, "Changes requested")
+            }
+            _ => None,
+        }
+    }
+
+    fn fetch_activities(
+        config_name: &str,
+        gitea_config: &GiteaConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<GiteaActivity>> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let date = day.format("%Y-%m-%d").to_string();
+        let mut activities: Vec<GiteaActivity> = vec![];
+        for page_idx in 1.. {
+            let page_str = page_idx.to_string();
+            let page_size = PAGE_SIZE.to_string();
+            let json = http::send_with_retry(|| {
+                client
+                    .get(&format!(
+                        "{}/api/v1/users/{}/activities/feeds",
+                        gitea_config.server_url, gitea_config.username
+                    ))
+                    .query(&[
+                        ("only-performed-by", "true"),
+                        ("date", &date),
+                        ("page", &page_str),
+                        ("limit", &page_size),
+                    ])
+                    .header("Authorization", format!("token {}", gitea_config.token))
+            })?
+            .text()?;
+            log::debug!("gitea activities page {}: {}", page_idx, json);
+            let mut page = serde_json::from_str::<Vec<GiteaActivity>>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            let page_len = page.len();
+            activities.append(&mut page);
+            if page_len < PAGE_SIZE {
+                break;
+            }
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Gitea, &cache_key, &serde_json::to_string(&activities)?)?;
+        Ok(activities)
+    }
+}
+
+impl EventProvider for Gitea {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Gitea"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.gitea.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                config.gitea[config_name].server_url.to_string(),
+            ),
+            (USERNAME_KEY, config.gitea[config_name].username.to_string()),
+            (TOKEN_KEY, config.gitea[config_name].token.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.gitea.insert(
+            config_name,
+            GiteaConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                username: config_values.remove(USERNAME_KEY).unwrap(),
+                token: config_values.remove(TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.gitea.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let gitea_config = &config.gitea[config_name];
+        let day_start = day.and_hms(0, 0, 0);
+        let next_day_start = day_start + chrono::Duration::days(1);
+        // we cache the activities per day, so we put the day in the cache key
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let activities = match config
+            .get_cached_day_contents(options, &Gitea, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<GiteaActivity>>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_activities(config_name, gitea_config, day)?,
+        };
+        Ok(activities
+            .iter()
+            .filter(|a| a.created >= day_start && a.created < next_day_start)
+            .filter_map(|a| Self::build_event(gitea_config, a))
+            .collect())
+    }
+}
+
+#[test]
+fn it_parses_a_gitea_push_activity() {
+    let json = r#"[{
+        "op_type": "commit_repo",
+        "repo": {"full_name": "me/cigale", "html_url": "https://git.example.com/me/cigale"},
+        "ref_name": "refs/heads/main",
+        "content": "{\"Commits\":[{\"Sha1\":\"bbbbbbbbb2\",\"Message\":\"fix the build\\n\"}],\"CompareURL\":\"me/cigale/compare/aaa...bbb\"}",
+        "created": "2021-07-01T10:15:00Z"
+    }]"#;
+    let config = GiteaConfig {
+        server_url: "https://git.example.com".to_string(),
+        username: "me".to_string(),
+        token: "".to_string(),
+    };
+    let activities = serde_json::from_str::<Vec<GiteaActivity>>(json).unwrap();
+    let event = Gitea::build_event(&config, &activities[0]).unwrap();
+    assert_eq!("fix the build", event.event_info);
+    assert_eq!(
+        "Push to me/cigale (main): 1 commit(s)",
+        event.event_contents_header
+    );
+}
+
+#[test]
+fn it_parses_a_gitea_issue_comment_activity() {
+    let json = r#"[{
+        "op_type": "comment_issue",
+        "repo": {"full_name": "me/cigale", "html_url": "https://git.example.com/me/cigale"},
+        "content": "12|looks good to me",
+        "created": "2021-07-01T10:15:00Z"
+    }]"#;
+    let config = GiteaConfig {
+        server_url: "https://git.example.com".to_string(),
+        username: "me".to_string(),
+        token: "".to_string(),
+    };
+    let activities = serde_json::from_str::<Vec<GiteaActivity>>(json).unwrap();
+    let event = Gitea::build_event(&config, &activities[0]).unwrap();
+    assert_eq!("looks good to me", event.event_info);
+    assert_eq!("Issue me/cigale#12 Comment", event.event_contents_header);
+}
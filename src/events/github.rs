@@ -0,0 +1,574 @@
+use super::events::{
+    day_bounds, parse_timezone, to_local_in_timezone, ConfigType, Event, EventBody, EventProvider,
+    FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use super::ratelimit;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// the github events API returns at most 300 events, 100 per page
+// https://docs.github.com/en/rest/reference/activity#list-events-for-the-authenticated-user
+const MAX_PAGES: usize = 3;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GitHubConfig {
+    pub username: String,
+    pub token: String, // config::SECRET_IN_KEYRING if stored in the keyring
+    // the real timezone of the server, if it reports wrong UTC timestamps
+    #[serde(default)] // was added later, after 0.5.3
+    pub timezone: Option<String>,
+}
+
+pub struct GitHub;
+const USERNAME_KEY: &str = "Username";
+const TOKEN_KEY: &str = "Personal Access Token";
+const TIMEZONE_KEY: &str = "Timezone override (optional)";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    repo: GitHubRepo,
+    payload: GitHubPayload,
+    created_at: DateTime<Local>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubRepo {
+    name: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubPayload {
+    action: Option<String>,
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    before: Option<String>,
+    head: Option<String>,
+    commits: Option<Vec<GitHubCommit>>,
+    issue: Option<GitHubIssue>,
+    pull_request: Option<GitHubPullRequest>,
+    comment: Option<GitHubComment>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubCommit {
+    sha: String,
+    message: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubIssue {
+    number: usize,
+    title: String,
+    html_url: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubPullRequest {
+    number: usize,
+    title: String,
+    html_url: String,
+    merged: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct GitHubComment {
+    body: String,
+    html_url: String,
+}
+
+impl GitHub {
+    fn capitalize(action: &str) -> String {
+        let mut chars = action.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect(),
+            None => "".to_string(),
+        }
+    }
+
+    fn open_in_browser_link(url: &str) -> String {
+        format!(
+            "<a href=\"{}\">Open in browser</a>",
+            glib::markup_escape_text(url)
+        )
+    }
+
+    fn build_push_event(evt: &GitHubEvent) -> Option<Event> {
+        let commits = evt.payload.commits.as_ref()?;
+        let branch = evt
+            .payload
+            .git_ref
+            .as_deref()
+            .map(|r| r.trim_start_matches("refs/heads/"))
+            .unwrap_or("");
+        let url = match (&evt.payload.before, &evt.payload.head) {
+            (Some(before), Some(head)) => format!(
+                "https://github.com/{}/compare/{}...{}",
+                evt.repo.name, before, head
+            ),
+            _ => format!("https://github.com/{}/commits/{}", evt.repo.name, branch),
+        };
+        let title = format!(
+            "Push to {} ({}): {} commit(s)",
+            evt.repo.name,
+            branch,
+            commits.len()
+        );
+        let body = format!(
+            "{}\n\n{}",
+            Self::open_in_browser_link(&url),
+            commits
+                .iter()
+                .map(|c| format!(
+                    "<b>{}</b> {}",
+                    &c.sha[..7.min(c.sha.len())],
+                    glib::markup_escape_text(&c.message)
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        Some(Event::new(
+            "GitHub",
+            Icon::CODE_BRANCH,
+            evt.created_at.time(),
+            commits
+                .first()
+                .map(|c| c.message.lines().next().unwrap_or("").to_string())
+                .unwrap_or_else(|| title.clone()),
+            title,
+            EventBody::Markup(body, WordWrapMode::NoWordWrap),
+            Some(format!("Push: {}", branch)),
+        ))
+    }
+
+    fn build_issue_event(evt: &GitHubEvent) -> Option<Event> {
+        let issue = evt.payload.issue.as_ref()?;
+        let action = Self::capitalize(evt.payload.action.as_deref().unwrap_or(""));
+        let title = format!("Issue #{} {}: {}", issue.number, action, issue.title);
+        Some(Event::new(
+            "GitHub",
+            if action == "Closed" {
+                Icon::CHECK_SQUARE
+            } else {
+                Icon::COMMENT_DOTS
+            },
+            evt.created_at.time(),
+            issue.title.clone(),
+            title.clone(),
+            EventBody::Markup(
+                format!(
+                    "{}\n\n{}",
+                    Self::open_in_browser_link(&issue.html_url),
+                    glib::markup_escape_text(&title)
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(format!("Issue {}", action.to_lowercase())),
+        ))
+    }
+
+    fn build_pull_request_event(evt: &GitHubEvent) -> Option<Event> {
+        let pr = evt.payload.pull_request.as_ref()?;
+        let action = match (evt.payload.action.as_deref(), pr.merged) {
+            (Some("closed"), Some(true)) => "Merged".to_string(),
+            (a, _) => Self::capitalize(a.unwrap_or("")),
+        };
+        let title = format!("Pull Request #{} {}: {}", pr.number, action, pr.title);
+        Some(Event::new(
+            "GitHub",
+            if action == "Merged" || action == "Closed" {
+                Icon::CHECK_SQUARE
+            } else {
+                Icon::CODE_BRANCH
+            },
+            evt.created_at.time(),
+            pr.title.clone(),
+            title.clone(),
+            EventBody::Markup(
+                format!(
+                    "{}\n\n{}",
+                    Self::open_in_browser_link(&pr.html_url),
+                    glib::markup_escape_text(&title)
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(format!("Pull Request {}", action.to_lowercase())),
+        ))
+    }
+
+    fn build_issue_comment_event(evt: &GitHubEvent) -> Option<Event> {
+        let issue = evt.payload.issue.as_ref()?;
+        let comment = evt.payload.comment.as_ref()?;
+        let title = format!("Issue #{} Comment: {}", issue.number, issue.title);
+        Some(Event::new(
+            "GitHub",
+            Icon::COMMENT_DOTS,
+            evt.created_at.time(),
+            issue.title.clone(),
+            title,
+            EventBody::Markup(
+                format!(
+                    "{}\n\n{}",
+                    Self::open_in_browser_link(&comment.html_url),
+                    glib::markup_escape_text(&comment.body)
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some("Issue comment".to_string()),
+        ))
+    }
+
+    fn build_event(evt: &GitHubEvent) -> Option<Event> {
+        match evt.event_type.as_str() {
+            "PushEvent" => Self::build_push_event(evt),
+            "IssuesEvent" => Self::build_issue_event(evt),
+            "PullRequestEvent" => Self::build_pull_request_event(evt),
+            "IssueCommentEvent" => Self::build_issue_comment_event(evt),
+            _ => None,
+        }
+    }
+
+    fn check_rate_limit(resp: &reqwest::blocking::Response) -> Result<()> {
+        let remaining = resp
+            .headers()
+            .get("X-RateLimit-Remaining")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<usize>().ok());
+        if remaining == Some(0) {
+            let reset = resp
+                .headers()
+                .get("X-RateLimit-Reset")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.parse::<i64>().ok())
+                .map(|r| {
+                    Local
+                        .timestamp(r, 0)
+                        .format(", please try again after %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|| "".to_string());
+            return Err(format!("The GitHub API rate limit was reached{}", reset).into());
+        }
+        Ok(())
+    }
+
+    fn config_values(github_config: &GitHubConfig, token: String) -> HashMap<&'static str, String> {
+        vec![
+            (USERNAME_KEY, github_config.username.to_string()),
+            (TOKEN_KEY, token),
+            (
+                TIMEZONE_KEY,
+                github_config
+                    .timezone
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_token(config_name: &str, github_config: &GitHubConfig) -> Result<String> {
+        Config::read_secret(
+            &GitHub,
+            config_name,
+            &github_config.username,
+            &github_config.token,
+        )
+    }
+
+    fn fetch_events(
+        config_name: &str,
+        github_config: &GitHubConfig,
+        day_start: DateTime<Local>,
+    ) -> Result<Vec<GitHubEvent>> {
+        let records = Self::fetch_events_until(config_name, github_config, day_start)?;
+        Config::write_to_cache(&GitHub, config_name, &serde_json::to_string(&records)?)?;
+        Ok(records)
+    }
+
+    /// the events are sorted newest first, we page until we reach `stop_at`
+    fn fetch_events_until(
+        config_name: &str,
+        github_config: &GitHubConfig,
+        stop_at: DateTime<Local>,
+    ) -> Result<Vec<GitHubEvent>> {
+        let token = Self::read_token(config_name, github_config)?;
+        let client = reqwest::blocking::ClientBuilder::new()
+            .user_agent(format!(
+                "Cigale/{} (https://github.com/emmanueltouzery/cigale)",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let mut records: Vec<GitHubEvent> = vec![];
+        for page_idx in 1..=MAX_PAGES {
+            let url = format!(
+                "https://api.github.com/users/{}/events?per_page=100&page={}",
+                github_config.username, page_idx
+            );
+            // not through http::send_with_retry, we want to check the rate limit headers
+            ratelimit::acquire_url(&url);
+            let resp = client
+                .get(&url)
+                .header("Authorization", format!("token {}", token))
+                .header("Accept", "application/vnd.github.v3+json")
+                .send()
+                .map_err(http::classify_error)?;
+            Self::check_rate_limit(&resp)?;
+            let json = resp
+                .error_for_status()
+                .map_err(http::classify_error)?
+                .text()?;
+            log::debug!("github events page {}: {}", page_idx, json);
+            let mut page = serde_json::from_str::<Vec<GitHubEvent>>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            let page_len = page.len();
+            records.append(&mut page);
+            // events are sorted newest first: stop when we went back enough
+            if page_len < 100 || records.last().map(|e| e.created_at < stop_at) == Some(true) {
+                break;
+            }
+        }
+        Ok(records)
+    }
+
+    fn build_events(
+        github_config: &GitHubConfig,
+        mut github_events: Vec<GitHubEvent>,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> Result<Vec<Event>> {
+        for evt in &mut github_events {
+            evt.created_at = to_local_in_timezone(
+                evt.created_at.with_timezone(&Utc),
+                github_config.timezone.as_deref(),
+            )?;
+        }
+        Ok(github_events
+            .iter()
+            .filter(|e| e.created_at >= from && e.created_at < to)
+            .filter_map(Self::build_event)
+            .collect())
+    }
+}
+
+impl EventProvider for GitHub {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (USERNAME_KEY, ConfigType::Text("")),
+            (TOKEN_KEY, ConfigType::Password),
+            (TIMEZONE_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::GITHUB
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            TIMEZONE_KEY if !value.trim().is_empty() => {
+                parse_timezone(value).map(|_| ()).map_err(|e| e.to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.github.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let github_config = &config.github[config_name];
+        Self::config_values(
+            github_config,
+            Self::read_token(config_name, github_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.github[config_name], "".to_string())
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let username = config_values.remove(USERNAME_KEY).unwrap();
+        let token = Config::store_secret(
+            &GitHub,
+            &config_name,
+            &username,
+            config_values.remove(TOKEN_KEY).unwrap(),
+        );
+        config.github.insert(
+            config_name,
+            GitHubConfig {
+                username,
+                token,
+                timezone: config_values
+                    .remove(TIMEZONE_KEY)
+                    .map(|tz| tz.trim().to_string())
+                    .filter(|tz| !tz.is_empty()),
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.github.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.github[&config_name];
+            Config::remove_replaced_secret(
+                &GitHub,
+                old_config_name,
+                &old.username,
+                &old.token,
+                &config_name,
+                &new.username,
+                &new.token,
+            );
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(github_config) = config.github.remove(&config_name) {
+            Config::remove_secret(
+                &GitHub,
+                &config_name,
+                &github_config.username,
+                &github_config.token,
+            );
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let github_config = &config.github[config_name];
+        let (day_start, next_day_start) = day_bounds(day);
+        let cached_events = config
+            .get_cached_day_contents(options, &GitHub, config_name, day)?
+            .and_then(|json| serde_json::from_str::<Vec<GitHubEvent>>(&json).ok())
+            // the cache is only useful if it goes back far enough
+            .filter(|evts| evts.last().map(|e| e.created_at < day_start) == Some(true));
+        let github_events = match cached_events {
+            Some(evts) => evts,
+            None => Self::fetch_events(config_name, github_config, day_start)?,
+        };
+        Self::build_events(github_config, github_events, day_start, next_day_start)
+    }
+
+    fn supports_incremental_fetch(&self) -> bool {
+        true
+    }
+
+    /// we stop paging as soon as we reach `since`
+    fn get_events_since(
+        &self,
+        config: &Config,
+        _options: &FetchOptions,
+        config_name: &str,
+        since: DateTime<Local>,
+    ) -> Result<Vec<Event>> {
+        let github_config = &config.github[config_name];
+        let (_, next_day_start) = day_bounds(since.date());
+        let github_events = Self::fetch_events_until(config_name, github_config, since)?;
+        Self::build_events(github_config, github_events, since, next_day_start)
+    }
+}
+
+#[test]
+fn it_parses_a_push_event() {
+    let json = r#"[{
+        "type": "PushEvent",
+        "repo": {"name": "emmanueltouzery/cigale"},
+        "payload": {
+            "ref": "refs/heads/master",
+            "before": "aaaaaaa1",
+            "head": "bbbbbbb2",
+            "commits": [{"sha": "bbbbbbb2", "message": "fix the build"}]
+        },
+        "created_at": "2021-07-01T10:15:00Z"
+    }]"#;
+    let evts = serde_json::from_str::<Vec<GitHubEvent>>(json).unwrap();
+    let event = GitHub::build_event(&evts[0]).unwrap();
+    assert_eq!("fix the build", event.event_info);
+    assert_eq!(
+        "Push to emmanueltouzery/cigale (master): 1 commit(s)",
+        event.event_contents_header
+    );
+}
+
+#[test]
+fn it_parses_a_merged_pull_request_event() {
+    let json = r#"[{
+        "type": "PullRequestEvent",
+        "repo": {"name": "emmanueltouzery/cigale"},
+        "payload": {
+            "action": "closed",
+            "pull_request": {
+                "number": 12,
+                "title": "Add github",
+                "html_url": "https://github.com/emmanueltouzery/cigale/pull/12",
+                "merged": true
+            }
+        },
+        "created_at": "2021-07-01T10:15:00Z"
+    }]"#;
+    let evts = serde_json::from_str::<Vec<GitHubEvent>>(json).unwrap();
+    let event = GitHub::build_event(&evts[0]).unwrap();
+    assert_eq!(
+        "Pull Request #12 Merged: Add github",
+        event.event_contents_header
+    );
+    assert_eq!(Icon::CHECK_SQUARE, event.event_type_icon);
+}
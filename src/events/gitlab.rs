@@ -1,4 +1,9 @@
-use super::events::{ConfigType, Event, EventBody, EventProvider, Result, WordWrapMode};
+use super::events::{
+    day_bounds, parse_timezone, to_local_in_timezone, ConfigType, Event, EventBody, EventProvider,
+    FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use super::ratelimit;
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
@@ -14,11 +19,15 @@ type ProjectId = usize;
 pub struct GitlabConfig {
     pub gitlab_url: String,
     pub personal_access_token: String,
+    // the real timezone of the server, if it reports wrong UTC timestamps
+    #[serde(default)] // was added later, after 0.5.3
+    pub timezone: Option<String>,
 }
 
 pub struct Gitlab;
 const GITLAB_URL_KEY: &str = "Gitlab URL";
 const PERSONAL_TOKEN_KEY: &str = "Personal Access Token";
+const TIMEZONE_KEY: &str = "Timezone override (optional)";
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 struct GitlabNote {
@@ -305,6 +314,7 @@ impl Gitlab {
             &Gitlab,
             config_name,
             &Local.ymd(1970, 1, 1).and_hms(0, 0, 0),
+            None,
         )?;
         match cache
             .and_then(|cached_json| serde_json::from_str::<Vec<GitlabProject>>(&cached_json).ok())
@@ -381,14 +391,17 @@ impl Gitlab {
         let mut page_idx = 1;
         let mut records = vec![];
         loop {
+            let url = format!(
+                "{}/{}?{}page={}",
+                gitlab_config.gitlab_url, get_url, get_params, page_idx
+            );
+            ratelimit::acquire_url(&url);
             let resp = client
-                .get(&format!(
-                    "{}/{}?{}page={}",
-                    gitlab_config.gitlab_url, get_url, get_params, page_idx
-                ))
+                .get(&url)
                 .header("PRIVATE-TOKEN", &gitlab_config.personal_access_token)
-                .send()?
-                .error_for_status()?;
+                .send()
+                .and_then(|r| r.error_for_status())
+                .map_err(http::classify_error)?;
             let page_count = resp
                 .headers()
                 .get("X-Total-Pages")
@@ -415,6 +428,7 @@ impl EventProvider for Gitlab {
         vec![
             (GITLAB_URL_KEY, ConfigType::Text("")),
             (PERSONAL_TOKEN_KEY, ConfigType::Password),
+            (TIMEZONE_KEY, ConfigType::Text("")),
         ]
     }
 
@@ -426,6 +440,19 @@ impl EventProvider for Gitlab {
         Icon::COMMENT_DOTS
     }
 
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            TIMEZONE_KEY if !value.trim().is_empty() => {
+                parse_timezone(value).map(|_| ()).map_err(|e| e.to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
         config.gitlab.keys().collect()
     }
@@ -452,6 +479,13 @@ impl EventProvider for Gitlab {
                 PERSONAL_TOKEN_KEY,
                 config.gitlab[config_name].personal_access_token.to_string(),
             ),
+            (
+                TIMEZONE_KEY,
+                config.gitlab[config_name]
+                    .timezone
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
         ]
         .into_iter()
         .collect()
@@ -468,6 +502,10 @@ impl EventProvider for Gitlab {
             GitlabConfig {
                 gitlab_url: config_values.remove(GITLAB_URL_KEY).unwrap(),
                 personal_access_token: config_values.remove(PERSONAL_TOKEN_KEY).unwrap(),
+                timezone: config_values
+                    .remove(TIMEZONE_KEY)
+                    .map(|tz| tz.trim().to_string())
+                    .filter(|tz| !tz.is_empty()),
             },
         );
     }
@@ -479,23 +517,27 @@ impl EventProvider for Gitlab {
     fn get_events(
         &self,
         config: &Config,
+        _options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>> {
         let gitlab_config = &config.gitlab[config_name];
-        let day_start = day.and_hms(0, 0, 0);
-        let next_day_start = day_start + chrono::Duration::days(1);
-        let gitlab_events: Vec<_> = Self::call_gitlab_rest::<GitlabEvent>(
+        let (day_start, next_day_start) = day_bounds(day);
+        let mut gitlab_events = Self::call_gitlab_rest::<GitlabEvent>(
             "/api/v4/events",
             &[
                 ("after", &day.pred().format("%F").to_string()),
                 ("before", &day.succ().format("%F").to_string()),
             ],
             gitlab_config,
-        )?
-        .into_iter()
-        .filter(|e| e.created_at >= day_start && e.created_at < next_day_start)
-        .collect();
+        )?;
+        for evt in &mut gitlab_events {
+            evt.created_at = to_local_in_timezone(
+                evt.created_at.with_timezone(&Utc),
+                gitlab_config.timezone.as_deref(),
+            )?;
+        }
+        gitlab_events.retain(|e| e.created_at >= day_start && e.created_at < next_day_start);
 
         let project_infos = Self::get_projects_info(
             config_name,
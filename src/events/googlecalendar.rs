@@ -0,0 +1,677 @@
+use super::events::{
+    day_bounds, ConfigType, Event, EventBody, EventProvider, FetchOptions, Result,
+};
+use super::http;
+use crate::config::{Config, SECRET_IN_KEYRING};
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+// We authorize through the OAuth device flow, which doesn't need a
+// redirect url: the first fetch gives the user a code to enter in
+// the browser, the next fetches poll for the authorization.
+// https://developers.google.com/identity/protocols/oauth2/limited-input-device
+// The user must create OAuth client credentials of type "TVs and Limited Input devices".
+const DEVICE_CODE_URL: &str = "https://oauth2.googleapis.com/device/code";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const SCOPE: &str = "https://www.googleapis.com/auth/calendar.events.readonly";
+const DEVICE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+// the keyring "username" for the refresh token
+const REFRESH_TOKEN_USERNAME: &str = "refresh_token";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GoogleCalendarConfig {
+    pub client_id: String,
+    pub client_secret: String, // config::SECRET_IN_KEYRING if stored in the keyring
+    pub calendar_id: String,
+}
+
+pub struct GoogleCalendar;
+const CLIENT_ID_KEY: &str = "OAuth client ID";
+const CLIENT_SECRET_KEY: &str = "OAuth client secret";
+const CALENDAR_ID_KEY: &str = "Calendar ID";
+
+/// the authorization request the user must approve in the browser.
+/// we keep it in the cache between fetches.
+#[derive(Deserialize, Serialize, Debug)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_url: String,
+    // seconds
+    expires_in: i64,
+    // filled by us when we get the code
+    #[serde(default)]
+    requested_at: Option<DateTime<Local>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleEventsPage {
+    #[serde(default)]
+    items: Vec<GoogleEvent>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleEvent {
+    #[serde(default)]
+    summary: String,
+    location: Option<String>,
+    html_link: Option<String>,
+    status: Option<String>,
+    start: GoogleEventTime,
+    end: Option<GoogleEventTime>,
+    #[serde(default)]
+    attendees: Vec<GoogleAttendee>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleEventTime {
+    // only for all-day events
+    date: Option<NaiveDate>,
+    date_time: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleAttendee {
+    email: Option<String>,
+    display_name: Option<String>,
+    #[serde(default)]
+    organizer: bool,
+}
+
+impl GoogleCalendar {
+    fn config_values(
+        gcal_config: &GoogleCalendarConfig,
+        client_secret: String,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (CLIENT_ID_KEY, gcal_config.client_id.to_string()),
+            (CLIENT_SECRET_KEY, client_secret),
+            (CALENDAR_ID_KEY, gcal_config.calendar_id.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_client_secret(config_name: &str, gcal_config: &GoogleCalendarConfig) -> Result<String> {
+        Config::read_secret(
+            &GoogleCalendar,
+            config_name,
+            &gcal_config.client_id,
+            &gcal_config.client_secret,
+        )
+    }
+
+    /// the refresh token is obtained while fetching, when we can't
+    /// update the config file. It goes to the keyring, or if the keyring
+    /// is not available, to a file in the (private) config folder.
+    fn refresh_token_path(config_name: &str) -> Result<std::path::PathBuf> {
+        Ok(Config::config_folder()?.join(format!(
+            "{}_{}.token",
+            GoogleCalendar.name(),
+            Config::sanitize_for_filename(config_name)
+        )))
+    }
+
+    fn save_refresh_token(config_name: &str, refresh_token: String) -> Result<()> {
+        let stored = Config::store_secret(
+            &GoogleCalendar,
+            config_name,
+            REFRESH_TOKEN_USERNAME,
+            refresh_token,
+        );
+        let path = Self::refresh_token_path(config_name)?;
+        if stored == SECRET_IN_KEYRING {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        } else {
+            fs::write(path, stored)?;
+        }
+        Ok(())
+    }
+
+    fn read_refresh_token(config_name: &str) -> Option<String> {
+        Self::refresh_token_path(config_name)
+            .ok()
+            .and_then(|p| fs::read_to_string(p).ok())
+            .or_else(|| {
+                Config::read_secret(
+                    &GoogleCalendar,
+                    config_name,
+                    REFRESH_TOKEN_USERNAME,
+                    SECRET_IN_KEYRING,
+                )
+                .ok()
+            })
+            .filter(|t| !t.is_empty())
+    }
+
+    fn remove_refresh_token(config_name: &str) {
+        if let Ok(path) = Self::refresh_token_path(config_name) {
+            let _ = fs::remove_file(path);
+        }
+        Config::remove_secret(
+            &GoogleCalendar,
+            config_name,
+            REFRESH_TOKEN_USERNAME,
+            SECRET_IN_KEYRING,
+        );
+    }
+
+    fn device_code_cache_key(config_name: &str) -> String {
+        format!("{}_device_code", config_name)
+    }
+
+    fn post_token_request(
+        client: &reqwest::blocking::Client,
+        params: &[(&str, &str)],
+    ) -> Result<TokenResponse> {
+        // the token endpoint reports 'authorization_pending' & co with
+        // an error status: we must read the body in all cases.
+        let json = client
+            .post(TOKEN_URL)
+            .form(params)
+            .send()
+            .map_err(http::classify_error)?
+            .text()?;
+        Ok(serde_json::from_str::<TokenResponse>(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?)
+    }
+
+    fn token_error(response: &TokenResponse) -> String {
+        format!(
+            "Google authorization error: {} {}",
+            response.error.as_deref().unwrap_or("unknown error"),
+            response.error_description.as_deref().unwrap_or("")
+        )
+    }
+
+    fn authorization_instructions(device_code: &DeviceCode) -> String {
+        format!(
+            "To give cigale access to your calendar, open {} in your browser and enter the code {}, then refresh",
+            device_code.verification_url, device_code.user_code
+        )
+    }
+
+    fn request_device_code(
+        client: &reqwest::blocking::Client,
+        config_name: &str,
+        gcal_config: &GoogleCalendarConfig,
+    ) -> Result<DeviceCode> {
+        let json = http::send_with_retry(|| {
+            client.post(DEVICE_CODE_URL).form(&[
+                ("client_id", gcal_config.client_id.as_str()),
+                ("scope", SCOPE),
+            ])
+        })?
+        .text()?;
+        let mut device_code = serde_json::from_str::<DeviceCode>(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+        device_code.requested_at = Some(Local::now());
+        Config::write_to_cache(
+            &GoogleCalendar,
+            &Self::device_code_cache_key(config_name),
+            &serde_json::to_string(&device_code)?,
+        )?;
+        Ok(device_code)
+    }
+
+    fn pending_device_code(config_name: &str) -> Option<DeviceCode> {
+        Config::get_cached_contents(
+            &GoogleCalendar,
+            &Self::device_code_cache_key(config_name),
+            &Local::now(),
+            Some(chrono::Duration::days(1)),
+        )
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<DeviceCode>(&json).ok())
+        .filter(|c| {
+            c.requested_at.map_or(false, |r| {
+                r + chrono::Duration::seconds(c.expires_in) > Local::now()
+            })
+        })
+    }
+
+    /// get an access token, from the refresh token if we have one,
+    /// otherwise going through the device flow. While the user didn't
+    /// authorize us yet, we return an error explaining what to do.
+    fn get_access_token(
+        client: &reqwest::blocking::Client,
+        config_name: &str,
+        gcal_config: &GoogleCalendarConfig,
+    ) -> Result<String> {
+        let client_secret = Self::read_client_secret(config_name, gcal_config)?;
+        if let Some(refresh_token) = Self::read_refresh_token(config_name) {
+            let response = Self::post_token_request(
+                client,
+                &[
+                    ("client_id", gcal_config.client_id.as_str()),
+                    ("client_secret", client_secret.as_str()),
+                    ("refresh_token", refresh_token.as_str()),
+                    ("grant_type", "refresh_token"),
+                ],
+            )?;
+            return match response.access_token {
+                Some(token) => Ok(token),
+                None if response.error.as_deref() == Some("invalid_grant") => {
+                    // the user revoked our access, or the token expired:
+                    // start over with the device flow on the next fetch
+                    Self::remove_refresh_token(config_name);
+                    Err(format!(
+                        "{}. Please refresh to authorize cigale again",
+                        Self::token_error(&response)
+                    )
+                    .into())
+                }
+                None => Err(Self::token_error(&response).into()),
+            };
+        }
+        let device_code = match Self::pending_device_code(config_name) {
+            Some(c) => c,
+            None => {
+                let c = Self::request_device_code(client, config_name, gcal_config)?;
+                return Err(Self::authorization_instructions(&c).into());
+            }
+        };
+        let response = Self::post_token_request(
+            client,
+            &[
+                ("client_id", gcal_config.client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("device_code", device_code.device_code.as_str()),
+                ("grant_type", DEVICE_GRANT_TYPE),
+            ],
+        )?;
+        match (response.access_token.clone(), response.error.as_deref()) {
+            (Some(access_token), _) => {
+                if let Some(refresh_token) = response.refresh_token {
+                    Self::save_refresh_token(config_name, refresh_token)?;
+                }
+                Ok(access_token)
+            }
+            (None, Some("authorization_pending")) | (None, Some("slow_down")) => {
+                Err(Self::authorization_instructions(&device_code).into())
+            }
+            (None, _) => {
+                // denied or expired: the next fetch will request a new code
+                let _ = Config::write_to_cache(
+                    &GoogleCalendar,
+                    &Self::device_code_cache_key(config_name),
+                    "",
+                );
+                Err(Self::token_error(&response).into())
+            }
+        }
+    }
+
+    fn fetch_events(
+        config_name: &str,
+        gcal_config: &GoogleCalendarConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<GoogleEvent>> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let access_token = Self::get_access_token(&client, config_name, gcal_config)?;
+        let (day_start, next_day_start) = day_bounds(day);
+        let mut events = vec![];
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut params = vec![
+                ("timeMin", day_start.to_rfc3339()),
+                ("timeMax", next_day_start.to_rfc3339()),
+                // expand the recurring events
+                ("singleEvents", "true".to_string()),
+                ("orderBy", "startTime".to_string()),
+                ("maxResults", "250".to_string()),
+            ];
+            if let Some(token) = &page_token {
+                params.push(("pageToken", token.clone()));
+            }
+            let json = http::send_with_retry(|| {
+                client
+                    .get(&format!(
+                        "https://www.googleapis.com/calendar/v3/calendars/{}/events",
+                        gcal_config.calendar_id
+                    ))
+                    .query(&params)
+                    .bearer_auth(&access_token)
+            })?
+            .text()?;
+            let page = serde_json::from_str::<GoogleEventsPage>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            events.extend(page.items);
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(
+            &GoogleCalendar,
+            &cache_key,
+            &serde_json::to_string(&events)?,
+        )?;
+        Ok(events)
+    }
+
+    fn to_event(gevent: &GoogleEvent, day: Date<Local>) -> Option<Event> {
+        if gevent.status.as_deref() == Some("cancelled") {
+            return None;
+        }
+        let start = gevent.start.date_time.map(|d| d.with_timezone(&Local));
+        let end = gevent
+            .end
+            .as_ref()
+            .and_then(|e| e.date_time)
+            .map(|d| d.with_timezone(&Local));
+        let all_day = start.is_none();
+        if all_day && gevent.start.date.is_none() {
+            return None;
+        }
+        let attendees = gevent
+            .attendees
+            .iter()
+            .filter_map(|a| a.display_name.as_ref().or_else(|| a.email.as_ref()))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("; ");
+        let organizer = gevent
+            .attendees
+            .iter()
+            .find(|a| a.organizer)
+            .and_then(|a| a.display_name.as_ref().or_else(|| a.email.as_ref()));
+        let mut body = vec![];
+        if let Some(location) = &gevent.location {
+            body.push(format!("Location: {}", location));
+        }
+        if let Some(organizer) = organizer {
+            body.push(format!("Organizer: {}", organizer));
+        }
+        if !attendees.is_empty() {
+            body.push(format!("Attendees: {}", attendees));
+        }
+        let extra_info = match (start, end) {
+            (Some(s), Some(e)) => {
+                let duration = e - s;
+                Some(format!(
+                    "End: {}; duration: {}:{:02}",
+                    e.format("%H:%M"),
+                    duration.num_hours(),
+                    duration.num_minutes() % 60
+                ))
+            }
+            _ if all_day => Some("All day".to_string()),
+            _ => None,
+        };
+        let event = Event::new(
+            "GCal",
+            if all_day {
+                Icon::CALENDAR_DAY
+            } else {
+                Icon::CALENDAR_ALT
+            },
+            start
+                .filter(|s| s.date() == day)
+                .map(|s| s.time())
+                // events which started the previous day, and all-day events
+                .unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0)),
+            gevent.summary.clone(),
+            gevent.summary.clone(),
+            EventBody::PlainText(body.join("\n")),
+            extra_info,
+        );
+        let event = match &gevent.html_link {
+            Some(link) => event.with_url(link),
+            None => event,
+        };
+        Some(match (start, end) {
+            (Some(s), Some(e)) => event.with_duration(e - s),
+            _ => event,
+        })
+    }
+}
+
+impl EventProvider for GoogleCalendar {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (CLIENT_ID_KEY, ConfigType::Text("")),
+            (CLIENT_SECRET_KEY, ConfigType::Password),
+            (CALENDAR_ID_KEY, ConfigType::Text("primary")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "GoogleCalendar"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CALENDAR_ALT
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.google_calendar.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            CLIENT_ID_KEY if value.trim().is_empty() => {
+                Err("The OAuth client ID is required".to_string())
+            }
+            CALENDAR_ID_KEY if value.trim().is_empty() => {
+                Err("The calendar ID is required, for instance 'primary'".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let gcal_config = &config.google_calendar[config_name];
+        Self::config_values(
+            gcal_config,
+            Self::read_client_secret(config_name, gcal_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.google_calendar[config_name], "".to_string())
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let client_id = config_values
+            .remove(CLIENT_ID_KEY)
+            .unwrap()
+            .trim()
+            .to_string();
+        let client_secret = Config::store_secret(
+            &GoogleCalendar,
+            &config_name,
+            &client_id,
+            config_values.remove(CLIENT_SECRET_KEY).unwrap(),
+        );
+        config.google_calendar.insert(
+            config_name,
+            GoogleCalendarConfig {
+                client_id,
+                client_secret,
+                calendar_id: config_values
+                    .remove(CALENDAR_ID_KEY)
+                    .unwrap()
+                    .trim()
+                    .to_string(),
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.google_calendar.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.google_calendar[&config_name];
+            Config::remove_replaced_secret(
+                &GoogleCalendar,
+                old_config_name,
+                &old.client_id,
+                &old.client_secret,
+                &config_name,
+                &new.client_id,
+                &new.client_secret,
+            );
+            // the authorization is for that client, under that name
+            if old_config_name != config_name || old.client_id != new.client_id {
+                Self::remove_refresh_token(old_config_name);
+            }
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(gcal_config) = config.google_calendar.remove(&config_name) {
+            Config::remove_secret(
+                &GoogleCalendar,
+                &config_name,
+                &gcal_config.client_id,
+                &gcal_config.client_secret,
+            );
+            Self::remove_refresh_token(&config_name);
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let gcal_config = &config.google_calendar[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let gevents = match config
+            .get_cached_day_contents(options, &GoogleCalendar, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<GoogleEvent>>(&json).ok())
+        {
+            Some(e) => e,
+            None => Self::fetch_events(config_name, gcal_config, day)?,
+        };
+        let mut result: Vec<Event> = gevents
+            .iter()
+            .filter_map(|e| Self::to_event(e, day))
+            .collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_converts_google_calendar_events() {
+    let json = r#"{
+        "items": [
+            {
+                "summary": "Sprint planning",
+                "location": "Room 1",
+                "htmlLink": "https://www.google.com/calendar/event?eid=abc",
+                "status": "confirmed",
+                "start": {"dateTime": "2020-02-25T09:00:00Z"},
+                "end": {"dateTime": "2020-02-25T10:30:00Z"},
+                "attendees": [
+                    {"email": "jane@example.com", "displayName": "Jane Doe", "organizer": true},
+                    {"email": "john@example.com"}
+                ]
+            },
+            {
+                "summary": "Holiday",
+                "start": {"date": "2020-02-25"},
+                "end": {"date": "2020-02-26"}
+            },
+            {
+                "summary": "Moved",
+                "status": "cancelled",
+                "start": {"dateTime": "2020-02-25T11:00:00Z"}
+            }
+        ]
+    }"#;
+    let page = serde_json::from_str::<GoogleEventsPage>(json).unwrap();
+    let day = page.items[0]
+        .start
+        .date_time
+        .unwrap()
+        .with_timezone(&Local)
+        .date();
+    let events = page
+        .items
+        .iter()
+        .filter_map(|e| GoogleCalendar::to_event(e, day))
+        .collect::<Vec<_>>();
+    assert_eq!(2, events.len());
+    assert_eq!("Sprint planning", events[0].event_info);
+    assert_eq!(
+        Some(chrono::Duration::minutes(90)),
+        events[0].event_duration
+    );
+    assert_eq!(
+        EventBody::PlainText(
+            "Location: Room 1\nOrganizer: Jane Doe\nAttendees: Jane Doe; john@example.com"
+                .to_string()
+        ),
+        events[0].event_contents_body
+    );
+    assert_eq!(
+        Some("https://www.google.com/calendar/event?eid=abc".to_string()),
+        events[0].event_url
+    );
+    assert_eq!(Icon::CALENDAR_DAY, events[1].event_type_icon);
+    assert_eq!(Some("All day".to_string()), events[1].event_extra_details);
+}
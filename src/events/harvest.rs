@@ -0,0 +1,286 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://help.getharvest.com/api-v2/timesheets-api/timesheets/time-entries/
+const API_URL: &str = "https://api.harvestapp.com/v2";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct HarvestConfig {
+    pub account_id: String,
+    pub access_token: String,
+}
+
+pub struct Harvest;
+const ACCOUNT_ID_KEY: &str = "Account id";
+const ACCESS_TOKEN_KEY: &str = "Access token";
+
+#[derive(Deserialize, Debug)]
+struct HarvestUser {
+    id: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct HarvestTimeEntriesPage {
+    time_entries: Vec<HarvestTimeEntry>,
+    next_page: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct HarvestNamed {
+    name: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct HarvestTimeEntry {
+    hours: f64,
+    notes: Option<String>,
+    // only with timestamp timers. "8:00am" or "08:00"
+    // depending on the account settings, in the account's timezone.
+    started_time: Option<String>,
+    ended_time: Option<String>,
+    // in UTC
+    created_at: DateTime<Utc>,
+    is_running: bool,
+    project: HarvestNamed,
+    task: HarvestNamed,
+}
+
+impl Harvest {
+    fn harvest_get(
+        client: &reqwest::blocking::Client,
+        harvest_config: &HarvestConfig,
+        url_path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String> {
+        Ok(http::send_with_retry(|| {
+            client
+                .get(&format!("{}{}", API_URL, url_path))
+                .bearer_auth(&harvest_config.access_token)
+                .header("Harvest-Account-Id", &harvest_config.account_id)
+                // harvest requires a user agent
+                .header(reqwest::header::USER_AGENT, "cigale")
+                .query(params)
+        })?
+        .text()?)
+    }
+
+    fn fetch_entries(
+        config_name: &str,
+        harvest_config: &HarvestConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<HarvestTimeEntry>> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let user_json = Self::harvest_get(&client, harvest_config, "/users/me", &[])?;
+        let user = serde_json::from_str::<HarvestUser>(&user_json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, user_json))?;
+        // harvest dates are plain dates, without timezone
+        let day_str = day.format("%Y-%m-%d").to_string();
+        let user_id = user.id.to_string();
+        let mut entries = vec![];
+        let mut page = 1;
+        loop {
+            let json = Self::harvest_get(
+                &client,
+                harvest_config,
+                "/time_entries",
+                &[
+                    ("user_id", &user_id),
+                    ("from", &day_str),
+                    ("to", &day_str),
+                    ("page", &page.to_string()),
+                ],
+            )?;
+            let entries_page = serde_json::from_str::<HarvestTimeEntriesPage>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            entries.extend(entries_page.time_entries);
+            match entries_page.next_page {
+                Some(p) => page = p,
+                None => break,
+            }
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Harvest, &cache_key, &serde_json::to_string(&entries)?)?;
+        Ok(entries)
+    }
+
+    fn parse_harvest_time(time_str: &str) -> Option<NaiveTime> {
+        let time_str = time_str.trim().to_uppercase();
+        NaiveTime::parse_from_str(&time_str, "%I:%M%p")
+            .or_else(|_| NaiveTime::parse_from_str(&time_str, "%H:%M"))
+            .ok()
+    }
+
+    fn entry_time(entry: &HarvestTimeEntry) -> NaiveTime {
+        entry
+            .started_time
+            .as_deref()
+            .and_then(Self::parse_harvest_time)
+            // duration-based timers: all we have is the creation timestamp
+            .unwrap_or_else(|| entry.created_at.with_timezone(&Local).time())
+    }
+
+    fn entry_to_event(entry: &HarvestTimeEntry) -> Event {
+        let title = format!("{} - {}", entry.project.name, entry.task.name);
+        let duration = chrono::Duration::minutes((entry.hours * 60.0).round() as i64);
+        let duration_str = format!(
+            "{}:{:02}",
+            duration.num_hours(),
+            duration.num_minutes() % 60
+        );
+        let mut body = vec![format!("Hours: {:.2}", entry.hours)];
+        if let Some(end) = &entry.ended_time {
+            body.push(format!("End: {}", end));
+        }
+        if let Some(notes) = entry.notes.as_ref().filter(|n| !n.is_empty()) {
+            body.push(format!("\n{}", notes));
+        }
+        let event = Event::new(
+            "Harvest",
+            Icon::CLOCK,
+            Self::entry_time(entry),
+            title.clone(),
+            title,
+            EventBody::PlainText(body.join("\n")),
+            Some(if entry.is_running {
+                "running".to_string()
+            } else {
+                duration_str
+            }),
+        );
+        if entry.is_running {
+            event
+        } else {
+            event.with_duration(duration)
+        }
+    }
+}
+
+impl EventProvider for Harvest {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (ACCOUNT_ID_KEY, ConfigType::Text("")),
+            (ACCESS_TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Harvest"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CLOCK
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.harvest.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                ACCOUNT_ID_KEY,
+                config.harvest[config_name].account_id.to_string(),
+            ),
+            (
+                ACCESS_TOKEN_KEY,
+                config.harvest[config_name].access_token.to_string(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.harvest.insert(
+            config_name,
+            HarvestConfig {
+                account_id: config_values
+                    .remove(ACCOUNT_ID_KEY)
+                    .unwrap()
+                    .trim()
+                    .to_string(),
+                access_token: config_values.remove(ACCESS_TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.harvest.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let harvest_config = &config.harvest[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let entries = match config
+            .get_cached_day_contents(options, &Harvest, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<HarvestTimeEntry>>(&json).ok())
+        {
+            Some(e) => e,
+            None => Self::fetch_entries(config_name, harvest_config, day)?,
+        };
+        let mut result: Vec<Event> = entries.iter().map(Self::entry_to_event).collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_converts_harvest_entries() {
+    let json = r#"{"time_entries": [
+        {"spent_date": "2020-02-25", "hours": 1.5, "notes": "Planning",
+         "started_time": "8:00am", "ended_time": "9:30am",
+         "created_at": "2020-02-25T07:00:00Z", "is_running": false,
+         "project": {"id": 1, "name": "Cigale"}, "task": {"id": 2, "name": "Design"}},
+        {"spent_date": "2020-02-25", "hours": 0.25, "notes": null,
+         "started_time": "14:00", "ended_time": null,
+         "created_at": "2020-02-25T13:00:00Z", "is_running": true,
+         "project": {"id": 1, "name": "Cigale"}, "task": {"id": 3, "name": "Support"}}
+    ], "next_page": null, "page": 1}"#;
+    let page = serde_json::from_str::<HarvestTimeEntriesPage>(json).unwrap();
+    assert_eq!(None, page.next_page);
+    let finished = Harvest::entry_to_event(&page.time_entries[0]);
+    assert_eq!("Cigale - Design", finished.event_contents_header);
+    assert_eq!(NaiveTime::from_hms(8, 0, 0), finished.event_time);
+    assert_eq!(Some(chrono::Duration::minutes(90)), finished.event_duration);
+    assert_eq!(
+        EventBody::PlainText("Hours: 1.50\nEnd: 9:30am\n\nPlanning".to_string()),
+        finished.event_contents_body
+    );
+    let running = Harvest::entry_to_event(&page.time_entries[1]);
+    assert_eq!(NaiveTime::from_hms(14, 0, 0), running.event_time);
+    assert_eq!(None, running.event_duration);
+    assert_eq!(Some("running".to_string()), running.event_extra_details);
+}
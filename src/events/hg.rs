@@ -0,0 +1,228 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::process::Command;
+
+// we shell out to the hg binary, using a template with
+// separators which are unlikely to appear in commit messages
+const FIELD_SEPARATOR: char = '\u{1f}';
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
+pub struct HgConfig {
+    pub repo_paths: Vec<String>,
+    pub author_filter: Option<String>, // None => all the authors
+}
+
+pub struct Hg;
+const REPO_FOLDER_KEY: &str = "Repository folder";
+const EXTRA_REPO_FOLDERS_KEY: &str = "Other repository folders";
+const AUTHOR_KEY: &str = "Commit Author";
+
+impl Hg {
+    fn run_hg_log(
+        repo_path: &str,
+        author_filter: &Option<String>,
+        day: Date<Local>,
+    ) -> Result<Option<String>> {
+        let mut cmd = Command::new("hg");
+        cmd.arg("log")
+            .arg("-R")
+            .arg(repo_path)
+            .arg("--date")
+            .arg(format!(
+                "{} 00:00:00 to {} 23:59:59",
+                day.format("%Y-%m-%d"),
+                day.format("%Y-%m-%d")
+            ))
+            .arg("--template")
+            .arg(format!(
+                "{{node|short}}{}{{date|hgdate}}{}{{author}}{}{{desc}}{}",
+                FIELD_SEPARATOR, FIELD_SEPARATOR, FIELD_SEPARATOR, RECORD_SEPARATOR
+            ));
+        if let Some(author) = author_filter {
+            cmd.arg("--user").arg(author);
+        }
+        let output = match cmd.output() {
+            Ok(o) => o,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::warn!(
+                    "hg is not installed or not in the PATH, skipping {}",
+                    repo_path
+                );
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !output.status.success() {
+            return Err(format!(
+                "hg log failed for {}: {}",
+                repo_path,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+    }
+
+    // hgdate is "<unix timestamp> <offset>"
+    fn parse_hgdate(hgdate: &str) -> Option<DateTime<Local>> {
+        let timestamp = hgdate.split(' ').next()?.parse::<i64>().ok()?;
+        Some(Utc.timestamp(timestamp, 0).with_timezone(&Local))
+    }
+
+    fn parse_hg_log(repo_path: &str, log_output: &str) -> Vec<Event> {
+        log_output
+            .split(RECORD_SEPARATOR)
+            .filter_map(|record| {
+                let mut fields = record.split(FIELD_SEPARATOR);
+                let node = fields.next()?;
+                let date = Self::parse_hgdate(fields.next()?)?;
+                let author = fields.next()?;
+                let desc = fields.next()?;
+                let summary = desc.lines().next().unwrap_or("").to_string();
+                Some(Event::new(
+                    "Hg",
+                    Icon::CODE_BRANCH,
+                    date.time(),
+                    summary.clone(),
+                    summary,
+                    EventBody::PlainText(format!(
+                        "{}\nChangeset {} by {}\n\n{}",
+                        repo_path, node, author, desc
+                    )),
+                    Some(node.to_string()),
+                ))
+            })
+            .collect()
+    }
+}
+
+impl EventProvider for Hg {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (REPO_FOLDER_KEY, ConfigType::Folder),
+            (EXTRA_REPO_FOLDERS_KEY, ConfigType::Text("")),
+            (AUTHOR_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Hg"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.hg.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let hg_config = &config.hg[config_name];
+        vec![
+            (
+                REPO_FOLDER_KEY,
+                hg_config
+                    .repo_paths
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (
+                EXTRA_REPO_FOLDERS_KEY,
+                std::env::join_paths(hg_config.repo_paths.iter().skip(1))
+                    .ok()
+                    .and_then(|p| p.to_str().map(|s| s.to_string()))
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (
+                AUTHOR_KEY,
+                hg_config
+                    .author_filter
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let mut repo_paths = vec![config_values.remove(REPO_FOLDER_KEY).unwrap()];
+        // paths separated like in the PATH environment variable
+        repo_paths.extend(
+            std::env::split_paths(
+                &config_values
+                    .remove(EXTRA_REPO_FOLDERS_KEY)
+                    .unwrap_or_else(|| "".to_string()),
+            )
+            .filter_map(|p| p.to_str().map(|s| s.to_string())),
+        );
+        config.hg.insert(
+            config_name,
+            HgConfig {
+                repo_paths: repo_paths.into_iter().filter(|p| !p.is_empty()).collect(),
+                author_filter: config_values.remove(AUTHOR_KEY).filter(|a| !a.is_empty()),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.hg.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        _options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let hg_config = &config.hg[config_name];
+        let mut result = vec![];
+        for repo_path in &hg_config.repo_paths {
+            match Self::run_hg_log(repo_path, &hg_config.author_filter, day)? {
+                Some(output) => result.append(&mut Self::parse_hg_log(repo_path, &output)),
+                None => return Ok(vec![]), // hg is not installed
+            }
+        }
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_parses_hg_log_output() {
+    let output = "1a2b3c4d5e6f\u{1f}1582646495 -3600\u{1f}John Doe <john@example.com>\u{1f}fix the build\n\nmore details\u{1e}";
+    let events = Hg::parse_hg_log("/repo", output);
+    assert_eq!(1, events.len());
+    assert_eq!("fix the build", events[0].event_info);
+    assert_eq!(
+        EventBody::PlainText(
+            "/repo\nChangeset 1a2b3c4d5e6f by John Doe <john@example.com>\n\nfix the build\n\nmore details"
+                .to_string()
+        ),
+        events[0].event_contents_body
+    );
+}
@@ -0,0 +1,363 @@
+use super::events::{EventError, EventProvider, FetchOptions, Result};
+use super::ratelimit;
+use crate::config::Config;
+use std::error::Error;
+use std::time::Duration;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+// we sleep while holding a fetch thread: if the server
+// wants us to wait longer, we rather report the error
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// exponential backoff: 1s, 2s, 4s...
+fn backoff_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt)
+}
+
+/// the delay requested by the server through the Retry-After header,
+/// for instance when we're being rate limited. we only support
+/// the delay in seconds form, not the HTTP date form.
+fn parse_retry_after(header_value: &str) -> Option<Duration> {
+    header_value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// the url without its query, which may carry a secret (API key...),
+/// for the logs: they end up in the bug reports
+fn loggable_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    url.set_query(None);
+    url.to_string()
+}
+
+/// drop the url from the error of a request whose url carries a secret:
+/// the errors are displayed in the UI and copied in the bug reports
+pub fn without_url(e: Box<dyn Error + Send + Sync>) -> Box<dyn Error + Send + Sync> {
+    match e.downcast::<reqwest::Error>() {
+        Ok(e) => e.without_url().into(),
+        Err(e) => match e.downcast::<EventError>() {
+            Ok(event_error) => match *event_error {
+                EventError::Network(e) => EventError::Network(e.without_url()).into(),
+                other => other.into(),
+            },
+            Err(e) => e,
+        },
+    }
+}
+
+/// classify a failed request, so that the UI can suggest a fix:
+/// the server rejected our credentials, or we couldn't reach it
+pub fn classify_error(e: reqwest::Error) -> Box<dyn Error + Send + Sync> {
+    match e.status() {
+        Some(reqwest::StatusCode::UNAUTHORIZED) | Some(reqwest::StatusCode::FORBIDDEN) => {
+            // not e.to_string(), which has the url
+            EventError::Auth(format!(
+                "The server rejected the credentials ({})",
+                e.status().map(|s| s.to_string()).unwrap_or_default()
+            ))
+            .into()
+        }
+        Some(s) if s.is_server_error() => EventError::Network(e).into(),
+        None if e.is_connect() || e.is_timeout() => EventError::Network(e).into(),
+        _ => e.into(),
+    }
+}
+
+/// send the request built by `build_request`, retrying with exponential
+/// backoff on connection errors and server errors (5xx), for instance
+/// while a server is being redeployed.
+/// client errors (4xx) are not retried, except 429 (too many requests):
+/// then we wait for the delay from Retry-After, or back off, and hold
+/// the other requests to that host meanwhile, unless the server asks
+/// to wait longer than a minute: then we fail.
+/// the requests go through the per-host rate limiter.
+/// we take a closure because a request can't be sent twice.
+pub fn send_with_retry<F>(build_request: F) -> Result<reqwest::blocking::Response>
+where
+    F: Fn() -> reqwest::blocking::RequestBuilder,
+{
+    send_with_retries(build_request, DEFAULT_MAX_RETRIES)
+}
+
+pub fn send_with_retries<F>(
+    build_request: F,
+    max_retries: u32,
+) -> Result<reqwest::blocking::Response>
+where
+    F: Fn() -> reqwest::blocking::RequestBuilder,
+{
+    // the builder doesn't give the url, we must build a request to get it
+    let host = build_request()
+        .build()
+        .ok()
+        .and_then(|r| r.url().host_str().map(|h| h.to_string()));
+    let mut attempt = 0;
+    loop {
+        if let Some(h) = &host {
+            ratelimit::acquire(h);
+        }
+        let result = build_request().send();
+        let retry_reason = match &result {
+            Ok(resp) if resp.status().is_server_error() => Some((
+                format!("{}: {}", loggable_url(resp.url()), resp.status()),
+                None,
+            )),
+            Ok(resp) if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => Some((
+                format!("{}: {}", loggable_url(resp.url()), resp.status()),
+                Some(
+                    resp.headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|h| h.to_str().ok())
+                        .and_then(parse_retry_after)
+                        .unwrap_or_else(|| backoff_delay(attempt)),
+                ),
+            )),
+            Err(e) if e.is_connect() || e.is_timeout() => Some((
+                format!(
+                    "{}: {}",
+                    e.url().map(loggable_url).unwrap_or_default(),
+                    e.source()
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(|| "connection failed".to_string())
+                ),
+                None,
+            )),
+            _ => None,
+        };
+        match retry_reason {
+            Some((reason, Some(delay))) if delay > MAX_RETRY_AFTER => {
+                log::warn!("{}, the server asks to retry in {:?}", reason, delay);
+                return match result?.error_for_status() {
+                    Err(e) => Err(EventError::Network(e).into()),
+                    Ok(resp) => Ok(resp),
+                };
+            }
+            Some((reason, retry_after)) if attempt < max_retries => {
+                let delay = retry_after.unwrap_or_else(|| backoff_delay(attempt));
+                if let (Some(h), Some(d)) = (&host, retry_after) {
+                    ratelimit::pause_host(h, d);
+                }
+                log::debug!(
+                    "{}, retrying in {:?} (attempt {}/{})",
+                    reason,
+                    delay,
+                    attempt + 1,
+                    max_retries
+                );
+                std::thread::sleep(delay);
+                attempt += 1;
+            }
+            _ => {
+                return result
+                    .and_then(|r| r.error_for_status())
+                    .map_err(classify_error)
+            }
+        }
+    }
+}
+
+/// the ETag and Last-Modified headers of a response. we send them back
+/// when refreshing, so that the server can tell us nothing changed.
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug, Default, PartialEq)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    pub fn from_response(resp: &reqwest::blocking::Response) -> CacheValidators {
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(|h| h.to_string())
+        };
+        CacheValidators {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// make the request conditional: the server will answer 304 Not Modified
+/// if the resource didn't change since we got these validators
+pub fn with_validators(
+    request: reqwest::blocking::RequestBuilder,
+    validators: Option<&CacheValidators>,
+) -> reqwest::blocking::RequestBuilder {
+    let request = match validators.and_then(|v| v.etag.as_ref()) {
+        Some(etag) => request.header(reqwest::header::IF_NONE_MATCH, etag),
+        None => request,
+    };
+    match validators.and_then(|v| v.last_modified.as_ref()) {
+        Some(date) => request.header(reqwest::header::IF_MODIFIED_SINCE, date),
+        None => request,
+    }
+}
+
+/// get the text built by `build_request` and cache it under `cache_key`.
+/// if we have a cached copy, we ask the server whether it changed, and
+/// reuse the cached copy if it didn't: that spares the download.
+pub fn get_text_revalidating<F>(
+    options: &FetchOptions,
+    event_provider: &dyn EventProvider,
+    cache_key: &str,
+    build_request: F,
+) -> Result<String>
+where
+    F: Fn() -> reqwest::blocking::RequestBuilder,
+{
+    // when the user forces a refresh, we don't trust the cache at all
+    let cached = if options.bypass_cache {
+        None
+    } else {
+        Config::get_cached_contents_with_validators(event_provider, cache_key)?
+    };
+    let resp =
+        send_with_retry(|| with_validators(build_request(), cached.as_ref().map(|(_, v)| v)))?;
+    let (text, validators) = match cached {
+        Some(c) if resp.status() == reqwest::StatusCode::NOT_MODIFIED => {
+            log::debug!(
+                "{}: not modified, using the cache",
+                loggable_url(resp.url())
+            );
+            c
+        }
+        _ => {
+            let validators = CacheValidators::from_response(&resp);
+            (resp.text()?, validators)
+        }
+    };
+    // write even if not modified, the cache is fresh again
+    Config::write_to_cache_with_validators(event_provider, cache_key, &text, &validators)?;
+    Ok(text)
+}
+
+#[test]
+fn it_computes_exponential_backoff() {
+    assert_eq!(
+        vec![1, 2, 4],
+        (0..3)
+            .map(|a| backoff_delay(a).as_secs())
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn it_parses_retry_after() {
+    assert_eq!(Some(Duration::from_secs(30)), parse_retry_after("30"));
+    assert_eq!(None, parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"));
+}
+
+#[test]
+fn it_sends_the_cache_validators() {
+    let client = reqwest::blocking::Client::new();
+    let validators = CacheValidators {
+        etag: Some("\"abc\"".to_string()),
+        last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+    };
+    let request = with_validators(client.get("http://localhost/feed"), Some(&validators))
+        .build()
+        .unwrap();
+    assert_eq!("\"abc\"", request.headers()[reqwest::header::IF_NONE_MATCH]);
+    assert_eq!(
+        "Wed, 21 Oct 2015 07:28:00 GMT",
+        request.headers()[reqwest::header::IF_MODIFIED_SINCE]
+    );
+    let request = with_validators(client.get("http://localhost/feed"), None)
+        .build()
+        .unwrap();
+    assert!(request.headers().is_empty());
+}
+
+/// serve the `responses` in order, one per connection. returns the
+/// server url, and the requests as the server received them.
+#[cfg(test)]
+pub fn serve(responses: Vec<String>) -> (String, std::sync::mpsc::Receiver<String>) {
+    use std::io::{Read, Write};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for response in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 4096];
+            // read the headers, then the body up to its content length
+            while let Ok(n) = stream.read(&mut buf) {
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                let complete = text.find("\r\n\r\n").map(|headers_end| {
+                    let content_length = text[..headers_end]
+                        .lines()
+                        .filter_map(|l| l.split_once(':'))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    request.len() >= headers_end + 4 + content_length
+                });
+                if n == 0 || complete == Some(true) {
+                    break;
+                }
+            }
+            let _ = sender.send(String::from_utf8_lossy(&request).to_string());
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    (url, receiver)
+}
+
+/// serve `response` to the first request, returns the url to request
+#[cfg(test)]
+fn serve_once(response: &str) -> String {
+    let (url, _) = serve(vec![response.to_string()]);
+    format!("{}activity", url)
+}
+
+#[test]
+fn it_keeps_the_secrets_of_urls_out_of_the_logs_and_errors() {
+    let url = reqwest::Url::parse("https://redmine/activity.atom?key=s3cret").unwrap();
+    assert_eq!("https://redmine/activity.atom", loggable_url(&url));
+    let url = serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    let client = reqwest::blocking::Client::new();
+    let err = send_with_retry(|| client.get(&url).query(&[("key", "s3cret")])).unwrap_err();
+    assert!(err.to_string().contains("s3cret"));
+    assert!(!without_url(err).to_string().contains("s3cret"));
+    let url = serve_once("HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n");
+    let err = send_with_retry(|| client.get(&url).query(&[("key", "s3cret")])).unwrap_err();
+    assert!(!without_url(err).to_string().contains("s3cret"));
+}
+
+#[test]
+fn it_gives_up_when_the_server_asks_to_wait_too_long() {
+    let url = serve_once(
+        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 3600\r\nContent-Length: 0\r\n\r\n",
+    );
+    let client = reqwest::blocking::Client::new();
+    let start = std::time::Instant::now();
+    let err = send_with_retry(|| client.get(&url)).unwrap_err();
+    assert!(start.elapsed() < MAX_RETRY_AFTER);
+    assert_eq!(
+        super::events::ErrorKind::Network,
+        super::events::ErrorKind::of(&*err)
+    );
+}
+
+#[test]
+fn it_reports_rejected_credentials() {
+    let url = serve_once("HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n");
+    let client = reqwest::blocking::Client::new();
+    let err = send_with_retry(|| client.get(&url)).unwrap_err();
+    assert!(err.is::<EventError>());
+    assert_eq!(
+        super::events::ErrorKind::Auth,
+        super::events::ErrorKind::of(&*err)
+    );
+}
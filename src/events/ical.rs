@@ -1,14 +1,18 @@
-use super::events::{ConfigType, Event, EventBody, EventProvider, Result};
+use super::events::{
+    ConfigType, Event, EventBody, EventError, EventProvider, FetchOptions, Result,
+};
+use super::http;
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
 use core::time::Duration;
 use ical::parser::ical::component::IcalEvent;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
 pub struct IcalConfig {
-    pub ical_url: String,
+    pub ical_url: String, // can also be a local file path
 }
 
 impl Ical {
@@ -27,6 +31,22 @@ impl Ical {
             .and_then(|n| Ical::get_property_value(event, n))
     }
 
+    fn get_property_values<'a>(
+        event: &'a IcalEvent,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a str> {
+        event
+            .properties
+            .iter()
+            .filter(move |p| p.name == name)
+            .filter_map(|s| s.value.as_deref())
+    }
+
+    // all-day events have a date without time: 20140331
+    fn is_all_day(ical_date_str: &str) -> bool {
+        !ical_date_str.contains('T')
+    }
+
     fn parse_ical_date(ical_date_str: &str) -> Option<DateTime<Local>> {
         Utc.datetime_from_str(ical_date_str, "%Y%m%dT%H%M%SZ")
             .ok()
@@ -42,17 +62,110 @@ impl Ical {
             })
     }
 
-    fn fetch_ical(config_name: &str, ical_url: &str) -> Result<String> {
-        let r = reqwest::blocking::ClientBuilder::new()
+    fn get_local_path(ical_url: &str) -> Option<&str> {
+        let path = ical_url.strip_prefix("file://").unwrap_or(ical_url);
+        Some(path).filter(|p| std::path::Path::new(p).is_file())
+    }
+
+    fn fetch_ical(options: &FetchOptions, config_name: &str, ical_url: &str) -> Result<String> {
+        let client = reqwest::blocking::ClientBuilder::new()
             .timeout(Duration::from_secs(30))
             .connect_timeout(Duration::from_secs(30))
-            .build()?
-            .get(ical_url)
-            .send()?
-            .error_for_status()?
-            .text()?;
-        Config::write_to_cache(&Ical, config_name, &r)?;
-        Ok(r)
+            .build()?;
+        http::get_text_revalidating(options, &Ical, config_name, || client.get(ical_url))
+    }
+
+    // recurring events: does the RRULE have an occurrence on that day?
+    // we support the common cases: FREQ DAILY/WEEKLY/MONTHLY/YEARLY,
+    // with INTERVAL, UNTIL, COUNT and BYDAY (for daily and weekly).
+    // https://tools.ietf.org/html/rfc5545#section-3.3.10
+    fn rrule_occurs_on(rrule: &str, dtstart: Date<Local>, day: Date<Local>) -> bool {
+        let rule: HashMap<&str, &str> = rrule
+            .split(';')
+            .filter_map(|part| {
+                let mut kv = part.splitn(2, '=');
+                Some((kv.next()?, kv.next()?))
+            })
+            .collect();
+        if day < dtstart {
+            return false;
+        }
+        if let Some(until) = rule.get("UNTIL").and_then(|u| Ical::parse_ical_date(u)) {
+            if day > until.date() {
+                return false;
+            }
+        }
+        let occurs = |d: Date<Local>| Ical::rrule_matches_pattern(&rule, dtstart, d);
+        match rule.get("COUNT").and_then(|c| c.parse::<usize>().ok()) {
+            None => occurs(day),
+            Some(count) => {
+                occurs(day)
+                    && (0..=(day - dtstart).num_days())
+                        .map(|i| dtstart + chrono::Duration::days(i))
+                        .filter(|d| occurs(*d))
+                        .count()
+                        <= count
+            }
+        }
+    }
+
+    fn rrule_matches_pattern(
+        rule: &HashMap<&str, &str>,
+        dtstart: Date<Local>,
+        day: Date<Local>,
+    ) -> bool {
+        let interval = rule
+            .get("INTERVAL")
+            .and_then(|i| i.parse::<i64>().ok())
+            .unwrap_or(1);
+        let by_day: Option<Vec<Weekday>> = rule.get("BYDAY").map(|days| {
+            days.split(',')
+                .filter_map(|d| match d {
+                    "MO" => Some(Weekday::Mon),
+                    "TU" => Some(Weekday::Tue),
+                    "WE" => Some(Weekday::Wed),
+                    "TH" => Some(Weekday::Thu),
+                    "FR" => Some(Weekday::Fri),
+                    "SA" => Some(Weekday::Sat),
+                    "SU" => Some(Weekday::Sun),
+                    _ => None,
+                })
+                .collect()
+        });
+        match rule.get("FREQ") {
+            Some(&"DAILY") => {
+                (day - dtstart).num_days() % interval == 0
+                    && by_day.map(|d| d.contains(&day.weekday())) != Some(false)
+            }
+            Some(&"WEEKLY") => {
+                let week_start = |d: Date<Local>| {
+                    d - chrono::Duration::days(d.weekday().num_days_from_monday() as i64)
+                };
+                let weeks = (week_start(day) - week_start(dtstart)).num_days() / 7;
+                weeks % interval == 0
+                    && by_day
+                        .unwrap_or_else(|| vec![dtstart.weekday()])
+                        .contains(&day.weekday())
+            }
+            Some(&"MONTHLY") => {
+                let months = (day.year() - dtstart.year()) as i64 * 12 + day.month() as i64
+                    - dtstart.month() as i64;
+                months % interval == 0 && day.day() == dtstart.day()
+            }
+            Some(&"YEARLY") => {
+                (day.year() - dtstart.year()) as i64 % interval == 0
+                    && day.month() == dtstart.month()
+                    && day.day() == dtstart.day()
+            }
+            _ => false,
+        }
+    }
+
+    fn is_excluded(event: &IcalEvent, day: Date<Local>) -> bool {
+        Ical::get_property_values(event, "EXDATE")
+            .flat_map(|v| v.split(','))
+            .filter_map(Ical::parse_ical_date)
+            .any(|d| d.date() == day)
     }
 
     fn add_event_if_in_range(
@@ -64,6 +177,7 @@ impl Ical {
         let start = Ical::get_property_value(event, "DTSTART");
         let end = Ical::get_property_value(event, "DTEND");
         let summary = Ical::get_property_value_any(event, &["SUMMARY", "DESCRIPTION", "LOCATION"]);
+        let all_day = start.map(Ical::is_all_day).unwrap_or(false);
         match (
             start.and_then(Ical::parse_ical_date),
             end.and_then(Ical::parse_ical_date),
@@ -71,27 +185,51 @@ impl Ical {
         ) {
             (Some(st), end_dt, Some(summ)) => {
                 if st >= *day_start && st < *next_day_start {
-                    result.push(Ical::build_event(summ, st, end_dt));
+                    result.push(Ical::build_event(summ, st, end_dt, all_day));
+                } else if let Some(rrule) = Ical::get_property_value(event, "RRULE") {
+                    let day = day_start.date();
+                    if st < *day_start
+                        && Ical::rrule_occurs_on(rrule, st.date(), day)
+                        && !Ical::is_excluded(event, day)
+                    {
+                        // move the occurrence to the day we're interested in
+                        let occurrence = day.and_time(st.time()).unwrap_or(*day_start);
+                        let occurrence_end = end_dt.map(|e| occurrence + (e - st));
+                        result.push(Ical::build_event(summ, occurrence, occurrence_end, all_day));
+                    }
                 }
             }
             _ => println!("Skipping event without start or summary: {:?}", event),
         }
     }
 
-    fn build_event(summ: &str, st: DateTime<Local>, end_dt: Option<DateTime<Local>>) -> Event {
+    fn build_event(
+        summ: &str,
+        st: DateTime<Local>,
+        end_dt: Option<DateTime<Local>>,
+        all_day: bool,
+    ) -> Event {
         let summary = summ.replace("\\,", ",");
-        let extra_info = end_dt.map(|e| {
-            let duration = e - st;
-            format!(
-                "End: {}; duration: {}:{:02}",
-                e.format("%H:%M"),
-                duration.num_hours(),
-                duration.num_minutes() % 60
-            )
-        });
+        let extra_info = if all_day {
+            Some("All day".to_string())
+        } else {
+            end_dt.map(|e| {
+                let duration = e - st;
+                format!(
+                    "End: {}; duration: {}:{:02}",
+                    e.format("%H:%M"),
+                    duration.num_hours(),
+                    duration.num_minutes() % 60
+                )
+            })
+        };
         Event::new(
             "Ical",
-            Icon::CALENDAR_ALT,
+            if all_day {
+                Icon::CALENDAR_DAY
+            } else {
+                Icon::CALENDAR_ALT
+            },
             st.time(),
             summary.to_string(),
             summary,
@@ -106,7 +244,7 @@ impl Ical {
     }
 }
 
-const URL_KEY: &str = "Ical URL";
+const URL_KEY: &str = "Ical URL or file path";
 
 pub struct Ical;
 
@@ -123,6 +261,13 @@ impl EventProvider for Ical {
         Icon::CALENDAR_ALT
     }
 
+    fn watched_paths(&self, config: &Config, config_name: &str) -> Vec<PathBuf> {
+        Ical::get_local_path(&config.ical[config_name].ical_url)
+            .map(PathBuf::from)
+            .into_iter()
+            .collect()
+    }
+
     fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
         config.ical.keys().collect()
     }
@@ -166,16 +311,22 @@ impl EventProvider for Ical {
     fn get_events(
         &self,
         config: &Config,
+        options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>> {
         let ical_config = &config.ical[config_name];
         let day_start = day.and_hms(0, 0, 0);
         let next_day_start = day_start + chrono::Duration::days(1);
-        let ical_text = match Config::get_cached_contents(&Ical, config_name, &next_day_start)? {
-            Some(t) => Ok(t),
-            None => Ical::fetch_ical(config_name, &ical_config.ical_url),
-        }?;
+        let ical_text = match Ical::get_local_path(&ical_config.ical_url) {
+            // no need to cache local files
+            Some(path) => std::fs::read_to_string(&path)
+                .map_err(|e| EventError::Config(format!("Can't read {}: {}", path.display(), e)))?,
+            None => match config.get_cached_day_contents(options, &Ical, config_name, day)? {
+                Some(t) => Ok(t),
+                None => Ical::fetch_ical(options, config_name, &ical_config.ical_url),
+            }?,
+        };
         let bytes = ical_text.as_bytes();
         let reader = ical::IcalParser::new(std::io::BufReader::new(bytes));
         let mut result = vec![];
@@ -194,10 +345,7 @@ impl EventProvider for Ical {
                     }
                 }
                 Err(_) => {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        "Ical error",
-                    )))
+                    return Err(EventError::Parse("Ical error".to_string()).into());
                 }
             }
         }
@@ -223,3 +371,44 @@ fn it_parses_ical_dates_correctly() {
         Ical::parse_ical_date(&"20140331".to_string()).unwrap()
     );
 }
+
+#[test]
+fn it_expands_weekly_recurring_events() {
+    // a weekly standup on mondays and wednesdays, every two weeks, starting monday 2020-02-03
+    let rrule = "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20200331T000000Z";
+    let dtstart = Local.ymd(2020, 2, 3);
+    assert_eq!(
+        true,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 2, 5))
+    );
+    assert_eq!(
+        false,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 2, 10))
+    );
+    assert_eq!(
+        true,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 2, 17))
+    );
+    assert_eq!(
+        false,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 2, 18))
+    );
+    assert_eq!(
+        false,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 4, 13))
+    );
+}
+
+#[test]
+fn it_respects_the_rrule_count() {
+    let rrule = "FREQ=DAILY;COUNT=3";
+    let dtstart = Local.ymd(2020, 2, 3);
+    assert_eq!(
+        true,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 2, 5))
+    );
+    assert_eq!(
+        false,
+        Ical::rrule_occurs_on(rrule, dtstart, Local.ymd(2020, 2, 6))
+    );
+}
@@ -0,0 +1,296 @@
+use super::email::Email;
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use std::collections::HashMap;
+
+const DEFAULT_PORT: u16 = 993;
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
+pub struct ImapConfig {
+    pub imap_server: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String, // config::SECRET_IN_KEYRING if stored in the keyring
+    pub folder: String,   // empty => autodetect the sent folder
+}
+
+pub struct Imap;
+const SERVER_KEY: &str = "IMAP server";
+const PORT_KEY: &str = "Port";
+const USERNAME_KEY: &str = "Username";
+const PASSWORD_KEY: &str = "Password";
+const FOLDER_KEY: &str = "Sent folder (empty to autodetect)";
+
+type ImapSession = imap::Session<native_tls::TlsStream<std::net::TcpStream>>;
+
+impl Imap {
+    fn config_values(imap_config: &ImapConfig, password: String) -> HashMap<&'static str, String> {
+        vec![
+            (SERVER_KEY, imap_config.imap_server.to_string()),
+            (PORT_KEY, imap_config.port.to_string()),
+            (USERNAME_KEY, imap_config.username.to_string()),
+            (PASSWORD_KEY, password),
+            (FOLDER_KEY, imap_config.folder.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_password(config_name: &str, imap_config: &ImapConfig) -> Result<String> {
+        Config::read_secret(
+            &Imap,
+            config_name,
+            &imap_config.username,
+            &imap_config.password,
+        )
+    }
+
+    // prefer the folder with the \Sent special-use flag (RFC 6154),
+    // fall back on the folder name
+    fn find_sent_folder(session: &mut ImapSession) -> Result<String> {
+        let folders = session.list(None, Some("*"))?;
+        let by_flag = folders.iter().find(|f| {
+            f.attributes()
+                .iter()
+                .any(|a| matches!(a, imap::types::NameAttribute::Custom(c) if c == "\\Sent"))
+        });
+        let by_name = || {
+            folders
+                .iter()
+                .find(|f| f.name().to_lowercase().contains("sent"))
+        };
+        by_flag
+            .or_else(by_name)
+            .map(|f| f.name().to_string())
+            .ok_or_else(|| "Can't find the sent folder, please configure it".into())
+    }
+
+    fn fetch_headers(
+        config_name: &str,
+        imap_config: &ImapConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<String>> {
+        let password = Self::read_password(config_name, imap_config)?;
+        let tls = native_tls::TlsConnector::builder().build()?;
+        let client = imap::connect(
+            (imap_config.imap_server.as_str(), imap_config.port),
+            &imap_config.imap_server,
+            &tls,
+        )?;
+        let mut session = client
+            .login(&imap_config.username, &password)
+            .map_err(|e| e.0)?;
+        let folder = if imap_config.folder.is_empty() {
+            Self::find_sent_folder(&mut session)?
+        } else {
+            imap_config.folder.clone()
+        };
+        session.select(&folder)?;
+        let seqs = session.search(format!("SENTON {}", day.format("%-d-%b-%Y")))?;
+        let mut headers = vec![];
+        if !seqs.is_empty() {
+            let seq_set = seqs
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            for msg in session.fetch(seq_set, "BODY.PEEK[HEADER]")?.iter() {
+                if let Some(h) = msg.header() {
+                    headers.push(String::from_utf8_lossy(h).to_string());
+                }
+            }
+        }
+        session.logout()?;
+        Ok(headers)
+    }
+
+    fn header_to_event(header: &str) -> Result<Option<Event>> {
+        let (headers, _) = mailparse::parse_headers(header.as_bytes())?;
+        let email_date = match Email::parse_email_headers_date(&headers) {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+        let email_subject =
+            Email::get_header_val(&headers, "Subject").unwrap_or_else(|| "-".to_string());
+        let to = Email::get_header_val(&headers, "To");
+        let body = to
+            .as_ref()
+            .map(|t| format!("To: {}\n", t))
+            .unwrap_or_else(|| "".to_string())
+            + &Email::get_header_val(&headers, "Cc")
+                .map(|c| format!("Cc: {}\n", c))
+                .unwrap_or_else(|| "".to_string());
+        Ok(Some(Event::new(
+            "Email",
+            Icon::ENVELOPE,
+            email_date.time(),
+            email_subject.clone(),
+            email_subject,
+            EventBody::PlainText(body),
+            to,
+        )))
+    }
+}
+
+impl EventProvider for Imap {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_KEY, ConfigType::Text("")),
+            (PORT_KEY, ConfigType::Text("993")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (PASSWORD_KEY, ConfigType::Password),
+            (FOLDER_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Imap"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::ENVELOPE
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.imap.keys().collect()
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let imap_config = &config.imap[config_name];
+        Self::config_values(
+            imap_config,
+            Self::read_password(config_name, imap_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.imap[config_name], "".to_string())
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(imap_config) = config.imap.remove(&config_name) {
+            Config::remove_secret(
+                &Imap,
+                &config_name,
+                &imap_config.username,
+                &imap_config.password,
+            );
+        }
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let username = config_values.remove(USERNAME_KEY).unwrap();
+        let password = Config::store_secret(
+            &Imap,
+            &config_name,
+            &username,
+            config_values.remove(PASSWORD_KEY).unwrap(),
+        );
+        config.imap.insert(
+            config_name,
+            ImapConfig {
+                imap_server: config_values.remove(SERVER_KEY).unwrap(),
+                port: config_values
+                    .remove(PORT_KEY)
+                    .and_then(|p| p.trim().parse().ok())
+                    .unwrap_or(DEFAULT_PORT),
+                username,
+                password,
+                folder: config_values.remove(FOLDER_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.imap.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.imap[&config_name];
+            Config::remove_replaced_secret(
+                &Imap,
+                old_config_name,
+                &old.username,
+                &old.password,
+                &config_name,
+                &new.username,
+                &new.password,
+            );
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let imap_config = &config.imap[config_name];
+        // we cache the headers per day, so we put the day in the cache key
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let headers = match config
+            .get_cached_day_contents(options, &Imap, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok())
+        {
+            Some(h) => h,
+            None => {
+                let h = Self::fetch_headers(config_name, imap_config, day)?;
+                Config::write_to_cache(&Imap, &cache_key, &serde_json::to_string(&h)?)?;
+                h
+            }
+        };
+        let mut result = vec![];
+        for header in headers {
+            if let Some(evt) = Self::header_to_event(&header)? {
+                result.push(evt);
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_converts_email_headers_to_an_event() {
+    let evt = Imap::header_to_event(
+        "Date: Fri, 27 Sep 2013 20:46:35 +0200\r\nSubject: hello\r\nTo: b@example.com\r\n\r\n",
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!("hello", evt.event_contents_header);
+    assert_eq!(
+        EventBody::PlainText("To: b@example.com\n".to_string()),
+        evt.event_contents_body
+    );
+}
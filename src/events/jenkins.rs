@@ -0,0 +1,379 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://www.jenkins.io/doc/book/using/remote-access-api/
+// the builds are returned newest first, we page through
+// them until we reach builds older than the day we display.
+const BUILDS_PAGE_SIZE: usize = 50;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct JenkinsConfig {
+    pub server_url: String,
+    pub username: String,
+    pub api_token: String,
+    pub job_filter: Option<String>, // None => all the jobs
+}
+
+pub struct Jenkins;
+const SERVER_URL_KEY: &str = "Server URL";
+const USERNAME_KEY: &str = "Username";
+const API_TOKEN_KEY: &str = "API Token";
+const JOB_FILTER_KEY: &str = "Job name filter";
+
+#[derive(Deserialize, Debug)]
+struct JenkinsJobs {
+    jobs: Vec<JenkinsJob>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JenkinsJob {
+    #[serde(rename = "_class")]
+    class: String,
+    name: String,
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JenkinsBuilds {
+    all_builds: Vec<JenkinsBuildInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JenkinsBuildInfo {
+    number: u64,
+    url: String,
+    result: Option<String>, // None => the build is still running
+    timestamp: i64,         // milliseconds since the epoch
+    duration: i64,          // milliseconds
+    #[serde(default)]
+    actions: Vec<JenkinsAction>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct JenkinsAction {
+    causes: Vec<JenkinsCause>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JenkinsCause {
+    user_id: Option<String>,
+}
+
+/// what we keep in the cache: the builds of the user for the day
+#[derive(Deserialize, Serialize, Debug)]
+struct JenkinsBuild {
+    job_name: String,
+    number: u64,
+    url: String,
+    result: Option<String>,
+    timestamp: i64,
+    duration: i64,
+}
+
+impl Jenkins {
+    fn jenkins_get(
+        client: &reqwest::blocking::Client,
+        jenkins_config: &JenkinsConfig,
+        url: &str,
+        tree: &str,
+    ) -> Result<String> {
+        Ok(http::send_with_retry(|| {
+            client
+                .get(&format!("{}api/json", url))
+                .basic_auth(&jenkins_config.username, Some(&jenkins_config.api_token))
+                .query(&[("tree", tree)])
+        })?
+        .text()?)
+    }
+
+    fn is_folder(job: &JenkinsJob) -> bool {
+        job.class.ends_with(".Folder")
+            || job.class.ends_with("OrganizationFolder")
+            || job.class.ends_with("WorkflowMultiBranchProject")
+    }
+
+    /// list the jobs recursively, descending in folders.
+    /// returns the full job names (folder/job) and the job urls.
+    fn list_jobs(
+        client: &reqwest::blocking::Client,
+        jenkins_config: &JenkinsConfig,
+        url: &str,
+        name_prefix: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let json = Self::jenkins_get(client, jenkins_config, url, "jobs[_class,name,url]")?;
+        let jobs = serde_json::from_str::<JenkinsJobs>(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+        let mut result = vec![];
+        for job in jobs.jobs {
+            let full_name = format!("{}{}", name_prefix, job.name);
+            if Self::is_folder(&job) {
+                result.append(&mut Self::list_jobs(
+                    client,
+                    jenkins_config,
+                    &job.url,
+                    &format!("{}/", full_name),
+                )?);
+            } else {
+                result.push((full_name, job.url));
+            }
+        }
+        Ok(result)
+    }
+
+    fn is_triggered_by(build: &JenkinsBuildInfo, username: &str) -> bool {
+        build
+            .actions
+            .iter()
+            .flat_map(|a| a.causes.iter())
+            .any(|c| c.user_id.as_deref() == Some(username))
+    }
+
+    fn fetch_job_builds(
+        client: &reqwest::blocking::Client,
+        jenkins_config: &JenkinsConfig,
+        job_name: &str,
+        job_url: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<JenkinsBuild>> {
+        let day_start = day.and_hms(0, 0, 0).timestamp_millis();
+        let next_day_start = (day.and_hms(0, 0, 0) + chrono::Duration::days(1)).timestamp_millis();
+        let mut result = vec![];
+        for page_idx in 0.. {
+            let json = Self::jenkins_get(
+                client,
+                jenkins_config,
+                job_url,
+                &format!(
+                    "allBuilds[number,url,result,timestamp,duration,actions[causes[userId]]]{{{},{}}}",
+                    page_idx * BUILDS_PAGE_SIZE,
+                    (page_idx + 1) * BUILDS_PAGE_SIZE
+                ),
+            )?;
+            let builds = serde_json::from_str::<JenkinsBuilds>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?
+                .all_builds;
+            let page_len = builds.len();
+            let reached_older_builds = builds.iter().any(|b| b.timestamp < day_start);
+            result.extend(
+                builds
+                    .into_iter()
+                    .filter(|b| b.timestamp >= day_start && b.timestamp < next_day_start)
+                    .filter(|b| Self::is_triggered_by(b, &jenkins_config.username))
+                    .map(|b| JenkinsBuild {
+                        job_name: job_name.to_string(),
+                        number: b.number,
+                        url: b.url,
+                        result: b.result,
+                        timestamp: b.timestamp,
+                        duration: b.duration,
+                    }),
+            );
+            if reached_older_builds || page_len < BUILDS_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    fn fetch_builds(
+        config_name: &str,
+        jenkins_config: &JenkinsConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<JenkinsBuild>> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let mut builds = vec![];
+        for (job_name, job_url) in Self::list_jobs(
+            &client,
+            jenkins_config,
+            &format!("{}/", jenkins_config.server_url),
+            "",
+        )? {
+            if let Some(filter) = &jenkins_config.job_filter {
+                if !job_name.contains(filter.as_str()) {
+                    continue;
+                }
+            }
+            builds.append(&mut Self::fetch_job_builds(
+                &client,
+                jenkins_config,
+                &job_name,
+                &job_url,
+                day,
+            )?);
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Jenkins, &cache_key, &serde_json::to_string(&builds)?)?;
+        Ok(builds)
+    }
+
+    fn build_to_event(build: &JenkinsBuild) -> Event {
+        let result = build.result.as_deref().unwrap_or("RUNNING");
+        let title = format!("{} #{}", build.job_name, build.number);
+        let event = Event::new(
+            "Jenkins",
+            match result {
+                "SUCCESS" => Icon::CHECK_SQUARE,
+                "FAILURE" | "UNSTABLE" => Icon::EXCLAMATION_TRIANGLE,
+                _ => Icon::COG,
+            },
+            Utc.timestamp_millis(build.timestamp)
+                .with_timezone(&Local)
+                .time(),
+            title.clone(),
+            title,
+            EventBody::Markup(
+                format!(
+                    "<a href=\"{}\">Open in the browser</a>\n\nResult: {}",
+                    glib::markup_escape_text(&build.url),
+                    result
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(format!("Build {}", result.to_lowercase())),
+        );
+        if build.result.is_some() {
+            event.with_duration(chrono::Duration::milliseconds(build.duration))
+        } else {
+            event
+        }
+    }
+}
+
+impl EventProvider for Jenkins {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (API_TOKEN_KEY, ConfigType::Password),
+            (JOB_FILTER_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Jenkins"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::COG
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.jenkins.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let jenkins_config = &config.jenkins[config_name];
+        vec![
+            (SERVER_URL_KEY, jenkins_config.server_url.to_string()),
+            (USERNAME_KEY, jenkins_config.username.to_string()),
+            (API_TOKEN_KEY, jenkins_config.api_token.to_string()),
+            (
+                JOB_FILTER_KEY,
+                jenkins_config
+                    .job_filter
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.jenkins.insert(
+            config_name,
+            JenkinsConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                username: config_values.remove(USERNAME_KEY).unwrap(),
+                api_token: config_values.remove(API_TOKEN_KEY).unwrap(),
+                job_filter: config_values
+                    .remove(JOB_FILTER_KEY)
+                    .filter(|f| !f.is_empty()),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.jenkins.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let jenkins_config = &config.jenkins[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let builds = match config
+            .get_cached_day_contents(options, &Jenkins, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<JenkinsBuild>>(&json).ok())
+        {
+            Some(b) => b,
+            None => Self::fetch_builds(config_name, jenkins_config, day)?,
+        };
+        let mut result: Vec<Event> = builds.iter().map(Self::build_to_event).collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_filters_jenkins_builds_by_user() {
+    let json = r#"{"allBuilds": [
+        {"number": 12, "url": "http://ci/job/app/12/", "result": "FAILURE",
+         "timestamp": 1582646495000, "duration": 60000,
+         "actions": [{"_class": "hudson.model.CauseAction", "causes": [{"userId": "john"}]}, {}]},
+        {"number": 11, "url": "http://ci/job/app/11/", "result": "SUCCESS",
+         "timestamp": 1582646000000, "duration": 60000,
+         "actions": [{"causes": [{"shortDescription": "Started by an SCM change"}]}]}
+    ]}"#;
+    let builds = serde_json::from_str::<JenkinsBuilds>(json)
+        .unwrap()
+        .all_builds;
+    assert_eq!(
+        vec![12],
+        builds
+            .iter()
+            .filter(|b| Jenkins::is_triggered_by(b, "john"))
+            .map(|b| b.number)
+            .collect::<Vec<_>>()
+    );
+}
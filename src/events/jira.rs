@@ -0,0 +1,506 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use super::ratelimit;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const PAGE_SIZE: usize = 50;
+
+// jira cloud authenticates with the email & an API token,
+// jira server with the username & password. In both cases
+// that's basic authentication, so we use the same config.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct JiraConfig {
+    pub server_url: String,
+    pub username: String,
+    pub api_token: String, // config::SECRET_IN_KEYRING if stored in the keyring
+}
+
+pub struct Jira;
+const SERVER_URL_KEY: &str = "Server URL";
+const USERNAME_KEY: &str = "Email (cloud) or username (server)";
+const API_TOKEN_KEY: &str = "API token (cloud) or password (server)";
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JiraSearchResult {
+    start_at: usize,
+    total: usize,
+    issues: Vec<JiraIssue>,
+}
+
+#[derive(Deserialize, Debug)]
+struct JiraIssue {
+    key: String,
+    fields: JiraIssueFields,
+}
+
+#[derive(Deserialize, Debug)]
+struct JiraIssueFields {
+    summary: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JiraWorklogs {
+    start_at: usize,
+    total: usize,
+    worklogs: Vec<JiraWorklog>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JiraWorklog {
+    author: JiraUser,
+    started: String,
+    time_spent_seconds: i64,
+    comment: Option<String>,
+}
+
+// jira cloud identifies users by accountId, jira server by name
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct JiraUser {
+    account_id: Option<String>,
+    name: Option<String>,
+}
+
+impl JiraUser {
+    fn is_same_user(&self, other: &JiraUser) -> bool {
+        match (&self.account_id, &other.account_id) {
+            (Some(a), Some(b)) => a == b,
+            _ => self.name.is_some() && self.name == other.name,
+        }
+    }
+}
+
+/// the http client, and the secret it authenticates with
+struct JiraSession {
+    client: reqwest::blocking::Client,
+    api_token: String,
+}
+
+impl Jira {
+    fn config_values(jira_config: &JiraConfig, api_token: String) -> HashMap<&'static str, String> {
+        vec![
+            (SERVER_URL_KEY, jira_config.server_url.to_string()),
+            (USERNAME_KEY, jira_config.username.to_string()),
+            (API_TOKEN_KEY, api_token),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_api_token(config_name: &str, jira_config: &JiraConfig) -> Result<String> {
+        Config::read_secret(
+            &Jira,
+            config_name,
+            &jira_config.username,
+            &jira_config.api_token,
+        )
+    }
+
+    fn init_session(config_name: &str, jira_config: &JiraConfig) -> Result<JiraSession> {
+        Ok(JiraSession {
+            client: reqwest::blocking::ClientBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .connect_timeout(Duration::from_secs(30))
+                .connection_verbose(true)
+                .build()?,
+            api_token: Self::read_api_token(config_name, jira_config)?,
+        })
+    }
+
+    fn call_jira_rest<T>(
+        session: &JiraSession,
+        jira_config: &JiraConfig,
+        url_path: &str,
+        get_params: &[(&str, &str)],
+    ) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let url = format!("{}{}", jira_config.server_url, url_path);
+        ratelimit::acquire_url(&url);
+        let json = session
+            .client
+            .get(&url)
+            .basic_auth(&jira_config.username, Some(&session.api_token))
+            .query(get_params)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .map_err(http::classify_error)?
+            .text()?;
+        log::debug!("{}: got back {}", url_path, json);
+        Ok(serde_json::from_str::<T>(&json)
+            .map_err(|e| format!("Failed parsing json {} {:?} -- {}", url_path, e, json))?)
+    }
+
+    fn search_issues(
+        session: &JiraSession,
+        jira_config: &JiraConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<JiraIssue>> {
+        let jql = format!(
+            "worklogAuthor = currentUser() AND worklogDate = \"{}\"",
+            day.format("%Y-%m-%d")
+        );
+        let mut issues = vec![];
+        loop {
+            let start_at = issues.len().to_string();
+            let page_size = PAGE_SIZE.to_string();
+            let mut page: JiraSearchResult = Self::call_jira_rest(
+                session,
+                jira_config,
+                "/rest/api/2/search",
+                &[
+                    ("jql", &jql),
+                    ("fields", "summary"),
+                    ("startAt", &start_at),
+                    ("maxResults", &page_size),
+                ],
+            )?;
+            let page_len = page.issues.len();
+            issues.append(&mut page.issues);
+            if page_len == 0 || page.start_at + page_len >= page.total {
+                break;
+            }
+        }
+        Ok(issues)
+    }
+
+    fn get_worklogs(
+        session: &JiraSession,
+        jira_config: &JiraConfig,
+        issue_key: &str,
+    ) -> Result<Vec<JiraWorklog>> {
+        let mut worklogs = vec![];
+        loop {
+            let start_at = worklogs.len().to_string();
+            let page_size = PAGE_SIZE.to_string();
+            let mut page: JiraWorklogs = Self::call_jira_rest(
+                session,
+                jira_config,
+                &format!("/rest/api/2/issue/{}/worklog", issue_key),
+                &[("startAt", &start_at), ("maxResults", &page_size)],
+            )?;
+            let page_len = page.worklogs.len();
+            worklogs.append(&mut page.worklogs);
+            if page_len == 0 || page.start_at + page_len >= page.total {
+                break;
+            }
+        }
+        Ok(worklogs)
+    }
+
+    // jira dates look like 2020-03-23T13:30:00.000+0100
+    fn parse_jira_date(date_str: &str) -> Result<DateTime<Local>> {
+        Ok(DateTime::parse_from_str(date_str, "%Y-%m-%dT%H:%M:%S%.f%z")?.with_timezone(&Local))
+    }
+
+    fn build_event(
+        jira_config: &JiraConfig,
+        issue: &JiraIssue,
+        worklog: &JiraWorklog,
+        started: DateTime<Local>,
+    ) -> Event {
+        let title = format!("{}: {}", issue.key, issue.fields.summary);
+        let duration = chrono::Duration::seconds(worklog.time_spent_seconds);
+        let duration_str = format!(
+            "{}:{:02}",
+            duration.num_hours(),
+            duration.num_minutes() % 60
+        );
+        Event::new(
+            "Jira",
+            Icon::TASKS,
+            started.time(),
+            title.clone(),
+            title,
+            EventBody::Markup(
+                format!(
+                    "<a href=\"{}/browse/{}\">Open in the browser</a>\n\nTime spent: {}\n\n{}",
+                    jira_config.server_url,
+                    issue.key,
+                    duration_str,
+                    glib::markup_escape_text(worklog.comment.as_deref().unwrap_or(""))
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(format!("Worklog {}", duration_str)),
+        )
+        .with_duration(duration)
+    }
+}
+
+impl EventProvider for Jira {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (API_TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Jira"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::TASKS
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.jira.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let jira_config = &config.jira[config_name];
+        Self::config_values(
+            jira_config,
+            Self::read_api_token(config_name, jira_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.jira[config_name], "".to_string())
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let username = config_values.remove(USERNAME_KEY).unwrap();
+        let api_token = Config::store_secret(
+            &Jira,
+            &config_name,
+            &username,
+            config_values.remove(API_TOKEN_KEY).unwrap(),
+        );
+        config.jira.insert(
+            config_name,
+            JiraConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                username,
+                api_token,
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.jira.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.jira[&config_name];
+            Config::remove_replaced_secret(
+                &Jira,
+                old_config_name,
+                &old.username,
+                &old.api_token,
+                &config_name,
+                &new.username,
+                &new.api_token,
+            );
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(jira_config) = config.jira.remove(&config_name) {
+            Config::remove_secret(
+                &Jira,
+                &config_name,
+                &jira_config.username,
+                &jira_config.api_token,
+            );
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        _options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let jira_config = &config.jira[config_name];
+        let day_start = day.and_hms(0, 0, 0);
+        let next_day_start = day_start + chrono::Duration::days(1);
+        let session = Self::init_session(config_name, jira_config)?;
+        let myself: JiraUser =
+            Self::call_jira_rest(&session, jira_config, "/rest/api/2/myself", &[])?;
+        let mut result = vec![];
+        for issue in Self::search_issues(&session, jira_config, day)? {
+            for worklog in Self::get_worklogs(&session, jira_config, &issue.key)? {
+                let started = Self::parse_jira_date(&worklog.started)?;
+                if worklog.author.is_same_user(&myself)
+                    && started >= day_start
+                    && started < next_day_start
+                {
+                    result.push(Self::build_event(jira_config, &issue, &worklog, started));
+                }
+            }
+        }
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_parses_jira_dates() {
+    assert_eq!(
+        FixedOffset::east(3600).ymd(2020, 3, 23).and_hms(13, 30, 0),
+        Jira::parse_jira_date("2020-03-23T13:30:00.000+0100").unwrap()
+    );
+}
+
+#[cfg(test)]
+fn json_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[test]
+fn it_matches_jira_users_by_account_id_or_name() {
+    let user = |account_id: Option<&str>, name: Option<&str>| JiraUser {
+        account_id: account_id.map(|a| a.to_string()),
+        name: name.map(|n| n.to_string()),
+    };
+    // jira cloud
+    assert!(user(Some("abc"), None).is_same_user(&user(Some("abc"), None)));
+    assert!(!user(Some("abc"), None).is_same_user(&user(Some("def"), None)));
+    // the account id wins over the name
+    assert!(!user(Some("abc"), Some("me")).is_same_user(&user(Some("def"), Some("me"))));
+    // jira server
+    assert!(user(None, Some("me")).is_same_user(&user(None, Some("me"))));
+    assert!(user(Some("abc"), Some("me")).is_same_user(&user(None, Some("me"))));
+    assert!(!user(None, Some("me")).is_same_user(&user(None, Some("you"))));
+    assert!(!user(None, None).is_same_user(&user(None, None)));
+}
+
+#[test]
+fn it_pages_through_the_search_results() {
+    let (server_url, requests) = http::serve(vec![
+        json_response(
+            r#"{"startAt": 0, "total": 3, "issues": [
+                {"key": "CIG-1", "fields": {"summary": "one"}},
+                {"key": "CIG-2", "fields": {"summary": "two"}}]}"#,
+        ),
+        json_response(
+            r#"{"startAt": 2, "total": 3, "issues": [
+                {"key": "CIG-3", "fields": {"summary": "three"}}]}"#,
+        ),
+    ]);
+    let jira_config = JiraConfig {
+        server_url: server_url.trim_end_matches('/').to_string(),
+        username: "me".to_string(),
+        api_token: "secret".to_string(),
+    };
+    let session = Jira::init_session("work", &jira_config).unwrap();
+    let issues = Jira::search_issues(&session, &jira_config, Local.ymd(2020, 3, 23)).unwrap();
+    assert_eq!(
+        vec!["CIG-1", "CIG-2", "CIG-3"],
+        issues.iter().map(|i| i.key.as_str()).collect::<Vec<_>>()
+    );
+    let requests: Vec<String> = requests.iter().collect();
+    assert_eq!(2, requests.len());
+    assert!(requests[0].starts_with("GET /rest/api/2/search?"));
+    assert!(requests[0].contains("startAt=0"));
+    assert!(requests[1].contains("startAt=2"));
+    // basic authentication with me:secret
+    assert!(requests[0]
+        .to_lowercase()
+        .contains("authorization: basic bwu6c2vjcmv0"));
+}
+
+#[test]
+fn it_keeps_my_worklogs_of_the_day() {
+    let day = Local.ymd(2020, 3, 23);
+    let worklog = |account_id: &str, started: DateTime<Local>| {
+        format!(
+            r#"{{"author": {{"accountId": "{}"}}, "started": "{}",
+            "timeSpentSeconds": 3600, "comment": "fixed it"}}"#,
+            account_id,
+            started.format("%Y-%m-%dT%H:%M:%S%.3f%z")
+        )
+    };
+    let day_start = day.and_hms(0, 0, 0);
+    let next_day_start = day_start + chrono::Duration::days(1);
+    let second = chrono::Duration::seconds(1);
+    let (server_url, _) = http::serve(vec![
+        json_response(r#"{"accountId": "me-id", "name": "me"}"#),
+        json_response(
+            r#"{"startAt": 0, "total": 1, "issues": [
+                {"key": "CIG-1", "fields": {"summary": "Fix the build"}}]}"#,
+        ),
+        // the worklogs of the issue, in two pages
+        json_response(&format!(
+            r#"{{"startAt": 0, "total": 5, "worklogs": [{}, {}, {}]}}"#,
+            worklog("me-id", day_start - second),
+            worklog("me-id", day_start),
+            worklog("someone-else", day_start + chrono::Duration::hours(10)),
+        )),
+        json_response(&format!(
+            r#"{{"startAt": 3, "total": 5, "worklogs": [{}, {}]}}"#,
+            worklog("me-id", next_day_start - second),
+            worklog("me-id", next_day_start),
+        )),
+    ]);
+    let mut config = Config::default_config();
+    config.jira.insert(
+        "work".to_string(),
+        JiraConfig {
+            server_url: server_url.trim_end_matches('/').to_string(),
+            username: "me".to_string(),
+            api_token: "secret".to_string(),
+        },
+    );
+    let events = Jira
+        .get_events(&config, &FetchOptions::default(), "work", day)
+        .unwrap();
+    assert_eq!(
+        vec![day_start.time(), (next_day_start - second).time()],
+        events.iter().map(|e| e.event_time).collect::<Vec<_>>()
+    );
+    assert!(events
+        .iter()
+        .all(|e| e.event_info == "CIG-1: Fix the build"));
+}
@@ -0,0 +1,332 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://api.mattermost.com/#tag/posts/operation/SearchPosts
+// requires a personal access token.
+const PAGE_SIZE: usize = 60;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MattermostConfig {
+    pub server_url: String,
+    pub token: String,
+}
+
+pub struct Mattermost;
+const SERVER_URL_KEY: &str = "Server URL";
+const TOKEN_KEY: &str = "Personal access token";
+
+#[derive(Deserialize, Debug)]
+struct MattermostUser {
+    id: String,
+    username: String,
+}
+
+#[derive(Serialize, Debug)]
+struct MattermostSearchRequest {
+    terms: String,
+    is_or_search: bool,
+    // so that the 'on:' modifier uses our local day
+    time_zone_offset: i32,
+    page: usize,
+    per_page: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct MattermostSearchResponse {
+    order: Vec<String>,
+    posts: HashMap<String, MattermostPost>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct MattermostPost {
+    id: String,
+    // milliseconds since the epoch
+    create_at: i64,
+    user_id: String,
+    channel_id: String,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MattermostChannel {
+    // empty for direct messages
+    display_name: String,
+    name: String,
+}
+
+/// what we keep in the cache
+#[derive(Deserialize, Serialize, Debug)]
+struct MattermostDayPosts {
+    posts: Vec<MattermostPost>,
+    // channel id => channel name
+    channels: HashMap<String, String>,
+}
+
+impl Mattermost {
+    fn parse_create_at(create_at: i64) -> DateTime<Local> {
+        Utc.timestamp_millis(create_at).with_timezone(&Local)
+    }
+
+    fn mattermost_get(
+        client: &reqwest::blocking::Client,
+        mattermost_config: &MattermostConfig,
+        url_path: &str,
+    ) -> Result<String> {
+        Ok(http::send_with_retry(|| {
+            client
+                .get(&format!(
+                    "{}/api/v4{}",
+                    mattermost_config.server_url, url_path
+                ))
+                .bearer_auth(&mattermost_config.token)
+        })?
+        .text()?)
+    }
+
+    fn fetch_posts(
+        config_name: &str,
+        mattermost_config: &MattermostConfig,
+        day: Date<Local>,
+    ) -> Result<MattermostDayPosts> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let user_json = Self::mattermost_get(&client, mattermost_config, "/users/me")?;
+        let user = serde_json::from_str::<MattermostUser>(&user_json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, user_json))?;
+        let mut posts = vec![];
+        let mut page = 0;
+        loop {
+            let request = MattermostSearchRequest {
+                terms: format!("from:{} on:{}", user.username, day.format("%Y-%m-%d")),
+                is_or_search: false,
+                time_zone_offset: day.offset().local_minus_utc(),
+                page,
+                per_page: PAGE_SIZE,
+            };
+            let json = http::send_with_retry(|| {
+                client
+                    .post(&format!(
+                        "{}/api/v4/posts/search",
+                        mattermost_config.server_url
+                    ))
+                    .bearer_auth(&mattermost_config.token)
+                    .json(&request)
+            })?
+            .text()?;
+            let MattermostSearchResponse {
+                order,
+                posts: mut page_posts,
+            } = serde_json::from_str::<MattermostSearchResponse>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            posts.extend(
+                order
+                    .iter()
+                    .filter_map(|id| page_posts.remove(id))
+                    .filter(|p| p.user_id == user.id),
+            );
+            if order.len() < PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+        let mut channels = HashMap::new();
+        for post in &posts {
+            if !channels.contains_key(&post.channel_id) {
+                let channel_json = Self::mattermost_get(
+                    &client,
+                    mattermost_config,
+                    &format!("/channels/{}", post.channel_id),
+                )?;
+                let channel = serde_json::from_str::<MattermostChannel>(&channel_json)
+                    .map_err(|e| format!("Failed parsing json {:?} -- {}", e, channel_json))?;
+                channels.insert(
+                    post.channel_id.clone(),
+                    if channel.display_name.is_empty() {
+                        channel.name
+                    } else {
+                        channel.display_name
+                    },
+                );
+            }
+        }
+        let day_posts = MattermostDayPosts { posts, channels };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Mattermost, &cache_key, &serde_json::to_string(&day_posts)?)?;
+        Ok(day_posts)
+    }
+
+    fn post_to_event(
+        server_url: &str,
+        post: &MattermostPost,
+        channels: &HashMap<String, String>,
+    ) -> Event {
+        // the redirect finds the team of the post for us
+        let permalink = format!("{}/_redirect/pl/{}", server_url, post.id);
+        Event::new(
+            "Mattermost",
+            Icon::COMMENT_DOTS,
+            Self::parse_create_at(post.create_at).time(),
+            post.message.lines().next().unwrap_or("").to_string(),
+            format!(
+                "~{}",
+                channels
+                    .get(&post.channel_id)
+                    .map(|c| c.as_str())
+                    .unwrap_or("?")
+            ),
+            EventBody::Markup(
+                format!(
+                    "<a href=\"{}\">Open in Mattermost</a>\n\n{}",
+                    glib::markup_escape_text(&permalink),
+                    glib::markup_escape_text(&post.message)
+                ),
+                // messages often contain long urls
+                WordWrapMode::CharWrap,
+            ),
+            Some("Message".to_string()),
+        )
+        .with_url(&permalink)
+    }
+}
+
+impl EventProvider for Mattermost {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Mattermost"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::COMMENT_DOTS
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.mattermost.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                config.mattermost[config_name].server_url.to_string(),
+            ),
+            (TOKEN_KEY, config.mattermost[config_name].token.to_string()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.mattermost.insert(
+            config_name,
+            MattermostConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                token: config_values.remove(TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.mattermost.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let mattermost_config = &config.mattermost[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let day_posts = match config
+            .get_cached_day_contents(options, &Mattermost, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<MattermostDayPosts>(&json).ok())
+        {
+            Some(p) => p,
+            None => Self::fetch_posts(config_name, mattermost_config, day)?,
+        };
+        let mut result: Vec<Event> = day_posts
+            .posts
+            .iter()
+            .filter(|p| Self::parse_create_at(p.create_at).date() == day)
+            .map(|p| Self::post_to_event(&mattermost_config.server_url, p, &day_posts.channels))
+            .collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_converts_mattermost_timestamps() {
+    assert_eq!(
+        Utc.ymd(2020, 2, 25).and_hms_milli(16, 1, 35, 123),
+        Mattermost::parse_create_at(1582646495123)
+    );
+}
+
+#[test]
+fn it_converts_mattermost_posts_to_events() {
+    let post = MattermostPost {
+        id: "abc".to_string(),
+        create_at: 1582646495123,
+        user_id: "me".to_string(),
+        channel_id: "chan".to_string(),
+        message: "deployed <v2>\nall good".to_string(),
+    };
+    let channels = vec![("chan".to_string(), "Town Square".to_string())]
+        .into_iter()
+        .collect();
+    let event = Mattermost::post_to_event("https://chat.example.com", &post, &channels);
+    assert_eq!("~Town Square", event.event_contents_header);
+    assert_eq!("deployed <v2>", event.event_info);
+    assert_eq!(
+        Some("https://chat.example.com/_redirect/pl/abc".to_string()),
+        event.event_url
+    );
+    assert_eq!(
+        EventBody::Markup(
+            "<a href=\"https://chat.example.com/_redirect/pl/abc\">Open in Mattermost</a>\n\ndeployed &lt;v2&gt;\nall good"
+                .to_string(),
+            WordWrapMode::CharWrap
+        ),
+        event.event_contents_body
+    );
+}
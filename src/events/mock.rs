@@ -0,0 +1,127 @@
+// events read from a JSON file, for demos and tests without credentials.
+// the file contains a list of {"date": "2020-02-25", "event": {...}},
+// with the events as serialized by Event.
+use super::events::{ConfigType, Event, EventProvider, FetchOptions, Result};
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use std::collections::HashMap;
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
+pub struct MockConfig {
+    pub fixture_path: String,
+}
+
+#[derive(serde_derive::Deserialize, Debug)]
+struct MockEntry {
+    date: NaiveDate,
+    event: Event,
+}
+
+pub struct Mock;
+const FIXTURE_PATH_KEY: &str = "Fixture file (JSON)";
+
+impl Mock {
+    fn parse_fixture(contents: &str, day: Date<Local>) -> Result<Vec<Event>> {
+        let entries: Vec<MockEntry> = serde_json::from_str(contents)?;
+        Ok(entries
+            .into_iter()
+            .filter(|e| e.date == day.naive_local())
+            .map(|e| e.event)
+            .collect())
+    }
+}
+
+impl EventProvider for Mock {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![(FIXTURE_PATH_KEY, ConfigType::File)]
+    }
+
+    fn name(&self) -> &'static str {
+        "Mock"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::TASKS
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.mock.keys().collect()
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![(
+            FIXTURE_PATH_KEY,
+            config.mock[config_name].fixture_path.to_string(),
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.mock.remove(&config_name);
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.mock.insert(
+            config_name,
+            MockConfig {
+                fixture_path: config_values.remove(FIXTURE_PATH_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn validate_config(&self, config_values: &HashMap<&'static str, String>) -> Result<()> {
+        let contents = std::fs::read_to_string(&config_values[FIXTURE_PATH_KEY])?;
+        serde_json::from_str::<Vec<MockEntry>>(&contents)?;
+        Ok(())
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        _options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let contents = std::fs::read_to_string(&config.mock[config_name].fixture_path)?;
+        Self::parse_fixture(&contents, day)
+    }
+}
+
+#[test]
+fn it_returns_the_fixture_events_of_the_day() {
+    let event = Event::new(
+        "Redmine",
+        Icon::TASKS,
+        NaiveTime::from_hms(14, 30, 0),
+        "Bug #12".to_string(),
+        "Bug #12: crash".to_string(),
+        super::events::EventBody::PlainText("fixed".to_string()),
+        None,
+    );
+    let fixture = format!(
+        r#"[{{"date": "2020-02-25", "event": {}}}, {{"date": "2020-02-26", "event": {}}}]"#,
+        serde_json::to_string(&event).unwrap(),
+        serde_json::to_string(&event).unwrap()
+    );
+    let events = Mock::parse_fixture(&fixture, Local.ymd(2020, 2, 25)).unwrap();
+    assert_eq!(vec![event], events);
+}
@@ -1,7 +1,35 @@
+pub mod asana;
+pub mod bitbucket;
+pub mod customjson;
 pub mod email;
 pub mod events;
+pub mod exchange;
+pub mod filewatch;
+pub mod gerrit;
 pub mod git;
+pub mod gitea;
+pub mod github;
 pub mod gitlab;
+pub mod googlecalendar;
+pub mod harvest;
+pub mod hg;
+pub mod http;
 pub mod ical;
+pub mod imap;
+pub mod jenkins;
+pub mod jira;
+pub mod mattermost;
+pub mod mock;
+pub mod onedev;
+pub mod phabricator;
+pub mod ratelimit;
 pub mod redmine;
+pub mod rssfeed;
+pub mod slack;
 pub mod stackexchange;
+pub mod svn;
+pub mod todoist;
+pub mod toggl;
+pub mod trello;
+pub mod twofactor;
+pub mod youtrack;
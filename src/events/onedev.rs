@@ -0,0 +1,557 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://docs.onedev.io/category/rest-api
+// onedev has no activity feed: we query the commits, pull requests
+// and issues separately, using onedev's query language.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct OneDevConfig {
+    pub server_url: String,
+    pub username: String,
+    pub access_token: String,
+}
+
+pub struct OneDev;
+const SERVER_URL_KEY: &str = "Server URL";
+const USERNAME_KEY: &str = "Username";
+const ACCESS_TOKEN_KEY: &str = "Access Token";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct OneDevUser {
+    id: i64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct OneDevProject {
+    id: i64,
+    path: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct OneDevPersonIdent {
+    #[serde(deserialize_with = "deserialize_onedev_date")]
+    when: DateTime<Local>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct OneDevCommit {
+    hash: String,
+    subject: String,
+    body: Option<String>,
+    committer: OneDevPersonIdent,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OneDevPullRequest {
+    number: i64,
+    title: String,
+    description: Option<String>,
+    target_project_id: i64,
+    #[serde(deserialize_with = "deserialize_onedev_date")]
+    submit_date: DateTime<Local>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OneDevIssue {
+    id: i64,
+    number: i64,
+    title: String,
+    project_id: i64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OneDevIssueChange {
+    user_id: Option<i64>,
+    #[serde(deserialize_with = "deserialize_onedev_date")]
+    date: DateTime<Local>,
+    // polymorphic, the "@class" field tells what changed
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OneDevIssueComment {
+    user_id: Option<i64>,
+    #[serde(deserialize_with = "deserialize_onedev_date")]
+    date: DateTime<Local>,
+    content: String,
+}
+
+/// what we keep in the cache for a day
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+struct OneDevDayActivity {
+    // project path, commit
+    commits: Vec<(String, OneDevCommit)>,
+    pull_requests: Vec<OneDevPullRequest>,
+    // issue, our changes on it, our comments on it
+    issues: Vec<(OneDevIssue, Vec<OneDevIssueChange>, Vec<OneDevIssueComment>)>,
+    projects: Vec<OneDevProject>,
+}
+
+// depending on the version, onedev gives dates as milliseconds
+// since the epoch or as ISO strings.
+fn deserialize_onedev_date<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<DateTime<Local>, D::Error> {
+    use serde::de::Error;
+    let value = <serde_json::Value as serde::Deserialize>::deserialize(deserializer)?;
+    match &value {
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .and_then(|ms| Local.timestamp_millis_opt(ms).single()),
+        serde_json::Value::String(s) => DateTime::parse_from_rfc3339(s)
+            .ok()
+            .map(|d| d.with_timezone(&Local)),
+        _ => None,
+    }
+    .ok_or_else(|| D::Error::custom(format!("Invalid onedev date: {}", value)))
+}
+
+impl OneDev {
+    fn get_json<T: serde::de::DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        onedev_config: &OneDevConfig,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        let json = http::send_with_retry(|| {
+            client
+                .get(&format!("{}/~api/{}", onedev_config.server_url, path))
+                .query(query)
+                .basic_auth(&onedev_config.username, Some(&onedev_config.access_token))
+        })?
+        .text()?;
+        log::debug!("onedev {}: {}", path, json);
+        Ok(serde_json::from_str(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?)
+    }
+
+    /// all the pages of a list, `query` without the paging parameters
+    fn get_json_pages<T: serde::de::DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        onedev_config: &OneDevConfig,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Vec<T>> {
+        let mut result = vec![];
+        loop {
+            let offset = result.len().to_string();
+            let count = PAGE_SIZE.to_string();
+            let mut page_query = query.to_vec();
+            page_query.push(("offset", &offset));
+            page_query.push(("count", &count));
+            let mut page: Vec<T> = Self::get_json(client, onedev_config, path, &page_query)?;
+            let page_len = page.len();
+            result.append(&mut page);
+            if page_len < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    fn fetch_day_activity(
+        config_name: &str,
+        onedev_config: &OneDevConfig,
+        day: Date<Local>,
+    ) -> Result<OneDevDayActivity> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let me: OneDevUser = Self::get_json(&client, onedev_config, "users/me", &[])?;
+        let (day_start, next_day_start) = super::events::day_bounds(day);
+        let in_day = |d: &DateTime<Local>| *d >= day_start && *d < next_day_start;
+        let day_str = day.format("%Y-%m-%d").to_string();
+        let next_day_str = next_day_start.format("%Y-%m-%d").to_string();
+
+        let projects: Vec<OneDevProject> =
+            Self::get_json_pages(&client, onedev_config, "projects", &[])?;
+        let mut commits = vec![];
+        let commit_query = format!(
+            "committed-by-me after({}) before({})",
+            day_str, next_day_str
+        );
+        for project in &projects {
+            let path = format!("repositories/{}/commits", project.id);
+            let hashes: Vec<String> = Self::get_json(
+                &client,
+                onedev_config,
+                &path,
+                &[("query", &commit_query), ("count", "1000")],
+            )?;
+            for hash in hashes {
+                let commit: OneDevCommit =
+                    Self::get_json(&client, onedev_config, &format!("{}/{}", path, hash), &[])?;
+                if in_day(&commit.committer.when) {
+                    commits.push((project.path.clone(), commit));
+                }
+            }
+        }
+
+        let pull_requests: Vec<OneDevPullRequest> = Self::get_json_pages(
+            &client,
+            onedev_config,
+            "pulls",
+            &[(
+                "query",
+                &format!(
+                    "submitted by me and \"Submit Date\" is since \"{}\"",
+                    day_str
+                ),
+            )],
+        )?
+        .into_iter()
+        .filter(|pr: &OneDevPullRequest| in_day(&pr.submit_date))
+        .collect();
+
+        let updated_issues: Vec<OneDevIssue> = Self::get_json_pages(
+            &client,
+            onedev_config,
+            "issues",
+            &[(
+                "query",
+                &format!("\"Last Activity Date\" is since \"{}\"", day_str),
+            )],
+        )?;
+        let mut issues = vec![];
+        for issue in updated_issues {
+            let is_mine = |user_id: Option<i64>| user_id == Some(me.id);
+            let changes: Vec<OneDevIssueChange> = Self::get_json(
+                &client,
+                onedev_config,
+                &format!("issues/{}/changes", issue.id),
+                &[],
+            )?;
+            let changes: Vec<_> = changes
+                .into_iter()
+                .filter(|c| is_mine(c.user_id) && in_day(&c.date))
+                .collect();
+            let comments: Vec<OneDevIssueComment> = Self::get_json(
+                &client,
+                onedev_config,
+                &format!("issues/{}/comments", issue.id),
+                &[],
+            )?;
+            let comments: Vec<_> = comments
+                .into_iter()
+                .filter(|c| is_mine(c.user_id) && in_day(&c.date))
+                .collect();
+            if !changes.is_empty() || !comments.is_empty() {
+                issues.push((issue, changes, comments));
+            }
+        }
+
+        let activity = OneDevDayActivity {
+            commits,
+            pull_requests,
+            issues,
+            projects,
+        };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&OneDev, &cache_key, &serde_json::to_string(&activity)?)?;
+        Ok(activity)
+    }
+
+    // "io.onedev.server.model.support.issue.changedata.IssueStateChangeData"
+    // => "State change"
+    fn describe_issue_change(change: &OneDevIssueChange) -> String {
+        let class = change
+            .data
+            .get("@class")
+            .and_then(|c| c.as_str())
+            .unwrap_or("");
+        let name = class
+            .rsplit('.')
+            .next()
+            .unwrap_or("")
+            .trim_start_matches("Issue")
+            .trim_end_matches("Data");
+        let mut words = String::new();
+        for (i, c) in name.chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                words.push(' ');
+                words.extend(c.to_lowercase());
+            } else {
+                words.push(c);
+            }
+        }
+        if words.is_empty() {
+            "Issue updated".to_string()
+        } else {
+            words
+        }
+    }
+
+    fn build_events(onedev_config: &OneDevConfig, activity: &OneDevDayActivity) -> Vec<Event> {
+        let project_path = |id: i64| {
+            activity
+                .projects
+                .iter()
+                .find(|p| p.id == id)
+                .map(|p| p.path.as_str())
+                .unwrap_or("")
+        };
+        let server_url = &onedev_config.server_url;
+        let mut events = vec![];
+        for (path, commit) in &activity.commits {
+            events.push(
+                Event::new(
+                    "OneDev",
+                    Icon::CODE_BRANCH,
+                    commit.committer.when.time(),
+                    commit.subject.clone(),
+                    format!("{}: {}", path, commit.subject),
+                    EventBody::PlainText(format!(
+                        "{}\n\n{}",
+                        commit.hash,
+                        commit.body.as_deref().unwrap_or("")
+                    )),
+                    Some(path.clone()),
+                )
+                .with_url(&format!("{}/{}/~commits/{}", server_url, path, commit.hash))
+                .with_category("commit")
+                .with_dedup_key(&format!("commit:{}", commit.hash)),
+            );
+        }
+        for pr in &activity.pull_requests {
+            let path = project_path(pr.target_project_id);
+            events.push(
+                Event::new(
+                    "OneDev",
+                    Icon::CODE_BRANCH,
+                    pr.submit_date.time(),
+                    pr.title.clone(),
+                    format!("Pull request {}#{} opened", path, pr.number),
+                    EventBody::PlainText(pr.description.clone().unwrap_or_default()),
+                    Some("Pull request opened".to_string()),
+                )
+                .with_url(&format!("{}/{}/~pulls/{}", server_url, path, pr.number))
+                .with_category("pull request"),
+            );
+        }
+        for (issue, changes, comments) in &activity.issues {
+            let path = project_path(issue.project_id);
+            let url = format!("{}/{}/~issues/{}", server_url, path, issue.number);
+            let header = format!("Issue {}#{}: {}", path, issue.number, issue.title);
+            for change in changes {
+                let description = Self::describe_issue_change(change);
+                events.push(
+                    Event::new(
+                        "OneDev",
+                        Icon::TASKS,
+                        change.date.time(),
+                        issue.title.clone(),
+                        header.clone(),
+                        EventBody::PlainText(description.clone()),
+                        Some(description),
+                    )
+                    .with_url(&url)
+                    .with_category("issue"),
+                );
+            }
+            for comment in comments {
+                events.push(
+                    Event::new(
+                        "OneDev",
+                        Icon::COMMENT_DOTS,
+                        comment.date.time(),
+                        issue.title.clone(),
+                        header.clone(),
+                        EventBody::PlainText(comment.content.clone()),
+                        Some("Comment".to_string()),
+                    )
+                    .with_url(&url)
+                    .with_category("issue"),
+                );
+            }
+        }
+        events.sort_by_key(|e| e.event_time);
+        events
+    }
+}
+
+impl EventProvider for OneDev {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (ACCESS_TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "OneDev"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.onedev.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                config.onedev[config_name].server_url.to_string(),
+            ),
+            (
+                USERNAME_KEY,
+                config.onedev[config_name].username.to_string(),
+            ),
+            (
+                ACCESS_TOKEN_KEY,
+                config.onedev[config_name].access_token.to_string(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.onedev.insert(
+            config_name,
+            OneDevConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                username: config_values.remove(USERNAME_KEY).unwrap(),
+                access_token: config_values.remove(ACCESS_TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.onedev.remove(&config_name);
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            SERVER_URL_KEY => reqwest::Url::parse(value.trim())
+                .map(|_| ())
+                .map_err(|e| format!("Invalid URL: {}", e)),
+            USERNAME_KEY if value.trim().is_empty() => Err("The username is required".to_string()),
+            ACCESS_TOKEN_KEY if value.trim().is_empty() => {
+                Err("The access token is required".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let onedev_config = &config.onedev[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let activity = match config
+            .get_cached_day_contents(options, &OneDev, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<OneDevDayActivity>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_day_activity(config_name, onedev_config, day)?,
+        };
+        Ok(Self::build_events(onedev_config, &activity))
+    }
+}
+
+#[test]
+fn it_builds_onedev_events() {
+    let json = r#"{
+        "commits": [["tools/cigale", {"hash": "0123abcd", "subject": "Fix the build",
+            "body": null, "committer": {"when": 1614249000000}}]],
+        "pull_requests": [{"number": 3, "title": "Add OneDev", "description": "please review",
+            "targetProjectId": 7, "submitDate": "2021-02-25T11:00:00Z"}],
+        "issues": [[{"id": 40, "number": 12, "title": "Crash", "projectId": 7},
+            [{"userId": 1, "date": 1614250000000,
+              "data": {"@class": "io.onedev.server.model.support.issue.changedata.IssueStateChangeData"}}],
+            [{"userId": 1, "date": 1614251000000, "content": "fixed"}]]],
+        "projects": [{"id": 7, "path": "tools/cigale"}]
+    }"#;
+    let config = OneDevConfig {
+        server_url: "https://onedev.example.com".to_string(),
+        username: "me".to_string(),
+        access_token: "".to_string(),
+    };
+    let activity = serde_json::from_str::<OneDevDayActivity>(json).unwrap();
+    let events = OneDev::build_events(&config, &activity);
+    assert_eq!(
+        vec![
+            (
+                "Fix the build",
+                "https://onedev.example.com/tools/cigale/~commits/0123abcd"
+            ),
+            (
+                "Crash",
+                "https://onedev.example.com/tools/cigale/~issues/12"
+            ),
+            (
+                "Crash",
+                "https://onedev.example.com/tools/cigale/~issues/12"
+            ),
+        ],
+        events
+            .iter()
+            .filter(|e| e.event_category.as_deref() != Some("pull request"))
+            .map(|e| (e.event_info.as_str(), e.event_url.as_deref().unwrap()))
+            .collect::<Vec<_>>()
+    );
+    let state_change = events
+        .iter()
+        .find(|e| e.event_time == Local.timestamp_millis(1614250000000).time())
+        .unwrap();
+    assert_eq!(
+        "State change",
+        state_change.event_contents_body.as_plain_text()
+    );
+    let pr = events
+        .iter()
+        .find(|e| e.event_category.as_deref() == Some("pull request"))
+        .unwrap();
+    assert_eq!(
+        Some("https://onedev.example.com/tools/cigale/~pulls/3".to_string()),
+        pr.event_url
+    );
+}
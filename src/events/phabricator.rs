@@ -0,0 +1,342 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde::de::DeserializeOwned;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://secure.phabricator.com/conduit/method/feed.query/
+// works for phorge too, which kept the conduit API.
+const PAGE_SIZE: &str = "100";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PhabricatorConfig {
+    pub server_url: String,
+    pub api_token: String,
+}
+
+pub struct Phabricator;
+const SERVER_URL_KEY: &str = "Server URL";
+const API_TOKEN_KEY: &str = "API Token";
+
+/// conduit wraps all the results in this envelope, and
+/// reports errors with a 200 http status
+#[derive(Deserialize, Debug)]
+struct ConduitResponse {
+    result: Option<serde_json::Value>,
+    error_code: Option<String>,
+    error_info: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PhabricatorUser {
+    phid: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct PhabricatorStory {
+    #[serde(rename = "authorPHID")]
+    author_phid: String,
+    #[serde(rename = "objectPHID")]
+    object_phid: String,
+    #[serde(rename = "chronologicalKey")]
+    chronological_key: String,
+    epoch: i64,
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PhabricatorObject {
+    uri: String,
+    full_name: String,
+    type_name: String,
+}
+
+/// what we keep in the cache
+#[derive(Deserialize, Serialize, Debug)]
+struct PhabricatorDayActivity {
+    stories: Vec<PhabricatorStory>,
+    objects: HashMap<String, PhabricatorObject>,
+}
+
+impl Phabricator {
+    fn parse_conduit_response<T: DeserializeOwned>(json: &str) -> Result<T> {
+        let response = serde_json::from_str::<ConduitResponse>(json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+        if let Some(code) = response.error_code {
+            return Err(format!(
+                "Phabricator error {}: {}",
+                code,
+                response.error_info.unwrap_or_else(|| "".to_string())
+            )
+            .into());
+        }
+        let result = match response.result {
+            // conduit is PHP: empty maps are serialized as empty lists
+            Some(serde_json::Value::Array(a)) if a.is_empty() => {
+                serde_json::Value::Object(serde_json::Map::new())
+            }
+            Some(r) => r,
+            None => serde_json::Value::Null,
+        };
+        Ok(serde_json::from_value(result)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?)
+    }
+
+    // conduit wants form-encoded POSTs, with the token as a parameter
+    fn conduit_call<T: DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        phabricator_config: &PhabricatorConfig,
+        method: &str,
+        params: &[(String, String)],
+    ) -> Result<T> {
+        let mut form = vec![(
+            "api.token".to_string(),
+            phabricator_config.api_token.clone(),
+        )];
+        form.extend(params.iter().cloned());
+        let json = http::send_with_retry(|| {
+            client
+                .post(&format!("{}/api/{}", phabricator_config.server_url, method))
+                .form(&form)
+        })?
+        .text()?;
+        Self::parse_conduit_response(&json)
+    }
+
+    fn fetch_activity(
+        config_name: &str,
+        phabricator_config: &PhabricatorConfig,
+        day: Date<Local>,
+    ) -> Result<PhabricatorDayActivity> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let user: PhabricatorUser =
+            Self::conduit_call(&client, phabricator_config, "user.whoami", &[])?;
+        let day_start = day.and_hms(0, 0, 0).timestamp();
+        let day_end = (day.and_hms(0, 0, 0) + chrono::Duration::days(1)).timestamp();
+        // the chronological keys are the epoch shifted left by 32 bits,
+        // so we can jump directly to the end of the day, then page back.
+        let mut before = ((day_end as u64) << 32).to_string();
+        let mut stories = vec![];
+        loop {
+            let page: HashMap<String, PhabricatorStory> = Self::conduit_call(
+                &client,
+                phabricator_config,
+                "feed.query",
+                &[
+                    ("filterPHIDs[0]".to_string(), user.phid.clone()),
+                    ("view".to_string(), "text".to_string()),
+                    ("limit".to_string(), PAGE_SIZE.to_string()),
+                    ("before".to_string(), before.clone()),
+                ],
+            )?;
+            let oldest = page.values().min_by_key(|s| s.epoch).cloned();
+            stories.extend(
+                page.into_values()
+                    .filter(|s| s.epoch >= day_start && s.author_phid == user.phid),
+            );
+            match oldest {
+                Some(s) if s.epoch >= day_start => before = s.chronological_key,
+                _ => break,
+            }
+        }
+        let mut object_params = vec![];
+        for (i, phid) in stories.iter().map(|s| &s.object_phid).enumerate() {
+            object_params.push((format!("phids[{}]", i), phid.clone()));
+        }
+        let objects = if object_params.is_empty() {
+            HashMap::new()
+        } else {
+            Self::conduit_call(&client, phabricator_config, "phid.query", &object_params)?
+        };
+        let activity = PhabricatorDayActivity { stories, objects };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Phabricator, &cache_key, &serde_json::to_string(&activity)?)?;
+        Ok(activity)
+    }
+
+    fn story_to_event(
+        story: &PhabricatorStory,
+        objects: &HashMap<String, PhabricatorObject>,
+    ) -> Option<Event> {
+        let object = objects.get(&story.object_phid)?;
+        // we're interested in revisions and tasks, not in
+        // commits (covered by the VCS providers), wiki pages...
+        let icon = if story.object_phid.starts_with("PHID-DREV-") {
+            Icon::CODE_BRANCH
+        } else if story.object_phid.starts_with("PHID-TASK-") {
+            Icon::TASKS
+        } else {
+            return None;
+        };
+        Some(
+            Event::new(
+                "Phabricator",
+                icon,
+                Local.timestamp(story.epoch, 0).time(),
+                object.full_name.clone(),
+                object.full_name.clone(),
+                EventBody::PlainText(story.text.clone().unwrap_or_else(|| "".to_string())),
+                Some(object.type_name.clone()),
+            )
+            .with_url(&object.uri),
+        )
+    }
+}
+
+impl EventProvider for Phabricator {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (API_TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Phabricator"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.phabricator.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                config.phabricator[config_name].server_url.to_string(),
+            ),
+            (
+                API_TOKEN_KEY,
+                config.phabricator[config_name].api_token.to_string(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.phabricator.insert(
+            config_name,
+            PhabricatorConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                api_token: config_values.remove(API_TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.phabricator.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let phabricator_config = &config.phabricator[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let activity = match config
+            .get_cached_day_contents(options, &Phabricator, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<PhabricatorDayActivity>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_activity(config_name, phabricator_config, day)?,
+        };
+        let day_start = day.and_hms(0, 0, 0).timestamp();
+        let day_end = (day.and_hms(0, 0, 0) + chrono::Duration::days(1)).timestamp();
+        let mut result: Vec<Event> = activity
+            .stories
+            .iter()
+            .filter(|s| s.epoch >= day_start && s.epoch < day_end)
+            .filter_map(|s| Self::story_to_event(s, &activity.objects))
+            .collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_parses_conduit_responses() {
+    let empty = Phabricator::parse_conduit_response::<HashMap<String, PhabricatorStory>>(
+        r#"{"result": [], "error_code": null, "error_info": null}"#,
+    )
+    .unwrap();
+    assert!(empty.is_empty());
+    let error = Phabricator::parse_conduit_response::<PhabricatorUser>(
+        r#"{"result": null, "error_code": "ERR-INVALID-AUTH", "error_info": "API token is bad."}"#,
+    );
+    assert_eq!(
+        "Phabricator error ERR-INVALID-AUTH: API token is bad.",
+        error.unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn it_converts_phabricator_stories_to_events() {
+    let stories = Phabricator::parse_conduit_response::<HashMap<String, PhabricatorStory>>(
+        r#"{"result": {
+            "PHID-STRY-1": {"class": "PhabricatorApplicationTransactionFeedStory",
+              "epoch": 1582624800, "authorPHID": "PHID-USER-me", "chronologicalKey": "6797000000000000000",
+              "objectPHID": "PHID-DREV-abc", "text": "me accepted D12: Fix the build."},
+            "PHID-STRY-2": {"class": "PhabricatorApplicationTransactionFeedStory",
+              "epoch": 1582624900, "authorPHID": "PHID-USER-me", "chronologicalKey": "6797000000000000001",
+              "objectPHID": "PHID-WIKI-abc", "text": "me edited the wiki."}
+        }, "error_code": null, "error_info": null}"#,
+    )
+    .unwrap();
+    let objects = Phabricator::parse_conduit_response::<HashMap<String, PhabricatorObject>>(
+        r#"{"result": {
+            "PHID-DREV-abc": {"phid": "PHID-DREV-abc", "uri": "https://phab.example.com/D12",
+              "typeName": "Differential Revision", "type": "DREV", "name": "D12",
+              "fullName": "D12: Fix the build", "status": "open"}
+        }, "error_code": null, "error_info": null}"#,
+    )
+    .unwrap();
+    let revision = Phabricator::story_to_event(&stories["PHID-STRY-1"], &objects).unwrap();
+    assert_eq!("D12: Fix the build", revision.event_contents_header);
+    assert_eq!(
+        Some("https://phab.example.com/D12".to_string()),
+        revision.event_url
+    );
+    assert_eq!(
+        Some("Differential Revision".to_string()),
+        revision.event_extra_details
+    );
+    assert!(Phabricator::story_to_event(&stories["PHID-STRY-2"], &objects).is_none());
+}
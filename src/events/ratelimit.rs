@@ -0,0 +1,162 @@
+// some APIs (GitHub, Slack, Stack Exchange) limit the number of requests
+// we can make. with several event sources on the same server, and the
+// week view fetching seven days in parallel, we could go over the limit:
+// all the requests go through a token bucket per host, shared by all
+// the event sources. we wait for a token rather than failing.
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct TokenBucket {
+    per_minute: u32,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(per_minute: u32, now: Instant) -> TokenBucket {
+        TokenBucket {
+            per_minute,
+            // allow a burst of a minute's worth of requests
+            tokens: per_minute as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.per_minute as f64 / 60.0).min(self.per_minute as f64);
+        self.last_refill = now;
+    }
+
+    /// take a token, or how long to wait until one is available
+    fn try_acquire(&mut self, now: Instant) -> Option<Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - self.tokens) * 60.0 / self.per_minute as f64,
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RateLimiter {
+    // hosts without a limit are not in there
+    buckets: HashMap<String, TokenBucket>,
+    // the host answered 429 (too many requests), wait until then.
+    // applies also to hosts without a configured limit.
+    paused_until: HashMap<String, Instant>,
+}
+
+impl RateLimiter {
+    fn set_host_limits(&mut self, limits: &HashMap<String, u32>, now: Instant) {
+        self.buckets
+            .retain(|host, bucket| limits.get(host) == Some(&bucket.per_minute));
+        for (host, per_minute) in limits.iter().filter(|(_, l)| **l > 0) {
+            self.buckets
+                .entry(host.clone())
+                .or_insert_with(|| TokenBucket::new(*per_minute, now));
+        }
+    }
+
+    fn try_acquire(&mut self, host: &str, now: Instant) -> Option<Duration> {
+        if let Some(until) = self.paused_until.get(host).copied() {
+            if until > now {
+                return Some(until - now);
+            }
+            self.paused_until.remove(host);
+        }
+        self.buckets
+            .get_mut(host)
+            .and_then(|bucket| bucket.try_acquire(now))
+    }
+
+    fn pause_host(&mut self, host: &str, until: Instant) {
+        let paused_until = self.paused_until.entry(host.to_string()).or_insert(until);
+        *paused_until = (*paused_until).max(until);
+    }
+}
+
+static RATE_LIMITER: Mutex<Option<RateLimiter>> = Mutex::new(None);
+
+fn with_limiter<T>(f: impl FnOnce(&mut RateLimiter) -> T) -> T {
+    // a panic while holding the lock can't leave the limiter inconsistent
+    let mut limiter = RATE_LIMITER.lock().unwrap_or_else(|e| e.into_inner());
+    f(limiter.get_or_insert_with(RateLimiter::default))
+}
+
+/// the maximum number of requests per minute, by host.
+/// 0 or a host missing from the map means no limit.
+pub fn set_host_limits(limits: &HashMap<String, u32>) {
+    with_limiter(|l| l.set_host_limits(limits, Instant::now()));
+}
+
+/// block until we're allowed to send a request to `host`
+pub fn acquire(host: &str) {
+    while let Some(delay) = with_limiter(|l| l.try_acquire(host, Instant::now())) {
+        log::debug!("{}: rate limited, waiting {:?}", host, delay);
+        std::thread::sleep(delay);
+    }
+}
+
+/// block until we're allowed to send a request to the host of `url`,
+/// for the requests which don't go through http::send_with_retry
+pub fn acquire_url(url: &str) {
+    if let Some(host) = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+    {
+        acquire(&host);
+    }
+}
+
+/// the server told us we're sending too many requests: hold all
+/// the requests to that host, not only the one which got rejected.
+pub fn pause_host(host: &str, delay: Duration) {
+    with_limiter(|l| l.pause_host(host, Instant::now() + delay));
+}
+
+#[test]
+fn it_refills_the_token_bucket() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::default();
+    limiter.set_host_limits(
+        &vec![("api.github.com".to_string(), 2)]
+            .into_iter()
+            .collect(),
+        start,
+    );
+    assert_eq!(None, limiter.try_acquire("api.github.com", start));
+    assert_eq!(None, limiter.try_acquire("api.github.com", start));
+    assert_eq!(
+        Some(Duration::from_secs(30)),
+        limiter.try_acquire("api.github.com", start)
+    );
+    assert_eq!(
+        None,
+        limiter.try_acquire("api.github.com", start + Duration::from_secs(30))
+    );
+    // other hosts are not limited
+    assert_eq!(None, limiter.try_acquire("example.com", start));
+}
+
+#[test]
+fn it_pauses_a_host_after_too_many_requests() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::default();
+    limiter.pause_host("example.com", start + Duration::from_secs(10));
+    assert_eq!(
+        Some(Duration::from_secs(10)),
+        limiter.try_acquire("example.com", start)
+    );
+    assert_eq!(
+        None,
+        limiter.try_acquire("example.com", start + Duration::from_secs(10))
+    );
+}
@@ -2,30 +2,102 @@
 // 1. unless the redmine admin greenlights it, a user may be unable to get an apikey
 // 2. the redmine rest api doesn't offer an activity API https://www.redmine.org/issues/14872
 //    without such an API, this would be very painful and very slow
-use super::events::{ConfigType, Event, EventBody, EventProvider, Result, WordWrapMode};
+use super::events::{
+    ConfigType, Event, EventBody, EventError, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use super::twofactor;
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
 use core::time::Duration;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
 
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
 pub struct RedmineConfig {
     pub server_url: String,
     pub username: String,
-    pub password: String,
+    pub password: String, // config::SECRET_IN_KEYRING if stored in the keyring
+    // display the activity of that user instead of the logged-in user
+    #[serde(default)] // was added later, after 0.5.3
+    pub user_id_override: Option<String>,
+    // display only the activity of that project (identifier as in the url)
+    #[serde(default)] // was added later, after 0.5.3
+    pub project_filter: Option<String>,
+    // when unset, display the activity of everybody, not only ours
+    #[serde(default = "RedmineConfig::default_only_my_activity")]
+    // was added later, after 0.5.3
+    pub only_my_activity: bool,
+    // activity types (as in redmine's show_<type> url parameters)
+    // to leave out. when empty, we let redmine pick the types.
+    #[serde(default)] // was added later, after 0.5.3
+    pub hidden_activity_types: Vec<String>,
+    // command printing the password, for instance `pass show work/redmine`.
+    // it runs without a shell (no pipes or variables), and we strip only the
+    // final newline of its output: unlike trimming all the trailing
+    // whitespace, that keeps passwords ending with spaces intact.
+    // takes precedence over `password` when set.
+    #[serde(default)] // was added later, after 0.5.3
+    pub password_command: Option<String>,
+    // the key of redmine's atom feeds ("My account" page): when set, we read
+    // the activity feed instead of logging in and scraping the html.
+    // config::SECRET_IN_KEYRING if stored in the keyring
+    #[serde(default)] // was added later, after 0.5.3
+    pub rss_key: Option<String>,
+}
+
+impl RedmineConfig {
+    fn default_only_my_activity() -> bool {
+        true
+    }
 }
 
 pub struct Redmine;
 const SERVER_URL_KEY: &str = "Server URL";
 const USERNAME_KEY: &str = "Username";
 const PASSWORD_KEY: &str = "Password";
+const PASSWORD_COMMAND_KEY: &str = "Password command (optional)";
+const RSS_KEY_KEY: &str = "RSS key (optional)";
+const USER_ID_OVERRIDE_KEY: &str = "User id (optional)";
+const PROJECT_FILTER_KEY: &str = "Project (optional)";
+const ONLY_MY_ACTIVITY_KEY: &str = "Only my activity";
+// the rss key is not tied to the username, unlike the password
+const RSS_KEY_KEYRING_USERNAME: &str = "rss_key";
+// config field, activity type in the redmine url parameters
+const ACTIVITY_TYPES: [(&str, &str); 7] = [
+    ("Show issues", "issues"),
+    ("Show changesets", "changesets"),
+    ("Show news", "news"),
+    ("Show documents", "documents"),
+    ("Show files", "files"),
+    ("Show wiki edits", "wiki_edits"),
+    ("Show messages", "messages"),
+];
+// some themes and locales add seconds, or display am/pm in lowercase
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+/// a logged-in redmine http client
+struct RedmineSession {
+    client: reqwest::blocking::Client,
+    user_id: String,
+    // hardened redmines want the token of the latest page in the next requests
+    csrf_token: Option<String>,
+}
 
 enum ActivityData {
     Done(Vec<Event>),
-    ReachedEndOfPage(Option<String>), // link to the previous page or None if no previous
+    // the events of the day at the end of the page, if any: the day may
+    // go on in the previous page. link to the previous page or None if no previous
+    ReachedEndOfPage(Vec<Event>, Option<String>),
+    // we got the login page instead of the activity
+    SessionExpired,
 }
 
+/// fetches an activity page: the url, or the first activity page if None.
+/// `relogin` requests to log in again first, for instance if the session expired.
+type ActivityPageFetcher<'a> = dyn FnMut(Option<&str>, bool) -> Result<String> + 'a;
+
 #[derive(Debug)]
 struct LocaleInfo {
     date_format: &'static str,
@@ -70,11 +142,25 @@ impl Redmine {
 
     fn parse_time(time_str: &str) -> Result<NaiveTime> {
         log::debug!("parse_time: parsing {}", time_str);
-        Ok(if time_str.contains(' ') {
-            NaiveTime::parse_from_str(time_str, "%I:%M %p")?
-        } else {
-            NaiveTime::parse_from_str(time_str, "%H:%M")?
-        })
+        let time_str = time_str.trim();
+        let uppercase_time_str = time_str.to_uppercase();
+        TIME_FORMATS
+            .iter()
+            .flat_map(|fmt| {
+                vec![
+                    NaiveTime::parse_from_str(time_str, fmt),
+                    NaiveTime::parse_from_str(&uppercase_time_str, fmt),
+                ]
+            })
+            .find_map(|r| r.ok())
+            .ok_or_else(|| {
+                format!(
+                    "Can't parse redmine time '{}', tried the formats: {}",
+                    time_str,
+                    TIME_FORMATS.join(", ")
+                )
+                .into()
+            })
     }
 
     fn redmine_locales() -> HashMap<&'static str, LocaleInfo> {
@@ -134,6 +220,122 @@ impl Redmine {
         .collect()
     }
 
+    // redmine derives the lang attribute from the browser/server locale,
+    // so we can get region variants that we don't know about, like pt-PT
+    // or en-US. In that case fall back to the base language.
+    fn find_locale<'a>(
+        redmine_locales: &'a HashMap<&'static str, LocaleInfo>,
+        locale_str: &str,
+    ) -> Option<&'a LocaleInfo> {
+        redmine_locales.get(locale_str).or_else(|| {
+            locale_str
+                .split('-')
+                .next()
+                .and_then(|base_lang| redmine_locales.get(base_lang))
+        })
+    }
+
+    fn config_values(
+        redmine_config: &RedmineConfig,
+        password: String,
+        rss_key: String,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (SERVER_URL_KEY, redmine_config.server_url.to_string()),
+            (USERNAME_KEY, redmine_config.username.to_string()),
+            (PASSWORD_KEY, password),
+            (
+                PASSWORD_COMMAND_KEY,
+                redmine_config
+                    .password_command
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (RSS_KEY_KEY, rss_key),
+            (
+                USER_ID_OVERRIDE_KEY,
+                redmine_config
+                    .user_id_override
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (
+                PROJECT_FILTER_KEY,
+                redmine_config
+                    .project_filter
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (
+                ONLY_MY_ACTIVITY_KEY,
+                redmine_config.only_my_activity.to_string(),
+            ),
+        ]
+        .into_iter()
+        .chain(ACTIVITY_TYPES.iter().map(|(key, activity_type)| {
+            (
+                *key,
+                (!redmine_config
+                    .hidden_activity_types
+                    .iter()
+                    .any(|h| h == activity_type))
+                .to_string(),
+            )
+        }))
+        .collect()
+    }
+
+    fn read_stored_password(config_name: &str, redmine_config: &RedmineConfig) -> Result<String> {
+        Config::read_secret(
+            &Redmine,
+            config_name,
+            &redmine_config.username,
+            &redmine_config.password,
+        )
+    }
+
+    fn read_rss_key(config_name: &str, redmine_config: &RedmineConfig) -> Result<Option<String>> {
+        redmine_config
+            .rss_key
+            .as_ref()
+            .map(|rss_key| {
+                Config::read_secret(&Redmine, config_name, RSS_KEY_KEYRING_USERNAME, rss_key)
+            })
+            .transpose()
+    }
+
+    fn read_password(config_name: &str, redmine_config: &RedmineConfig) -> Result<String> {
+        match &redmine_config.password_command {
+            Some(cmd) => Self::run_password_command(cmd),
+            None => Self::read_stored_password(config_name, redmine_config),
+        }
+    }
+
+    fn run_password_command(password_command: &str) -> Result<String> {
+        // run the command directly, without a shell interpreting
+        // pipes, redirections or variables in it.
+        let args = shell_words::split(password_command)
+            .map_err(|e| EventError::Config(format!("Invalid password command: {}", e)))?;
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| EventError::Config("The password command is empty".to_string()))?;
+        let output = Command::new(program).args(args).output()?;
+        if !output.status.success() {
+            return Err(EventError::Config(format!(
+                "The password command failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ))
+            .into());
+        }
+        // only strip the final newline, the password may end with whitespace
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let password = stdout
+            .strip_suffix("\r\n")
+            .or_else(|| stdout.strip_suffix('\n'))
+            .unwrap_or(&stdout);
+        Ok(password.to_string())
+    }
+
     fn parse_events<'a>(
         redmine_config: &RedmineConfig,
         contents_elt: &scraper::element_ref::ElementRef<'a>,
@@ -153,33 +355,274 @@ impl Redmine {
                 let time_elt = &next_time.unwrap();
                 let time_str = time_elt.inner_html();
                 let time = Self::parse_time(&time_str)?;
-                let description_elt = &it_descriptions
+                let description_elt = &it_descriptions.next().ok_or_else(|| {
+                    EventError::Parse("Redmine event: no description?".to_string())
+                })?;
+                let link_elt = &it_links
                     .next()
-                    .ok_or("Redmine event: no description?")?;
-                let link_elt = &it_links.next().ok_or("Redmine event: no link?")?;
-                result.push(Event::new(
+                    .ok_or_else(|| EventError::Parse("Redmine event: no link?".to_string()))?;
+                let event = Event::new(
                     "Redmine",
-                    Icon::TASKS,
+                    Self::parse_event_icon(link_elt),
                     time,
                     link_elt.inner_html(),
                     link_elt.inner_html(),
                     EventBody::Markup(
-                        format!(
-                            "<a href=\"{}{}\">Open in the browser</a>\n{}",
-                            redmine_config.server_url,
-                            link_elt.value().attr("href").unwrap_or(""),
-                            glib::markup_escape_text(&description_elt.inner_html()),
-                        ),
+                        glib::markup_escape_text(&description_elt.inner_html()).to_string(),
                         WordWrapMode::WordWrap,
                     ),
                     None,
-                ));
+                )
+                .with_url(&Self::server_url_join(
+                    redmine_config,
+                    link_elt.value().attr("href").unwrap_or(""),
+                )?);
+                let event = match Self::parse_event_category(link_elt) {
+                    Some(c) => event.with_category(c),
+                    None => event,
+                };
+                let event = match Self::parse_changeset_revision(link_elt) {
+                    Some(rev) => event.with_dedup_key(&format!("commit:{}", rev)),
+                    None => event,
+                };
+                result.push(match Self::parse_time_entry_duration(link_elt) {
+                    Some(d) => event.with_duration(d),
+                    None => event,
+                });
             }
         }
         Ok(result)
     }
 
-    fn init_client(redmine_config: &RedmineConfig) -> Result<(reqwest::blocking::Client, String)> {
+    // the dt containing the link has a class for the
+    // kind of activity: issue-edit, changeset, time-entry...
+    // newer redmines also have it with an icon- prefix.
+    fn has_event_class(link_elt: &scraper::element_ref::ElementRef, class: &str) -> bool {
+        link_elt
+            .parent()
+            .and_then(scraper::element_ref::ElementRef::wrap)
+            .map_or(false, |dt| {
+                dt.value()
+                    .classes()
+                    .any(|c| c == class || c.strip_prefix("icon-") == Some(class))
+            })
+    }
+
+    // changeset links end with the revision: .../repository/revisions/<sha>
+    fn parse_changeset_revision(link_elt: &scraper::element_ref::ElementRef) -> Option<String> {
+        if !Self::has_event_class(link_elt, "changeset") {
+            return None;
+        }
+        link_elt
+            .value()
+            .attr("href")
+            .and_then(|href| href.split("/revisions/").nth(1))
+            .map(|rev| rev.trim_end_matches('/').to_lowercase())
+            .filter(|rev| !rev.is_empty())
+    }
+
+    fn parse_event_icon(link_elt: &scraper::element_ref::ElementRef) -> Icon {
+        [
+            ("changeset", Icon::CODE_BRANCH),
+            ("issue-note", Icon::COMMENT_DOTS),
+            ("message", Icon::COMMENT_DOTS),
+            ("reply", Icon::COMMENT_DOTS),
+            ("issue-closed", Icon::CHECK_SQUARE),
+            ("wiki-page", Icon::FILE_ALT),
+            ("document", Icon::FILE_ALT),
+            ("time-entry", Icon::CLOCK),
+            ("news", Icon::NEWSPAPER),
+        ]
+        .iter()
+        .find(|(class, _)| Self::has_event_class(link_elt, class))
+        .map(|(_, icon)| icon.clone())
+        .unwrap_or(Icon::TASKS)
+    }
+
+    fn parse_event_category(link_elt: &scraper::element_ref::ElementRef) -> Option<&'static str> {
+        [
+            ("issue", "issue"),
+            ("issue-edit", "issue"),
+            ("issue-note", "issue"),
+            ("issue-closed", "issue"),
+            ("changeset", "commit"),
+            ("wiki-page", "wiki"),
+            ("time-entry", "time entry"),
+            ("news", "news"),
+            ("document", "document"),
+            ("message", "forum"),
+            ("reply", "forum"),
+        ]
+        .iter()
+        .find(|(class, _)| Self::has_event_class(link_elt, class))
+        .map(|(_, category)| *category)
+    }
+
+    // time entries are displayed like "2.50 hours (Bug #12: title)",
+    // in the dt with the 'time-entry' class
+    fn parse_time_entry_duration(
+        link_elt: &scraper::element_ref::ElementRef,
+    ) -> Option<chrono::Duration> {
+        if !Self::has_event_class(link_elt, "time-entry") {
+            return None;
+        }
+        Self::parse_time_entry_hours(&link_elt.inner_html())
+    }
+
+    fn parse_time_entry_hours(title: &str) -> Option<chrono::Duration> {
+        let hours = title
+            .split_whitespace()
+            .next()?
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()?;
+        Some(chrono::Duration::minutes((hours * 60.0).round() as i64))
+    }
+
+    // rails puts the token in a meta tag of every page, and in the
+    // forms. the login page of older redmines only has the form one.
+    fn extract_csrf_token(html: &str) -> Option<String> {
+        let doc = scraper::Html::parse_document(html);
+        let meta_sel = scraper::Selector::parse("meta[name=csrf-token]").unwrap();
+        let input_sel = scraper::Selector::parse("input[name=authenticity_token]").unwrap();
+        doc.select(&meta_sel)
+            .next()
+            .and_then(|m| m.value().attr("content"))
+            .or_else(|| {
+                doc.select(&input_sel)
+                    .next()
+                    .and_then(|i| i.value().attr("value"))
+            })
+            .map(|t| t.to_string())
+    }
+
+    fn is_csrf_failure(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+        error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(|e| e.status())
+            == Some(reqwest::StatusCode::UNPROCESSABLE_ENTITY)
+    }
+
+    /// get a page, sending the csrf token. some hardened redmines reject
+    /// the token after a while: in that case we refresh it and retry once.
+    fn get_html(
+        session: &mut RedmineSession,
+        redmine_config: &RedmineConfig,
+        url: &str,
+    ) -> Result<String> {
+        Self::get_html_revalidating(session, redmine_config, url, None).map(|(html, _)| html)
+    }
+
+    /// like get_html, but if we have a cached copy of the page, we ask
+    /// the server whether it changed, and reuse it if it didn't.
+    fn get_html_revalidating(
+        session: &mut RedmineSession,
+        redmine_config: &RedmineConfig,
+        url: &str,
+        cached: Option<(String, http::CacheValidators)>,
+    ) -> Result<(String, http::CacheValidators)> {
+        let client = &session.client;
+        let validators = cached.as_ref().map(|(_, v)| v);
+        // stock redmines only check the token on POSTs and ignore it on
+        // GETs. the 422s on /activity come from hardened setups which check
+        // it on every request, so we always send the token of the latest page.
+        let send = |csrf_token: &Option<String>| {
+            http::send_with_retry(|| {
+                let request = match csrf_token {
+                    Some(t) => client.get(url).header("X-CSRF-Token", t),
+                    None => client.get(url),
+                };
+                http::with_validators(request, validators)
+            })
+        };
+        let resp = match send(&session.csrf_token) {
+            Err(e) if Self::is_csrf_failure(e.as_ref()) => {
+                log::info!("Redmine rejected the CSRF token, refreshing it");
+                let home =
+                    http::send_with_retry(|| client.get(&redmine_config.server_url))?.text()?;
+                session.csrf_token = Self::extract_csrf_token(&home);
+                send(&session.csrf_token)?
+            }
+            r => r?,
+        };
+        match cached {
+            Some(c) if resp.status() == reqwest::StatusCode::NOT_MODIFIED => {
+                // the csrf token in the cached page is outdated, keep ours
+                log::debug!("{}: not modified, using the cache", url);
+                Ok(c)
+            }
+            _ => {
+                let validators = http::CacheValidators::from_response(&resp);
+                let html = resp.text()?;
+                if let Some(token) = Self::extract_csrf_token(&html) {
+                    session.csrf_token = Some(token);
+                }
+                Ok((html, validators))
+            }
+        }
+    }
+
+    // with two-factor authentication enabled, after the password
+    // redmine displays a form asking for the code
+    fn is_two_factor_challenge(html: &str) -> bool {
+        let doc = scraper::Html::parse_document(html);
+        let code_sel = scraper::Selector::parse("input[name='twofa_code']").unwrap();
+        doc.select(&code_sel).next().is_some()
+    }
+
+    fn two_factor_form_url(redmine_config: &RedmineConfig, html: &str) -> Result<String> {
+        let doc = scraper::Html::parse_document(html);
+        let form_sel = scraper::Selector::parse("form[action*='twofa']").unwrap();
+        let action = doc
+            .select(&form_sel)
+            .next()
+            .and_then(|f| f.value().attr("action"))
+            .unwrap_or("account/twofa");
+        Self::server_url_join(redmine_config, action)
+    }
+
+    /// `interactive` is false when nobody waits for the fetch, for
+    /// instance a background poll: we can't ask for the code then.
+    fn submit_two_factor_code(
+        client: &reqwest::blocking::Client,
+        redmine_config: &RedmineConfig,
+        challenge_html: &str,
+        interactive: bool,
+    ) -> Result<String> {
+        if !interactive {
+            return Err(EventError::Auth(
+                "This Redmine account requires a two-factor authentication code, refresh to enter it"
+                    .to_string(),
+            )
+            .into());
+        }
+        let code = twofactor::ask_code(&format!("Redmine ({})", redmine_config.server_url))?
+            .ok_or_else(|| {
+                EventError::Auth(
+                    "This Redmine account requires two-factor authentication, and no code was entered"
+                        .to_string(),
+                )
+            })?;
+        let auth_token = Self::extract_csrf_token(challenge_html).ok_or_else(|| {
+            EventError::Parse("Can't find the redmine authenticity token".to_string())
+        })?;
+        let url = Self::two_factor_form_url(redmine_config, challenge_html)?;
+        let html = http::send_with_retry(|| {
+            client
+                .post(&url)
+                .form(&[("twofa_code", &code), ("authenticity_token", &auth_token)])
+        })?
+        .text()?;
+        if Self::is_two_factor_challenge(&html) {
+            return Err(EventError::Auth(
+                "Redmine rejected the two-factor authentication code".to_string(),
+            )
+            .into());
+        }
+        Ok(html)
+    }
+
+    fn init_client(redmine_config: &RedmineConfig, interactive: bool) -> Result<RedmineSession> {
         let client = reqwest::blocking::ClientBuilder::new()
             .cookie_store(true)
             .timeout(Duration::from_secs(30))
@@ -187,31 +630,48 @@ impl Redmine {
             .connection_verbose(true)
             .build()?;
 
-        let html = client
-            .get(&redmine_config.server_url)
-            .send()?
-            .error_for_status()?
-            .text()?;
+        let html = http::send_with_retry(|| client.get(&redmine_config.server_url))?.text()?;
         log::debug!("Got back html {}", html);
-        let doc = scraper::Html::parse_document(&html);
-        let sel = scraper::Selector::parse("input[name=authenticity_token]").unwrap();
-        let auth_token_node = doc.select(&sel).next().unwrap();
-        let auth_token = auth_token_node.value().attr("value").unwrap();
+        let auth_token = Self::extract_csrf_token(&html).ok_or_else(|| {
+            EventError::Parse("Can't find the redmine authenticity token".to_string())
+        })?;
 
-        let html = client
-            .post(&format!("{}/login", redmine_config.server_url))
-            .form(&[
+        let login_url = Self::server_url_join(redmine_config, "login")?;
+        let html = http::send_with_retry(|| {
+            client.post(&login_url).form(&[
                 ("username", &redmine_config.username),
                 ("password", &redmine_config.password),
                 ("login", &"Login".to_string()),
                 ("utf8", &"✓".to_string()),
                 ("back_url", &redmine_config.server_url),
-                ("authenticity_token", &auth_token.to_string()),
+                ("authenticity_token", &auth_token),
             ])
-            .send()?
-            .error_for_status()?
-            .text()?;
+        })?
+        .text()?;
+        let html = if Self::is_two_factor_challenge(&html) {
+            Self::submit_two_factor_code(&client, redmine_config, &html, interactive)?
+        } else {
+            html
+        };
+        // the token may be single-use: the next requests need the new one
+        let csrf_token = Self::extract_csrf_token(&html);
         let doc = scraper::Html::parse_document(&html);
+        // redmine displays the login form again when the login failed
+        let login_sel = scraper::Selector::parse("#login-form").unwrap();
+        if doc.select(&login_sel).next().is_some() {
+            return Err(EventError::Auth(
+                "Redmine login failed, the username or the password is wrong".to_string(),
+            )
+            .into());
+        }
+        if let Some(user_id) = &redmine_config.user_id_override {
+            // no need to find out who we are
+            return Ok(RedmineSession {
+                client,
+                user_id: user_id.clone(),
+                csrf_token,
+            });
+        }
         let user_sel = scraper::Selector::parse("a.user.active").unwrap();
         let user_id = doc
             .select(&user_sel)
@@ -221,25 +681,312 @@ impl Redmine {
             .attr("href")
             .ok_or("Failed getting the user id#2")?
             .replace("/users/", "");
-        Ok((client, user_id))
+        Ok(RedmineSession {
+            client,
+            user_id,
+            csrf_token,
+        })
     }
 
     fn fetch_activity_html(
+        options: &FetchOptions,
         config_name: &str,
         redmine_config: &RedmineConfig,
-    ) -> Result<(reqwest::blocking::Client, String)> {
-        let (client, user_id) = Self::init_client(redmine_config)?;
+    ) -> Result<(RedmineSession, String)> {
+        let mut session = Self::init_client(redmine_config, options.interactive)?;
 
-        let html = client
-            .get(&format!(
-                "{}/activity?user_id={}",
-                redmine_config.server_url, user_id
-            ))
-            .send()?
-            .error_for_status()?
-            .text()?;
-        Config::write_to_cache(&Redmine, config_name, &html)?;
-        Ok((client, html))
+        let url = Self::activity_url(redmine_config, &session.user_id)?;
+        let cache_key = Self::cache_key(config_name, redmine_config);
+        // when the user forces a refresh, we don't trust the cache at all
+        let cached = if options.bypass_cache {
+            None
+        } else {
+            Config::get_cached_contents_with_validators(&Redmine, &cache_key)?
+        };
+        let (html, validators) =
+            Self::get_html_revalidating(&mut session, redmine_config, &url, cached)?;
+        Config::write_to_cache_with_validators(&Redmine, &cache_key, &html, &validators)?;
+        Ok((session, html))
+    }
+
+    /// the server may be under a subpath, like https://example.com/redmine:
+    /// relative paths go under it, and links starting with / are already
+    /// absolute paths on the server, including the subpath.
+    fn server_url_join(redmine_config: &RedmineConfig, path: &str) -> Result<String> {
+        let base = format!("{}/", redmine_config.server_url.trim_end_matches('/'));
+        Ok(reqwest::Url::parse(&base)?.join(path)?.to_string())
+    }
+
+    fn activity_url(redmine_config: &RedmineConfig, user_id: &str) -> Result<String> {
+        let url = match &redmine_config.project_filter {
+            Some(project) => {
+                Self::server_url_join(redmine_config, &format!("projects/{}/activity", project))?
+            }
+            None => Self::server_url_join(redmine_config, "activity")?,
+        };
+        let mut params = vec![];
+        if redmine_config.only_my_activity {
+            params.push(format!("user_id={}", user_id));
+        }
+        // as soon as there is one show_ parameter, redmine
+        // displays only the types we list explicitly
+        if !redmine_config.hidden_activity_types.is_empty() {
+            params.extend(
+                ACTIVITY_TYPES
+                    .iter()
+                    .filter(|(_, t)| !redmine_config.hidden_activity_types.iter().any(|h| h == t))
+                    .map(|(_, t)| format!("show_{}=1", t)),
+            );
+        }
+        if params.is_empty() {
+            Ok(url)
+        } else {
+            Ok(format!("{}?{}", url, params.join("&")))
+        }
+    }
+
+    /// the atom version of the activity page. for feeds, redmine ignores
+    /// the dates and gives the latest events only.
+    fn feed_url(redmine_config: &RedmineConfig, rss_key: &str) -> Result<String> {
+        // the key authenticates us, but we don't get our user id without logging in
+        let user_id = match (
+            &redmine_config.user_id_override,
+            redmine_config.only_my_activity,
+        ) {
+            (Some(id), _) => id.as_str(),
+            (None, true) => return Err(EventError::Config(
+                "Redmine: the user id is required with the RSS key, to display only your activity"
+                    .to_string(),
+            )
+            .into()),
+            (None, false) => "",
+        };
+        let mut url = reqwest::Url::parse(&Self::activity_url(redmine_config, user_id)?)?;
+        url.set_path(&format!("{}.atom", url.path()));
+        url.query_pairs_mut().append_pair("key", rss_key);
+        Ok(url.to_string())
+    }
+
+    /// with fetch options, we go through the cache
+    fn fetch_feed(
+        options: Option<&FetchOptions>,
+        cache_key: &str,
+        redmine_config: &RedmineConfig,
+        rss_key: &str,
+    ) -> Result<String> {
+        let url = Self::feed_url(redmine_config, rss_key)?;
+        // no connection_verbose: it would log the url, with the key
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .build()?;
+        let xml = match options {
+            Some(o) => http::get_text_revalidating(o, &Redmine, cache_key, || client.get(&url)),
+            None => http::send_with_retry(|| client.get(&url)).and_then(|r| Ok(r.text()?)),
+        }
+        // the url has the key
+        .map_err(http::without_url)?;
+        log::debug!("Got back the activity feed {}", xml);
+        Ok(xml)
+    }
+
+    /// the events of `day` from the activity feed.
+    /// None if the feed doesn't go back to that day.
+    fn get_feed_events(
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        redmine_config: &RedmineConfig,
+        rss_key: &str,
+        day: Date<Local>,
+    ) -> Result<Option<Vec<Event>>> {
+        let cache_key = format!("{}_feed", Self::cache_key(config_name, redmine_config));
+        let xml = match config.get_cached_day_contents(options, &Redmine, &cache_key, day)? {
+            Some(xml) => xml,
+            None => Self::fetch_feed(Some(options), &cache_key, redmine_config, rss_key)?,
+        };
+        Self::feed_to_events(&xml, day)
+    }
+
+    fn feed_to_events(xml: &str, day: Date<Local>) -> Result<Option<Vec<Event>>> {
+        let feed = feed_rs::parser::parse(xml.as_bytes())?;
+        let (day_start, next_day_start) = super::events::day_bounds(day);
+        let mut oldest: Option<DateTime<Local>> = None;
+        let mut events = vec![];
+        for entry in &feed.entries {
+            let time = match entry.updated.or(entry.published) {
+                Some(t) => t.with_timezone(&Local),
+                None => continue,
+            };
+            oldest = Some(oldest.map_or(time, |o| o.min(time)));
+            if time >= day_start && time < next_day_start {
+                events.push(Self::feed_entry_to_event(entry, time));
+            }
+        }
+        // the feed has only the latest events: if they're all from that
+        // day or later, we may be missing the earlier events of the day.
+        if oldest.map_or(false, |o| o >= day_start) {
+            return Ok(None);
+        }
+        events.sort_by_key(|e| e.event_time);
+        Ok(Some(events))
+    }
+
+    fn feed_entry_to_event(entry: &feed_rs::model::Entry, time: DateTime<Local>) -> Event {
+        let title = entry
+            .title
+            .as_ref()
+            .map(|t| t.content.clone())
+            .unwrap_or_else(|| "-".to_string());
+        let url = entry
+            .links
+            .first()
+            .map(|l| l.href.clone())
+            .unwrap_or_else(|| entry.id.clone());
+        // the description is html
+        let description = entry
+            .content
+            .as_ref()
+            .and_then(|c| c.body.clone())
+            .or_else(|| entry.summary.as_ref().map(|s| s.content.clone()))
+            .map(|html| {
+                scraper::Html::parse_fragment(&html)
+                    .root_element()
+                    .text()
+                    .collect::<String>()
+            })
+            .unwrap_or_default();
+        // the feed has no css classes, the kind of event is in the url
+        let (icon, category) = [
+            ("/revisions/", Icon::CODE_BRANCH, "commit"),
+            ("/issues/", Icon::TASKS, "issue"),
+            ("/wiki/", Icon::FILE_ALT, "wiki"),
+            ("/time_entries", Icon::CLOCK, "time entry"),
+            ("/news/", Icon::NEWSPAPER, "news"),
+            ("/documents/", Icon::FILE_ALT, "document"),
+            ("/boards/", Icon::COMMENT_DOTS, "forum"),
+        ]
+        .iter()
+        .find(|(path, _, _)| url.contains(path))
+        .map(|(_, icon, category)| (icon.clone(), Some(*category)))
+        .unwrap_or((Icon::TASKS, None));
+        let event = Event::new(
+            "Redmine",
+            icon,
+            time.time(),
+            title.clone(),
+            title.clone(),
+            EventBody::Markup(
+                glib::markup_escape_text(description.trim()).to_string(),
+                WordWrapMode::WordWrap,
+            ),
+            None,
+        )
+        .with_url(&url);
+        let event = match category {
+            Some(c) => event.with_category(c),
+            None => event,
+        };
+        let revision = url
+            .split("/revisions/")
+            .nth(1)
+            .and_then(|rev| rev.split(|c| c == '/' || c == '?' || c == '#').next())
+            .filter(|rev| !rev.is_empty());
+        let event = match revision {
+            Some(rev) => event.with_dedup_key(&format!("commit:{}", rev.to_lowercase())),
+            None => event,
+        };
+        match Some(&title)
+            .filter(|_| category == Some("time entry"))
+            .and_then(|t| Self::parse_time_entry_hours(t))
+        {
+            Some(d) => event.with_duration(d),
+            None => event,
+        }
+    }
+
+    /// log in and read the activity pages
+    fn scrape_events(
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let cache_key = Self::cache_key(config_name, &config.redmine[config_name]);
+        let redmine_config = &RedmineConfig {
+            password: Self::read_password(config_name, &config.redmine[config_name])?,
+            ..config.redmine[config_name].clone()
+        };
+        let redmine_locales = Self::redmine_locales();
+        let (mut session, activity_html) =
+            match config.get_cached_day_contents(options, &Redmine, &cache_key, day)? {
+                Some(t) => Ok((None, t)),
+                None => Self::fetch_activity_html(options, config_name, redmine_config)
+                    .map(|(a, b)| (Some(a), b)),
+            }?;
+        // the first activity page is page 1
+        let mut page_number = 1;
+        let mut fetch_page = |url: Option<&str>, relogin: bool| -> Result<String> {
+            if url.is_some() && !relogin {
+                page_number += 1;
+                options.progress.report(format!(
+                    "{}: fetching activity page {}…",
+                    config_name, page_number
+                ));
+            }
+            if relogin || session.is_none() {
+                session = Some(Self::init_client(redmine_config, options.interactive)?);
+            }
+            let session = session.as_mut().unwrap();
+            let url = match url {
+                Some(u) => u.to_string(),
+                None => Self::activity_url(redmine_config, &session.user_id)?,
+            };
+            Self::get_html(session, redmine_config, &url)
+        };
+        Self::get_events_with_paging(
+            day,
+            activity_html,
+            None,
+            redmine_config,
+            &redmine_locales,
+            &mut fetch_page,
+            false,
+        )
+    }
+
+    /// different activity filters must not share the cache
+    fn cache_key(config_name: &str, redmine_config: &RedmineConfig) -> String {
+        let mut key = config_name.to_string();
+        if !redmine_config.only_my_activity {
+            key.push_str("_all");
+        }
+        for (_, activity_type) in ACTIVITY_TYPES.iter() {
+            if redmine_config
+                .hidden_activity_types
+                .iter()
+                .any(|h| h == activity_type)
+            {
+                key.push_str("_no_");
+                key.push_str(activity_type);
+            }
+        }
+        key
+    }
+
+    /// the activity types for which the checkbox is unchecked
+    fn hidden_activity_types(config_values: &HashMap<&'static str, String>) -> Vec<String> {
+        ACTIVITY_TYPES
+            .iter()
+            .filter(|(key, _)| config_values.get(key).map_or(false, |v| v == "false"))
+            .map(|(_, t)| t.to_string())
+            .collect()
+    }
+
+    fn optional_field(value: Option<String>) -> Option<String> {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
     }
 
     fn parse_html(
@@ -249,20 +996,24 @@ impl Redmine {
         activity_html: &str,
     ) -> Result<ActivityData> {
         let doc = scraper::Html::parse_document(activity_html);
-        let locale_str = doc
-            .root_element()
-            .value()
-            .attr("lang")
-            .ok_or("Can't find the language in the HTML")?;
+        let activity_sel = scraper::Selector::parse("div#activity").unwrap();
+        let login_sel = scraper::Selector::parse("#login-form, input#username").unwrap();
+        if doc.select(&activity_sel).next().is_none() && doc.select(&login_sel).next().is_some() {
+            return Ok(ActivityData::SessionExpired);
+        }
+        let locale_str =
+            doc.root_element().value().attr("lang").ok_or_else(|| {
+                EventError::Parse("Can't find the language in the HTML".to_string())
+            })?;
         log::debug!("Locale str: {}", locale_str);
-        let locale = redmine_locales
-            .get(locale_str)
-            .ok_or(format!("Unknown locale {}", locale_str))?;
+        let locale = Self::find_locale(redmine_locales, locale_str)
+            .ok_or_else(|| EventError::Parse(format!("Unknown locale {}", locale_str)))?;
         let day_sel = scraper::Selector::parse("div#content div#activity h3").unwrap();
         let day_contents_sel =
             scraper::Selector::parse("div#content div#activity h3 + dl").unwrap();
         let mut it_day = doc.select(&day_sel);
         let mut it_contents = doc.select(&day_contents_sel);
+        let mut day_events = vec![];
         loop {
             let next_day = it_day.next();
             let contents = it_contents.next();
@@ -274,8 +1025,12 @@ impl Redmine {
                         return Ok(ActivityData::Done(vec![]));
                     }
                     if cur_date == day {
-                        return Self::parse_events(redmine_config, &contents_elt)
-                            .map(ActivityData::Done);
+                        day_events = Self::parse_events(redmine_config, &contents_elt)?;
+                        if it_day.next().is_some() {
+                            // an older day follows, we have all the events of the day
+                            return Ok(ActivityData::Done(day_events));
+                        }
+                        break;
                     }
                 }
                 _ => {
@@ -283,44 +1038,79 @@ impl Redmine {
                 }
             }
         }
-        // no matches in this page, search for the 'previous' paging link
+        // the day may go on in the previous page, search for the 'previous' paging link
         let previous_sel = scraper::Selector::parse("li.previous.page a").unwrap();
         let previous_url = doc
             .select(&previous_sel)
             .next()
             .and_then(|p| p.value().attr("href"));
         Ok(ActivityData::ReachedEndOfPage(
-            previous_url.map(|s| redmine_config.server_url.clone() + s),
+            day_events,
+            previous_url
+                .map(|s| Self::server_url_join(redmine_config, s))
+                .transpose()?,
         ))
     }
 
+    /// an activity row can appear on two pages when we page through the
+    /// activity, drop the repeated events, keeping the first occurrence.
+    fn dedup_events(events: Vec<Event>) -> Vec<Event> {
+        let mut seen = HashSet::new();
+        events
+            .into_iter()
+            .filter(|e| seen.insert((e.event_time, e.event_info.clone(), e.event_url.clone())))
+            .collect()
+    }
+
+    /// `page_url` is the url of `activity_html`, None for the first activity page.
+    /// `relogged_in` is set when we already logged in again for that page.
     fn get_events_with_paging(
         day: Date<Local>,
         activity_html: String,
+        page_url: Option<String>,
         redmine_config: &RedmineConfig,
         redmine_locales: &HashMap<&'static str, LocaleInfo>,
-        client_opt: Option<reqwest::blocking::Client>,
+        fetch_page: &mut ActivityPageFetcher,
+        relogged_in: bool,
     ) -> Result<Vec<Event>> {
         match Self::parse_html(redmine_config, redmine_locales, day, &activity_html) {
             Ok(ActivityData::Done(events)) => Ok(events),
             Err(e) => Err(e),
-            Ok(ActivityData::ReachedEndOfPage(None)) => Ok(vec![]),
-            Ok(ActivityData::ReachedEndOfPage(Some(new_url))) => {
+            Ok(ActivityData::ReachedEndOfPage(events, None)) => Ok(events),
+            Ok(ActivityData::ReachedEndOfPage(mut events, Some(new_url))) => {
                 // recursively check for the previous page
-                let client = match client_opt {
-                    Some(c) => c,
-                    None => Self::init_client(redmine_config)?.0,
-                };
-                println!("Fetching {}", new_url);
-                let html = client.get(&new_url).send()?.error_for_status()?.text()?;
+                log::debug!("Fetching {}", new_url);
+                let html = fetch_page(Some(&new_url), false)?;
+                events.extend(Self::get_events_with_paging(
+                    day,
+                    html,
+                    Some(new_url),
+                    redmine_config,
+                    redmine_locales,
+                    fetch_page,
+                    false,
+                )?);
+                Ok(Self::dedup_events(events))
+            }
+            Ok(ActivityData::SessionExpired) if !relogged_in => {
+                // the session expired while we were paging, log in
+                // again and retry that page, but only once.
+                log::info!("Redmine session expired, logging in again");
+                let html = fetch_page(page_url.as_deref(), true)?;
                 Self::get_events_with_paging(
                     day,
                     html,
+                    page_url,
                     redmine_config,
                     redmine_locales,
-                    Some(client),
+                    fetch_page,
+                    true,
                 )
             }
+            Ok(ActivityData::SessionExpired) => Err(EventError::Auth(
+                "Got the Redmine login page even after logging in again".to_string(),
+            )
+            .into()),
         }
     }
 }
@@ -339,11 +1129,22 @@ impl EventProvider for Redmine {
     }
 
     fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
-        vec![
+        let mut fields = vec![
             (SERVER_URL_KEY, ConfigType::Text("")),
             (USERNAME_KEY, ConfigType::Text("")),
             (PASSWORD_KEY, ConfigType::Password),
-        ]
+            (PASSWORD_COMMAND_KEY, ConfigType::Text("")),
+            (RSS_KEY_KEY, ConfigType::Password),
+            (USER_ID_OVERRIDE_KEY, ConfigType::Text("")),
+            (PROJECT_FILTER_KEY, ConfigType::Text("")),
+            (ONLY_MY_ACTIVITY_KEY, ConfigType::Checkbox(true)),
+        ];
+        fields.extend(
+            ACTIVITY_TYPES
+                .iter()
+                .map(|(key, _)| (*key, ConfigType::Checkbox(true))),
+        );
+        fields
     }
 
     fn field_values(
@@ -354,27 +1155,55 @@ impl EventProvider for Redmine {
         Ok(Vec::new())
     }
 
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            SERVER_URL_KEY if value.trim().is_empty() => {
+                Err("The server URL is required".to_string())
+            }
+            SERVER_URL_KEY => reqwest::Url::parse(value.trim())
+                .map(|_| ())
+                .map_err(|e| format!("Invalid URL: {}", e)),
+            USERNAME_KEY if value.trim().is_empty() => Err("The username is required".to_string()),
+            USER_ID_OVERRIDE_KEY
+                if value.trim().parse::<u64>().is_err() && !value.trim().is_empty() =>
+            {
+                Err("The user id must be a number".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn get_config_values(
         &self,
         config: &Config,
         config_name: &str,
     ) -> HashMap<&'static str, String> {
-        vec![
-            (
-                SERVER_URL_KEY,
-                config.redmine[config_name].server_url.to_string(),
-            ),
-            (
-                USERNAME_KEY,
-                config.redmine[config_name].username.to_string(),
-            ),
-            (
-                PASSWORD_KEY,
-                config.redmine[config_name].password.to_string(),
-            ),
-        ]
-        .into_iter()
-        .collect()
+        let redmine_config = &config.redmine[config_name];
+        Self::config_values(
+            redmine_config,
+            Self::read_stored_password(config_name, redmine_config).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+            Self::read_rss_key(config_name, redmine_config)
+                .unwrap_or_else(|e| {
+                    log::warn!("{}", e);
+                    None
+                })
+                .unwrap_or_else(|| "".to_string()),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.redmine[config_name], "".to_string(), "".to_string())
     }
 
     fn add_config_values(
@@ -383,38 +1212,184 @@ impl EventProvider for Redmine {
         config_name: String,
         mut config_values: HashMap<&'static str, String>,
     ) {
+        let username = config_values.remove(USERNAME_KEY).unwrap();
+        let hidden_activity_types = Self::hidden_activity_types(&config_values);
+        let password = Config::store_secret(
+            &Redmine,
+            &config_name,
+            &username,
+            config_values.remove(PASSWORD_KEY).unwrap(),
+        );
+        let rss_key = Self::optional_field(config_values.remove(RSS_KEY_KEY)).map(|rss_key| {
+            Config::store_secret(&Redmine, &config_name, RSS_KEY_KEYRING_USERNAME, rss_key)
+        });
         config.redmine.insert(
             config_name,
             RedmineConfig {
                 server_url: config_values.remove(SERVER_URL_KEY).unwrap(),
-                username: config_values.remove(USERNAME_KEY).unwrap(),
-                password: config_values.remove(PASSWORD_KEY).unwrap(),
+                username,
+                password,
+                user_id_override: Self::optional_field(config_values.remove(USER_ID_OVERRIDE_KEY)),
+                project_filter: Self::optional_field(config_values.remove(PROJECT_FILTER_KEY)),
+                only_my_activity: config_values
+                    .remove(ONLY_MY_ACTIVITY_KEY)
+                    .map(|v| v == "true")
+                    .unwrap_or(true),
+                hidden_activity_types,
+                password_command: Self::optional_field(config_values.remove(PASSWORD_COMMAND_KEY)),
+                rss_key,
             },
         );
     }
 
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.redmine.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            let new = &config.redmine[&config_name];
+            Config::remove_replaced_secret(
+                &Redmine,
+                old_config_name,
+                &old.username,
+                &old.password,
+                &config_name,
+                &new.username,
+                &new.password,
+            );
+            Config::remove_replaced_secret(
+                &Redmine,
+                old_config_name,
+                RSS_KEY_KEYRING_USERNAME,
+                old.rss_key.as_deref().unwrap_or(""),
+                &config_name,
+                RSS_KEY_KEYRING_USERNAME,
+                new.rss_key.as_deref().unwrap_or(""),
+            );
+        }
+    }
+
     fn remove_config(&self, config: &mut Config, config_name: String) {
-        config.redmine.remove(&config_name);
+        if let Some(redmine_config) = config.redmine.remove(&config_name) {
+            Config::remove_secret(
+                &Redmine,
+                &config_name,
+                &redmine_config.username,
+                &redmine_config.password,
+            );
+            if let Some(rss_key) = &redmine_config.rss_key {
+                Config::remove_secret(&Redmine, &config_name, RSS_KEY_KEYRING_USERNAME, rss_key);
+            }
+        }
+    }
+
+    fn validate_config(&self, config_values: &HashMap<&'static str, String>) -> Result<()> {
+        let password_command =
+            Self::optional_field(config_values.get(PASSWORD_COMMAND_KEY).cloned());
+        let redmine_config = RedmineConfig {
+            server_url: config_values[SERVER_URL_KEY].clone(),
+            username: config_values[USERNAME_KEY].clone(),
+            password: match &password_command {
+                Some(cmd) => Self::run_password_command(cmd)?,
+                None => config_values[PASSWORD_KEY].clone(),
+            },
+            user_id_override: Self::optional_field(
+                config_values.get(USER_ID_OVERRIDE_KEY).cloned(),
+            ),
+            project_filter: Self::optional_field(config_values.get(PROJECT_FILTER_KEY).cloned()),
+            only_my_activity: config_values
+                .get(ONLY_MY_ACTIVITY_KEY)
+                .map(|v| v == "true")
+                .unwrap_or(true),
+            hidden_activity_types: Self::hidden_activity_types(config_values),
+            password_command,
+            rss_key: Self::optional_field(config_values.get(RSS_KEY_KEY).cloned()),
+        };
+        if let Some(rss_key) = &redmine_config.rss_key {
+            // we won't log in, check the feed instead
+            let xml = Self::fetch_feed(None, "", &redmine_config, rss_key)?;
+            return Ok(feed_rs::parser::parse(xml.as_bytes()).map(|_| ())?);
+        }
+        // init_client fails if we can't find the user id after login.
+        // the user is waiting in the add event source dialog.
+        Self::init_client(&redmine_config, true).map(|_| ())
     }
 
     fn get_events(
         &self,
         config: &Config,
+        options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>> {
         log::debug!("redmine::get_events");
+        // past days can't change anymore, so we can cache the parsed events,
+        // instead of the activity pages which we'd have to parse again.
+        let is_past_day = day < Local::today();
+        let cache_key = Self::cache_key(config_name, &config.redmine[config_name]);
+        let events_cache_key = format!("{}_events_{}", cache_key, day.format("%Y%m%d"));
+        if is_past_day {
+            if let Some(events) = config
+                .get_cached_day_contents(options, &Redmine, &events_cache_key, day)?
+                .and_then(|json| serde_json::from_str::<Vec<Event>>(&json).ok())
+            {
+                return Ok(events);
+            }
+        }
         let redmine_config = &config.redmine[config_name];
-        let redmine_locales = Self::redmine_locales();
-        let day_start = day.and_hms(0, 0, 0);
-        let next_day_start = day_start + chrono::Duration::days(1);
-        let (client, activity_html) =
-            match Config::get_cached_contents(&Redmine, config_name, &next_day_start)? {
-                Some(t) => Ok((None, t)),
-                None => Self::fetch_activity_html(config_name, redmine_config)
-                    .map(|(a, b)| (Some(a), b)),
-            }?;
-        Self::get_events_with_paging(day, activity_html, redmine_config, &redmine_locales, client)
+        let rss_key = Self::read_rss_key(config_name, redmine_config)?;
+        let feed_events = match &rss_key {
+            Some(rss_key) => {
+                Self::get_feed_events(config, options, config_name, redmine_config, rss_key, day)?
+            }
+            None => None,
+        };
+        let events = match (feed_events, &rss_key) {
+            (Some(events), _) => events,
+            (None, Some(_)) => {
+                log::info!(
+                    "{}: the activity feed doesn't go back to {}, reading the activity pages",
+                    config_name,
+                    day
+                );
+                Self::scrape_events(config, options, config_name, day).map_err(|e| {
+                    format!(
+                        "The Redmine activity feed doesn't go back to {}, and reading the activity pages failed: {}",
+                        day.format("%Y-%m-%d"),
+                        e
+                    )
+                })?
+            }
+            (None, None) => Self::scrape_events(config, options, config_name, day)?,
+        };
+        if is_past_day {
+            Config::write_to_cache(
+                &Redmine,
+                &events_cache_key,
+                &serde_json::to_string(&events)?,
+            )?;
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+fn test_config() -> RedmineConfig {
+    RedmineConfig {
+        server_url: "https://redmine".to_string(),
+        username: "me".to_string(),
+        password: "".to_string(),
+        user_id_override: None,
+        project_filter: None,
+        only_my_activity: true,
+        hidden_activity_types: vec![],
+        password_command: None,
+        rss_key: None,
     }
 }
 
@@ -449,6 +1424,77 @@ fn it_parses_iso_dates_correctly() {
     );
 }
 
+#[test]
+fn it_falls_back_to_the_base_language_for_unknown_locales() {
+    let locales = Redmine::redmine_locales();
+    assert_eq!(
+        "%d/%m/%Y",
+        Redmine::find_locale(&locales, "pt-PT").unwrap().date_format
+    );
+    assert_eq!(
+        "%d/%m/%Y",
+        Redmine::find_locale(&locales, "en-GB").unwrap().date_format
+    );
+    assert_eq!(
+        "%m/%d/%Y",
+        Redmine::find_locale(&locales, "en-US").unwrap().date_format
+    );
+    assert!(Redmine::find_locale(&locales, "xx-YY").is_none());
+}
+
+#[test]
+fn it_categorizes_redmine_events() {
+    let html = scraper::Html::parse_fragment(
+        r#"<dl><dt class="changeset icon"><a href="/r/1">Revision 1</a></dt>
+        <dt class="issue-edit icon"><a href="/issues/2">Bug #2</a></dt>
+        <dt class="unknown icon"><a href="/x/3">Other</a></dt></dl>"#,
+    );
+    let sel = scraper::Selector::parse("dt a").unwrap();
+    assert_eq!(
+        vec![Some("commit"), Some("issue"), None],
+        html.select(&sel)
+            .map(|l| Redmine::parse_event_category(&l))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn it_reads_the_revision_of_redmine_changesets() {
+    let html = scraper::Html::parse_fragment(
+        r#"<dl><dt class="changeset icon"><a href="/projects/p/repository/revisions/3F5A9c">Revision 3f5a9c</a></dt>
+        <dt class="issue-edit icon"><a href="/issues/2">Bug #2</a></dt></dl>"#,
+    );
+    let sel = scraper::Selector::parse("dt a").unwrap();
+    assert_eq!(
+        vec![Some("3f5a9c".to_string()), None],
+        html.select(&sel)
+            .map(|l| Redmine::parse_changeset_revision(&l))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn it_picks_icons_for_redmine_events() {
+    let html = scraper::Html::parse_fragment(
+        r#"<dl><dt class="icon icon-changeset"><a href="/r/1">Revision 1</a></dt>
+        <dt class="issue-note icon"><a href="/issues/2">Bug #2</a></dt>
+        <dt class="icon icon-wiki-page"><a href="/wiki/3">Wiki</a></dt>
+        <dt class="unknown icon"><a href="/x/4">Other</a></dt></dl>"#,
+    );
+    let sel = scraper::Selector::parse("dt a").unwrap();
+    assert_eq!(
+        vec![
+            Icon::CODE_BRANCH,
+            Icon::COMMENT_DOTS,
+            Icon::FILE_ALT,
+            Icon::TASKS
+        ],
+        html.select(&sel)
+            .map(|l| Redmine::parse_event_icon(&l))
+            .collect::<Vec<_>>()
+    );
+}
+
 #[test]
 fn it_parses_us_times_correctly() {
     assert_eq!(
@@ -464,3 +1510,436 @@ fn it_parses_iso_times_correctly() {
         Redmine::parse_time("13:30").unwrap()
     );
 }
+
+#[test]
+fn it_parses_times_with_seconds() {
+    assert_eq!(
+        NaiveTime::from_hms(13, 30, 5),
+        Redmine::parse_time("13:30:05").unwrap()
+    );
+}
+
+#[test]
+fn it_parses_lowercase_pm_times() {
+    assert_eq!(
+        NaiveTime::from_hms(13, 30, 0),
+        Redmine::parse_time("1:30 pm").unwrap()
+    );
+    assert!(Redmine::parse_time("half past one").is_err());
+}
+
+#[test]
+fn it_builds_the_activity_url() {
+    let mut redmine_config = test_config();
+    assert_eq!(
+        "https://redmine/activity?user_id=12",
+        Redmine::activity_url(&redmine_config, "12").unwrap()
+    );
+    redmine_config.project_filter = Some("cigale".to_string());
+    assert_eq!(
+        "https://redmine/projects/cigale/activity?user_id=12",
+        Redmine::activity_url(&redmine_config, "12").unwrap()
+    );
+    redmine_config.only_my_activity = false;
+    assert_eq!(
+        "https://redmine/projects/cigale/activity",
+        Redmine::activity_url(&redmine_config, "12").unwrap()
+    );
+    assert_ne!(
+        Redmine::cache_key("work", &redmine_config),
+        Redmine::cache_key(
+            "work",
+            &RedmineConfig {
+                only_my_activity: true,
+                ..redmine_config.clone()
+            }
+        )
+    );
+    redmine_config.only_my_activity = true;
+    redmine_config.project_filter = None;
+    redmine_config.hidden_activity_types = vec!["news".to_string(), "files".to_string()];
+    assert_eq!(
+        "https://redmine/activity?user_id=12&show_issues=1&show_changesets=1\
+         &show_documents=1&show_wiki_edits=1&show_messages=1",
+        Redmine::activity_url(&redmine_config, "12").unwrap()
+    );
+    assert_eq!(
+        "work_no_news_no_files",
+        Redmine::cache_key("work", &redmine_config)
+    );
+}
+
+#[test]
+fn it_joins_urls_for_root_and_subpath_servers() {
+    let mut redmine_config = RedmineConfig {
+        server_url: "https://redmine.example.com".to_string(),
+        only_my_activity: false,
+        ..test_config()
+    };
+    assert_eq!(
+        "https://redmine.example.com/login",
+        Redmine::server_url_join(&redmine_config, "login").unwrap()
+    );
+    assert_eq!(
+        "https://redmine.example.com/activity?from=2020-02-20",
+        Redmine::server_url_join(&redmine_config, "/activity?from=2020-02-20").unwrap()
+    );
+    for server_url in &[
+        "https://intranet.example.com/redmine",
+        "https://intranet.example.com/redmine/",
+    ] {
+        redmine_config.server_url = server_url.to_string();
+        assert_eq!(
+            "https://intranet.example.com/redmine/login",
+            Redmine::server_url_join(&redmine_config, "login").unwrap()
+        );
+        assert_eq!(
+            "https://intranet.example.com/redmine/activity",
+            Redmine::activity_url(&redmine_config, "12").unwrap()
+        );
+        // the links in the pages are absolute paths, with the subpath
+        assert_eq!(
+            "https://intranet.example.com/redmine/activity?from=2020-02-20",
+            Redmine::server_url_join(&redmine_config, "/redmine/activity?from=2020-02-20").unwrap()
+        );
+        assert_eq!(
+            "https://intranet.example.com/redmine/issues/2",
+            Redmine::server_url_join(&redmine_config, "/redmine/issues/2").unwrap()
+        );
+    }
+}
+
+#[test]
+fn it_validates_redmine_fields() {
+    assert!(Redmine
+        .validate_field(SERVER_URL_KEY, "https://redmine.example.com")
+        .is_ok());
+    assert!(Redmine.validate_field(SERVER_URL_KEY, "").is_err());
+    assert!(Redmine
+        .validate_field(SERVER_URL_KEY, "redmine.example.com")
+        .is_err());
+    assert!(Redmine.validate_field(USERNAME_KEY, " ").is_err());
+    assert!(Redmine.validate_field(PASSWORD_KEY, "").is_ok());
+    assert!(Redmine.validate_field(USER_ID_OVERRIDE_KEY, "").is_ok());
+    assert!(Redmine.validate_field(USER_ID_OVERRIDE_KEY, "12").is_ok());
+    assert!(Redmine
+        .validate_field(USER_ID_OVERRIDE_KEY, "john")
+        .is_err());
+}
+
+#[test]
+fn it_detects_the_two_factor_challenge() {
+    let challenge = r#"<html lang="en"><head><meta name="csrf-token" content="tok" /></head>
+        <body><form action="/redmine/account/twofa" method="post">
+        <input type="text" name="twofa_code" id="twofa_code" /></form></body></html>"#;
+    assert!(Redmine::is_two_factor_challenge(challenge));
+    assert!(!Redmine::is_two_factor_challenge(
+        r#"<html><body><a class="user active" href="/users/12">me</a></body></html>"#
+    ));
+    let redmine_config = RedmineConfig {
+        server_url: "https://example.com/redmine".to_string(),
+        ..test_config()
+    };
+    assert_eq!(
+        "https://example.com/redmine/account/twofa",
+        Redmine::two_factor_form_url(&redmine_config, challenge).unwrap()
+    );
+}
+
+#[test]
+fn it_refreshes_the_csrf_token_after_login() {
+    let response = |status: &str, body: &str| {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    };
+    // the login page of older redmines only has the token in the form
+    let login_page = r#"<html><body><form action="/login" method="post">
+        <input type="hidden" name="authenticity_token" value="before" />
+        <input type="text" name="username" /></form></body></html>"#;
+    let page = |token: &str, body: &str| {
+        format!(
+            r#"<html><head>
+            <meta name="csrf-param" content="authenticity_token" />
+            <meta name="csrf-token" content="{}" /></head>
+            <body>{}</body></html>"#,
+            token, body
+        )
+    };
+    let (server_url, requests) = http::serve(vec![
+        response("200 OK", login_page),
+        response(
+            "200 OK",
+            &page("after", r#"<a class="user active" href="/users/12">me</a>"#),
+        ),
+        response(
+            "422 Unprocessable Entity",
+            "Can't verify CSRF token authenticity",
+        ),
+        // the home page, to get a new token
+        response("200 OK", &page("refreshed", "")),
+        response("200 OK", &page("latest", "the activity")),
+    ]);
+    let redmine_config = RedmineConfig {
+        server_url,
+        ..test_config()
+    };
+    let mut session = Redmine::init_client(&redmine_config, false).unwrap();
+    assert_eq!("12", session.user_id);
+    assert_eq!(Some("after"), session.csrf_token.as_deref());
+    let activity_url = Redmine::server_url_join(&redmine_config, "activity").unwrap();
+    let html = Redmine::get_html(&mut session, &redmine_config, &activity_url).unwrap();
+    assert!(html.contains("the activity"));
+    assert_eq!(Some("latest"), session.csrf_token.as_deref());
+
+    let requests: Vec<String> = requests.iter().collect();
+    let csrf_header = |request: &str| {
+        request
+            .lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("x-csrf-token"))
+            .map(|(_, value)| value.trim().to_string())
+    };
+    assert_eq!(5, requests.len());
+    assert!(requests[1].starts_with("POST /login"));
+    assert!(requests[1].contains("authenticity_token=before"));
+    assert!(requests[2].starts_with("GET /activity"));
+    assert_eq!(Some("after".to_string()), csrf_header(&requests[2]));
+    assert!(requests[4].starts_with("GET /activity"));
+    assert_eq!(Some("refreshed".to_string()), csrf_header(&requests[4]));
+}
+
+#[test]
+fn it_logs_in_again_when_the_session_expires_while_paging() {
+    let activity_page = |dates: &[&str], previous_link: &str| {
+        format!(
+            r#"<html lang="en"><body><div id="content"><div id="activity">{}
+            </div><ul><li class="previous page"><a href="{}">Previous</a></li></ul></div>
+            </body></html>"#,
+            dates
+                .iter()
+                .map(|date| format!(
+                    r#"<h3>{}</h3>
+                    <dl><dt class="issue-edit icon"><a href="/issues/2">Bug #2</a></dt>
+                    <dd><span class="description">fixed</span><span class="time">10:15</span></dd></dl>"#,
+                    date
+                ))
+                .collect::<Vec<_>>()
+                .join(""),
+            previous_link
+        )
+    };
+    let login_page = r#"<html lang="en"><body><div id="login-form"><form>
+        <input type="text" name="username" id="username" /></form></div></body></html>"#;
+    let redmine_config = test_config();
+    let mut requests = vec![];
+    let mut fetch_page = |url: Option<&str>, relogin: bool| -> Result<String> {
+        requests.push((url.map(|u| u.to_string()), relogin));
+        Ok(if relogin {
+            activity_page(&["2020-02-25", "2020-02-24"], "/activity?from=2020-02-20")
+        } else {
+            login_page.to_string()
+        })
+    };
+    let events = Redmine::get_events_with_paging(
+        Local.ymd(2020, 2, 25),
+        activity_page(&["2020-02-26"], "/activity?from=2020-02-25"),
+        None,
+        &redmine_config,
+        &Redmine::redmine_locales(),
+        &mut fetch_page,
+        false,
+    )
+    .unwrap();
+    assert_eq!(1, events.len());
+    assert_eq!("Bug #2", events[0].event_info);
+    let page_url = Some("https://redmine/activity?from=2020-02-25".to_string());
+    assert_eq!(vec![(page_url.clone(), false), (page_url, true)], requests);
+}
+
+#[test]
+fn it_reads_a_day_split_across_two_activity_pages() {
+    let activity_page = |days: &[(&str, &[(&str, &str)])], previous_link: &str| {
+        format!(
+            r#"<html lang="en"><body><div id="content"><div id="activity">{}</div>
+            <ul><li class="previous page"><a href="{}">Previous</a></li></ul></div>
+            </body></html>"#,
+            days.iter()
+                .map(|(date, rows)| format!(
+                    "<h3>{}</h3><dl>{}</dl>",
+                    date,
+                    rows.iter()
+                        .map(|(issue, time)| format!(
+                            r#"<dt class="issue-edit icon"><a href="/issues/{0}">Bug #{0}</a></dt>
+                            <dd><span class="description">fixed</span><span class="time">{1}</span></dd>"#,
+                            issue, time
+                        ))
+                        .collect::<Vec<_>>()
+                        .join("")
+                ))
+                .collect::<Vec<_>>()
+                .join(""),
+            previous_link
+        )
+    };
+    let redmine_config = test_config();
+    let mut requests = vec![];
+    let mut fetch_page = |url: Option<&str>, _relogin: bool| -> Result<String> {
+        requests.push(url.map(|u| u.to_string()));
+        // the row at the page boundary shows up on both pages
+        Ok(activity_page(
+            &[
+                ("2020-02-25", &[("3", "10:15"), ("2", "09:30")]),
+                ("2020-02-24", &[("1", "17:00")]),
+            ],
+            "/activity?from=2020-02-20",
+        ))
+    };
+    let events = Redmine::get_events_with_paging(
+        Local.ymd(2020, 2, 25),
+        activity_page(
+            &[
+                ("2020-02-26", &[("5", "08:00")]),
+                ("2020-02-25", &[("4", "11:00"), ("3", "10:15")]),
+            ],
+            "/activity?from=2020-02-25",
+        ),
+        None,
+        &redmine_config,
+        &Redmine::redmine_locales(),
+        &mut fetch_page,
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        vec!["Bug #4", "Bug #3", "Bug #2"],
+        events
+            .iter()
+            .map(|e| e.event_info.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vec![Some("https://redmine/activity?from=2020-02-25".to_string())],
+        requests
+    );
+}
+
+#[test]
+fn it_reads_the_password_from_a_command() {
+    assert_eq!(
+        "s3cret pass \n",
+        Redmine::run_password_command("printf 's3cret pass \\n\\r\\n'").unwrap()
+    );
+    assert_eq!(
+        "s3cret",
+        Redmine::run_password_command("printf 's3cret\\n'").unwrap()
+    );
+    // not run through a shell: the semicolon and the redirection are
+    // passed to echo as plain arguments
+    assert_eq!(
+        "oops >&2; exit 3",
+        Redmine::run_password_command("echo oops '>&2;' exit 3").unwrap()
+    );
+    assert!(Redmine::run_password_command("false").is_err());
+    assert!(Redmine::run_password_command("printf 'unterminated").is_err());
+    assert!(Redmine::run_password_command("").is_err());
+}
+
+#[test]
+fn it_reads_the_redmine_activity_feed() {
+    let day = Local.ymd(2020, 3, 23);
+    let entry = |title: &str, href: &str, updated: DateTime<Local>| {
+        format!(
+            r#"<entry><title>{}</title><link rel="alternate" href="{}"/><id>{}</id>
+            <updated>{}</updated><author><name>Me</name></author>
+            <content type="html">&lt;p&gt;Fixed the &lt;b&gt;crash&lt;/b&gt;&lt;/p&gt;</content></entry>"#,
+            title,
+            href,
+            href,
+            updated.to_rfc3339()
+        )
+    };
+    let feed = |entries: &[String]| {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom"><title>Redmine: Activity</title>
+            <id>https://redmine/activity</id><updated>2020-03-24T10:00:00Z</updated>{}</feed>"#,
+            entries.join("")
+        )
+    };
+    let entries = vec![
+        entry(
+            "Revision 3F5A9c (cigale): Fix the crash",
+            "https://redmine/projects/cigale/repository/revisions/3F5A9c",
+            day.and_hms(15, 0, 0),
+        ),
+        entry(
+            "Bug #12 (Closed): Crash",
+            "https://redmine/issues/12#change-44",
+            day.and_hms(9, 30, 0),
+        ),
+        entry(
+            "1.50 hours (Bug #12 (Closed): Crash)",
+            "https://redmine/time_entries?issue_id=12",
+            day.and_hms(8, 0, 0),
+        ),
+        entry(
+            "Bug #11 (New): Slow",
+            "https://redmine/issues/11",
+            day.pred().and_hms(17, 0, 0),
+        ),
+    ];
+    let events = Redmine::feed_to_events(&feed(&entries), day)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        vec![
+            "1.50 hours (Bug #12 (Closed): Crash)",
+            "Bug #12 (Closed): Crash",
+            "Revision 3F5A9c (cigale): Fix the crash"
+        ],
+        events
+            .iter()
+            .map(|e| e.event_info.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        Some(chrono::Duration::minutes(90)),
+        events[0].event_duration
+    );
+    assert_eq!(Some("issue".to_string()), events[1].event_category);
+    assert_eq!(
+        "Fixed the crash",
+        events[1].event_contents_body.as_plain_text()
+    );
+    assert_eq!(Some("commit:3f5a9c".to_string()), events[2].dedup_key);
+    // without the event of the day before, the feed may be missing events of that day
+    assert!(Redmine::feed_to_events(&feed(&entries[..3]), day)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn it_builds_the_activity_feed_url() {
+    let redmine_config = RedmineConfig {
+        user_id_override: Some("12".to_string()),
+        project_filter: Some("cigale".to_string()),
+        rss_key: Some("abc".to_string()),
+        ..test_config()
+    };
+    assert_eq!(
+        "https://redmine/projects/cigale/activity.atom?user_id=12&key=abc",
+        Redmine::feed_url(&redmine_config, "abc").unwrap()
+    );
+    assert!(Redmine::feed_url(
+        &RedmineConfig {
+            user_id_override: None,
+            ..redmine_config
+        },
+        "abc"
+    )
+    .is_err());
+}
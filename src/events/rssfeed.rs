@@ -0,0 +1,195 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use std::collections::{HashMap, HashSet};
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
+pub struct RssFeedConfig {
+    pub feed_urls: Vec<String>,
+}
+
+pub struct RssFeed;
+const FEED_URLS_KEY: &str = "Feed URLs (space-separated)";
+
+impl RssFeed {
+    fn fetch_feed(
+        config: &Config,
+        options: &FetchOptions,
+        client: &reqwest::blocking::Client,
+        cache_key: &str,
+        feed_url: &str,
+        day: Date<Local>,
+    ) -> Result<String> {
+        if let Some(xml) = config.get_cached_day_contents(options, &RssFeed, cache_key, day)? {
+            return Ok(xml);
+        }
+        http::get_text_revalidating(options, &RssFeed, cache_key, || client.get(feed_url))
+    }
+
+    fn feed_to_events(
+        xml: &str,
+        day_start: &DateTime<Local>,
+        next_day_start: &DateTime<Local>,
+        seen_ids: &mut HashSet<String>,
+    ) -> Result<Vec<Event>> {
+        let feed = feed_rs::parser::parse(xml.as_bytes())?;
+        let feed_title = feed.title.map(|t| t.content);
+        let mut result = vec![];
+        for entry in feed.entries {
+            let entry_time = match entry.published.or(entry.updated) {
+                Some(t) => t.with_timezone(&Local),
+                None => continue,
+            };
+            if entry_time < *day_start
+                || entry_time >= *next_day_start
+                || !seen_ids.insert(entry.id.clone())
+            {
+                continue;
+            }
+            let title = entry
+                .title
+                .map(|t| t.content)
+                .unwrap_or_else(|| "-".to_string());
+            let link = entry
+                .links
+                .first()
+                .map(|l| format!("<a href=\"{}\">Open in the browser</a>\n\n", l.href))
+                .unwrap_or_else(|| "".to_string());
+            let summary = entry.summary.map(|s| s.content).unwrap_or_default();
+            result.push(Event::new(
+                "Feed",
+                Icon::RSS,
+                entry_time.time(),
+                title.clone(),
+                title,
+                EventBody::Markup(
+                    link + &glib::markup_escape_text(&summary),
+                    WordWrapMode::WordWrap,
+                ),
+                feed_title.clone(),
+            ));
+        }
+        Ok(result)
+    }
+}
+
+impl EventProvider for RssFeed {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![(FEED_URLS_KEY, ConfigType::Text(""))]
+    }
+
+    fn name(&self) -> &'static str {
+        "RssFeed"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::RSS
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.rss_feed.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![(
+            FEED_URLS_KEY,
+            config.rss_feed[config_name].feed_urls.join(" "),
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.rss_feed.insert(
+            config_name,
+            RssFeedConfig {
+                feed_urls: config_values
+                    .remove(FEED_URLS_KEY)
+                    .unwrap()
+                    .split_whitespace()
+                    .map(|u| u.to_string())
+                    .collect(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.rss_feed.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let rss_config = &config.rss_feed[config_name];
+        let day_start = day.and_hms(0, 0, 0);
+        let next_day_start = day_start + chrono::Duration::days(1);
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .build()?;
+        let mut seen_ids = HashSet::new();
+        let mut result = vec![];
+        for (i, feed_url) in rss_config.feed_urls.iter().enumerate() {
+            // we cache the raw xml of each feed, per day
+            let cache_key = format!("{}_{}_{}", config_name, i, day.format("%Y%m%d"));
+            let xml = Self::fetch_feed(config, options, &client, &cache_key, feed_url, day)?;
+            result.append(&mut Self::feed_to_events(
+                &xml,
+                &day_start,
+                &next_day_start,
+                &mut seen_ids,
+            )?);
+        }
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_dedupes_feed_entries_by_guid() {
+    let xml = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>Changelog</title>
+<item><guid>entry-1</guid><title>Release 1.0</title><link>https://example.com/1</link>
+<description>First release</description><pubDate>Tue, 25 Feb 2020 12:00:00 GMT</pubDate></item>
+<item><guid>entry-2</guid><title>Release 0.9</title>
+<pubDate>Mon, 24 Feb 2020 12:00:00 GMT</pubDate></item>
+</channel></rss>"#;
+    let day_start = Utc.ymd(2020, 2, 25).and_hms(0, 0, 0).with_timezone(&Local);
+    let next_day_start = day_start + chrono::Duration::days(1);
+    let mut seen_ids = HashSet::new();
+    let events = RssFeed::feed_to_events(xml, &day_start, &next_day_start, &mut seen_ids).unwrap();
+    assert_eq!(1, events.len());
+    assert_eq!("Release 1.0", events[0].event_info);
+    assert_eq!(
+        0,
+        RssFeed::feed_to_events(xml, &day_start, &next_day_start, &mut seen_ids)
+            .unwrap()
+            .len()
+    );
+}
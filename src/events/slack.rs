@@ -0,0 +1,226 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://api.slack.com/methods/search.messages
+// requires a user token with the search:read scope.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SlackConfig {
+    pub token: String,
+}
+
+pub struct Slack;
+const TOKEN_KEY: &str = "User Token";
+
+#[derive(Deserialize, Debug)]
+struct SlackSearchResponse {
+    ok: bool,
+    error: Option<String>,
+    messages: Option<SlackMessages>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SlackMessages {
+    matches: Vec<SlackMessage>,
+    paging: SlackPaging,
+}
+
+#[derive(Deserialize, Debug)]
+struct SlackPaging {
+    pages: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct SlackMessage {
+    ts: String,
+    text: String,
+    permalink: String,
+    channel: SlackChannel,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct SlackChannel {
+    name: String,
+}
+
+impl Slack {
+    // slack timestamps are strings like "1582646495.000200":
+    // seconds since the epoch, then a unique suffix
+    fn parse_ts(ts: &str) -> Option<DateTime<Local>> {
+        let secs = ts.split('.').next()?.parse::<i64>().ok()?;
+        Some(Utc.timestamp(secs, 0).with_timezone(&Local))
+    }
+
+    fn fetch_messages(
+        config_name: &str,
+        slack_config: &SlackConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<SlackMessage>> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let query = format!("from:me on:{}", day.format("%Y-%m-%d"));
+        let page_size = PAGE_SIZE.to_string();
+        let mut messages = vec![];
+        let mut page_idx = 1;
+        loop {
+            let page_str = page_idx.to_string();
+            // slack applies rate limits, answering 429 with a Retry-After
+            // header, which send_with_retry honors
+            let json = http::send_with_retry(|| {
+                client
+                    .get("https://slack.com/api/search.messages")
+                    .bearer_auth(&slack_config.token)
+                    .query(&[
+                        ("query", query.as_str()),
+                        ("sort", "timestamp"),
+                        ("count", &page_size),
+                        ("page", &page_str),
+                    ])
+            })?
+            .text()?;
+            let response = serde_json::from_str::<SlackSearchResponse>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            // slack reports errors with a 200 status code
+            if !response.ok {
+                return Err(format!(
+                    "Slack error: {}",
+                    response.error.unwrap_or_else(|| "unknown".to_string())
+                )
+                .into());
+            }
+            let mut page = match response.messages {
+                Some(m) => m,
+                None => break,
+            };
+            messages.append(&mut page.matches);
+            if page_idx >= page.paging.pages {
+                break;
+            }
+            page_idx += 1;
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Slack, &cache_key, &serde_json::to_string(&messages)?)?;
+        Ok(messages)
+    }
+
+    fn message_to_event(message: &SlackMessage) -> Option<Event> {
+        let time = Self::parse_ts(&message.ts)?;
+        Some(Event::new(
+            "Slack",
+            Icon::SLACK,
+            time.time(),
+            message.text.lines().next().unwrap_or("").to_string(),
+            format!("#{}", message.channel.name),
+            EventBody::Markup(
+                format!(
+                    "<a href=\"{}\">Open in Slack</a>\n\n{}",
+                    glib::markup_escape_text(&message.permalink),
+                    glib::markup_escape_text(&message.text)
+                ),
+                // messages often contain long urls
+                WordWrapMode::CharWrap,
+            ),
+            Some("Message".to_string()),
+        ))
+    }
+}
+
+impl EventProvider for Slack {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![(TOKEN_KEY, ConfigType::Password)]
+    }
+
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::SLACK
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.slack.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![(TOKEN_KEY, config.slack[config_name].token.to_string())]
+            .into_iter()
+            .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.slack.insert(
+            config_name,
+            SlackConfig {
+                token: config_values.remove(TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.slack.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let slack_config = &config.slack[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let messages = match config
+            .get_cached_day_contents(options, &Slack, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<SlackMessage>>(&json).ok())
+        {
+            Some(m) => m,
+            None => Self::fetch_messages(config_name, slack_config, day)?,
+        };
+        Ok(messages
+            .iter()
+            // the 'on:' search modifier uses the slack user's timezone,
+            // which may differ from the local one
+            .filter(|m| Self::parse_ts(&m.ts).map(|t| t.date()) == Some(day))
+            .filter_map(Self::message_to_event)
+            .collect())
+    }
+}
+
+#[test]
+fn it_converts_slack_timestamps() {
+    assert_eq!(
+        Utc.ymd(2020, 2, 25).and_hms(16, 1, 35),
+        Slack::parse_ts("1582646495.000200").unwrap()
+    );
+    assert_eq!(None, Slack::parse_ts("not a timestamp"));
+}
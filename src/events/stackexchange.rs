@@ -4,7 +4,13 @@
 // so I have to scrap the website
 // my understand is that scraping is acceptable if there is no alternative:
 // https://meta.stackexchange.com/a/446/218504
-use super::events::{ConfigType, Event, EventBody, EventProvider, Result, WordWrapMode};
+// for the questions, answers and comments, we use the official API:
+// https://api.stackexchange.com/docs/timeline-on-users
+use super::events::{
+    ConfigType, Event, EventBody, EventError, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use super::ratelimit;
 use crate::config::Config;
 use crate::icons::*;
 use chrono::prelude::*;
@@ -14,14 +20,41 @@ use std::collections::HashMap;
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
 pub struct StackExchangeConfig {
     pub exchange_site_url: String,
-    pub username: String,
+    pub username: String, // empty => don't fetch the votes
     pub password: String,
+    #[serde(default)] // user_id was added later, after 0.5.3
+    pub user_id: Option<u64>, // None => don't fetch the timeline
+    #[serde(default)] // api_key was added later, after 0.5.3
+    pub api_key: Option<String>,
 }
 
 pub struct StackExchange;
 const EXCHANGE_SITE_URL: &str = "Stack Exchange site url";
 const USERNAME_KEY: &str = "username";
 const PASSWORD_KEY: &str = "password";
+const USER_ID_KEY: &str = "user id";
+const API_KEY_KEY: &str = "API key";
+
+const API_HOST: &str = "api.stackexchange.com";
+
+#[derive(serde_derive::Deserialize, Debug)]
+struct TimelineResponse {
+    items: Vec<TimelineItem>,
+    has_more: bool,
+    // if present, the number of seconds we must wait
+    // before hitting the same API method again
+    backoff: Option<u64>,
+}
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Debug)]
+struct TimelineItem {
+    timeline_type: String,
+    post_type: Option<String>,
+    title: Option<String>,
+    detail: Option<String>,
+    link: Option<String>,
+    creation_date: i64,
+}
 
 impl StackExchange {
     fn url_escape(msg: &str) -> String {
@@ -65,11 +98,12 @@ impl StackExchange {
                 ("oauth_version", ""),
                 ("oauth_server", ""),
             ])
-            .send()?
-            .error_for_status()?;
+            .send()
+            .and_then(|r| r.error_for_status())
+            .map_err(http::classify_error)?;
         let html = resp.text()?;
         if html.contains("Human verification") && html.contains("Are you a human being?") {
-            Err("Login rejected: human verification failed".into())
+            Err(EventError::Auth("Login rejected: human verification failed".to_string()).into())
         } else {
             Ok(html)
         }
@@ -80,17 +114,14 @@ impl StackExchange {
         stackexchange_config: &StackExchangeConfig,
         url_path: &str,
     ) -> Result<String> {
-        log::debug!(
-            "getting {}",
-            &format!("{}{}", stackexchange_config.exchange_site_url, url_path)
-        );
+        let url = format!("{}{}", stackexchange_config.exchange_site_url, url_path);
+        log::debug!("getting {}", &url);
+        ratelimit::acquire_url(&url);
         let resp = client
-            .get(&format!(
-                "{}{}",
-                stackexchange_config.exchange_site_url, url_path
-            ))
-            .send()?
-            .error_for_status()?;
+            .get(&url)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .map_err(http::classify_error)?;
 
         let html = resp.text()?;
         log::debug!(
@@ -145,6 +176,124 @@ impl StackExchange {
         Ok(votes_page_html)
     }
 
+    // the API takes the site domain, for instance stackoverflow.com
+    fn api_site(stackexchange_config: &StackExchangeConfig) -> &str {
+        let url = &stackexchange_config.exchange_site_url;
+        url.split("://").last().unwrap_or(url).trim_end_matches('/')
+    }
+
+    fn get_timeline_json(
+        config_name: &str,
+        stackexchange_config: &StackExchangeConfig,
+        user_id: u64,
+        day: Date<Local>,
+    ) -> Result<String> {
+        // the API mandates gzip-compressed responses,
+        // reqwest decompresses them transparently
+        let client = reqwest::blocking::ClientBuilder::new()
+            .gzip(true)
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let from_date = day.and_hms(0, 0, 0).timestamp().to_string();
+        let to_date = (day.and_hms(0, 0, 0) + chrono::Duration::days(1))
+            .timestamp()
+            .to_string();
+        let mut items = vec![];
+        for page_idx in 1.. {
+            let page_str = page_idx.to_string();
+            let json = http::send_with_retry(|| {
+                let req = client
+                    .get(&format!(
+                        "https://{}/2.3/users/{}/timeline",
+                        API_HOST, user_id
+                    ))
+                    .query(&[
+                        ("site", Self::api_site(stackexchange_config)),
+                        ("fromdate", &from_date),
+                        ("todate", &to_date),
+                        ("page", &page_str),
+                        ("pagesize", "100"),
+                    ]);
+                match &stackexchange_config.api_key {
+                    Some(key) => req.query(&[("key", key)]),
+                    None => req,
+                }
+            })?
+            .text()?;
+            log::debug!("stackexchange timeline page {}: {}", page_idx, json);
+            let mut response = serde_json::from_str::<TimelineResponse>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            if let Some(backoff) = response.backoff {
+                // the backoff applies to all our requests to the API, also
+                // those of the other days fetched in parallel for the week view
+                log::debug!("stackexchange asks to back off for {}s", backoff);
+                ratelimit::pause_host(API_HOST, Duration::from_secs(backoff));
+            }
+            items.append(&mut response.items);
+            if !response.has_more {
+                break;
+            }
+        }
+        let json = serde_json::to_string(&items)?;
+        let cache_key = format!("{}_timeline_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&StackExchange, &cache_key, &json)?;
+        Ok(json)
+    }
+
+    // the API returns html-encoded titles
+    fn decode_html(html: &str) -> String {
+        scraper::Html::parse_fragment(html)
+            .root_element()
+            .text()
+            .collect()
+    }
+
+    fn get_timeline_events(timeline_json: &str) -> Result<Vec<Event>> {
+        let items = serde_json::from_str::<Vec<TimelineItem>>(timeline_json)?;
+        Ok(items
+            .iter()
+            .filter_map(|item| {
+                let (icon, action) = match item.timeline_type.as_str() {
+                    "asked" => (Icon::COMMENT_DOTS, "Question"),
+                    "answered" => (Icon::COMMENT_DOTS, "Answer"),
+                    "commented" => (Icon::COMMENT_DOTS, "Comment"),
+                    "accepted" => (Icon::CHECK_SQUARE, "Accepted answer"),
+                    "revision" => (Icon::TASKS, "Edit"),
+                    _ => return None,
+                };
+                let title = Self::decode_html(item.title.as_deref().unwrap_or(""));
+                let time = Utc
+                    .timestamp(item.creation_date, 0)
+                    .with_timezone(&Local)
+                    .time();
+                Some(Event::new(
+                    "S.Exch",
+                    icon,
+                    time,
+                    title.clone(),
+                    format!("{}: {}", action, title),
+                    EventBody::Markup(
+                        format!(
+                            "<a href=\"{}\">Open in the browser</a>\n\n{}",
+                            glib::markup_escape_text(item.link.as_deref().unwrap_or("")),
+                            glib::markup_escape_text(&Self::decode_html(
+                                item.detail.as_deref().unwrap_or("")
+                            ))
+                        ),
+                        WordWrapMode::WordWrap,
+                    ),
+                    Some(format!(
+                        "{} ({})",
+                        action,
+                        item.post_type.as_deref().unwrap_or("post")
+                    )),
+                ))
+            })
+            .collect())
+    }
+
     fn get_votes(
         votes_page_html: &str,
         stackexchange_config: &StackExchangeConfig,
@@ -229,6 +378,8 @@ impl EventProvider for StackExchange {
             ),
             (USERNAME_KEY, ConfigType::Text("")),
             (PASSWORD_KEY, ConfigType::Password),
+            (USER_ID_KEY, ConfigType::Text("")),
+            (API_KEY_KEY, ConfigType::Text("")),
         ]
     }
 
@@ -260,6 +411,20 @@ impl EventProvider for StackExchange {
                 PASSWORD_KEY,
                 config.stackexchange[config_name].password.to_string(),
             ),
+            (
+                USER_ID_KEY,
+                config.stackexchange[config_name]
+                    .user_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (
+                API_KEY_KEY,
+                config.stackexchange[config_name]
+                    .api_key
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
         ]
         .into_iter()
         .collect()
@@ -277,6 +442,10 @@ impl EventProvider for StackExchange {
                 exchange_site_url: config_values.remove(EXCHANGE_SITE_URL).unwrap(),
                 username: config_values.remove(USERNAME_KEY).unwrap(),
                 password: config_values.remove(PASSWORD_KEY).unwrap(),
+                user_id: config_values
+                    .remove(USER_ID_KEY)
+                    .and_then(|id| id.trim().parse().ok()),
+                api_key: config_values.remove(API_KEY_KEY).filter(|k| !k.is_empty()),
             },
         );
     }
@@ -288,6 +457,7 @@ impl EventProvider for StackExchange {
     fn get_events(
         &self,
         config: &Config,
+        options: &FetchOptions,
         config_name: &str,
         day: Date<Local>,
     ) -> Result<Vec<Event>> {
@@ -296,17 +466,51 @@ impl EventProvider for StackExchange {
         let day_start = day.and_hms(0, 0, 0);
         let next_day_start = day_start + chrono::Duration::days(1);
 
-        let votes_page_html =
-            match Config::get_cached_contents(&StackExchange, config_name, &next_day_start)? {
-                Some(t) => Ok(t),
-                None => Self::get_votes_page_html(config_name, stackexchange_config),
-            }?;
-
-        Self::get_votes(
-            &votes_page_html,
-            stackexchange_config,
-            day_start,
-            next_day_start,
-        )
+        let mut result = vec![];
+        if !stackexchange_config.username.is_empty() {
+            let votes_page_html =
+                match config.get_cached_day_contents(options, &StackExchange, config_name, day)? {
+                    Some(t) => Ok(t),
+                    None => Self::get_votes_page_html(config_name, stackexchange_config),
+                }?;
+            result.append(&mut Self::get_votes(
+                &votes_page_html,
+                stackexchange_config,
+                day_start,
+                next_day_start,
+            )?);
+        }
+        if let Some(user_id) = stackexchange_config.user_id {
+            let cache_key = format!("{}_timeline_{}", config_name, day.format("%Y%m%d"));
+            let timeline_json =
+                match config.get_cached_day_contents(options, &StackExchange, &cache_key, day)? {
+                    Some(t) => Ok(t),
+                    None => {
+                        Self::get_timeline_json(config_name, stackexchange_config, user_id, day)
+                    }
+                }?;
+            result.append(&mut Self::get_timeline_events(&timeline_json)?);
+        }
+        Ok(result)
     }
 }
+
+#[test]
+fn it_parses_the_stackexchange_timeline() {
+    let json = r#"[
+        {"timeline_type": "answered", "post_type": "answer", "title": "How to use &quot;Rc&quot;?",
+         "detail": null, "link": "https://stackoverflow.com/a/123", "creation_date": 1582646495},
+        {"timeline_type": "badge", "title": null, "detail": "Nice Answer",
+         "link": "https://stackoverflow.com/badges/1", "creation_date": 1582646495}
+    ]"#;
+    let events = StackExchange::get_timeline_events(json).unwrap();
+    assert_eq!(1, events.len());
+    assert_eq!(
+        "Answer: How to use \"Rc\"?",
+        events[0].event_contents_header
+    );
+    assert_eq!(
+        Some("Answer (answer)".to_string()),
+        events[0].event_extra_details
+    );
+}
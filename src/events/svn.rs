@@ -0,0 +1,328 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::process::Command;
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Clone, Debug)]
+pub struct SvnConfig {
+    pub repo_url: String,
+    pub username: Option<String>, // None => all the authors, anonymous access
+    pub password: Option<String>,
+}
+
+pub struct Svn;
+const REPO_URL_KEY: &str = "Repository URL";
+const USERNAME_KEY: &str = "Username";
+const PASSWORD_KEY: &str = "Password";
+
+impl Svn {
+    fn config_values(svn_config: &SvnConfig, password: String) -> HashMap<&'static str, String> {
+        vec![
+            (REPO_URL_KEY, svn_config.repo_url.to_string()),
+            (
+                USERNAME_KEY,
+                svn_config
+                    .username
+                    .clone()
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+            (PASSWORD_KEY, password),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn read_password(config_name: &str, svn_config: &SvnConfig) -> Result<Option<String>> {
+        match &svn_config.password {
+            Some(p) => Config::read_secret(
+                &Svn,
+                config_name,
+                svn_config.username.as_deref().unwrap_or(""),
+                p,
+            )
+            .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn run_svn_log(
+        config_name: &str,
+        svn_config: &SvnConfig,
+        day: Date<Local>,
+    ) -> Result<Option<String>> {
+        let mut cmd = Command::new("svn");
+        // svn includes the last revision before the start date,
+        // we filter by date when parsing.
+        cmd.arg("log")
+            .arg("--xml")
+            .arg("--verbose")
+            .arg("--non-interactive")
+            .arg("-r")
+            .arg(format!(
+                "{{{}}}:{{{}}}",
+                day.format("%Y-%m-%d"),
+                day.succ().format("%Y-%m-%d")
+            ));
+        if let Some(username) = &svn_config.username {
+            cmd.arg("--username").arg(username);
+        }
+        if let Some(password) = Self::read_password(config_name, svn_config)? {
+            cmd.arg("--password").arg(password);
+        }
+        cmd.arg(&svn_config.repo_url);
+        let output = match cmd.output() {
+            Ok(o) => o,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                log::warn!(
+                    "svn is not installed or not in the PATH, skipping {}",
+                    svn_config.repo_url
+                );
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !output.status.success() {
+            return Err(format!(
+                "svn log failed for {}: {}",
+                svn_config.repo_url,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+    }
+
+    fn child_text<'a>(node: &roxmltree::Node<'a, '_>, tag_name: &str) -> Option<&'a str> {
+        node.children()
+            .find(|c| c.has_tag_name(tag_name))
+            .and_then(|c| c.text())
+    }
+
+    fn parse_svn_log(
+        repo_url: &str,
+        author_filter: &Option<String>,
+        day: Date<Local>,
+        log_xml: &str,
+    ) -> Result<Vec<Event>> {
+        let doc = roxmltree::Document::parse(log_xml)?;
+        Ok(doc
+            .descendants()
+            .filter(|n| n.has_tag_name("logentry"))
+            .filter_map(|entry| {
+                let revision = entry.attribute("revision")?;
+                let author = Self::child_text(&entry, "author").unwrap_or("");
+                if author_filter.as_ref().map_or(false, |a| a != author) {
+                    return None;
+                }
+                let date = DateTime::parse_from_rfc3339(Self::child_text(&entry, "date")?)
+                    .ok()?
+                    .with_timezone(&Local);
+                if date.date() != day {
+                    return None;
+                }
+                let msg = Self::child_text(&entry, "msg").unwrap_or("");
+                let summary = msg.lines().next().unwrap_or("").to_string();
+                let paths = entry
+                    .descendants()
+                    .filter(|n| n.has_tag_name("path"))
+                    .map(|p| {
+                        format!(
+                            "{} {}",
+                            p.attribute("action").unwrap_or(" "),
+                            p.text().unwrap_or("")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Some(Event::new(
+                    "Svn",
+                    Icon::CODE_BRANCH,
+                    date.time(),
+                    summary.clone(),
+                    summary,
+                    EventBody::PlainText(format!(
+                        "{}\nRevision r{} by {}\n\n{}\n\n{}",
+                        repo_url, revision, author, msg, paths
+                    )),
+                    Some(format!("r{}", revision)),
+                ))
+            })
+            .collect())
+    }
+}
+
+impl EventProvider for Svn {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (REPO_URL_KEY, ConfigType::Text("")),
+            (USERNAME_KEY, ConfigType::Text("")),
+            (PASSWORD_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Svn"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CODE_BRANCH
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.svn.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        let svn_config = &config.svn[config_name];
+        Self::config_values(
+            svn_config,
+            Self::read_password(config_name, svn_config)
+                .unwrap_or_else(|e| {
+                    log::warn!("{}", e);
+                    None
+                })
+                .unwrap_or_else(|| "".to_string()),
+        )
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        Self::config_values(&config.svn[config_name], "".to_string())
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let username = config_values.remove(USERNAME_KEY).filter(|u| !u.is_empty());
+        let password = config_values
+            .remove(PASSWORD_KEY)
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                Config::store_secret(&Svn, &config_name, username.as_deref().unwrap_or(""), p)
+            });
+        config.svn.insert(
+            config_name,
+            SvnConfig {
+                repo_url: config_values.remove(REPO_URL_KEY).unwrap(),
+                username,
+                password,
+            },
+        );
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.svn.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some((old, Some(old_password))) = old.as_ref().map(|o| (o, &o.password)) {
+            let new = &config.svn[&config_name];
+            Config::remove_replaced_secret(
+                &Svn,
+                old_config_name,
+                old.username.as_deref().unwrap_or(""),
+                old_password,
+                &config_name,
+                new.username.as_deref().unwrap_or(""),
+                new.password.as_deref().unwrap_or(""),
+            );
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(svn_config) = config.svn.remove(&config_name) {
+            if let Some(password) = &svn_config.password {
+                Config::remove_secret(
+                    &Svn,
+                    &config_name,
+                    svn_config.username.as_deref().unwrap_or(""),
+                    password,
+                );
+            }
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        _options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let svn_config = &config.svn[config_name];
+        let mut result = match Self::run_svn_log(config_name, svn_config, day)? {
+            Some(output) => {
+                Self::parse_svn_log(&svn_config.repo_url, &svn_config.username, day, &output)?
+            }
+            None => vec![], // svn is not installed
+        };
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_parses_svn_log_output() {
+    let output = r#"<?xml version="1.0" encoding="UTF-8"?>
+<log>
+<logentry revision="41">
+<author>john</author>
+<date>2020-02-24T10:00:00.000000Z</date>
+<msg>from the previous day</msg>
+</logentry>
+<logentry revision="42">
+<author>john</author>
+<date>2020-02-25T12:00:00.000000Z</date>
+<paths>
+<path action="M" kind="file">/trunk/main.c</path>
+</paths>
+<msg>fix the build
+
+more details</msg>
+</logentry>
+<logentry revision="43">
+<author>jane</author>
+<date>2020-02-25T13:00:00.000000Z</date>
+<msg>someone else's commit</msg>
+</logentry>
+</log>"#;
+    let day = Utc
+        .ymd(2020, 2, 25)
+        .and_hms(12, 0, 0)
+        .with_timezone(&Local)
+        .date();
+    let events = Svn::parse_svn_log("svn://repo", &Some("john".to_string()), day, output).unwrap();
+    assert_eq!(1, events.len());
+    assert_eq!("fix the build", events[0].event_info);
+    assert_eq!(
+        EventBody::PlainText(
+            "svn://repo\nRevision r42 by john\n\nfix the build\n\nmore details\n\nM /trunk/main.c"
+                .to_string()
+        ),
+        events[0].event_contents_body
+    );
+}
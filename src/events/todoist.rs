@@ -0,0 +1,305 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://developer.todoist.com/sync/v9/#get-all-completed-items
+const API_URL: &str = "https://api.todoist.com/sync/v9/completed/get_all";
+// the maximum the API allows
+const PAGE_SIZE: usize = 200;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TodoistConfig {
+    pub api_token: String, // config::SECRET_IN_KEYRING if stored in the keyring
+}
+
+pub struct Todoist;
+const API_TOKEN_KEY: &str = "API Token";
+// todoist tokens are not tied to a username
+const API_TOKEN_USERNAME: &str = "api_token";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct TodoistItemObject {
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct TodoistCompletedItem {
+    content: String,
+    completed_at: DateTime<Utc>,
+    task_id: String,
+    project_id: String,
+    item_object: Option<TodoistItemObject>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct TodoistProject {
+    name: String,
+}
+
+/// what we get from the API, and what we keep in the cache
+#[derive(Deserialize, Serialize, Debug, Default)]
+struct TodoistCompleted {
+    items: Vec<TodoistCompletedItem>,
+    // by project id
+    #[serde(default)]
+    projects: HashMap<String, TodoistProject>,
+}
+
+impl Todoist {
+    fn read_api_token(config_name: &str, todoist_config: &TodoistConfig) -> Result<String> {
+        Config::read_secret(
+            &Todoist,
+            config_name,
+            API_TOKEN_USERNAME,
+            &todoist_config.api_token,
+        )
+    }
+
+    fn fetch_completed(api_token: &str, day: Date<Local>) -> Result<TodoistCompleted> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        // the API wants UTC times
+        let day_start = day.and_hms(0, 0, 0).with_timezone(&Utc);
+        let next_day_start = day_start + chrono::Duration::days(1);
+        let since = day_start.format("%Y-%m-%dT%H:%M:%S").to_string();
+        let until = next_day_start.format("%Y-%m-%dT%H:%M:%S").to_string();
+        let mut result = TodoistCompleted::default();
+        loop {
+            let offset = result.items.len().to_string();
+            let json = http::send_with_retry(|| {
+                client.get(API_URL).bearer_auth(api_token).query(&[
+                    ("since", since.as_str()),
+                    ("until", until.as_str()),
+                    ("limit", &PAGE_SIZE.to_string()),
+                    ("offset", offset.as_str()),
+                    ("annotate_items", "true"),
+                ])
+            })?
+            .text()?;
+            log::debug!("todoist completed items: {}", json);
+            let page = serde_json::from_str::<TodoistCompleted>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            let page_len = page.items.len();
+            result.items.extend(page.items);
+            result.projects.extend(page.projects);
+            if page_len < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(result)
+    }
+
+    fn item_to_event(
+        item: &TodoistCompletedItem,
+        projects: &HashMap<String, TodoistProject>,
+    ) -> Event {
+        let project = projects.get(&item.project_id).map(|p| p.name.as_str());
+        let labels = item
+            .item_object
+            .as_ref()
+            .map(|o| o.labels.clone())
+            .unwrap_or_else(Vec::new);
+        let mut body = vec![];
+        if let Some(p) = project {
+            body.push(format!("Project: {}", p));
+        }
+        if !labels.is_empty() {
+            body.push(format!("Labels: {}", labels.join(", ")));
+        }
+        Event::new(
+            "Todoist",
+            Icon::CHECK_SQUARE,
+            item.completed_at.with_timezone(&Local).time(),
+            item.content.clone(),
+            item.content.clone(),
+            EventBody::PlainText(body.join("\n")),
+            project.map(|p| p.to_string()),
+        )
+        .with_url(&format!("https://todoist.com/app/task/{}", item.task_id))
+        .with_category("task")
+    }
+}
+
+impl EventProvider for Todoist {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![(API_TOKEN_KEY, ConfigType::Password)]
+    }
+
+    fn name(&self) -> &'static str {
+        "Todoist"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CHECK_SQUARE
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.todoist.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![(
+            API_TOKEN_KEY,
+            Self::read_api_token(config_name, &config.todoist[config_name]).unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                "".to_string()
+            }),
+        )]
+        .into_iter()
+        .collect()
+    }
+
+    fn get_config_values_without_secrets(
+        &self,
+        _config: &Config,
+        _config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![(API_TOKEN_KEY, "".to_string())].into_iter().collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        let api_token = Config::store_secret(
+            &Todoist,
+            &config_name,
+            API_TOKEN_USERNAME,
+            config_values.remove(API_TOKEN_KEY).unwrap(),
+        );
+        config
+            .todoist
+            .insert(config_name, TodoistConfig { api_token });
+    }
+
+    fn update_config_values(
+        &self,
+        config: &mut Config,
+        old_config_name: &str,
+        config_name: String,
+        config_values: HashMap<&'static str, String>,
+    ) {
+        let old = config.todoist.remove(old_config_name);
+        self.add_config_values(config, config_name.clone(), config_values);
+        if let Some(old) = old {
+            Config::remove_replaced_secret(
+                &Todoist,
+                old_config_name,
+                API_TOKEN_USERNAME,
+                &old.api_token,
+                &config_name,
+                API_TOKEN_USERNAME,
+                &config.todoist[&config_name].api_token,
+            );
+        }
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        if let Some(todoist_config) = config.todoist.remove(&config_name) {
+            Config::remove_secret(
+                &Todoist,
+                &config_name,
+                API_TOKEN_USERNAME,
+                &todoist_config.api_token,
+            );
+        }
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            API_TOKEN_KEY if value.trim().is_empty() => {
+                Err("The API token is required".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let completed = match config
+            .get_cached_day_contents(options, &Todoist, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<TodoistCompleted>(&json).ok())
+        {
+            Some(c) => c,
+            None => {
+                let api_token = Self::read_api_token(config_name, &config.todoist[config_name])?;
+                let completed = Self::fetch_completed(&api_token, day)?;
+                Config::write_to_cache(&Todoist, &cache_key, &serde_json::to_string(&completed)?)?;
+                completed
+            }
+        };
+        Ok(completed
+            .items
+            .iter()
+            .map(|i| Self::item_to_event(i, &completed.projects))
+            .collect())
+    }
+}
+
+#[test]
+fn it_converts_todoist_completed_items() {
+    let json = r#"{
+        "items": [
+            {"content": "Write the report", "completed_at": "2020-02-25T09:30:00.000000Z",
+             "task_id": "123", "project_id": "10",
+             "item_object": {"labels": ["work", "urgent"]}},
+            {"content": "Buy milk", "completed_at": "2020-02-25T17:00:00.000000Z",
+             "task_id": "124", "project_id": "11"}
+        ],
+        "projects": {"10": {"name": "Cigale"}}
+    }"#;
+    let completed = serde_json::from_str::<TodoistCompleted>(json).unwrap();
+    let report = Todoist::item_to_event(&completed.items[0], &completed.projects);
+    assert_eq!("Write the report", report.event_info);
+    assert_eq!(Some("Cigale".to_string()), report.event_extra_details);
+    assert_eq!(
+        "Project: Cigale\nLabels: work, urgent",
+        report.event_contents_body.as_plain_text()
+    );
+    assert_eq!(
+        Some("https://todoist.com/app/task/123".to_string()),
+        report.event_url
+    );
+    let milk = Todoist::item_to_event(&completed.items[1], &completed.projects);
+    assert_eq!(None, milk.event_extra_details);
+    assert_eq!("", milk.event_contents_body.as_plain_text());
+}
+
+#[test]
+fn it_parses_a_day_without_completed_todoist_items() {
+    let completed = serde_json::from_str::<TodoistCompleted>(r#"{"items": []}"#).unwrap();
+    assert!(completed.items.is_empty());
+}
@@ -0,0 +1,278 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://developers.track.toggl.com/docs/api/time_entries
+const API_URL: &str = "https://api.track.toggl.com/api/v9";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TogglConfig {
+    pub api_token: String,
+    pub workspace_id: Option<u64>, // None => all the workspaces
+}
+
+pub struct Toggl;
+const API_TOKEN_KEY: &str = "API Token";
+const WORKSPACE_ID_KEY: &str = "Workspace id (optional)";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct TogglTimeEntry {
+    description: Option<String>,
+    start: DateTime<Local>,
+    stop: Option<DateTime<Local>>,
+    // in seconds. negative for running entries
+    duration: i64,
+    workspace_id: u64,
+    project_id: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct TogglProject {
+    id: u64,
+    name: String,
+}
+
+/// what we keep in the cache
+#[derive(Deserialize, Serialize, Debug)]
+struct TogglDayEntries {
+    entries: Vec<TogglTimeEntry>,
+    projects: Vec<TogglProject>,
+}
+
+impl Toggl {
+    fn toggl_get(
+        client: &reqwest::blocking::Client,
+        toggl_config: &TogglConfig,
+        url_path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String> {
+        // toggl uses basic auth, with the token as username
+        Ok(http::send_with_retry(|| {
+            client
+                .get(&format!("{}{}", API_URL, url_path))
+                .basic_auth(&toggl_config.api_token, Some("api_token"))
+                .query(params)
+        })?
+        .text()?)
+    }
+
+    fn fetch_entries(
+        config_name: &str,
+        toggl_config: &TogglConfig,
+        day: Date<Local>,
+    ) -> Result<TogglDayEntries> {
+        let client = reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?;
+        let day_start = day.and_hms(0, 0, 0);
+        let json = Self::toggl_get(
+            &client,
+            toggl_config,
+            "/me/time_entries",
+            &[
+                ("start_date", &day_start.to_rfc3339()),
+                (
+                    "end_date",
+                    &(day_start + chrono::Duration::days(1)).to_rfc3339(),
+                ),
+            ],
+        )?;
+        let entries = serde_json::from_str::<Vec<TogglTimeEntry>>(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?
+            .into_iter()
+            .filter(|e| {
+                toggl_config
+                    .workspace_id
+                    .map_or(true, |w| w == e.workspace_id)
+            })
+            .collect();
+        let projects_json = Self::toggl_get(&client, toggl_config, "/me/projects", &[])?;
+        let projects = serde_json::from_str::<Vec<TogglProject>>(&projects_json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, projects_json))?;
+        let day_entries = TogglDayEntries { entries, projects };
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Toggl, &cache_key, &serde_json::to_string(&day_entries)?)?;
+        Ok(day_entries)
+    }
+
+    fn entry_duration(entry: &TogglTimeEntry) -> Option<chrono::Duration> {
+        if entry.duration >= 0 {
+            Some(chrono::Duration::seconds(entry.duration))
+        } else {
+            // the entry is still running
+            None
+        }
+    }
+
+    fn entry_to_event(entry: &TogglTimeEntry, projects: &[TogglProject]) -> Event {
+        let description = entry
+            .description
+            .clone()
+            .filter(|d| !d.is_empty())
+            .unwrap_or_else(|| "(no description)".to_string());
+        let project = entry
+            .project_id
+            .and_then(|id| projects.iter().find(|p| p.id == id))
+            .map(|p| p.name.as_str());
+        let duration = Self::entry_duration(entry);
+        let duration_str = match duration {
+            Some(d) => format!("{}:{:02}", d.num_hours(), d.num_minutes() % 60),
+            None => "running".to_string(),
+        };
+        let mut body = vec![];
+        if let Some(p) = project {
+            body.push(format!("Project: {}", p));
+        }
+        body.push(format!("Duration: {}", duration_str));
+        if let Some(stop) = entry.stop {
+            body.push(format!("End: {}", stop.format("%H:%M")));
+        }
+        let event = Event::new(
+            "Toggl",
+            Icon::CLOCK,
+            entry.start.time(),
+            description.clone(),
+            description,
+            EventBody::PlainText(body.join("\n")),
+            Some(match project {
+                Some(p) => format!("{}; {}", p, duration_str),
+                None => duration_str,
+            }),
+        );
+        match duration {
+            Some(d) => event.with_duration(d),
+            None => event,
+        }
+    }
+}
+
+impl EventProvider for Toggl {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (API_TOKEN_KEY, ConfigType::Password),
+            (WORKSPACE_ID_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Toggl"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::CLOCK
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.toggl.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                API_TOKEN_KEY,
+                config.toggl[config_name].api_token.to_string(),
+            ),
+            (
+                WORKSPACE_ID_KEY,
+                config.toggl[config_name]
+                    .workspace_id
+                    .map(|w| w.to_string())
+                    .unwrap_or_else(|| "".to_string()),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.toggl.insert(
+            config_name,
+            TogglConfig {
+                api_token: config_values.remove(API_TOKEN_KEY).unwrap(),
+                workspace_id: config_values
+                    .remove(WORKSPACE_ID_KEY)
+                    .and_then(|w| w.trim().parse().ok()),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.toggl.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let toggl_config = &config.toggl[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let day_entries = match config
+            .get_cached_day_contents(options, &Toggl, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<TogglDayEntries>(&json).ok())
+        {
+            Some(e) => e,
+            None => Self::fetch_entries(config_name, toggl_config, day)?,
+        };
+        let mut result: Vec<Event> = day_entries
+            .entries
+            .iter()
+            .map(|e| Self::entry_to_event(e, &day_entries.projects))
+            .collect();
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_converts_toggl_entries() {
+    let json = r#"[
+        {"description": "Code review", "start": "2020-02-25T09:00:00+00:00",
+         "stop": "2020-02-25T10:30:00+00:00", "duration": 5400,
+         "workspace_id": 1, "project_id": 10},
+        {"description": "", "start": "2020-02-25T11:00:00+00:00",
+         "stop": null, "duration": -1582628400, "workspace_id": 1, "project_id": null}
+    ]"#;
+    let entries = serde_json::from_str::<Vec<TogglTimeEntry>>(json).unwrap();
+    let projects = vec![TogglProject {
+        id: 10,
+        name: "Cigale".to_string(),
+    }];
+    let finished = Toggl::entry_to_event(&entries[0], &projects);
+    assert_eq!("Code review", finished.event_info);
+    assert_eq!(Some(chrono::Duration::minutes(90)), finished.event_duration);
+    assert_eq!(
+        Some("Cigale; 1:30".to_string()),
+        finished.event_extra_details
+    );
+    let running = Toggl::entry_to_event(&entries[1], &projects);
+    assert_eq!("(no description)", running.event_info);
+    assert_eq!(None, running.event_duration);
+    assert_eq!(Some("running".to_string()), running.event_extra_details);
+}
@@ -0,0 +1,312 @@
+use super::events::{
+    ConfigType, Event, EventBody, EventProvider, FetchOptions, Result, WordWrapMode,
+};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://developer.atlassian.com/cloud/trello/rest/api-group-boards/#api-boards-boardid-actions-get
+// the maximum page size for actions
+const ACTIONS_LIMIT: &str = "1000";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TrelloConfig {
+    pub api_key: String,
+    pub token: String,
+    pub board_ids: Vec<String>,
+}
+
+pub struct Trello;
+const API_KEY_KEY: &str = "API Key";
+const TOKEN_KEY: &str = "Token";
+const BOARD_IDS_KEY: &str = "Board ids (space-separated)";
+
+#[derive(Deserialize, Debug)]
+struct TrelloMember {
+    id: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TrelloAction {
+    id_member_creator: String,
+    #[serde(rename = "type")]
+    action_type: String,
+    date: DateTime<Local>,
+    data: TrelloActionData,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TrelloActionData {
+    card: Option<TrelloCard>,
+    board: Option<TrelloNamed>,
+    list: Option<TrelloNamed>,
+    list_before: Option<TrelloNamed>,
+    list_after: Option<TrelloNamed>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct TrelloCard {
+    name: String,
+    short_link: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct TrelloNamed {
+    name: String,
+}
+
+impl Trello {
+    fn get_client() -> Result<reqwest::blocking::Client> {
+        Ok(reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?)
+    }
+
+    fn trello_get(
+        client: &reqwest::blocking::Client,
+        trello_config: &TrelloConfig,
+        url_path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<String> {
+        Ok(http::send_with_retry(|| {
+            client
+                .get(&format!("https://api.trello.com/1{}", url_path))
+                .query(&[
+                    ("key", trello_config.api_key.as_str()),
+                    ("token", trello_config.token.as_str()),
+                ])
+                .query(params)
+        })?
+        .text()?)
+    }
+
+    fn fetch_actions(
+        config_name: &str,
+        trello_config: &TrelloConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<TrelloAction>> {
+        let client = Self::get_client()?;
+        let member_json =
+            Self::trello_get(&client, trello_config, "/members/me", &[("fields", "id")])?;
+        let member = serde_json::from_str::<TrelloMember>(&member_json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, member_json))?;
+        let since = day.and_hms(0, 0, 0).to_rfc3339();
+        let before = (day.and_hms(0, 0, 0) + chrono::Duration::days(1)).to_rfc3339();
+        let mut actions = vec![];
+        for board_id in &trello_config.board_ids {
+            let json = Self::trello_get(
+                &client,
+                trello_config,
+                &format!("/boards/{}/actions", board_id),
+                &[
+                    ("since", &since),
+                    ("before", &before),
+                    ("limit", ACTIONS_LIMIT),
+                    ("filter", "createCard,updateCard,commentCard"),
+                ],
+            )?;
+            let board_actions = serde_json::from_str::<Vec<TrelloAction>>(&json)
+                .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?;
+            // only keep the actions of the authenticated member
+            actions.extend(
+                board_actions
+                    .into_iter()
+                    .filter(|a| a.id_member_creator == member.id),
+            );
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&Trello, &cache_key, &serde_json::to_string(&actions)?)?;
+        Ok(actions)
+    }
+
+    fn action_description(action: &TrelloAction) -> Option<(&'static str, String)> {
+        let data = &action.data;
+        match action.action_type.as_str() {
+            "createCard" => Some((
+                "Card created",
+                format!(
+                    "Created in list {}",
+                    data.list.as_ref().map(|l| l.name.as_str()).unwrap_or("?")
+                ),
+            )),
+            "commentCard" => Some((
+                "Card comment",
+                data.text.clone().unwrap_or_else(|| "".to_string()),
+            )),
+            "updateCard" => match (&data.list_before, &data.list_after) {
+                (Some(before), Some(after)) => Some((
+                    "Card moved",
+                    format!("Moved from {} to {}", before.name, after.name),
+                )),
+                // other card updates (description, due date...) are too noisy
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn action_to_event(action: &TrelloAction) -> Option<Event> {
+        let card = action.data.card.as_ref()?;
+        let (action_desc, details) = Self::action_description(action)?;
+        let link = card
+            .short_link
+            .as_ref()
+            .map(|l| {
+                format!(
+                    "<a href=\"https://trello.com/c/{}\">Open in the browser</a>\n\n",
+                    glib::markup_escape_text(l)
+                )
+            })
+            .unwrap_or_else(|| "".to_string());
+        Some(Event::new(
+            "Trello",
+            Icon::TRELLO,
+            action.date.time(),
+            card.name.clone(),
+            card.name.clone(),
+            EventBody::Markup(
+                format!(
+                    "{}{}{}",
+                    link,
+                    action
+                        .data
+                        .board
+                        .as_ref()
+                        .map(|b| format!("Board: {}\n", glib::markup_escape_text(&b.name)))
+                        .unwrap_or_else(|| "".to_string()),
+                    glib::markup_escape_text(&details)
+                ),
+                WordWrapMode::WordWrap,
+            ),
+            Some(action_desc.to_string()),
+        ))
+    }
+}
+
+impl EventProvider for Trello {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (API_KEY_KEY, ConfigType::Text("")),
+            (TOKEN_KEY, ConfigType::Password),
+            (BOARD_IDS_KEY, ConfigType::Text("")),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "Trello"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::TRELLO
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.trello.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (API_KEY_KEY, config.trello[config_name].api_key.to_string()),
+            (TOKEN_KEY, config.trello[config_name].token.to_string()),
+            (
+                BOARD_IDS_KEY,
+                config.trello[config_name].board_ids.join(" "),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.trello.insert(
+            config_name,
+            TrelloConfig {
+                api_key: config_values.remove(API_KEY_KEY).unwrap(),
+                token: config_values.remove(TOKEN_KEY).unwrap(),
+                board_ids: config_values
+                    .remove(BOARD_IDS_KEY)
+                    .unwrap()
+                    .split_whitespace()
+                    .map(|b| b.to_string())
+                    .collect(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.trello.remove(&config_name);
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let trello_config = &config.trello[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let actions = match config
+            .get_cached_day_contents(options, &Trello, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<TrelloAction>>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_actions(config_name, trello_config, day)?,
+        };
+        let mut result: Vec<Event> = actions.iter().filter_map(Self::action_to_event).collect();
+        // the actions of the different boards are merged in one timeline
+        result.sort_by_key(|e| e.event_time);
+        Ok(result)
+    }
+}
+
+#[test]
+fn it_converts_trello_actions_to_events() {
+    let json = r#"[
+        {"idMemberCreator": "me", "type": "updateCard", "date": "2020-02-25T10:00:00.000Z",
+         "data": {"card": {"name": "Fix the build", "shortLink": "abc123"},
+                  "board": {"name": "Sprint"},
+                  "listBefore": {"name": "Doing"}, "listAfter": {"name": "Done"}}},
+        {"idMemberCreator": "me", "type": "updateCard", "date": "2020-02-25T11:00:00.000Z",
+         "data": {"card": {"name": "Fix the build", "shortLink": "abc123"}}}
+    ]"#;
+    let actions = serde_json::from_str::<Vec<TrelloAction>>(json).unwrap();
+    let events: Vec<_> = actions.iter().filter_map(Trello::action_to_event).collect();
+    assert_eq!(1, events.len());
+    assert_eq!("Fix the build", events[0].event_contents_header);
+    assert_eq!(
+        EventBody::Markup(
+            "<a href=\"https://trello.com/c/abc123\">Open in the browser</a>\n\nBoard: Sprint\nMoved from Doing to Done"
+                .to_string(),
+            WordWrapMode::WordWrap
+        ),
+        events[0].event_contents_body
+    );
+}
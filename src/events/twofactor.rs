@@ -0,0 +1,66 @@
+// some servers ask for a one-time code (TOTP) after the password.
+// we ask the user for it when logging in, the code is never stored.
+use super::events::Result;
+use gtk::prelude::*;
+use std::sync::mpsc;
+
+/// ask the user for the current two-factor code of `source_desc`.
+/// the event providers run on background threads: we display the
+/// dialog on the gtk thread and wait for the answer.
+/// None if the user cancelled.
+pub fn ask_code(source_desc: &str) -> Result<Option<String>> {
+    let context = glib::MainContext::default();
+    if context.is_owner() {
+        return Ok(run_code_dialog(source_desc));
+    }
+    let (sender, receiver) = mpsc::channel();
+    let source_desc = source_desc.to_string();
+    context.invoke(move || {
+        sender
+            .send(run_code_dialog(&source_desc))
+            .unwrap_or_else(|err| println!("Thread communication error: {}", err));
+    });
+    Ok(receiver.recv()?)
+}
+
+fn run_code_dialog(source_desc: &str) -> Option<String> {
+    let parent = gtk::Window::list_toplevels()
+        .into_iter()
+        .filter_map(|w| w.downcast::<gtk::Window>().ok())
+        .find(|w| w.is_active());
+    let dialog = gtk::Dialog::with_buttons(
+        Some("Two-factor authentication"),
+        parent.as_ref(),
+        gtk::DialogFlags::all(),
+        &[
+            ("Cancel", gtk::ResponseType::Cancel),
+            ("Log in", gtk::ResponseType::Ok),
+        ],
+    );
+    dialog.set_default_response(gtk::ResponseType::Ok);
+    let contents = gtk::BoxBuilder::new()
+        .orientation(gtk::Orientation::Vertical)
+        .spacing(6)
+        .margin(10)
+        .build();
+    contents.add(
+        &gtk::LabelBuilder::new()
+            .label(&format!(
+                "Enter the authentication code from your app to log in to {}",
+                source_desc
+            ))
+            .xalign(0.0)
+            .build(),
+    );
+    let entry = gtk::EntryBuilder::new()
+        .activates_default(true)
+        .input_purpose(gtk::InputPurpose::Digits)
+        .build();
+    contents.add(&entry);
+    dialog.content_area().pack_start(&contents, true, true, 0);
+    dialog.show_all();
+    let r = dialog.run();
+    let code = entry.text().trim().to_string();
+    dialog.close();
+    Some(code).filter(|c| r == gtk::ResponseType::Ok && !c.is_empty())
+}
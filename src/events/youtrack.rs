@@ -0,0 +1,407 @@
+use super::events::{ConfigType, Event, EventBody, EventProvider, FetchOptions, Result};
+use super::http;
+use crate::config::Config;
+use crate::icons::*;
+use chrono::prelude::*;
+use core::time::Duration;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// https://www.jetbrains.com/help/youtrack/devportal/resource-api-issues.html
+// we list the issues we updated that day, then their activities
+// to find out what we did exactly.
+const PAGE_SIZE: usize = 100;
+const ACTIVITY_CATEGORIES: &str = "CommentsCategory,IssueCreatedCategory,IssueResolvedCategory,\
+                                   CustomFieldCategory,SummaryCategory,DescriptionCategory";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct YouTrackConfig {
+    pub server_url: String,
+    pub permanent_token: String,
+}
+
+pub struct YouTrack;
+const SERVER_URL_KEY: &str = "Server URL";
+const PERMANENT_TOKEN_KEY: &str = "Permanent Token";
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct YouTrackUser {
+    login: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct YouTrackIssue {
+    id_readable: String,
+    summary: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct YouTrackCategory {
+    id: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct YouTrackField {
+    name: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct YouTrackActivity {
+    // milliseconds since the epoch
+    timestamp: i64,
+    author: YouTrackUser,
+    category: YouTrackCategory,
+    field: Option<YouTrackField>,
+    // depending on the category: a list of objects, a string, or nothing
+    #[serde(default)]
+    added: serde_json::Value,
+    #[serde(default)]
+    removed: serde_json::Value,
+}
+
+/// what we keep in the cache for a day
+#[derive(Deserialize, Serialize, Clone, Debug)]
+struct YouTrackIssueActivities {
+    issue: YouTrackIssue,
+    activities: Vec<YouTrackActivity>,
+}
+
+impl YouTrack {
+    fn build_client() -> Result<reqwest::blocking::Client> {
+        Ok(reqwest::blocking::ClientBuilder::new()
+            .timeout(Duration::from_secs(30))
+            .connect_timeout(Duration::from_secs(30))
+            .connection_verbose(true)
+            .build()?)
+    }
+
+    fn get_json<T: serde::de::DeserializeOwned>(
+        client: &reqwest::blocking::Client,
+        youtrack_config: &YouTrackConfig,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        let json = http::send_with_retry(|| {
+            client
+                .get(&format!("{}/api/{}", youtrack_config.server_url, path))
+                .query(query)
+                .bearer_auth(&youtrack_config.permanent_token)
+                .header("Accept", "application/json")
+        })?
+        .text()?;
+        log::debug!("youtrack {}: {}", path, json);
+        Ok(serde_json::from_str(&json)
+            .map_err(|e| format!("Failed parsing json {:?} -- {}", e, json))?)
+    }
+
+    fn fetch_issue_activities(
+        config_name: &str,
+        youtrack_config: &YouTrackConfig,
+        day: Date<Local>,
+    ) -> Result<Vec<YouTrackIssueActivities>> {
+        let client = Self::build_client()?;
+        let me: YouTrackUser =
+            Self::get_json(&client, youtrack_config, "users/me", &[("fields", "login")])?;
+        let query = format!("updated: {} updater: me", day.format("%Y-%m-%d"));
+        let mut issues: Vec<YouTrackIssue> = vec![];
+        loop {
+            let skip = issues.len().to_string();
+            let top = PAGE_SIZE.to_string();
+            let mut page: Vec<YouTrackIssue> = Self::get_json(
+                &client,
+                youtrack_config,
+                "issues",
+                &[
+                    ("query", &query),
+                    ("fields", "idReadable,summary"),
+                    ("$skip", &skip),
+                    ("$top", &top),
+                ],
+            )?;
+            let page_len = page.len();
+            issues.append(&mut page);
+            if page_len < PAGE_SIZE {
+                break;
+            }
+        }
+        let start = day.and_hms(0, 0, 0).timestamp_millis().to_string();
+        let end = (day.and_hms(0, 0, 0) + chrono::Duration::days(1))
+            .timestamp_millis()
+            .to_string();
+        let mut result = vec![];
+        for issue in issues {
+            let activities: Vec<YouTrackActivity> = Self::get_json(
+                &client,
+                youtrack_config,
+                &format!("issues/{}/activities", issue.id_readable),
+                &[
+                    ("categories", ACTIVITY_CATEGORIES),
+                    ("start", &start),
+                    ("end", &end),
+                    (
+                        "fields",
+                        "timestamp,author(login),category(id),field(name),\
+                         added(name,text,login),removed(name,text,login)",
+                    ),
+                ],
+            )?;
+            result.push(YouTrackIssueActivities {
+                issue,
+                activities: activities
+                    .into_iter()
+                    .filter(|a| a.author.login == me.login)
+                    .collect(),
+            });
+        }
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        Config::write_to_cache(&YouTrack, &cache_key, &serde_json::to_string(&result)?)?;
+        Ok(result)
+    }
+
+    fn describe_value(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(Self::describe_value)
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join(", "),
+            serde_json::Value::Object(obj) => ["name", "text", "login"]
+                .iter()
+                .find_map(|k| obj.get(*k).and_then(|v| v.as_str()))
+                .unwrap_or("")
+                .to_string(),
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Null => "".to_string(),
+            v => v.to_string(),
+        }
+    }
+
+    /// (what happened, details)
+    fn describe_activity(activity: &YouTrackActivity) -> (String, String) {
+        let change = || {
+            format!(
+                "{} → {}",
+                Self::describe_value(&activity.removed),
+                Self::describe_value(&activity.added)
+            )
+        };
+        match activity.category.id.as_str() {
+            "CommentsCategory" => ("Comment".to_string(), Self::describe_value(&activity.added)),
+            "IssueCreatedCategory" => ("Created".to_string(), "".to_string()),
+            "IssueResolvedCategory" => ("Resolved".to_string(), "".to_string()),
+            "SummaryCategory" => ("Summary changed".to_string(), change()),
+            "DescriptionCategory" => (
+                "Description changed".to_string(),
+                Self::describe_value(&activity.added),
+            ),
+            "CustomFieldCategory" => (
+                format!(
+                    "{} changed",
+                    activity
+                        .field
+                        .as_ref()
+                        .map(|f| f.name.as_str())
+                        .unwrap_or("Field")
+                ),
+                change(),
+            ),
+            _ => ("Updated".to_string(), "".to_string()),
+        }
+    }
+
+    fn build_event(
+        youtrack_config: &YouTrackConfig,
+        issue: &YouTrackIssue,
+        activity: &YouTrackActivity,
+    ) -> Option<Event> {
+        let time = Local.timestamp_millis_opt(activity.timestamp).single()?;
+        let (action, details) = Self::describe_activity(activity);
+        let url = format!("{}/issue/{}", youtrack_config.server_url, issue.id_readable);
+        let title = format!("{}: {}", issue.id_readable, issue.summary);
+        Some(
+            Event::new(
+                "YouTrack",
+                match activity.category.id.as_str() {
+                    "CommentsCategory" => Icon::COMMENT_DOTS,
+                    "IssueResolvedCategory" => Icon::CHECK_SQUARE,
+                    _ => Icon::TASKS,
+                },
+                time.time(),
+                title.clone(),
+                title,
+                EventBody::PlainText(if details.is_empty() {
+                    action.clone()
+                } else {
+                    format!("{}\n\n{}", action, details)
+                }),
+                Some(action),
+            )
+            .with_url(&url)
+            .with_category("issue"),
+        )
+    }
+}
+
+impl EventProvider for YouTrack {
+    fn get_config_fields(&self) -> Vec<(&'static str, ConfigType)> {
+        vec![
+            (SERVER_URL_KEY, ConfigType::Text("")),
+            (PERMANENT_TOKEN_KEY, ConfigType::Password),
+        ]
+    }
+
+    fn name(&self) -> &'static str {
+        "YouTrack"
+    }
+
+    fn default_icon(&self) -> Icon {
+        Icon::TASKS
+    }
+
+    fn get_config_names<'a>(&self, config: &'a Config) -> Vec<&'a String> {
+        config.youtrack.keys().collect()
+    }
+
+    fn field_values(
+        &self,
+        _cur_values: &HashMap<&'static str, String>,
+        _field_name: &'static str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn get_config_values(
+        &self,
+        config: &Config,
+        config_name: &str,
+    ) -> HashMap<&'static str, String> {
+        vec![
+            (
+                SERVER_URL_KEY,
+                config.youtrack[config_name].server_url.to_string(),
+            ),
+            (
+                PERMANENT_TOKEN_KEY,
+                config.youtrack[config_name].permanent_token.to_string(),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn add_config_values(
+        &self,
+        config: &mut Config,
+        config_name: String,
+        mut config_values: HashMap<&'static str, String>,
+    ) {
+        config.youtrack.insert(
+            config_name,
+            YouTrackConfig {
+                server_url: config_values
+                    .remove(SERVER_URL_KEY)
+                    .unwrap()
+                    .trim_end_matches('/')
+                    .to_string(),
+                permanent_token: config_values.remove(PERMANENT_TOKEN_KEY).unwrap(),
+            },
+        );
+    }
+
+    fn remove_config(&self, config: &mut Config, config_name: String) {
+        config.youtrack.remove(&config_name);
+    }
+
+    fn validate_field(
+        &self,
+        field_name: &'static str,
+        value: &str,
+    ) -> std::result::Result<(), String> {
+        match field_name {
+            SERVER_URL_KEY => reqwest::Url::parse(value.trim())
+                .map(|_| ())
+                .map_err(|e| format!("Invalid URL: {}", e)),
+            PERMANENT_TOKEN_KEY if value.trim().is_empty() => {
+                Err("The permanent token is required".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn get_events(
+        &self,
+        config: &Config,
+        options: &FetchOptions,
+        config_name: &str,
+        day: Date<Local>,
+    ) -> Result<Vec<Event>> {
+        let youtrack_config = &config.youtrack[config_name];
+        let cache_key = format!("{}_{}", config_name, day.format("%Y%m%d"));
+        let issue_activities = match config
+            .get_cached_day_contents(options, &YouTrack, &cache_key, day)?
+            .and_then(|json| serde_json::from_str::<Vec<YouTrackIssueActivities>>(&json).ok())
+        {
+            Some(a) => a,
+            None => Self::fetch_issue_activities(config_name, youtrack_config, day)?,
+        };
+        let mut events: Vec<Event> = issue_activities
+            .iter()
+            .flat_map(|ia| {
+                ia.activities
+                    .iter()
+                    .filter_map(move |a| Self::build_event(youtrack_config, &ia.issue, a))
+            })
+            .collect();
+        events.sort_by_key(|e| e.event_time);
+        Ok(events)
+    }
+}
+
+#[test]
+fn it_converts_youtrack_activities() {
+    let json = r#"[{
+        "issue": {"idReadable": "CIG-12", "summary": "Crash on startup"},
+        "activities": [
+            {"timestamp": 1614249000000, "author": {"login": "me"},
+             "category": {"id": "CustomFieldCategory"}, "field": {"name": "State"},
+             "added": [{"name": "Fixed"}], "removed": [{"name": "Open"}]},
+            {"timestamp": 1614250000000, "author": {"login": "me"},
+             "category": {"id": "CommentsCategory"}, "field": {"name": "comments"},
+             "added": [{"text": "fixed in master"}], "removed": []},
+            {"timestamp": 1614251000000, "author": {"login": "me"},
+             "category": {"id": "IssueCreatedCategory"}}
+        ]
+    }]"#;
+    let config = YouTrackConfig {
+        server_url: "https://youtrack.example.com".to_string(),
+        permanent_token: "".to_string(),
+    };
+    let issue_activities = serde_json::from_str::<Vec<YouTrackIssueActivities>>(json).unwrap();
+    let ia = &issue_activities[0];
+    let events: Vec<Event> = ia
+        .activities
+        .iter()
+        .filter_map(|a| YouTrack::build_event(&config, &ia.issue, a))
+        .collect();
+    assert_eq!(3, events.len());
+    assert_eq!("CIG-12: Crash on startup", events[0].event_info);
+    assert_eq!(
+        "State changed\n\nOpen → Fixed",
+        events[0].event_contents_body.as_plain_text()
+    );
+    assert_eq!(
+        Some("https://youtrack.example.com/issue/CIG-12".to_string()),
+        events[0].event_url
+    );
+    assert_eq!(
+        Local.timestamp_millis(1614249000000).time(),
+        events[0].event_time
+    );
+    assert_eq!(
+        "Comment\n\nfixed in master",
+        events[1].event_contents_body.as_plain_text()
+    );
+    assert_eq!("Created", events[2].event_contents_body.as_plain_text());
+}
@@ -0,0 +1,371 @@
+use crate::events::events::{Event, FetchedEvents, Result};
+use chrono::prelude::*;
+use serde_derive::Serialize;
+
+/// a flat, serializable view of an event, for the CSV & JSON exports
+#[derive(Serialize, Debug)]
+struct ExportedEvent<'a> {
+    date: String,
+    time: String,
+    provider: &'a str,
+    title: &'a str,
+    subtitle: &'a str,
+    body: String,
+    url: &'a str,
+}
+
+fn to_exported_events(day: Date<Local>, events: &[Event]) -> Vec<ExportedEvent> {
+    let mut sorted_events: Vec<&Event> = events.iter().collect();
+    sorted_events.sort_by_key(|e| e.event_time);
+    sorted_events
+        .into_iter()
+        .map(|e| ExportedEvent {
+            date: day.format("%Y-%m-%d").to_string(),
+            time: e.event_time.format("%H:%M").to_string(),
+            provider: e.event_type_desc,
+            title: e.event_contents_header.trim(),
+            subtitle: e.event_extra_details.as_deref().unwrap_or(""),
+            body: e.event_contents_body.as_plain_text().trim().to_string(),
+            url: e.event_url.as_deref().unwrap_or(""),
+        })
+        .collect()
+}
+
+pub fn to_csv(day: Date<Local>, events: &[Event]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for event in to_exported_events(day, events) {
+        writer.serialize(event)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+pub fn to_json(day: Date<Local>, events: &[Event]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&to_exported_events(
+        day, events,
+    ))?)
+}
+
+fn sorted_by_time<'a>(events: impl IntoIterator<Item = &'a Event>) -> Vec<&'a Event> {
+    let mut sorted_events: Vec<&Event> = events.into_iter().collect();
+    sorted_events.sort_by_key(|e| e.event_time);
+    sorted_events
+}
+
+/// the time and title of an event, as in the markdown export
+fn event_title(event: &Event) -> (String, &str) {
+    (
+        event.event_time.format("%H:%M").to_string(),
+        event.event_contents_header.trim(),
+    )
+}
+
+/// one line per event, for the command-line
+pub fn to_text(events: &[Event]) -> String {
+    sorted_by_time(events)
+        .iter()
+        .map(|e| {
+            format!(
+                "{} [{}] {}{}\n",
+                e.event_time.format("%H:%M"),
+                e.event_type_desc,
+                e.event_contents_header.trim(),
+                e.event_extra_details
+                    .as_ref()
+                    .map(|d| format!(" ({})", d))
+                    .unwrap_or_else(|| "".to_string())
+            )
+        })
+        .collect()
+}
+
+/// a single event, for instance to paste it in a chat
+pub fn event_to_text(event: &Event) -> String {
+    let mut result = format!(
+        "{} {}",
+        event.event_time.format("%H:%M"),
+        event.event_contents_header.trim()
+    );
+    if let Some(subtitle) = &event.event_extra_details {
+        result.push_str(&format!(" ({})", subtitle));
+    }
+    if let Some(url) = &event.event_url {
+        result.push_str(&format!("\n{}", url));
+    }
+    let body = event.event_contents_body.as_plain_text();
+    if !body.trim().is_empty() {
+        result.push_str(&format!("\n\n{}", body.trim()));
+    }
+    result
+}
+
+/// export the events of a day to markdown, for
+/// instance to paste them in a timesheet
+pub fn to_markdown(day: Date<Local>, events: &[Event]) -> String {
+    let mut result = format!("## {}\n\n", day.format("%A, %Y-%m-%d"));
+    for event in sorted_by_time(events) {
+        let (time, title) = event_title(event);
+        result.push_str(&format!("- {} **{}**", time, title));
+        if let Some(subtitle) = &event.event_extra_details {
+            result.push_str(&format!(" — {}", subtitle));
+        }
+        if let Some(url) = &event.event_url {
+            result.push_str(&format!(" ([link]({}))", url));
+        }
+        result.push('\n');
+        // the body is indented to be part of the bullet
+        for line in event
+            .event_contents_body
+            .as_plain_text()
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+        {
+            result.push_str(&format!("  {}\n", line.trim_end()));
+        }
+    }
+    result
+}
+
+/// per project, the time spent on each day of a week,
+/// what people submit for billing
+pub struct Timesheet {
+    pub days: Vec<Date<Local>>,
+    // project, then the duration for each of the days
+    pub rows: Vec<(String, Vec<chrono::Duration>)>,
+}
+
+/// the key under which we group an event in the timesheet: the project
+/// if the title has one ("Project - Bug #12: crash" in redmine, "PROJ-12"
+/// in jira), else the issue ("Bug #12"), and else the event source.
+fn timesheet_project(event: &Event) -> String {
+    let title = event.event_contents_header.trim();
+    let project_prefix_re = regex::Regex::new(r"^(.+?) - .*#\d+").unwrap();
+    let jira_key_re = regex::Regex::new(r"\b([A-Z][A-Z0-9]+)-\d+\b").unwrap();
+    let issue_re = regex::Regex::new(r"^(.*?#\d+)").unwrap();
+    project_prefix_re
+        .captures(title)
+        .or_else(|| jira_key_re.captures(title))
+        .or_else(|| issue_re.captures(title))
+        .map(|c| c[1].trim().to_string())
+        .unwrap_or_else(|| event.event_type_desc.to_string())
+}
+
+pub fn week_timesheet(days: &[(Date<Local>, FetchedEvents)]) -> Timesheet {
+    let mut rows: Vec<(String, Vec<chrono::Duration>)> = vec![];
+    for (day_idx, (_, fetched)) in days.iter().enumerate() {
+        for event in &fetched.events {
+            if let Some(duration) = event.event_duration {
+                let project = timesheet_project(event);
+                let row_idx = match rows.iter().position(|(p, _)| *p == project) {
+                    Some(idx) => idx,
+                    None => {
+                        rows.push((project, vec![chrono::Duration::zero(); days.len()]));
+                        rows.len() - 1
+                    }
+                };
+                rows[row_idx].1[day_idx] = rows[row_idx].1[day_idx] + duration;
+            }
+        }
+    }
+    rows.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Timesheet {
+        days: days.iter().map(|(d, _)| *d).collect(),
+        rows,
+    }
+}
+
+fn format_hours(duration: chrono::Duration) -> String {
+    format!("{:.2}", duration.num_minutes() as f64 / 60.0)
+}
+
+fn sum_durations<'a>(durations: impl Iterator<Item = &'a chrono::Duration>) -> chrono::Duration {
+    durations.fold(chrono::Duration::zero(), |a, b| a + *b)
+}
+
+/// the header, the project rows and the totals row, as cells
+fn timesheet_table(timesheet: &Timesheet) -> Vec<Vec<String>> {
+    let mut table = vec![std::iter::once("Project".to_string())
+        .chain(
+            timesheet
+                .days
+                .iter()
+                .map(|d| d.format("%a %m-%d").to_string()),
+        )
+        .chain(std::iter::once("Total".to_string()))
+        .collect()];
+    for (project, durations) in &timesheet.rows {
+        table.push(
+            std::iter::once(project.clone())
+                .chain(durations.iter().map(|d| format_hours(*d)))
+                .chain(std::iter::once(format_hours(sum_durations(
+                    durations.iter(),
+                ))))
+                .collect(),
+        );
+    }
+    let day_totals: Vec<chrono::Duration> = (0..timesheet.days.len())
+        .map(|i| sum_durations(timesheet.rows.iter().map(|(_, d)| &d[i])))
+        .collect();
+    table.push(
+        std::iter::once("Total".to_string())
+            .chain(day_totals.iter().map(|d| format_hours(*d)))
+            .chain(std::iter::once(format_hours(sum_durations(
+                day_totals.iter(),
+            ))))
+            .collect(),
+    );
+    table
+}
+
+pub fn timesheet_to_csv(timesheet: &Timesheet) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in timesheet_table(timesheet) {
+        writer.write_record(&row)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+pub fn timesheet_to_markdown(timesheet: &Timesheet) -> String {
+    let table = timesheet_table(timesheet);
+    let mut result = match timesheet.days.first() {
+        Some(d) => format!("## Week of {}\n\n", d.format("%Y-%m-%d")),
+        None => "".to_string(),
+    };
+    for (i, row) in table.iter().enumerate() {
+        result.push_str(&format!("| {} |\n", row.join(" | ")));
+        if i == 0 {
+            result.push_str(&format!("|{}\n", "---|".repeat(row.len())));
+        }
+    }
+    result
+}
+
+/// one line per event, for instance for a standup summary in a chat
+pub fn to_summary(events: &[&Event]) -> String {
+    sorted_by_time(events.iter().copied())
+        .into_iter()
+        .map(|e| {
+            let (time, title) = event_title(e);
+            format!("{} — {}\n", time, title)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+use crate::events::events::{EventBody, WordWrapMode};
+#[cfg(test)]
+use crate::icons::*;
+
+#[cfg(test)]
+fn get_test_events() -> Vec<Event> {
+    vec![
+        Event::new(
+            "Redmine",
+            Icon::TASKS,
+            NaiveTime::from_hms(14, 30, 0),
+            "info".to_string(),
+            "Bug #12: crash".to_string(),
+            EventBody::Markup("fixed &amp; tested".to_string(), WordWrapMode::WordWrap),
+            Some("Issue edited".to_string()),
+        )
+        .with_url("http://redmine/issues/12"),
+        Event::new(
+            "Git",
+            Icon::CODE_BRANCH,
+            NaiveTime::from_hms(9, 5, 0),
+            "info".to_string(),
+            "fix the build".to_string(),
+            EventBody::PlainText("".to_string()),
+            None,
+        ),
+    ]
+}
+
+#[test]
+fn it_exports_events_to_markdown() {
+    let events = get_test_events();
+    assert_eq!(
+        "## Tuesday, 2020-02-25\n\n- 09:05 **fix the build**\n- 14:30 **Bug #12: crash** — Issue edited ([link](http://redmine/issues/12))\n  fixed & tested\n",
+        to_markdown(Local.ymd(2020, 2, 25), &events)
+    );
+}
+
+#[test]
+fn it_exports_a_day_summary() {
+    let events = get_test_events();
+    assert_eq!(
+        "09:05 — fix the build\n14:30 — Bug #12: crash\n",
+        to_summary(&events.iter().collect::<Vec<_>>())
+    );
+}
+
+#[test]
+fn it_exports_a_single_event_to_text() {
+    let events = get_test_events();
+    assert_eq!(
+        "14:30 Bug #12: crash (Issue edited)\nhttp://redmine/issues/12\n\nfixed & tested",
+        event_to_text(&events[0])
+    );
+}
+
+#[test]
+fn it_exports_events_to_csv() {
+    let events = get_test_events();
+    assert_eq!(
+        "date,time,provider,title,subtitle,body,url\n\
+         2020-02-25,09:05,Git,fix the build,,,\n\
+         2020-02-25,14:30,Redmine,Bug #12: crash,Issue edited,fixed & tested,http://redmine/issues/12\n",
+        to_csv(Local.ymd(2020, 2, 25), &events).unwrap()
+    );
+}
+
+#[test]
+fn it_builds_a_week_timesheet() {
+    let event = |header: &str, mins: i64| {
+        Event::new(
+            "Redmine",
+            Icon::CLOCK,
+            NaiveTime::from_hms(10, 0, 0),
+            "info".to_string(),
+            header.to_string(),
+            EventBody::PlainText("".to_string()),
+            None,
+        )
+        .with_duration(chrono::Duration::minutes(mins))
+    };
+    let fetched = |events| FetchedEvents {
+        events,
+        errors: vec![],
+    };
+    let days = vec![
+        (
+            Local.ymd(2020, 2, 24),
+            fetched(vec![
+                event("Cigale - Bug #12: crash", 90),
+                event("PROJ-3: write the docs", 30),
+                // no duration, not in the timesheet
+                get_test_events().remove(1),
+            ]),
+        ),
+        (
+            Local.ymd(2020, 2, 25),
+            fetched(vec![event("Cigale - Feature #13: export", 60)]),
+        ),
+    ];
+    let timesheet = week_timesheet(&days);
+    assert_eq!(
+        "## Week of 2020-02-24\n\n\
+         | Project | Mon 02-24 | Tue 02-25 | Total |\n\
+         |---|---|---|---|\n\
+         | Cigale | 1.50 | 1.00 | 2.50 |\n\
+         | PROJ | 0.50 | 0.00 | 0.50 |\n\
+         | Total | 2.00 | 1.00 | 3.00 |\n",
+        timesheet_to_markdown(&timesheet)
+    );
+    assert_eq!(
+        "Project,Mon 02-24,Tue 02-25,Total\n\
+         Cigale,1.50,1.00,2.50\n\
+         PROJ,0.50,0.00,0.50\n\
+         Total,2.00,1.00,3.00\n",
+        timesheet_to_csv(&timesheet).unwrap()
+    );
+}
@@ -1,3 +1,7 @@
+/// an icon from icons.gresource. the svgs there are monochrome, and
+/// aliased with a "-symbolic" suffix: gtk then recolors them with the
+/// foreground color of the theme, so that they remain legible with
+/// dark themes. new icons must follow the same naming.
 #[derive(PartialEq, Debug, Clone)]
 pub struct Icon(&'static str);
 
@@ -9,6 +13,7 @@ impl Icon {
     pub const ANGLE_LEFT: Icon = Icon("angle-left-symbolic");
     pub const ANGLE_RIGHT: Icon = Icon("angle-right-symbolic");
     pub const CALENDAR_ALT: Icon = Icon("calendar-alt-symbolic");
+    pub const CALENDAR_DAY: Icon = Icon("calendar-day-symbolic");
     pub const TASKS: Icon = Icon("tasks-symbolic");
     pub const COMMENT_DOTS: Icon = Icon("comment-dots-symbolic");
     pub const CODE_BRANCH: Icon = Icon("code-branch-symbolic");
@@ -18,5 +23,60 @@ impl Icon {
     pub const COPY: Icon = Icon("copy-symbolic");
     pub const COG: Icon = Icon("cog-symbolic");
     pub const EXCLAMATION_TRIANGLE: Icon = Icon("exclamation-triangle-symbolic");
+    pub const RSS: Icon = Icon("rss-symbolic");
+    pub const GITHUB: Icon = Icon("github-symbolic");
+    pub const SLACK: Icon = Icon("slack-symbolic");
+    pub const TRELLO: Icon = Icon("trello-symbolic");
+    pub const CLOCK: Icon = Icon("clock-symbolic");
+    pub const EYE: Icon = Icon("eye-symbolic");
+    pub const EYE_SLASH: Icon = Icon("eye-slash-symbolic");
+    pub const FILE_ALT: Icon = Icon("file-alt-symbolic");
+    pub const NEWSPAPER: Icon = Icon("newspaper-symbolic");
     pub const APP_ICON: Icon = Icon("com.github.emmanueltouzery.cigale");
+
+    // keep in sync with the constants above
+    const ALL: &'static [Icon] = &[
+        Icon::ANGLE_LEFT,
+        Icon::ANGLE_RIGHT,
+        Icon::CALENDAR_ALT,
+        Icon::CALENDAR_DAY,
+        Icon::TASKS,
+        Icon::COMMENT_DOTS,
+        Icon::CODE_BRANCH,
+        Icon::ENVELOPE,
+        Icon::THUMBS_UP,
+        Icon::CHECK_SQUARE,
+        Icon::COPY,
+        Icon::COG,
+        Icon::EXCLAMATION_TRIANGLE,
+        Icon::RSS,
+        Icon::GITHUB,
+        Icon::SLACK,
+        Icon::TRELLO,
+        Icon::CLOCK,
+        Icon::EYE,
+        Icon::EYE_SLASH,
+        Icon::FILE_ALT,
+        Icon::NEWSPAPER,
+        Icon::APP_ICON,
+    ];
+
+    /// the icons are serialized by name, for instance in the cache.
+    pub fn from_name(name: &str) -> Option<Icon> {
+        Self::ALL.iter().find(|i| i.0 == name).cloned()
+    }
+}
+
+impl serde::Serialize for Icon {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Icon {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Icon, D::Error> {
+        let name = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Icon::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("Unknown icon {}", name)))
+    }
 }
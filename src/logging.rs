@@ -0,0 +1,108 @@
+// our logger: env_logger configured through RUST_LOG as usual, plus
+// a level for our own logs which the user can change at runtime from
+// the preferences, and a buffer of the recent logs for bug reports.
+use itertools::Itertools;
+use log::LevelFilter;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+const RECENT_LOGS_MAX: usize = 2000;
+
+const LEVEL_FILTERS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+// the level for our own logs, on top of what RUST_LOG enables
+static APP_LEVEL: AtomicUsize = AtomicUsize::new(0);
+static ENV_LEVEL: AtomicUsize = AtomicUsize::new(0);
+static RECENT_LOGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+fn load_level(level: &AtomicUsize) -> LevelFilter {
+    LEVEL_FILTERS
+        .get(level.load(Ordering::Relaxed))
+        .copied()
+        .unwrap_or(LevelFilter::Off)
+}
+
+fn store_level(level: &AtomicUsize, filter: LevelFilter) {
+    level.store(filter as usize, Ordering::Relaxed);
+}
+
+struct Logger {
+    env_logger: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.env_logger.enabled(metadata)
+            || (metadata.target().starts_with("cigale")
+                && metadata.level() <= load_level(&APP_LEVEL))
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {}: {}",
+            chrono::Local::now().format("%H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if self.env_logger.matches(record) {
+            self.env_logger.log(record);
+        } else {
+            eprintln!("{}", line);
+        }
+        if let Ok(mut recent_logs) = RECENT_LOGS.lock() {
+            if recent_logs.len() >= RECENT_LOGS_MAX {
+                recent_logs.pop_front();
+            }
+            recent_logs.push_back(line);
+        }
+    }
+
+    fn flush(&self) {
+        self.env_logger.flush();
+    }
+}
+
+pub fn init() {
+    let env_logger = env_logger::Builder::from_default_env().build();
+    let env_level = env_logger.filter();
+    store_level(&ENV_LEVEL, env_level);
+    let logger = Logger { env_logger };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(env_level);
+    }
+}
+
+/// change the level of our own logs, takes effect immediately
+pub fn set_app_level(level: LevelFilter) {
+    store_level(&APP_LEVEL, level);
+    log::set_max_level(level.max(load_level(&ENV_LEVEL)));
+}
+
+/// the recent logs, oldest first, for instance for a bug report
+pub fn recent_logs() -> String {
+    RECENT_LOGS
+        .lock()
+        .map(|l| l.iter().join("\n"))
+        .unwrap_or_else(|e| format!("Can't read the logs: {}", e))
+}
+
+#[test]
+fn it_stores_and_loads_log_levels() {
+    let level = AtomicUsize::new(0);
+    for filter in &LEVEL_FILTERS {
+        store_level(&level, *filter);
+        assert_eq!(*filter, load_level(&level));
+    }
+}
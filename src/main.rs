@@ -1,11 +1,22 @@
 use relm::Widget;
+mod cli;
 mod config;
 mod events;
+mod export;
 mod icons;
+mod logging;
 mod widgets;
 
 fn main() {
-    env_logger::init();
+    logging::init();
+    match config::Config::config_folder() {
+        Ok(folder) => log::info!("Using the config folder {}", folder.display()),
+        Err(e) => log::error!("Can't access the config folder: {}", e),
+    }
+
+    if let Some(exit_code) = cli::run_if_requested() {
+        std::process::exit(exit_code);
+    }
 
     let res_bytes = include_bytes!("icons.bin");
     let data = glib::Bytes::from(&res_bytes[..]);
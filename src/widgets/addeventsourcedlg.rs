@@ -2,7 +2,7 @@ use crate::config::Config;
 use crate::events::events::{get_event_providers, ConfigType, EventProvider};
 use crate::icons::*;
 use gtk::prelude::*;
-use relm::{ContainerWidget, Widget};
+use relm::{Channel, ContainerWidget, Widget};
 use relm_derive::{widget, Msg};
 use std::collections::{HashMap, HashSet};
 
@@ -58,6 +58,9 @@ pub enum Msg {
     EditConfig(String, &'static str, String, HashMap<&'static str, String>),
     SourceNameChanged,
     FormChanged,
+    FieldChanged,
+    TestConnection,
+    TestConnectionResult(Result<(), String>),
 }
 
 pub struct Model {
@@ -69,6 +72,7 @@ pub struct Model {
     dialog: gtk::Dialog,
     edit_model: Option<EventSourceEditModel>,
     event_provider: Option<Box<dyn EventProvider>>,
+    test_connection_label: Option<gtk::Label>,
 }
 
 #[derive(Clone)]
@@ -156,6 +160,7 @@ impl Widget for AddEventSourceDialog {
             dialog: dialog_params.dialog,
             edit_model: dialog_params.edit_model,
             event_provider: None,
+            test_connection_label: None,
         }
     }
 
@@ -191,6 +196,12 @@ impl Widget for AddEventSourceDialog {
                 .active_text()
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| "".to_string()),
+            ConfigType::Checkbox(_) => entry
+                .clone()
+                .dynamic_cast::<gtk::CheckButton>()
+                .unwrap()
+                .is_active()
+                .to_string(),
         }
     }
 
@@ -215,7 +226,6 @@ impl Widget for AddEventSourceDialog {
                     self.widgets.wizard_stack.set_visible_child_name("step2");
 
                     self.model.next_btn.set_label("Add");
-                    self.model.next_btn.set_sensitive(false); // must enter an event source name
                 } else {
                     // we're at the second step: add the event source
                     let ep = &crate::events::events::get_event_providers()
@@ -242,16 +252,8 @@ impl Widget for AddEventSourceDialog {
                 ));
                 self.model.dialog.emit_close();
             }
-            Msg::SourceNameChanged => {
-                let txt = self.widgets.provider_name_entry.text();
-                let source_name = txt.as_str();
-                let form_is_valid = !source_name.is_empty()
-                    && !self.model.existing_source_names.contains(source_name)
-                    && !self
-                        .model
-                        .existing_source_names_sanitized
-                        .contains(&Config::sanitize_for_filename(source_name).to_string());
-                self.model.next_btn.set_sensitive(form_is_valid);
+            Msg::SourceNameChanged | Msg::FieldChanged => {
+                self.update_next_sensitivity();
             }
             Msg::AddConfig(_, _, _) => {
                 // this is meant for wintitlebar... we emit here, not interested by it ourselves
@@ -261,8 +263,82 @@ impl Widget for AddEventSourceDialog {
             }
             Msg::FormChanged => {
                 self.update_form();
+                self.update_next_sensitivity();
+            }
+            Msg::TestConnection => {
+                if let Some(label) = &self.model.test_connection_label {
+                    label.set_text("Testing...");
+                }
+                self.test_connection();
+            }
+            Msg::TestConnectionResult(r) => {
+                if let Some(label) = &self.model.test_connection_label {
+                    label.set_markup(&match r {
+                        Ok(_) => {
+                            "<span foreground=\"green\">✔ Connection successful</span>".to_string()
+                        }
+                        Err(e) => format!(
+                            "<span foreground=\"red\">{}</span>",
+                            glib::markup_escape_text(&e)
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    fn test_connection(&self) {
+        let stream = self.model.relm.stream().clone();
+        let (_channel, sender) = Channel::new(move |r| {
+            stream.emit(Msg::TestConnectionResult(r));
+        });
+        let provider_name = self.model.event_provider.as_ref().unwrap().name();
+        let entry_values = self.get_entry_values();
+        // validating may go to the network, don't block the GUI
+        std::thread::spawn(move || {
+            let ep = get_event_providers()
+                .into_iter()
+                .find(|ep| ep.name() == provider_name)
+                .unwrap();
+            sender
+                .send(ep.validate_config(&entry_values).map_err(|e| e.to_string()))
+                .unwrap_or_else(|err| println!("Thread communication error: {}", err));
+        });
+    }
+
+    /// the source name must be unique, and the provider must accept
+    /// all the field values. Invalid fields get the error style.
+    fn update_next_sensitivity(&self) {
+        let txt = self.widgets.provider_name_entry.text();
+        let source_name = txt.as_str();
+        let name_is_valid = !source_name.is_empty()
+            && !self.model.existing_source_names.contains(source_name)
+            && !self
+                .model
+                .existing_source_names_sanitized
+                .contains(&Config::sanitize_for_filename(source_name).to_string());
+        let mut fields_are_valid = true;
+        if let (Some(ep), Some(components)) =
+            (&self.model.event_provider, &self.model.entry_components)
+        {
+            for (field_name, widget) in components {
+                let style = widget.style_context();
+                match ep.validate_field(field_name, &self.get_entry_val(field_name, widget)) {
+                    Ok(()) => {
+                        style.remove_class("error");
+                        widget.set_tooltip_text(None);
+                    }
+                    Err(e) => {
+                        fields_are_valid = false;
+                        style.add_class("error");
+                        widget.set_tooltip_text(Some(&e));
+                    }
+                }
             }
         }
+        self.model
+            .next_btn
+            .set_sensitive(name_is_valid && fields_are_valid);
     }
 
     fn get_provider_index_if_step2(&self) -> usize {
@@ -373,13 +449,29 @@ impl Widget for AddEventSourceDialog {
                     relm::connect!(self.model.relm, btn, connect_file_set(_), Msg::FormChanged);
                     btn.upcast::<gtk::Widget>()
                 }
-                ConfigType::Password => gtk::EntryBuilder::new()
-                    .text(field_val.unwrap_or(""))
-                    .visibility(false) // password field
-                    .secondary_icon_name(Icon::EXCLAMATION_TRIANGLE.name())
-                    .secondary_icon_tooltip_text("Passwords are not encrypted in the config file")
-                    .build()
-                    .upcast::<gtk::Widget>(),
+                ConfigType::Password => {
+                    let entry = gtk::EntryBuilder::new()
+                        .text(field_val.unwrap_or(""))
+                        .visibility(false) // password field
+                        .secondary_icon_name(Icon::EYE.name())
+                        .secondary_icon_tooltip_text("Show the password")
+                        .build();
+                    // only the display changes, text() returns the password either way
+                    entry.connect_icon_press(|e, pos, _| {
+                        if pos == gtk::EntryIconPosition::Secondary {
+                            let visible = !e.visibility();
+                            e.set_visibility(visible);
+                            let (icon, tooltip) = if visible {
+                                (Icon::EYE_SLASH, "Hide the password")
+                            } else {
+                                (Icon::EYE, "Show the password")
+                            };
+                            e.set_icon_from_icon_name(pos, Some(icon.name()));
+                            e.set_icon_tooltip_text(pos, Some(tooltip));
+                        }
+                    });
+                    entry.upcast::<gtk::Widget>()
+                }
                 ConfigType::Combo => {
                     let combo = gtk::ComboBoxText::new();
                     let combo_items = self.refresh_combo(
@@ -395,15 +487,49 @@ impl Widget for AddEventSourceDialog {
                     );
                     combo.upcast::<gtk::Widget>()
                 }
+                ConfigType::Checkbox(def) => {
+                    let check = gtk::CheckButtonBuilder::new()
+                        .active(field_val.map(|v| v == "true").unwrap_or(def))
+                        .build();
+                    relm::connect!(self.model.relm, check, connect_toggled(_), Msg::FormChanged);
+                    check.upcast::<gtk::Widget>()
+                }
             };
+            if let Some(entry) = entry_widget.downcast_ref::<gtk::Entry>() {
+                relm::connect!(
+                    self.model.relm,
+                    entry,
+                    connect_changed(_),
+                    Msg::FieldChanged
+                );
+            }
             entry_components.insert(field.0, entry_widget.clone());
             self.widgets
                 .config_fields_grid
                 .attach(entry_widget, 2, i, 1, 1);
             i += 1;
         }
+        let test_connection_btn = gtk::Button::with_label("Test connection");
+        relm::connect!(
+            self.model.relm,
+            test_connection_btn,
+            connect_clicked(_),
+            Msg::TestConnection
+        );
+        self.widgets
+            .config_fields_grid
+            .attach(&test_connection_btn, 1, i, 1, 1);
+        let test_connection_label = gtk::LabelBuilder::new()
+            .xalign(0.0)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        self.widgets
+            .config_fields_grid
+            .attach(&test_connection_label, 2, i, 1, 1);
+        self.model.test_connection_label = Some(test_connection_label);
         self.model.entry_components = Some(entry_components);
         self.widgets.config_fields_grid.show_all();
+        self.update_next_sensitivity();
     }
 
     view! {
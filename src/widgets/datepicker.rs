@@ -12,8 +12,10 @@ pub enum DatePickerMsg {
     MonthChanged,
     NextDay,
     PreviousDay,
+    Today,
     DayPicked(Date<Local>),
     PrevNextDaySkipChanged(PrevNextDaySkipWeekends),
+    DateFormatChanged(String),
 }
 
 pub struct DatePickerModel {
@@ -33,6 +35,10 @@ pub struct DatePickerModel {
     // user clicks on a specific day.
     month_change_ongoing: bool,
     prev_next_skip: PrevNextDaySkipWeekends,
+    // chrono format string, validated in the preferences
+    date_format: String,
+    // no point in navigating to the future
+    can_go_next: bool,
 }
 
 #[widget]
@@ -57,27 +63,43 @@ impl Widget for DatePicker {
             DatePickerMsg::MonthChanged
         );
         // https://askubuntu.com/a/138520/188440
-        self.widgets.prev_button.add_accelerator(
-            "activate",
-            &self.model.accel_group,
-            65361, //arrow left
+        for modifier in &[
             gdk::ModifierType::MOD1_MASK,
-            gtk::AccelFlags::VISIBLE,
-        );
-        self.widgets.next_button.add_accelerator(
+            gdk::ModifierType::CONTROL_MASK,
+        ] {
+            self.widgets.prev_button.add_accelerator(
+                "activate",
+                &self.model.accel_group,
+                65361, //arrow left
+                *modifier,
+                gtk::AccelFlags::VISIBLE,
+            );
+            self.widgets.next_button.add_accelerator(
+                "activate",
+                &self.model.accel_group,
+                65363, //arrow right
+                *modifier,
+                gtk::AccelFlags::VISIBLE,
+            );
+        }
+        self.widgets.today_button.add_accelerator(
             "activate",
             &self.model.accel_group,
-            65363, //arrow right
-            gdk::ModifierType::MOD1_MASK,
+            't'.into(),
+            gdk::ModifierType::CONTROL_MASK,
             gtk::AccelFlags::VISIBLE,
-        )
+        );
     }
     fn model(
         relm: &relm::Relm<Self>,
-        params: (gtk::AccelGroup, PrevNextDaySkipWeekends),
+        params: (
+            gtk::AccelGroup,
+            PrevNextDaySkipWeekends,
+            Date<Local>,
+            String,
+        ),
     ) -> DatePickerModel {
-        let (accel_group, prev_next_skip) = params;
-        let date = Local::today().pred();
+        let (accel_group, prev_next_skip, date, date_format) = params;
         let cal = gtk::Calendar::new();
         Self::calendar_set_date(&cal, date);
         DatePickerModel {
@@ -88,6 +110,8 @@ impl Widget for DatePicker {
             date,
             month_change_ongoing: false,
             prev_next_skip,
+            date_format,
+            can_go_next: Self::next_date(prev_next_skip, date) <= Local::today(),
         }
     }
 
@@ -97,8 +121,8 @@ impl Widget for DatePicker {
         cal.set_day(date.day() as i32);
     }
 
-    fn next_date<Tz: TimeZone>(&self, dt: Date<Tz>) -> Date<Tz> {
-        if self.model.prev_next_skip == PrevNextDaySkipWeekends::DontSkip {
+    fn next_date<Tz: TimeZone>(prev_next_skip: PrevNextDaySkipWeekends, dt: Date<Tz>) -> Date<Tz> {
+        if prev_next_skip == PrevNextDaySkipWeekends::DontSkip {
             dt.succ()
         } else {
             let mut dt = dt.succ();
@@ -148,6 +172,8 @@ impl Widget for DatePicker {
             }
             DatePickerMsg::DayPicked(d) => {
                 self.model.date = d;
+                self.model.can_go_next =
+                    Self::next_date(self.model.prev_next_skip, d) <= Local::today();
                 if self.model.month_change_ongoing {
                     self.model.month_change_ongoing = false;
                 } else {
@@ -160,16 +186,37 @@ impl Widget for DatePicker {
                 let (_y, m, _d) = self.model.calendar.date();
                 self.model.month_change_ongoing = m + 1 != self.model.date.month();
             }
-            DatePickerMsg::NextDay => self
-                .model
-                .relm
-                .stream()
-                .emit(DatePickerMsg::DayPicked(self.next_date(self.model.date))),
+            DatePickerMsg::NextDay => {
+                // the accelerator is active even if the button is disabled
+                if self.model.can_go_next {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(DatePickerMsg::DayPicked(Self::next_date(
+                            self.model.prev_next_skip,
+                            self.model.date,
+                        )))
+                }
+            }
             DatePickerMsg::PreviousDay => self.model.relm.stream().emit(DatePickerMsg::DayPicked(
                 self.previous_date(self.model.date),
             )),
+            DatePickerMsg::Today => {
+                // the accelerator is active even if the button is disabled
+                if self.model.date != Local::today() {
+                    self.model
+                        .relm
+                        .stream()
+                        .emit(DatePickerMsg::DayPicked(Local::today()))
+                }
+            }
             DatePickerMsg::PrevNextDaySkipChanged(new_prev_next) => {
-                self.model.prev_next_skip = new_prev_next
+                self.model.prev_next_skip = new_prev_next;
+                self.model.can_go_next =
+                    Self::next_date(self.model.prev_next_skip, self.model.date) <= Local::today();
+            }
+            DatePickerMsg::DateFormatChanged(date_format) => {
+                self.model.date_format = date_format;
             }
         }
     }
@@ -205,7 +252,7 @@ impl Widget for DatePicker {
                 always_show_image: true,
                 image: Some(&gtk::Image::from_icon_name(
                     Some(Icon::CALENDAR_ALT.name()), gtk::IconSize::Menu)),
-                label: self.model.date.format("%A, %Y-%m-%d").to_string().as_str(),
+                label: self.model.date.format(&self.model.date_format).to_string().as_str(),
                 clicked => DatePickerMsg::ButtonClicked
             },
             #[name="next_button"]
@@ -215,8 +262,17 @@ impl Widget for DatePicker {
                     Some(Icon::ANGLE_RIGHT.name()), gtk::IconSize::Menu)),
                 valign: gtk::Align::Center,
                 relief: gtk::ReliefStyle::None,
+                sensitive: self.model.can_go_next,
                 clicked => DatePickerMsg::NextDay
             },
+            #[name="today_button"]
+            gtk::Button {
+                label: "Today",
+                valign: gtk::Align::Center,
+                tooltip_text: Some("Display today's events (Ctrl+T)"),
+                sensitive: self.model.date != Local::today(),
+                clicked => DatePickerMsg::Today
+            },
         }
     }
 }
@@ -1,96 +1,230 @@
+use crate::config::EventRowsLayout;
 use crate::events::events::Event;
 use gtk::prelude::*;
 use relm::Widget;
 use relm_derive::{widget, Msg};
 
+/// derive a color from the category name, so that a category
+/// always gets the same color, without configuration.
+fn category_color(category: &str) -> String {
+    // FNV-1a, we want the same color between runs, which
+    // std's DefaultHasher doesn't guarantee.
+    let hash = category.bytes().fold(0x811c_9dc5_u32, |h, b| {
+        (h ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    });
+    let hue = f64::from(hash % 360);
+    // hsl to rgb with a fixed saturation and lightness, readable with white text
+    let (saturation, lightness) = (0.55, 0.42);
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f64).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let to_u8 = |v: f64| ((v + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", to_u8(r), to_u8(g), to_u8(b))
+}
+
+fn category_markup(category: &Option<String>) -> String {
+    category
+        .as_ref()
+        .map(|c| {
+            format!(
+                "<span background=\"{}\" foreground=\"white\"> {} </span>",
+                category_color(c),
+                glib::markup_escape_text(c)
+            )
+        })
+        .unwrap_or_else(|| "".to_string())
+}
+
+/// in compact mode we only display the time, the icon and the title,
+/// the tooltip gives what we don't display.
+fn compact_tooltip(event: &Event) -> String {
+    let mut lines = vec![event.event_type_desc.to_string()];
+    if let Some(category) = &event.event_category {
+        lines.push(category.clone());
+    }
+    if let Some(details) = &event.event_extra_details {
+        lines.push(details.clone());
+    }
+    lines.push(event.event_info.clone());
+    lines.join("\n")
+}
+
+fn time_markup(event: &Event) -> String {
+    format!("<b>{}</b>", event.event_time.format("%H:%M"))
+}
+
 #[derive(Msg)]
 pub enum EventListItemMsg {}
 
 pub struct EventListItemModel {
     event: Event,
+    layout: EventRowsLayout,
 }
 
 #[widget]
 impl Widget for EventListItem {
-    fn init_view(&mut self) {}
+    fn init_view(&mut self) {
+        self.widgets
+            .layout_stack
+            .set_visible_child_name(match self.model.layout {
+                EventRowsLayout::Comfortable => "comfortable",
+                EventRowsLayout::Compact => "compact",
+            });
+    }
 
-    fn model(event: Event) -> EventListItemModel {
-        EventListItemModel { event }
+    fn model(params: (Event, EventRowsLayout)) -> EventListItemModel {
+        let (event, layout) = params;
+        EventListItemModel { event, layout }
     }
 
     fn update(&mut self, _event: EventListItemMsg) {}
 
     view! {
-        gtk::Box {
-            orientation: gtk::Orientation::Horizontal,
-            margin_start: 10,
-            margin_end: 10,
-            margin_top: 10,
-            margin_bottom: 10,
+        #[name="layout_stack"]
+        gtk::Stack {
+            // size the row to the displayed layout
+            homogeneous: false,
             gtk::Box {
-                orientation: gtk::Orientation::Vertical,
-                valign: gtk::Align::Center,
                 child: {
-                    padding: 3,
+                    name: Some("compact")
+                },
+                orientation: gtk::Orientation::Horizontal,
+                margin_start: 10,
+                margin_end: 10,
+                margin_top: 3,
+                margin_bottom: 3,
+                spacing: 6,
+                tooltip_text: Some(compact_tooltip(&self.model.event).as_str()),
+                #[style_class="event_time"]
+                gtk::Label {
+                    label: &time_markup(&self.model.event),
+                    use_markup: true,
                 },
                 gtk::Image {
                     icon_name: Some(self.model.event.event_type_icon.name()),
-                    icon_size: gtk::IconSize::Dnd
+                    icon_size: gtk::IconSize::Menu
                 },
-                #[style_class="event_provider_name"]
                 gtk::Label {
-                    text: self.model.event.event_type_desc,
+                    child: {
+                        expand: true,
+                        fill: true,
+                    },
+                    text: self.model.event.event_info.as_str(),
+                    halign: gtk::Align::Start,
+                    ellipsize: pango::EllipsizeMode::End
                 },
             },
             gtk::Box {
-                orientation: gtk::Orientation::Vertical,
                 child: {
-                    padding: 2,
-                    pack_type: gtk::PackType::Start,
-                    expand: true,
-                    fill: true,
+                    name: Some("comfortable")
                 },
+                orientation: gtk::Orientation::Horizontal,
+                margin_start: 10,
+                margin_end: 10,
+                margin_top: 10,
+                margin_bottom: 10,
                 gtk::Box {
-                    orientation: gtk::Orientation::Horizontal,
+                    orientation: gtk::Orientation::Vertical,
+                    valign: gtk::Align::Center,
                     child: {
+                        padding: 3,
+                    },
+                    gtk::Image {
+                        icon_name: Some(self.model.event.event_type_icon.name()),
+                        icon_size: gtk::IconSize::Dnd
+                    },
+                    #[style_class="event_provider_name"]
+                    gtk::Label {
+                        text: self.model.event.event_type_desc,
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Vertical,
+                    child: {
+                        padding: 2,
                         pack_type: gtk::PackType::Start,
                         expand: true,
                         fill: true,
                     },
-                    #[name="event_time_label"]
-                    #[style_class="event_time"]
-                    gtk::Label {
+                    gtk::Box {
+                        orientation: gtk::Orientation::Horizontal,
                         child: {
                             pack_type: gtk::PackType::Start,
-                            padding: 3,
+                            expand: true,
+                            fill: true,
+                        },
+                        #[name="event_time_label"]
+                        #[style_class="event_time"]
+                        gtk::Label {
+                            child: {
+                                pack_type: gtk::PackType::Start,
+                                padding: 3,
+                            },
+                            label: &time_markup(&self.model.event),
+                            use_markup: true,
+                            halign: gtk::Align::Start
+                        },
+                        #[style_class="event_category"]
+                        gtk::Label {
+                            child: {
+                                pack_type: gtk::PackType::Start,
+                                padding: 3,
+                            },
+                            markup: &category_markup(&self.model.event.event_category),
+                            valign: gtk::Align::Center,
+                        },
+                        gtk::Label {
+                            child: {
+                                pack_type: gtk::PackType::End,
+                                padding: 3,
+                            },
+                            text: self.model.event.event_extra_details.as_ref().unwrap_or(&"".to_string()).as_str(),
+                            halign: gtk::Align::Start,
+                            ellipsize: pango::EllipsizeMode::End
                         },
-                        // text: format!("<b>{}</b>", event.event_time) // doesn't compile
-                        label: ("<b>".to_string() + &self.model.event.event_time.format("%H:%M").to_string() + "</b>").as_str(),
-                        use_markup: true,
-                        // text: self.model.event.event_time.as_str(),
-                        halign: gtk::Align::Start
                     },
                     gtk::Label {
                         child: {
-                            pack_type: gtk::PackType::End,
-                            padding: 3,
+                            expand: true,
+                            fill: true,
+                            padding: 5
                         },
-                        text: self.model.event.event_extra_details.as_ref().unwrap_or(&"".to_string()).as_str(),
+                        text: self.model.event.event_info.as_str(),
                         halign: gtk::Align::Start,
                         ellipsize: pango::EllipsizeMode::End
-                    },
-                },
-                gtk::Label {
-                    child: {
-                        expand: true,
-                        fill: true,
-                        padding: 5
-                    },
-                    text: self.model.event.event_info.as_str(),
-                    halign: gtk::Align::Start,
-                    ellipsize: pango::EllipsizeMode::End
+                    }
                 }
             }
         }
     }
 }
+
+#[test]
+fn it_puts_what_compact_rows_hide_in_the_tooltip() {
+    let event = Event::new(
+        "Git",
+        crate::icons::Icon::CODE_BRANCH,
+        chrono::NaiveTime::from_hms(9, 5, 0),
+        "Fix the build".to_string(),
+        "Fix the build".to_string(),
+        crate::events::events::EventBody::PlainText("".to_string()),
+        Some("cigale".to_string()),
+    );
+    assert_eq!("<b>09:05</b>", time_markup(&event));
+    assert_eq!("Git\ncigale\nFix the build", compact_tooltip(&event));
+}
+
+#[test]
+fn it_derives_stable_category_colors() {
+    assert_eq!(category_color("commit"), category_color("commit"));
+    assert_ne!(category_color("commit"), category_color("meeting"));
+    assert_eq!(7, category_color("issue").len());
+}
@@ -1,22 +1,94 @@
 use super::datepicker::DatePickerMsg::DayPicked as DatePickerDayPickedMsg;
 use super::datepicker::*;
 use super::event::EventListItem;
-use crate::config::Config;
-use crate::events::events::Event;
+use super::weekview::Msg as WeekViewMsg;
+use super::weekview::Msg::DayClicked as WeekViewDayClickedMsg;
+use super::weekview::WeekView;
+use crate::config::{Config, EventRowsLayout};
+use crate::events::events::{ErrorKind, Event, FetchOptions, FetchProgress, FetchedEvents};
 use crate::icons::*;
 use chrono::prelude::*;
+use glib::signal::Inhibit;
 use gtk::prelude::*;
+use itertools::Itertools;
 use relm::{Channel, ContainerWidget, Widget};
 use relm_derive::{widget, Msg};
+use std::collections::HashSet;
 
 #[derive(Msg)]
 pub enum Msg {
     EventSelected(Option<usize>),
     DayChange(Date<Local>),
-    GotEvents(Result<Vec<Event>, String>),
+    GotEvents(FetchedEvents),
+    // the startup background fetch for today completed
+    GotTodayEvents(FetchedEvents),
+    // a slow event source tells where it's at
+    FetchProgress(String),
     ConfigUpdate(Box<Config>), // box to prevent large size difference between variants
     CopyHeader,
     CopyAllHeaders,
+    CopyDaySummary,
+    EventListKeyPress(gdk::EventKey),
+    EventListButtonPress(gdk::EventButton),
+    CopyEvent,
+    CopyEventLink,
+    OpenEventLink,
+    RevealCacheFile,
+    ForceRefresh,
+    FetchDone,
+    ExportMarkdown,
+    CopyMarkdown,
+    ExportCsv,
+    ExportJson,
+    ExportWeekTimesheet,
+    GotWeekTimesheet(Vec<(Date<Local>, FetchedEvents)>),
+    SearchChanged(String),
+    ProviderToggled(&'static str, bool),
+    WeekViewToggled(bool),
+    WeekDayClicked(Date<Local>),
+    PollTick,
+    // a local file of an event source changed
+    WatchedFilesChanged,
+    ErrorsBarResponse(gtk::ResponseType),
+    // event list generation, index of the first event to add
+    AddEventRows(u64, usize),
+    RowsLayoutChanged(EventRowsLayout),
+    GroupByHourChanged(bool),
+}
+
+// on busy days, adding all the rows at once makes the UI stutter.
+// we add them by chunks, the first one immediately, the next ones on idle.
+const EVENT_ROWS_CHUNK_SIZE: usize = 50;
+
+/// what we display in place of the event list
+#[derive(PartialEq, Debug)]
+enum EventListState {
+    Loading,
+    Events,
+    // all the event sources answered, with no events
+    Empty,
+    // no events, but some event sources failed
+    Failed,
+}
+
+impl EventListState {
+    fn for_events(events: Option<&[Event]>, fetch_errors: &[String]) -> EventListState {
+        match events {
+            None => EventListState::Loading,
+            Some(e) if !e.is_empty() => EventListState::Events,
+            Some(_) if fetch_errors.is_empty() => EventListState::Empty,
+            Some(_) => EventListState::Failed,
+        }
+    }
+
+    fn child_name(&self) -> &'static str {
+        match self {
+            EventListState::Loading => "loading",
+            EventListState::Events => "events",
+            EventListState::Empty => "empty",
+            EventListState::Failed => "failed",
+        }
+    }
 }
 
 pub struct Model {
@@ -24,9 +96,40 @@ pub struct Model {
     accel_group: gtk::AccelGroup,
     relm: relm::Relm<EventView>,
     // events will be None while we're loading
-    events: Option<Result<Vec<Event>, String>>,
+    events: Option<Vec<Event>>,
+    // displayed next to the spinner while we're loading
+    fetch_progress: String,
+    fetch_errors: Vec<String>,
+    // the user closed the errors bar, until the errors change
+    fetch_errors_dismissed: bool,
+    // some sources failed because of the network, trying again may help
+    fetch_errors_retriable: bool,
+    // in the errors bar, visible when the errors are retriable
+    retry_button: Option<gtk::Button>,
     current_event: Option<Event>,
+    // index of the selected row: the start of a shift-click range
+    selected_row: Option<usize>,
+    // first and last row indexes of the shift-click range
+    range_selection: Option<(usize, usize)>,
+    // summary of the shift-click range, shown next to its last row
+    range_popover: gtk::Popover,
+    range_popover_label: gtk::Label,
     day: Date<Local>,
+    search_text: String,
+    // kept across day changes
+    hidden_providers: HashSet<&'static str>,
+    week_view_visible: bool,
+    last_poll: std::time::Instant,
+    // set when a background poll is in progress, to notify
+    // about the new events when we get the results
+    polling: bool,
+    // false when the poll is a reload after a local file changed
+    notify_poll_results: bool,
+    // watches the local files of the event sources for the displayed day
+    file_watcher: Option<notify::RecommendedWatcher>,
+    // bumped every time we repopulate the event list, so that
+    // pending chunks from a previous population get dropped
+    event_list_generation: u64,
 }
 
 #[widget]
@@ -41,57 +144,349 @@ impl Widget for EventView {
             gdk::ModifierType::CONTROL_MASK,
             gtk::AccelFlags::VISIBLE,
         );
+        self.widgets.copy_day_button.add_accelerator(
+            "activate",
+            &self.model.accel_group,
+            'a'.into(),
+            gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK,
+            gtk::AccelFlags::VISIBLE,
+        );
+
+        let vbox = gtk::BoxBuilder::new()
+            .margin(10)
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+        let add_menu_item = |label: &str, msg: fn() -> Msg| {
+            let btn = gtk::ModelButtonBuilder::new().label(label).build();
+            super::wintitlebar::left_align_menu(&btn);
+            relm::connect!(self.model.relm, &btn, connect_clicked(_), msg());
+            vbox.add(&btn);
+        };
+        add_menu_item("Export to Markdown...", || Msg::ExportMarkdown);
+        add_menu_item("Copy as Markdown", || Msg::CopyMarkdown);
+        add_menu_item("Export to CSV...", || Msg::ExportCsv);
+        add_menu_item("Export to JSON...", || Msg::ExportJson);
+        add_menu_item("Export week timesheet...", || Msg::ExportWeekTimesheet);
+        vbox.show_all();
+        let popover = gtk::Popover::new(None::<&gtk::MenuButton>);
+        popover.add(&vbox);
+        self.widgets.export_button.set_popover(Some(&popover));
+
+        let view_vbox = gtk::BoxBuilder::new()
+            .margin(10)
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+        let compact_rows_btn = gtk::CheckButtonBuilder::new()
+            .label("Compact rows")
+            .active(self.model.config.event_rows_layout == EventRowsLayout::Compact)
+            .build();
+        relm::connect!(
+            self.model.relm,
+            &compact_rows_btn,
+            connect_toggled(b),
+            Msg::RowsLayoutChanged(if b.is_active() {
+                EventRowsLayout::Compact
+            } else {
+                EventRowsLayout::Comfortable
+            })
+        );
+        view_vbox.add(&compact_rows_btn);
+        let group_by_hour_btn = gtk::CheckButtonBuilder::new()
+            .label("Group by hour")
+            .active(self.model.config.group_events_by_hour)
+            .build();
+        relm::connect!(
+            self.model.relm,
+            &group_by_hour_btn,
+            connect_toggled(b),
+            Msg::GroupByHourChanged(b.is_active())
+        );
+        view_vbox.add(&group_by_hour_btn);
+        view_vbox.show_all();
+        let view_popover = gtk::Popover::new(None::<&gtk::MenuButton>);
+        view_popover.add(&view_vbox);
+        self.widgets.view_button.set_popover(Some(&view_popover));
+
+        self.model.retry_button = Some(
+            self.widgets
+                .info_bar
+                .add_button("Retry", gtk::ResponseType::Apply),
+        );
+        self.widgets
+            .info_bar
+            .add_button("Details", gtk::ResponseType::Accept);
+        relm::connect!(
+            self.model.relm,
+            &self.widgets.info_bar,
+            connect_response(_, r),
+            Msg::ErrorsBarResponse(r)
+        );
+
+        // we tick every minute, and check on the tick whether it's
+        // time to poll. that way config changes apply immediately.
+        let stream = self.model.relm.stream().clone();
+        glib::timeout_add_seconds_local(60, move || {
+            stream.emit(Msg::PollTick);
+            glib::Continue(true)
+        });
     }
 
     fn model(relm: &relm::Relm<Self>, params: (Config, gtk::AccelGroup)) -> Model {
         let (config, accel_group) = params;
-        let day = Local::today().pred();
-        EventView::fetch_events(&config, relm, day);
+        let day = config.initial_day();
+        EventView::fetch_events(&config, relm, day, false, true);
+        if config.warm_today_cache_on_startup && day != Local::today() {
+            // this goes through the cache, so if the user switches to today
+            // later, the events will be there without waiting for the network.
+            EventView::spawn_fetch(
+                &config,
+                relm,
+                Local::today(),
+                FetchOptions::default(),
+                Msg::GotTodayEvents,
+            );
+        }
+        let range_popover_label = gtk::LabelBuilder::new().margin(10).build();
+        let range_popover = gtk::Popover::new(None::<&gtk::ListBoxRow>);
+        range_popover.add(&range_popover_label);
+        range_popover_label.show();
         Model {
             config,
             accel_group,
             relm: relm.clone(),
             events: None,
+            fetch_progress: "".to_string(),
+            fetch_errors: vec![],
+            fetch_errors_dismissed: false,
+            fetch_errors_retriable: false,
+            retry_button: None,
             current_event: None,
+            selected_row: None,
+            range_selection: None,
+            range_popover,
+            range_popover_label,
             day,
+            search_text: "".to_string(),
+            hidden_providers: HashSet::new(),
+            week_view_visible: false,
+            last_poll: std::time::Instant::now(),
+            polling: false,
+            notify_poll_results: false,
+            file_watcher: None,
+            event_list_generation: 0,
         }
     }
 
-    fn update_events(&mut self) {
+    // the events matching the search filter
+    fn displayed_events(&self) -> Vec<&Event> {
+        let search_lower = self.model.search_text.to_lowercase();
+        self.model
+            .events
+            .iter()
+            .flatten()
+            .filter(|e| !self.model.hidden_providers.contains(e.event_type_desc))
+            .filter(|e| e.matches_search(&search_lower))
+            .collect()
+    }
+
+    // one toggle per provider which contributed events for the day
+    fn update_provider_toggles(&self) {
+        for child in self.widgets.provider_toggles.children() {
+            self.widgets.provider_toggles.remove(&child);
+        }
+        let providers = self
+            .model
+            .events
+            .iter()
+            .flatten()
+            .map(|e| e.event_type_desc)
+            .unique();
+        for provider in providers {
+            let btn = gtk::ToggleButtonBuilder::new()
+                .label(provider)
+                .active(!self.model.hidden_providers.contains(provider))
+                .build();
+            relm::connect!(
+                self.model.relm,
+                &btn,
+                connect_toggled(b),
+                Msg::ProviderToggled(provider, b.is_active())
+            );
+            self.widgets.provider_toggles.add(&btn);
+        }
+        self.widgets.provider_toggles.show_all();
+    }
+
+    fn update_event_list(&mut self) {
         self.model.current_event = None;
+        self.model.selected_row = None;
+        self.model.range_selection = None;
+        self.model.range_popover.popdown();
+        self.model
+            .range_popover
+            .set_relative_to(None::<&gtk::ListBoxRow>);
         for child in self.widgets.event_list.children() {
             self.widgets.event_list.remove(&child);
         }
-        match &self.model.events {
-            Some(Ok(events)) => {
-                log::info!("Fetched events: no errors");
-                for event in events {
-                    let _child = self
-                        .widgets
-                        .event_list
-                        .add_widget::<EventListItem>(event.clone());
-                }
+        self.model.event_list_generation += 1;
+        self.update_hour_separators();
+        self.add_event_rows(0);
+        let displayed_events = self.displayed_events();
+        self.widgets
+            .total_duration_label
+            .set_text(&crate::events::events::format_total_duration(
+                &displayed_events,
+            ));
+        let state =
+            EventListState::for_events(self.model.events.as_deref(), &self.model.fetch_errors);
+        if state == EventListState::Failed {
+            self.widgets.failed_sources_label.set_text(&format!(
+                "Failed fetching the events:\n\n{}",
+                self.model.fetch_errors.join("\n")
+            ));
+        }
+        self.widgets
+            .event_list_stack
+            .set_visible_child_name(state.child_name());
+    }
+
+    // the separators are row headers: they can't be selected,
+    // and the row indexes still match the displayed events
+    fn update_hour_separators(&self) {
+        if !self.model.config.group_events_by_hour {
+            self.widgets.event_list.set_header_func(None);
+            return;
+        }
+        let hours: Vec<u32> = self
+            .displayed_events()
+            .iter()
+            .map(|e| e.event_time.hour())
+            .collect();
+        self.widgets
+            .event_list
+            .set_header_func(Some(Box::new(move |row, _before| {
+                let header = hour_separator(&hours, row.index() as usize).map(|hour| {
+                    let label = gtk::LabelBuilder::new()
+                        .label(&format!("<b>{:02}:00</b>", hour))
+                        .use_markup(true)
+                        .xalign(0.0)
+                        .margin_top(6)
+                        .margin_start(10)
+                        .build();
+                    label.style_context().add_class("dim-label");
+                    let vbox = gtk::BoxBuilder::new()
+                        .orientation(gtk::Orientation::Vertical)
+                        .spacing(3)
+                        .build();
+                    vbox.add(&label);
+                    vbox.add(&gtk::Separator::new(gtk::Orientation::Horizontal));
+                    vbox.show_all();
+                    vbox
+                });
+                row.set_header(header.as_ref());
+            })));
+    }
+
+    /// highlight the rows between the two indexes, and display
+    /// how many events there are and how long they took
+    fn select_range(&mut self, from: usize, to: usize) {
+        self.clear_range_selection();
+        let (first, last) = (from.min(to), from.max(to));
+        self.model.range_selection = Some((first, last));
+        for idx in first..=last {
+            if let Some(row) = self.widgets.event_list.row_at_index(idx as i32) {
+                row.style_context().add_class("event_range");
             }
-            Some(Err(err)) => {
-                let info_contents = self
-                    .widgets
-                    .info_bar
-                    .content_area()
-                    .dynamic_cast::<gtk::Box>() // https://github.com/gtk-rs/gtk/issues/947
-                    .unwrap();
-                for child in info_contents.children() {
-                    info_contents.remove(&child);
+        }
+        let displayed_events = self.displayed_events();
+        let range_events: Vec<&Event> = displayed_events
+            .iter()
+            .skip(first)
+            .take(last - first + 1)
+            .copied()
+            .collect();
+        if let Some(row) = self.widgets.event_list.row_at_index(to as i32) {
+            self.model
+                .range_popover_label
+                .set_text(&crate::events::events::format_range_summary(&range_events));
+            self.model.range_popover.set_relative_to(Some(&row));
+            self.model.range_popover.popup();
+        }
+    }
+
+    fn clear_range_selection(&mut self) {
+        self.model.range_popover.popdown();
+        if let Some((first, last)) = self.model.range_selection.take() {
+            for idx in first..=last {
+                if let Some(row) = self.widgets.event_list.row_at_index(idx as i32) {
+                    row.style_context().remove_class("event_range");
                 }
-                log::error!("Fetched events: errors present: {}", err.to_string());
-                info_contents.add(
+            }
+        }
+    }
+
+    /// add a chunk of rows to the event list, starting at `start`,
+    /// and schedule the next chunk if there are more events
+    fn add_event_rows(&self, start: usize) {
+        let displayed_events = self.displayed_events();
+        for event in displayed_events
+            .iter()
+            .skip(start)
+            .take(EVENT_ROWS_CHUNK_SIZE)
+        {
+            let _child = self.widgets.event_list.add_widget::<EventListItem>((
+                (*event).clone(),
+                self.model.config.event_rows_layout,
+            ));
+        }
+        let next_start = start + EVENT_ROWS_CHUNK_SIZE;
+        if next_start < displayed_events.len() {
+            let stream = self.model.relm.stream().clone();
+            let generation = self.model.event_list_generation;
+            glib::idle_add_local(move || {
+                stream.emit(Msg::AddEventRows(generation, next_start));
+                glib::Continue(false)
+            });
+        }
+    }
+
+    fn update_events(&mut self) {
+        self.update_provider_toggles();
+        self.update_event_list();
+        let info_contents = self
+            .widgets
+            .info_bar
+            .content_area()
+            .dynamic_cast::<gtk::Box>() // https://github.com/gtk-rs/gtk/issues/947
+            .unwrap();
+        for child in info_contents.children() {
+            info_contents.remove(&child);
+        }
+        if let Some(btn) = &self.model.retry_button {
+            btn.set_visible(self.model.fetch_errors_retriable);
+        }
+        if !self.model.fetch_errors.is_empty() {
+            log::error!(
+                "Fetched events: errors present: {}",
+                self.model.fetch_errors.join("\n")
+            );
+            // one line per failed event source, the details
+            // button gives the full error texts
+            let errors_box = gtk::BoxBuilder::new()
+                .orientation(gtk::Orientation::Vertical)
+                .build();
+            for error in &self.model.fetch_errors {
+                errors_box.add(
                     &gtk::LabelBuilder::new()
-                        .label(err.to_string().as_str())
+                        .label(error.as_str())
+                        .tooltip_text(error.as_str())
+                        .xalign(0.0)
                         .ellipsize(pango::EllipsizeMode::End)
                         .build(),
                 );
-                info_contents.show_all();
             }
-            None => {}
+            info_contents.add(&errors_box);
+            info_contents.show_all();
         }
 
         let has_event_sources =
@@ -105,44 +500,526 @@ impl Widget for EventView {
             });
     }
 
-    fn fetch_events(config: &Config, relm: &relm::Relm<Self>, day: Date<Local>) {
+    /// `interactive` is set when the user waits for the events,
+    /// as opposed to background reloads
+    fn fetch_events(
+        config: &Config,
+        relm: &relm::Relm<Self>,
+        day: Date<Local>,
+        bypass_cache: bool,
+        interactive: bool,
+    ) {
+        let stream = relm.stream().clone();
+        let (_progress_channel, progress_sender) = Channel::new(move |progress| {
+            stream.emit(Msg::FetchProgress(progress));
+        });
+        // the providers report from rayon's threads
+        let progress_sender = std::sync::Mutex::new(progress_sender);
+        let options = FetchOptions {
+            bypass_cache,
+            interactive,
+            progress: FetchProgress::new(move |progress| {
+                if let Ok(sender) = progress_sender.lock() {
+                    sender
+                        .send(progress)
+                        .unwrap_or_else(|err| println!("Thread communication error: {}", err));
+                }
+            }),
+        };
+        Self::spawn_fetch(config, relm, day, options, Msg::GotEvents);
+    }
+
+    fn spawn_fetch(
+        config: &Config,
+        relm: &relm::Relm<Self>,
+        day: Date<Local>,
+        options: FetchOptions,
+        to_msg: fn(FetchedEvents) -> Msg,
+    ) {
         let stream = relm.stream().clone();
         let (_channel, sender) = Channel::new(move |events| {
-            stream.emit(Msg::GotEvents(events));
+            stream.emit(to_msg(events));
         });
         let c = config.clone();
         std::thread::spawn(move || {
             sender
-                .send(crate::events::events::get_all_events(c, day).map_err(|e| e.to_string()))
+                .send(crate::events::events::get_all_events(c, &options, day))
+                .unwrap_or_else(|err| println!("Thread communication error: {}", err));
+        });
+    }
+
+    fn show_event_context_menu(&self, event: &gdk::EventButton) {
+        let has_link = self
+            .model
+            .current_event
+            .as_ref()
+            .and_then(|e| e.link())
+            .is_some();
+        let menu = gtk::Menu::new();
+        let open_item = gtk::MenuItem::with_label("Open in browser");
+        open_item.set_sensitive(has_link);
+        relm::connect!(
+            self.model.relm,
+            &open_item,
+            connect_activate(_),
+            Msg::OpenEventLink
+        );
+        menu.append(&open_item);
+        let copy_item = gtk::MenuItem::with_label("Copy");
+        relm::connect!(
+            self.model.relm,
+            &copy_item,
+            connect_activate(_),
+            Msg::CopyEvent
+        );
+        menu.append(&copy_item);
+        let copy_link_item = gtk::MenuItem::with_label("Copy link");
+        copy_link_item.set_sensitive(has_link);
+        relm::connect!(
+            self.model.relm,
+            &copy_link_item,
+            connect_activate(_),
+            Msg::CopyEventLink
+        );
+        menu.append(&copy_link_item);
+        menu.append(&gtk::SeparatorMenuItem::new());
+        // to troubleshoot the parsing of the data we got from the server
+        let reveal_cache_item = gtk::MenuItem::with_label("Show the cache file");
+        relm::connect!(
+            self.model.relm,
+            &reveal_cache_item,
+            connect_activate(_),
+            Msg::RevealCacheFile
+        );
+        menu.append(&reveal_cache_item);
+        menu.show_all();
+        menu.set_attach_widget(Some(&self.widgets.event_list));
+        menu.popup_at_pointer(Some(&**event));
+    }
+
+    fn get_main_window(&self) -> Option<gtk::Window> {
+        self.widgets
+            .events_stack
+            .toplevel()
+            .and_then(|w| w.dynamic_cast::<gtk::Window>().ok())
+    }
+
+    fn export_to_file(&self, default_name: &str, contents: &str) {
+        let main_win = self.get_main_window();
+        let dialog = gtk::FileChooserDialog::with_buttons(
+            Some("Export events"),
+            main_win.as_ref(),
+            gtk::FileChooserAction::Save,
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Save", gtk::ResponseType::Accept),
+            ],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_name(default_name);
+        let r = dialog.run();
+        let filename = dialog.filename();
+        dialog.close();
+        if let (gtk::ResponseType::Accept, Some(path)) = (r, filename) {
+            if let Err(e) = std::fs::write(&path, contents) {
+                let dialog = gtk::MessageDialog::new(
+                    main_win.as_ref(),
+                    gtk::DialogFlags::all(),
+                    gtk::MessageType::Error,
+                    gtk::ButtonsType::Close,
+                    "Error exporting the events",
+                );
+                dialog.set_secondary_text(Some(&format!("{}: {}", path.display(), e)));
+                let _r = dialog.run();
+                dialog.close();
+            }
+        }
+    }
+
+    fn fetch_week_timesheet(&self) {
+        let stream = self.model.relm.stream().clone();
+        let (_channel, sender) = Channel::new(move |days| {
+            stream.emit(Msg::GotWeekTimesheet(days));
+        });
+        let c = self.model.config.clone();
+        let week_start = self.model.config.week_start(self.model.day);
+        let days = (0..7)
+            .map(|i| week_start + chrono::Duration::days(i))
+            .collect();
+        std::thread::spawn(move || {
+            sender
+                .send(crate::events::events::get_all_events_for_days(
+                    c,
+                    &FetchOptions::default(),
+                    days,
+                ))
                 .unwrap_or_else(|err| println!("Thread communication error: {}", err));
         });
     }
 
+    // show the timesheet before saving it, it's what people will submit
+    fn show_week_timesheet(&self, days: &[(Date<Local>, FetchedEvents)]) {
+        let timesheet = crate::export::week_timesheet(days);
+        let markdown = crate::export::timesheet_to_markdown(&timesheet);
+        let dialog = gtk::Dialog::with_buttons(
+            Some("Week timesheet"),
+            self.get_main_window().as_ref(),
+            gtk::DialogFlags::all(),
+            &[
+                ("Cancel", gtk::ResponseType::Cancel),
+                ("Save as CSV", gtk::ResponseType::Other(0)),
+                ("Save as Markdown", gtk::ResponseType::Other(1)),
+            ],
+        );
+        dialog.set_default_size(700, 350);
+        let text_view = gtk::TextViewBuilder::new()
+            .editable(false)
+            .monospace(true)
+            .margin(6)
+            .build();
+        text_view
+            .buffer()
+            .unwrap()
+            .set_text(&if timesheet.rows.is_empty() {
+                "No time was tracked in that week".to_string()
+            } else {
+                markdown.clone()
+            });
+        let scrolled = gtk::ScrolledWindowBuilder::new()
+            .child(&text_view)
+            .vexpand(true)
+            .build();
+        dialog.content_area().pack_start(&scrolled, true, true, 0);
+        dialog.show_all();
+        let r = dialog.run();
+        dialog.close();
+        let default_name = |ext: &str| match timesheet.days.first() {
+            Some(d) => format!("cigale-timesheet-{}.{}", d.format("%Y-%m-%d"), ext),
+            None => format!("cigale-timesheet.{}", ext),
+        };
+        match r {
+            gtk::ResponseType::Other(0) => match crate::export::timesheet_to_csv(&timesheet) {
+                Ok(csv) => self.export_to_file(&default_name("csv"), &csv),
+                Err(e) => log::error!("Error exporting the timesheet to CSV: {}", e),
+            },
+            gtk::ResponseType::Other(1) => self.export_to_file(&default_name("md"), &markdown),
+            _ => {}
+        }
+    }
+
+    fn show_fetch_errors(&self) {
+        let dialog = gtk::MessageDialog::new(
+            self.get_main_window().as_ref(),
+            gtk::DialogFlags::all(),
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Close,
+            "Some event sources failed",
+        );
+        dialog.set_secondary_text(Some(&self.model.fetch_errors.join("\n\n")));
+        let _r = dialog.run();
+        dialog.close();
+    }
+
+    fn refetch(&mut self, bypass_cache: bool) {
+        self.model.events = None;
+        self.model.fetch_progress = "".to_string();
+        self.model.fetch_errors = vec![];
+        self.model.fetch_errors_retriable = false;
+        self.update_events();
+        EventView::fetch_events(
+            &self.model.config,
+            &self.model.relm,
+            self.model.day,
+            bypass_cache,
+            true,
+        );
+    }
+
+    fn watch_local_sources(&mut self) {
+        // drop the previous watcher first, the sources may have changed
+        self.model.file_watcher = None;
+        let paths = crate::events::events::get_watched_paths(&self.model.config);
+        let stream = self.model.relm.stream().clone();
+        let (_channel, sender) = Channel::new(move |_| {
+            stream.emit(Msg::WatchedFilesChanged);
+        });
+        match crate::events::filewatch::watch_paths(&paths, move || {
+            sender
+                .send(())
+                .unwrap_or_else(|err| println!("Thread communication error: {}", err));
+        }) {
+            Ok(watcher) => self.model.file_watcher = watcher,
+            Err(e) => log::warn!("Error watching the local event sources: {}", e),
+        }
+    }
+
+    fn notify_new_events(previous: &[Event], current: &[Event]) {
+        let new_events = crate::events::events::new_events(previous, current);
+        if new_events.is_empty() {
+            return;
+        }
+        let body = new_events
+            .iter()
+            .map(|e| format!("{} {}", e.event_type_desc, e.event_contents_header.trim()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Err(e) = notify_rust::Notification::new()
+            .appname("Cigale")
+            .summary(&format!("{} new event(s)", new_events.len()))
+            .body(&body)
+            .icon(Icon::APP_ICON.name())
+            .show()
+        {
+            log::error!("Error displaying the notification: {}", e);
+        }
+    }
+
+    /// open the folder of the cache file holding the current
+    /// event in the file manager
+    fn reveal_cache_file(&self) -> crate::events::events::Result<()> {
+        let event_type = match &self.model.current_event {
+            Some(e) => e.event_type_desc,
+            None => return Ok(()),
+        };
+        let eps = crate::events::events::get_event_providers();
+        let ep = match eps.iter().find(|ep| ep.name() == event_type) {
+            Some(ep) => ep,
+            None => return Err(format!("No event provider for {}", event_type).into()),
+        };
+        // the event doesn't tell which event source it came from,
+        // pick the first source of that provider with a cache file
+        let mut cache_file = None;
+        for config_name in ep.get_config_names(&self.model.config) {
+            cache_file = Config::day_cache_file(ep.as_ref(), config_name, self.model.day)?;
+            if cache_file.is_some() {
+                break;
+            }
+        }
+        let cache_file = cache_file.ok_or(format!(
+            "No cache file for {} on {}",
+            event_type, self.model.day
+        ))?;
+        log::info!("Cache file: {}", cache_file.display());
+        let folder = cache_file.parent().ok_or("The cache file has no folder?")?;
+        gtk::show_uri_on_window(
+            self.get_main_window().as_ref(),
+            &glib::filename_to_uri(folder, None)?,
+            gtk::current_event_time(),
+        )?;
+        Ok(())
+    }
+
     fn update(&mut self, event: Msg) {
         match event {
             Msg::EventSelected(row_idx) => {
-                if let Some(Ok(events)) = &self.model.events {
-                    self.model.current_event = row_idx.and_then(|idx| events.get(idx)).cloned();
+                self.model.selected_row = row_idx;
+                self.model.current_event = row_idx
+                    .and_then(|idx| self.displayed_events().get(idx).cloned())
+                    .cloned();
+            }
+            Msg::AddEventRows(generation, start) => {
+                if generation == self.model.event_list_generation {
+                    self.add_event_rows(start);
+                }
+            }
+            Msg::RowsLayoutChanged(layout) => {
+                // my parent saves it in the config
+                self.model.config.event_rows_layout = layout;
+                self.update_event_list();
+            }
+            Msg::GroupByHourChanged(group) => {
+                // my parent saves it in the config
+                self.model.config.group_events_by_hour = group;
+                self.update_event_list();
+            }
+            Msg::SearchChanged(text) => {
+                self.model.search_text = text;
+                self.update_event_list();
+            }
+            Msg::WeekViewToggled(visible) => {
+                self.model.week_view_visible = visible;
+                if visible {
+                    self.components
+                        .week_view
+                        .emit(WeekViewMsg::LoadWeek(self.model.day));
+                }
+            }
+            Msg::WeekDayClicked(day) => {
+                // the date picker will notify us through DayPicked
+                self.components
+                    .date_picker
+                    .emit(DatePickerMsg::DayPicked(day));
+            }
+            Msg::ProviderToggled(provider, active) => {
+                if active {
+                    self.model.hidden_providers.remove(provider);
+                } else {
+                    self.model.hidden_providers.insert(provider);
+                }
+                self.update_event_list();
+            }
+            Msg::WatchedFilesChanged => {
+                // reload quietly, like a poll. but the user made the
+                // changes, no point in notifying about them.
+                if self.model.events.is_some() && !self.model.polling {
+                    self.model.polling = true;
+                    self.model.notify_poll_results = false;
+                    EventView::fetch_events(
+                        &self.model.config,
+                        &self.model.relm,
+                        self.model.day,
+                        false,
+                        false,
+                    );
+                }
+            }
+            Msg::ErrorsBarResponse(r) => {
+                if r == gtk::ResponseType::Accept {
+                    self.show_fetch_errors();
+                } else if r == gtk::ResponseType::Apply {
+                    // the sources which failed have nothing in the cache
+                    self.refetch(false);
+                } else {
+                    self.model.fetch_errors_dismissed = true;
+                }
+            }
+            Msg::PollTick => {
+                let interval = std::time::Duration::from_secs(
+                    self.model.config.notify_poll_interval_mins as u64 * 60,
+                );
+                // we poll only for today, past days won't get new events.
+                // and we don't poll while we're loading the events.
+                if self.model.config.notify_new_events
+                    && self.model.day == Local::today()
+                    && self.model.events.is_some()
+                    && !self.model.polling
+                    && self.model.last_poll.elapsed() >= interval
+                {
+                    self.model.last_poll = std::time::Instant::now();
+                    self.model.polling = true;
+                    self.model.notify_poll_results = true;
+                    // the cache ttl decides whether we hit the network
+                    EventView::fetch_events(
+                        &self.model.config,
+                        &self.model.relm,
+                        self.model.day,
+                        false,
+                        false,
+                    );
                 }
             }
             Msg::DayChange(day) => {
+                self.model.polling = false;
+                // we'll watch again when we get the events of the new day
+                self.model.file_watcher = None;
                 self.model.events = None;
+                self.model.fetch_progress = "".to_string();
+                self.model.fetch_errors = vec![];
+                self.model.fetch_errors_retriable = false;
                 self.model.day = day;
+                Config::save_last_viewed_date(day);
                 self.update_events();
-                EventView::fetch_events(&self.model.config, &self.model.relm, day);
+                if self.model.week_view_visible {
+                    self.components.week_view.emit(WeekViewMsg::LoadWeek(day));
+                }
+                EventView::fetch_events(&self.model.config, &self.model.relm, day, false, true);
+            }
+            Msg::ForceRefresh => {
+                self.refetch(true);
+            }
+            Msg::FetchProgress(progress) => {
+                if self.model.events.is_none() {
+                    self.model.fetch_progress = progress;
+                }
             }
-            Msg::GotEvents(events) => {
+            Msg::GotEvents(fetched) => {
+                self.model.fetch_progress = "".to_string();
+                let fetch_errors: Vec<String> = fetched
+                    .errors
+                    .iter()
+                    .map(|e| e.to_string_with_hint())
+                    .collect();
+                if fetch_errors != self.model.fetch_errors {
+                    self.model.fetch_errors_dismissed = false;
+                }
+                self.model.fetch_errors = fetch_errors;
+                self.model.fetch_errors_retriable = fetched
+                    .errors
+                    .iter()
+                    .any(|e| e.kind() == ErrorKind::Network);
+                let events = if self.model.config.merge_events_across_sources {
+                    crate::events::events::merge_events_across_sources(fetched.events)
+                } else {
+                    fetched.events
+                };
+                let events = if self.model.config.group_related_events {
+                    crate::events::events::group_related_events(
+                        events,
+                        chrono::Duration::minutes(
+                            self.model.config.group_related_events_window_mins as i64,
+                        ),
+                    )
+                } else {
+                    events
+                };
+                if self.model.polling {
+                    self.model.polling = false;
+                    let previous = self.model.events.take().unwrap_or_else(Vec::new);
+                    if self.model.notify_poll_results {
+                        Self::notify_new_events(&previous, &events);
+                    }
+                    if previous == events {
+                        // don't lose the selection if nothing changed
+                        self.model.events = Some(previous);
+                        self.model.relm.stream().emit(Msg::FetchDone);
+                        return;
+                    }
+                } else {
+                    self.watch_local_sources();
+                }
                 self.model.events = Some(events);
                 self.update_events();
+                self.model.relm.stream().emit(Msg::FetchDone);
+            }
+            Msg::GotTodayEvents(fetched) => {
+                // the user moved to today while we were fetching it in the
+                // background: no need to wait for the other fetch.
+                if self.model.day == Local::today() && self.model.events.is_none() {
+                    self.model.relm.stream().emit(Msg::GotEvents(fetched));
+                }
+            }
+            Msg::FetchDone => {
+                // meant for my parent, not for me
             }
             Msg::ConfigUpdate(config) => {
                 self.model.config = *config;
-                EventView::fetch_events(&self.model.config, &self.model.relm, self.model.day);
+                self.components
+                    .week_view
+                    .emit(WeekViewMsg::ConfigUpdate(Box::new(
+                        self.model.config.clone(),
+                    )));
+                if self.model.week_view_visible {
+                    self.components
+                        .week_view
+                        .emit(WeekViewMsg::LoadWeek(self.model.day));
+                }
+                EventView::fetch_events(
+                    &self.model.config,
+                    &self.model.relm,
+                    self.model.day,
+                    false,
+                    true,
+                );
                 self.components
                     .date_picker
                     .emit(DatePickerMsg::PrevNextDaySkipChanged(
                         self.model.config.prev_next_day_skip_weekends,
                     ));
+                self.components
+                    .date_picker
+                    .emit(DatePickerMsg::DateFormatChanged(
+                        self.model.config.date_display_format.clone(),
+                    ));
             }
             Msg::CopyHeader => {
                 if let Some(clip) = gtk::Clipboard::default(&self.widgets.events_stack.display()) {
@@ -155,10 +1032,126 @@ impl Widget for EventView {
                     );
                 }
             }
+            Msg::EventListKeyPress(key) => {
+                // only when the event list has the focus, so that we
+                // don't steal ctrl-c from the search entry or the labels
+                let state = key.state();
+                if state.contains(gdk::ModifierType::CONTROL_MASK)
+                    && key.keyval().to_lower() == gdk::keys::constants::c
+                {
+                    self.model.relm.stream().emit(
+                        if state.contains(gdk::ModifierType::SHIFT_MASK) {
+                            Msg::CopyEventLink
+                        } else {
+                            Msg::CopyEvent
+                        },
+                    );
+                }
+            }
+            Msg::CopyEvent => {
+                let m_clip = &gtk::Clipboard::default(&self.widgets.events_stack.display());
+                if let (Some(clip), Some(event)) = (m_clip, &self.model.current_event) {
+                    clip.set_text(&crate::export::event_to_text(event));
+                }
+            }
+            Msg::CopyEventLink => {
+                let m_clip = &gtk::Clipboard::default(&self.widgets.events_stack.display());
+                let m_link = self.model.current_event.as_ref().and_then(|e| e.link());
+                if let (Some(clip), Some(link)) = (m_clip, m_link) {
+                    clip.set_text(&link);
+                }
+            }
+            Msg::EventListButtonPress(event) => {
+                if is_range_click(&event) {
+                    let (_x, y) = event.position();
+                    if let Some(row) = self.widgets.event_list.row_at_y(y as i32) {
+                        match self.model.selected_row {
+                            Some(start) => self.select_range(start, row.index() as usize),
+                            // nothing to start the range from, the list
+                            // didn't get the click, select the row ourselves
+                            None => self.widgets.event_list.select_row(Some(&row)),
+                        }
+                    }
+                } else if event.event_type() == gdk::EventType::ButtonPress && event.button() == 1 {
+                    self.clear_range_selection();
+                }
+                if event.event_type() == gdk::EventType::ButtonPress && event.button() == 3 {
+                    let (_x, y) = event.position();
+                    if let Some(row) = self.widgets.event_list.row_at_y(y as i32) {
+                        // we get EventSelected, which updates current_event
+                        self.widgets.event_list.select_row(Some(&row));
+                        self.show_event_context_menu(&event);
+                    }
+                }
+            }
+            Msg::RevealCacheFile => {
+                if let Err(e) = self.reveal_cache_file() {
+                    log::error!("Error showing the cache file: {}", e);
+                }
+            }
+            Msg::OpenEventLink => {
+                if let Some(link) = self.model.current_event.as_ref().and_then(|e| e.link()) {
+                    if let Err(e) = gtk::show_uri_on_window(
+                        self.get_main_window().as_ref(),
+                        &link,
+                        gtk::current_event_time(),
+                    ) {
+                        log::error!("Error opening the link {}: {}", link, e);
+                    }
+                }
+            }
+            Msg::ExportMarkdown => {
+                if let Some(events) = &self.model.events {
+                    self.export_to_file(
+                        &format!("cigale-{}.md", self.model.day.format("%Y-%m-%d")),
+                        &crate::export::to_markdown(self.model.day, events),
+                    );
+                }
+            }
+            Msg::ExportCsv => {
+                if let Some(events) = &self.model.events {
+                    match crate::export::to_csv(self.model.day, events) {
+                        Ok(csv) => self.export_to_file(
+                            &format!("cigale-{}.csv", self.model.day.format("%Y-%m-%d")),
+                            &csv,
+                        ),
+                        Err(e) => log::error!("Error exporting to CSV: {}", e),
+                    }
+                }
+            }
+            Msg::ExportJson => {
+                if let Some(events) = &self.model.events {
+                    match crate::export::to_json(self.model.day, events) {
+                        Ok(json) => self.export_to_file(
+                            &format!("cigale-{}.json", self.model.day.format("%Y-%m-%d")),
+                            &json,
+                        ),
+                        Err(e) => log::error!("Error exporting to JSON: {}", e),
+                    }
+                }
+            }
+            Msg::ExportWeekTimesheet => {
+                self.fetch_week_timesheet();
+            }
+            Msg::GotWeekTimesheet(days) => {
+                self.show_week_timesheet(&days);
+            }
+            Msg::CopyMarkdown => {
+                let m_clip = &gtk::Clipboard::default(&self.widgets.events_stack.display());
+                let m_events = &self.model.events;
+                if let (Some(clip), Some(event_list)) = (m_clip, m_events) {
+                    clip.set_text(&crate::export::to_markdown(self.model.day, event_list));
+                }
+            }
+            Msg::CopyDaySummary => {
+                if let Some(clip) = gtk::Clipboard::default(&self.widgets.events_stack.display()) {
+                    clip.set_text(&crate::export::to_summary(&self.displayed_events()));
+                }
+            }
             Msg::CopyAllHeaders => {
                 let m_clip = &gtk::Clipboard::default(&self.widgets.events_stack.display());
                 let m_events = &self.model.events;
-                if let (Some(clip), Some(Ok(event_list))) = (m_clip, m_events) {
+                if let (Some(clip), Some(event_list)) = (m_clip, m_events) {
                     clip.set_text(
                         &event_list
                             .iter()
@@ -183,18 +1176,78 @@ impl Widget for EventView {
                     orientation: gtk::Orientation::Horizontal,
                     #[name="date_picker"]
                     DatePicker(self.model.accel_group.clone(),
-                               self.model.config.prev_next_day_skip_weekends) {
+                               self.model.config.prev_next_day_skip_weekends,
+                               self.model.day,
+                               self.model.config.date_display_format.clone()) {
                         DatePickerDayPickedMsg(d) => Msg::DayChange(d)
                     },
                     gtk::Spinner {
                         active: self.model.events.is_none()
-                    }
+                    },
+                    gtk::Label {
+                        margin_start: 6,
+                        text: &self.model.fetch_progress,
+                        visible: self.model.events.is_none(),
+                        ellipsize: pango::EllipsizeMode::End,
+                    },
+                    gtk::ToggleButton {
+                        child: {
+                            pack_type: gtk::PackType::End,
+                        },
+                        margin_end: 10,
+                        valign: gtk::Align::Center,
+                        label: "Week overview",
+                        active: self.model.week_view_visible,
+                        toggled(t) => Msg::WeekViewToggled(t.is_active()),
+                    },
+                    #[name="view_button"]
+                    gtk::MenuButton {
+                        child: {
+                            pack_type: gtk::PackType::End,
+                        },
+                        margin_end: 10,
+                        valign: gtk::Align::Center,
+                        image: Some(&gtk::Image::from_icon_name(
+                            Some("view-more-symbolic"), gtk::IconSize::Menu)),
+                        tooltip_text: Some("View options"),
+                    },
+                    #[name="export_button"]
+                    gtk::MenuButton {
+                        child: {
+                            pack_type: gtk::PackType::End,
+                        },
+                        margin_end: 10,
+                        valign: gtk::Align::Center,
+                        image: Some(&gtk::Image::from_icon_name(
+                            Some("document-save-symbolic"), gtk::IconSize::Menu)),
+                        tooltip_text: Some("Export the events"),
+                        sensitive: self.model.events.is_some(),
+                    },
+                    #[name="copy_day_button"]
+                    gtk::Button {
+                        child: {
+                            pack_type: gtk::PackType::End,
+                        },
+                        margin_end: 6,
+                        valign: gtk::Align::Center,
+                        image: Some(&gtk::Image::from_icon_name(
+                            Some(Icon::COPY.name()), gtk::IconSize::Menu)),
+                        tooltip_text: Some("Copy the displayed events to the clipboard"),
+                        sensitive: self.model.events.is_some(),
+                        clicked => Msg::CopyDaySummary
+                    },
+                },
+                gtk::Revealer {
+                    reveal_child: self.model.week_view_visible,
+                    #[name="week_view"]
+                    WeekView(self.model.config.clone()) {
+                        WeekViewDayClickedMsg(d) => Msg::WeekDayClicked(d),
+                    },
                 },
                 #[name="info_bar"]
                 gtk::InfoBar {
-                    revealed: self.model.events.as_ref()
-                                               .filter(|r| r.is_err())
-                                               .is_some(),
+                    revealed: !self.model.fetch_errors.is_empty() && !self.model.fetch_errors_dismissed,
+                    show_close_button: true,
                     message_type: gtk::MessageType::Error,
                 },
                 gtk::Box {
@@ -203,19 +1256,109 @@ impl Widget for EventView {
                         fill: true,
                         expand: true,
                     },
-                    gtk::ScrolledWindow {
+                    gtk::Box {
+                        orientation: gtk::Orientation::Vertical,
                         halign: gtk::Align::Start,
                         width_request: 350,
-                        gtk::Box {
-                            #[name="event_list"]
-                            gtk::ListBox {
+                        gtk::SearchEntry {
+                            margin_start: 6,
+                            margin_end: 6,
+                            margin_bottom: 6,
+                            placeholder_text: Some("Filter events"),
+                            search_changed(e) => Msg::SearchChanged(e.text().to_string()),
+                        },
+                        #[name="provider_toggles"]
+                        gtk::FlowBox {
+                            margin_start: 6,
+                            margin_end: 6,
+                            margin_bottom: 6,
+                            selection_mode: gtk::SelectionMode::None,
+                            column_spacing: 3,
+                            row_spacing: 3,
+                        },
+                        #[name="event_list_stack"]
+                        gtk::Stack {
+                            child: {
+                                fill: true,
+                                expand: true,
+                            },
+                            gtk::ScrolledWindow {
                                 child: {
-                                    fill: true,
-                                    expand: true,
+                                    name: Some("events")
                                 },
-                                row_selected(_, row) => Msg::EventSelected(row.map(|r| r.index() as usize))
-                            }
-                        }
+                                gtk::Box {
+                                    #[name="event_list"]
+                                    gtk::ListBox {
+                                        child: {
+                                            fill: true,
+                                            expand: true,
+                                        },
+                                        row_selected(_, row) => Msg::EventSelected(row.map(|r| r.index() as usize)),
+                                        key_press_event(_, key) => (Msg::EventListKeyPress(key.clone()), Inhibit(false)),
+                                        // the list must not select the row of a range click,
+                                        // the selected row is the start of the range
+                                        button_press_event(_, event) => (Msg::EventListButtonPress(event.clone()), Inhibit(is_range_click(event))),
+                                    }
+                                }
+                            },
+                            gtk::Box {
+                                child: {
+                                    name: Some("loading")
+                                },
+                                orientation: gtk::Orientation::Vertical,
+                                valign: gtk::Align::Center,
+                                spacing: 10,
+                                gtk::Spinner {
+                                    active: self.model.events.is_none(),
+                                    width_request: 32,
+                                    height_request: 32,
+                                },
+                                gtk::Label {
+                                    text: "Fetching the events...",
+                                },
+                            },
+                            gtk::Box {
+                                child: {
+                                    name: Some("empty")
+                                },
+                                orientation: gtk::Orientation::Vertical,
+                                valign: gtk::Align::Center,
+                                spacing: 10,
+                                gtk::Image {
+                                    icon_name: Some(Icon::CALENDAR_DAY.name()),
+                                    pixel_size: 48,
+                                },
+                                gtk::Label {
+                                    text: "No activity recorded for this day",
+                                },
+                            },
+                            gtk::Box {
+                                child: {
+                                    name: Some("failed")
+                                },
+                                orientation: gtk::Orientation::Vertical,
+                                valign: gtk::Align::Center,
+                                spacing: 10,
+                                gtk::Image {
+                                    icon_name: Some(Icon::EXCLAMATION_TRIANGLE.name()),
+                                    pixel_size: 48,
+                                },
+                                #[name="failed_sources_label"]
+                                gtk::Label {
+                                    justify: gtk::Justification::Center,
+                                    line_wrap: true,
+                                    margin_start: 6,
+                                    margin_end: 6,
+                                },
+                            },
+                        },
+                        #[name="total_duration_label"]
+                        gtk::Label {
+                            margin_top: 6,
+                            margin_bottom: 6,
+                            xalign: 0.0,
+                            margin_start: 6,
+                        },
                     },
                     gtk::Box {
                         orientation: gtk::Orientation::Vertical,
@@ -259,6 +1402,17 @@ impl Widget for EventView {
                                 clicked => Msg::CopyHeader
                             }
                         },
+                        gtk::LinkButton {
+                            label: "Open in the browser",
+                            halign: gtk::Align::Start,
+                            // same link as the "Open in browser" context menu
+                            uri: &self.model.current_event.as_ref()
+                                                          .and_then(|e| e.link())
+                                                          .unwrap_or_default(),
+                            visible: self.model.current_event.as_ref()
+                                                             .and_then(|e| e.link())
+                                                             .is_some(),
+                        },
                         gtk::ScrolledWindow {
                             child: {
                                 expand: true,
@@ -286,6 +1440,7 @@ impl Widget for EventView {
                                     xalign: 0.0,
                                     yalign: 0.0,
                                     line_wrap: true,
+                                    line_wrap_mode: pango::WrapMode::WordChar,
                                     visible: self.model.current_event.as_ref()
                                                                      .filter(|e| e.event_contents_body.is_markup())
                                                                      .is_none(),
@@ -311,15 +1466,19 @@ impl Widget for EventView {
                                     yalign: 0.0,
                                     line_wrap: self.model.current_event.as_ref()
                                                                        .filter(|e| e.event_contents_body.is_markup())
-                                                                       .map(|e| e.event_contents_body.is_word_wrap())
-                                                                       .unwrap_or(false),
+                                                                       .and_then(|e| e.event_contents_body.wrap_mode())
+                                                                       .is_some(),
+                                    line_wrap_mode: self.model.current_event.as_ref()
+                                                                            .filter(|e| e.event_contents_body.is_markup())
+                                                                            .and_then(|e| e.event_contents_body.wrap_mode())
+                                                                            .unwrap_or(pango::WrapMode::Word),
                                     visible: self.model.current_event.as_ref()
                                                                      .filter(|e| e.event_contents_body.is_markup())
                                                                      .is_some(),
-                                    markup: self.model.current_event.as_ref()
-                                                                    .filter(|e| e.event_contents_body.is_markup())
-                                                                    .map(|e| e.event_contents_body.as_str())
-                                                                    .unwrap_or(""),
+                                    markup: &self.model.current_event.as_ref()
+                                                                     .filter(|e| e.event_contents_body.is_markup())
+                                                                     .map(|e| e.event_contents_body.as_markup())
+                                                                     .unwrap_or_default(),
                                 }
                             }
                         }
@@ -337,3 +1496,49 @@ impl Widget for EventView {
         }
     }
 }
+
+/// shift-click selects a range of events, from the selected event
+fn is_range_click(event: &gdk::EventButton) -> bool {
+    event.event_type() == gdk::EventType::ButtonPress
+        && event.button() == 1
+        && event.state().contains(gdk::ModifierType::SHIFT_MASK)
+}
+
+/// the hour to display above the row at `index`, when it's
+/// the first event of that hour
+fn hour_separator(hours: &[u32], index: usize) -> Option<u32> {
+    let hour = *hours.get(index)?;
+    if index > 0 && hours.get(index - 1) == Some(&hour) {
+        None
+    } else {
+        Some(hour)
+    }
+}
+
+#[test]
+fn it_picks_the_event_list_state() {
+    let errors = vec!["Redmine - work: timeout".to_string()];
+    assert_eq!(
+        EventListState::Loading,
+        EventListState::for_events(None, &errors)
+    );
+    assert_eq!(
+        EventListState::Empty,
+        EventListState::for_events(Some(&[][..]), &[])
+    );
+    assert_eq!(
+        EventListState::Failed,
+        EventListState::for_events(Some(&[][..]), &errors)
+    );
+}
+
+#[test]
+fn it_puts_hour_separators_only_above_the_first_event_of_an_hour() {
+    let hours = vec![9, 9, 10, 14, 14];
+    assert_eq!(
+        vec![Some(9), None, Some(10), Some(14), None, None],
+        (0..6)
+            .map(|i| hour_separator(&hours, i))
+            .collect::<Vec<_>>()
+    );
+}
@@ -8,6 +8,7 @@ use std::collections::HashMap;
 #[derive(Msg)]
 pub enum EventSourceListItemMsg {
     ActionsClicked(gtk::Button),
+    EnabledToggled(bool),
 }
 
 pub struct EventSourceListItemInfo {
@@ -15,6 +16,7 @@ pub struct EventSourceListItemInfo {
     pub event_provider_name: &'static str,
     pub config_name: String,
     pub event_source: HashMap<&'static str, String>,
+    pub enabled: bool,
 }
 
 pub struct Model {
@@ -49,10 +51,11 @@ impl Widget for EventSourceListItem {
             self.widgets.items_box.attach(&desc, 0, i, 1, 1);
             self.widgets.items_box.attach(
                 &gtk::LabelBuilder::new()
-                    .label(if field_type == ConfigType::Password {
-                        "●●●●●"
-                    } else {
-                        kv.1
+                    .label(match field_type {
+                        ConfigType::Password => "●●●●●",
+                        ConfigType::Checkbox(_) if kv.1 == "true" => "Yes",
+                        ConfigType::Checkbox(_) => "No",
+                        _ => kv.1,
                     })
                     .ellipsize(pango::EllipsizeMode::End)
                     .xalign(0.0)
@@ -76,6 +79,9 @@ impl Widget for EventSourceListItem {
             EventSourceListItemMsg::ActionsClicked(_) => {
                 // meant for my parent
             }
+            EventSourceListItemMsg::EnabledToggled(_) => {
+                // meant for my parent
+            }
         }
     }
 
@@ -100,6 +106,7 @@ impl Widget for EventSourceListItem {
                     cell: {
                         width: 2
                     },
+                    sensitive: self.model.list_item_info.enabled,
                     gtk::Image {
                         icon_name: Some(
                             self.model.list_item_info.event_provider_icon.name()),
@@ -113,15 +120,26 @@ impl Widget for EventSourceListItem {
                         xalign: 0.0,
                     }
                 },
+                gtk::Switch {
+                    active: self.model.list_item_info.enabled,
+                    tooltip_text: Some("Fetch the events of this source"),
+                    hexpand: true,
+                    halign: gtk::Align::End,
+                    valign: gtk::Align::Center,
+                    cell: {
+                        left_attach: 2,
+                        top_attach: 0,
+                    },
+                    state_set(_, state) =>
+                        (EventSourceListItemMsg::EnabledToggled(state), Inhibit(false))
+                },
                 #[name="event_source_actions_btn"]
                 gtk::Button {
                     always_show_image: true,
                     image: Some(&gtk::Image::from_icon_name(
                         Some(Icon::COG.name()), gtk::IconSize::Menu)),
-                    hexpand: true,
-                    halign: gtk::Align::End,
                     cell: {
-                        left_attach: 2,
+                        left_attach: 3,
                         top_attach: 0,
                     },
                     button_release_event(c, _) =>
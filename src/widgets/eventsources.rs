@@ -12,6 +12,12 @@ pub enum Msg {
     ActionsClicked(gtk::Button, &'static str, String),
     EditEventSource(&'static str, String),
     RemoveEventSource(&'static str, String),
+    MoveEventSource(String, bool),
+    SetEventSourceEnabled(&'static str, String, bool),
+    SourceRemoved(String),
+    HideUndo(String),
+    UndoBarResponse(gtk::ResponseType),
+    UndoRemove,
 }
 
 pub struct Model {
@@ -20,12 +26,23 @@ pub struct Model {
     eventsource_action_popover: gtk::Popover,
 
     eventsource_list_items: Vec<relm::Component<EventSourceListItem>>,
+    // the source we offer to restore in the undo bar
+    removed_source: Option<String>,
 }
 
 #[widget]
 impl Widget for EventSources {
     fn init_view(&mut self) {
         self.update_eventsources();
+        self.widgets
+            .undo_bar
+            .add_button("Undo", gtk::ResponseType::Accept);
+        relm::connect!(
+            self.model.relm,
+            &self.widgets.undo_bar,
+            connect_response(_, r),
+            Msg::UndoBarResponse(r)
+        );
     }
 
     fn model(relm: &relm::Relm<Self>, config: Config) -> Model {
@@ -36,6 +53,7 @@ impl Widget for EventSources {
                 .position(gtk::PositionType::Bottom)
                 .build(),
             eventsource_list_items: vec![],
+            removed_source: None,
         }
     }
 
@@ -63,6 +81,10 @@ impl Widget for EventSources {
                 wintitlebar::left_align_menu(&edit_btn);
                 let remove_btn = gtk::ModelButtonBuilder::new().label("Remove").build();
                 wintitlebar::left_align_menu(&remove_btn);
+                let move_up_btn = gtk::ModelButtonBuilder::new().label("Move up").build();
+                wintitlebar::left_align_menu(&move_up_btn);
+                let move_down_btn = gtk::ModelButtonBuilder::new().label("Move down").build();
+                wintitlebar::left_align_menu(&move_down_btn);
                 // my parent is listening to these editeventsource / removeeventsource event.
                 let config_name1 = config_name.clone();
                 relm::connect!(
@@ -78,8 +100,24 @@ impl Widget for EventSources {
                     connect_clicked(_),
                     Msg::RemoveEventSource(ep_name, config_name.clone())
                 );
+                let config_name2 = config_name.clone();
+                relm::connect!(
+                    self.model.relm,
+                    &move_up_btn,
+                    connect_clicked(_),
+                    Msg::MoveEventSource(config_name2.clone(), true)
+                );
+                relm::connect!(
+                    self.model.relm,
+                    &move_down_btn,
+                    connect_clicked(_),
+                    Msg::MoveEventSource(config_name.clone(), false)
+                );
                 vbox.add(&edit_btn);
                 vbox.add(&remove_btn);
+                vbox.add(&gtk::Separator::new(gtk::Orientation::Horizontal));
+                vbox.add(&move_up_btn);
+                vbox.add(&move_down_btn);
                 popover.add(&vbox);
                 vbox.show_all();
                 popover.popup();
@@ -90,6 +128,52 @@ impl Widget for EventSources {
             Msg::RemoveEventSource(_, _) => {
                 // that's meant only for my parent, not for me.
             }
+            Msg::MoveEventSource(_, _) => {
+                // that's meant only for my parent, not for me.
+            }
+            Msg::SetEventSourceEnabled(_, _, _) => {
+                // that's meant only for my parent, not for me.
+            }
+            Msg::SourceRemoved(config_name) => {
+                let contents = self
+                    .widgets
+                    .undo_bar
+                    .content_area()
+                    .dynamic_cast::<gtk::Box>() // https://github.com/gtk-rs/gtk/issues/947
+                    .unwrap();
+                for child in contents.children() {
+                    contents.remove(&child);
+                }
+                contents.add(
+                    &gtk::LabelBuilder::new()
+                        .label(&format!("Removed the '{}' event source", config_name))
+                        .ellipsize(pango::EllipsizeMode::End)
+                        .build(),
+                );
+                contents.show_all();
+                self.model.removed_source = Some(config_name.clone());
+                // the undo is offered for a few seconds only
+                let stream = self.model.relm.stream().clone();
+                glib::timeout_add_seconds_local(10, move || {
+                    stream.emit(Msg::HideUndo(config_name.clone()));
+                    glib::Continue(false)
+                });
+            }
+            Msg::HideUndo(config_name) => {
+                // another source may have been removed since
+                if self.model.removed_source.as_ref() == Some(&config_name) {
+                    self.model.removed_source = None;
+                }
+            }
+            Msg::UndoBarResponse(r) => {
+                if r == gtk::ResponseType::Accept {
+                    self.model.relm.stream().emit(Msg::UndoRemove);
+                }
+                self.model.removed_source = None;
+            }
+            Msg::UndoRemove => {
+                // that's meant only for my parent, not for me.
+            }
         }
     }
 
@@ -99,28 +183,38 @@ impl Widget for EventSources {
         }
         self.model.eventsource_list_items.clear();
         let event_providers = crate::events::events::get_event_providers();
-        for event_provider in event_providers {
-            for event_config_name in event_provider.get_config_names(&self.model.config) {
-                let event_config =
-                    event_provider.get_config_values(&self.model.config, event_config_name);
-                let child = self
-                    .widgets
-                    .eventsources_list
-                    .add_widget::<EventSourceListItem>(EventSourceListItemInfo {
-                        event_provider_name: event_provider.name(),
-                        event_provider_icon: event_provider.default_icon(),
-                        config_name: event_config_name.to_string(),
-                        event_source: event_config.clone(),
-                    });
-                let ep_name = event_provider.name();
-                let cfg_name = event_config_name.to_string();
-                relm::connect!(
-                    child@EventSourceListItemMsg::ActionsClicked(ref btn),
-                    self.model.relm,
-                    Msg::ActionsClicked(btn.clone(), ep_name, cfg_name.clone())
-                );
-                self.model.eventsource_list_items.push(child);
-            }
+        for (event_provider, event_config_name) in
+            crate::events::events::ordered_event_sources(&event_providers, &self.model.config)
+        {
+            // the list doesn't display the secrets, no need to read the keyring
+            let event_config = event_provider
+                .get_config_values_without_secrets(&self.model.config, event_config_name);
+            let child = self
+                .widgets
+                .eventsources_list
+                .add_widget::<EventSourceListItem>(EventSourceListItemInfo {
+                    event_provider_name: event_provider.name(),
+                    event_provider_icon: event_provider.default_icon(),
+                    config_name: event_config_name.to_string(),
+                    event_source: event_config.clone(),
+                    enabled: self
+                        .model
+                        .config
+                        .is_source_enabled(event_provider.name(), event_config_name),
+                });
+            let ep_name = event_provider.name();
+            let cfg_name = event_config_name.to_string();
+            relm::connect!(
+                child@EventSourceListItemMsg::ActionsClicked(ref btn),
+                self.model.relm,
+                Msg::ActionsClicked(btn.clone(), ep_name, cfg_name.clone())
+            );
+            relm::connect!(
+                child@EventSourceListItemMsg::EnabledToggled(enabled),
+                self.model.relm,
+                Msg::SetEventSourceEnabled(ep_name, cfg_name.clone(), enabled)
+            );
+            self.model.eventsource_list_items.push(child);
         }
         let children = self.widgets.eventsources_list.children();
         self.widgets
@@ -141,24 +235,36 @@ impl Widget for EventSources {
     }
 
     view! {
-        #[name="eventsources_stack"]
-        gtk::Stack {
-            gtk::ScrolledWindow {
-                child: {
-                    name: Some("events")
-                },
-                #[name="eventsources_list"]
-                #[style_class="item_list"]
-                gtk::ListBox {
-                    selection_mode: gtk::SelectionMode::None,
-                }
+        gtk::Box {
+            orientation: gtk::Orientation::Vertical,
+            #[name="undo_bar"]
+            gtk::InfoBar {
+                revealed: self.model.removed_source.is_some(),
+                message_type: gtk::MessageType::Info,
             },
-            gtk::Label {
+            #[name="eventsources_stack"]
+            gtk::Stack {
                 child: {
-                    name: Some("no-events")
+                    fill: true,
+                    expand: true,
                 },
-                markup: "No event sources have been set up yet.\n\nUse the <b>'New'</b> button on the top-left of this window to add one.",
-                justify: gtk::Justification::Center,
+                gtk::ScrolledWindow {
+                    child: {
+                        name: Some("events")
+                    },
+                    #[name="eventsources_list"]
+                    #[style_class="item_list"]
+                    gtk::ListBox {
+                        selection_mode: gtk::SelectionMode::None,
+                    }
+                },
+                gtk::Label {
+                    child: {
+                        name: Some("no-events")
+                    },
+                    markup: "No event sources have been set up yet.\n\nUse the <b>'New'</b> button on the top-left of this window to add one.",
+                    justify: gtk::Justification::Center,
+                }
             }
         }
     }
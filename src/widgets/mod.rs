@@ -5,5 +5,6 @@ mod events;
 mod eventsource;
 mod eventsources;
 mod preferences;
+mod weekview;
 pub mod win;
 mod wintitlebar;
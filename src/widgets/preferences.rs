@@ -1,13 +1,45 @@
-use crate::config::{Config, PrevNextDaySkipWeekends};
+use crate::config::{Config, LogLevel, PrevNextDaySkipWeekends};
+use chrono::prelude::*;
 use gtk::prelude::*;
 use gtk::traits::SettingsExt;
 use relm::Widget;
 use relm_derive::{widget, Msg};
 
+const WEEK_DAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "Monday"),
+    (Weekday::Tue, "Tuesday"),
+    (Weekday::Wed, "Wednesday"),
+    (Weekday::Thu, "Thursday"),
+    (Weekday::Fri, "Friday"),
+    (Weekday::Sat, "Saturday"),
+    (Weekday::Sun, "Sunday"),
+];
+
+const LOG_LEVELS: [(LogLevel, &str); 4] = [
+    (LogLevel::Error, "Errors"),
+    (LogLevel::Warn, "Warnings"),
+    (LogLevel::Info, "Information"),
+    (LogLevel::Debug, "Debug"),
+];
+
 #[derive(Msg)]
 pub enum Msg {
     DarkThemeToggled(bool),
     PrevNextSkipWeekendsToggled(bool),
+    CacheTtlChanged(f64),
+    GroupRelatedEventsToggled(bool),
+    MergeEventsAcrossSourcesToggled(bool),
+    RestoreLastViewedDateToggled(bool),
+    NotifyNewEventsToggled(bool),
+    NotifyPollIntervalChanged(f64),
+    WeekStartsOnChanged(Option<u32>),
+    CacheEvictionDaysChanged(f64),
+    ClearCache,
+    WarmTodayCacheToggled(bool),
+    MaxConcurrentFetchesChanged(f64),
+    LogLevelChanged(Option<u32>),
+    DateFormatChanged(String),
+    CopyDiagnostics,
     ConfigUpdated(Box<Config>),
     KeyPress(gdk::EventKey),
 }
@@ -16,27 +48,100 @@ pub struct Model {
     relm: relm::Relm<Preferences>,
     prefer_dark_theme: bool,
     prev_next_day_skip_weekends: PrevNextDaySkipWeekends,
+    cache_ttl_mins: f64,
+    group_related_events: bool,
+    merge_events_across_sources: bool,
+    restore_last_viewed_date: bool,
+    notify_new_events: bool,
+    notify_poll_interval_mins: f64,
+    cache_eviction_days: f64,
+    cache_size_desc: String,
+    warm_today_cache_on_startup: bool,
+    max_concurrent_fetches: f64,
+    date_display_format: String,
+    // empty when the date format is valid
+    date_format_error: String,
     config: Config,
     win: gtk::Window,
 }
 
 #[widget]
 impl Widget for Preferences {
-    fn init_view(&mut self) {}
+    fn init_view(&mut self) {
+        for (_, day_name) in &WEEK_DAYS {
+            self.widgets.week_starts_on_combo.append_text(day_name);
+        }
+        self.widgets.week_starts_on_combo.set_active(
+            WEEK_DAYS
+                .iter()
+                .position(|(d, _)| *d == self.model.config.week_starts_on)
+                .map(|p| p as u32),
+        );
+        relm::connect!(
+            self.model.relm,
+            &self.widgets.week_starts_on_combo,
+            connect_changed(c),
+            Msg::WeekStartsOnChanged(c.active())
+        );
+        for (_, level_name) in &LOG_LEVELS {
+            self.widgets.log_level_combo.append_text(level_name);
+        }
+        self.widgets.log_level_combo.set_active(
+            LOG_LEVELS
+                .iter()
+                .position(|(l, _)| *l == self.model.config.log_level)
+                .map(|p| p as u32),
+        );
+        relm::connect!(
+            self.model.relm,
+            &self.widgets.log_level_combo,
+            connect_changed(c),
+            Msg::LogLevelChanged(c.active())
+        );
+    }
 
     fn model(relm: &relm::Relm<Self>, win: gtk::Window) -> Model {
         let config = Config::read_config();
         let prefer_dark_theme = config.prefer_dark_theme;
         let prev_next_day_skip_weekends = config.prev_next_day_skip_weekends;
+        let cache_ttl_mins = (config.cache_ttl_secs / 60) as f64;
+        let group_related_events = config.group_related_events;
+        let merge_events_across_sources = config.merge_events_across_sources;
+        let restore_last_viewed_date = config.restore_last_viewed_date;
+        let notify_new_events = config.notify_new_events;
+        let notify_poll_interval_mins = config.notify_poll_interval_mins as f64;
+        let cache_eviction_days = config.cache_eviction_days as f64;
+        let warm_today_cache_on_startup = config.warm_today_cache_on_startup;
+        let max_concurrent_fetches = config.max_concurrent_fetches as f64;
+        let date_display_format = config.date_display_format.clone();
         Model {
             relm: relm.clone(),
             prefer_dark_theme,
             prev_next_day_skip_weekends,
+            cache_ttl_mins,
+            group_related_events,
+            merge_events_across_sources,
+            restore_last_viewed_date,
+            notify_new_events,
+            notify_poll_interval_mins,
+            cache_eviction_days,
+            cache_size_desc: Self::cache_size_desc(),
+            warm_today_cache_on_startup,
+            max_concurrent_fetches,
+            date_display_format,
+            date_format_error: "".to_string(),
             config,
             win,
         }
     }
 
+    fn cache_size_desc() -> String {
+        match Config::cache_size() {
+            Ok(size) => format!("Cache size: {}", glib::format_size(size)),
+            Err(e) => format!("Cache size: unknown ({})", e),
+        }
+    }
+
     fn update_config(&self) {
         self.model.config.save_config(&self.model.win);
         self.model
@@ -62,6 +167,97 @@ impl Widget for Preferences {
                 };
                 self.update_config();
             }
+            Msg::CacheTtlChanged(mins) => {
+                self.model.config.cache_ttl_secs = (mins as u64) * 60;
+                self.update_config();
+            }
+            Msg::GroupRelatedEventsToggled(t) => {
+                self.model.config.group_related_events = t;
+                self.update_config();
+            }
+            Msg::MergeEventsAcrossSourcesToggled(t) => {
+                self.model.config.merge_events_across_sources = t;
+                self.update_config();
+            }
+            Msg::RestoreLastViewedDateToggled(t) => {
+                self.model.config.restore_last_viewed_date = t;
+                self.update_config();
+            }
+            Msg::NotifyNewEventsToggled(t) => {
+                self.model.notify_new_events = t;
+                self.model.config.notify_new_events = t;
+                self.update_config();
+            }
+            Msg::NotifyPollIntervalChanged(mins) => {
+                self.model.config.notify_poll_interval_mins = mins as u32;
+                self.update_config();
+            }
+            Msg::WeekStartsOnChanged(idx) => {
+                if let Some((day, _)) = idx.and_then(|i| WEEK_DAYS.get(i as usize)) {
+                    self.model.config.week_starts_on = *day;
+                    self.update_config();
+                }
+            }
+            Msg::MaxConcurrentFetchesChanged(count) => {
+                self.model.config.max_concurrent_fetches = count as u32;
+                self.update_config();
+            }
+            Msg::CacheEvictionDaysChanged(days) => {
+                self.model.config.cache_eviction_days = days as u32;
+                self.update_config();
+            }
+            Msg::ClearCache => {
+                if let Err(e) = Config::clear_cache() {
+                    let dialog = gtk::MessageDialog::new(
+                        Some(&self.widgets.prefs_win),
+                        gtk::DialogFlags::all(),
+                        gtk::MessageType::Error,
+                        gtk::ButtonsType::Close,
+                        &format!("Error clearing the cache: {}", e),
+                    );
+                    let _r = dialog.run();
+                    dialog.close();
+                }
+                self.model.cache_size_desc = Self::cache_size_desc();
+            }
+            Msg::WarmTodayCacheToggled(t) => {
+                self.model.config.warm_today_cache_on_startup = t;
+                self.update_config();
+            }
+            Msg::LogLevelChanged(idx) => {
+                if let Some((level, _)) = idx.and_then(|i| LOG_LEVELS.get(i as usize)) {
+                    crate::logging::set_app_level(level.level_filter());
+                    self.model.config.log_level = *level;
+                    self.update_config();
+                }
+            }
+            Msg::DateFormatChanged(format) => {
+                let style = self.widgets.date_format_entry.style_context();
+                match Config::validate_date_format(&format) {
+                    Ok(()) => {
+                        style.remove_class("error");
+                        self.model.date_format_error = "".to_string();
+                        if format != self.model.config.date_display_format {
+                            self.model.config.date_display_format = format;
+                            self.update_config();
+                        }
+                    }
+                    Err(e) => {
+                        // keep the last valid format in the config
+                        style.add_class("error");
+                        self.model.date_format_error = e;
+                    }
+                }
+            }
+            Msg::CopyDiagnostics => {
+                if let Some(clip) = gtk::Clipboard::default(&self.widgets.prefs_win.display()) {
+                    clip.set_text(&format!(
+                        "Cigale {}\n\n{}",
+                        env!("CARGO_PKG_VERSION"),
+                        crate::logging::recent_logs()
+                    ));
+                }
+            }
             Msg::ConfigUpdated(_) => {
                 // meant for my parent, not for me
             }
@@ -101,6 +297,131 @@ impl Widget for Preferences {
                     active: self.model.prev_next_day_skip_weekends == PrevNextDaySkipWeekends::Skip,
                     toggled(t) => Msg::PrevNextSkipWeekendsToggled(t.is_active())
                 },
+                gtk::CheckButton {
+                    label: "On startup, display the day which was displayed last",
+                    active: self.model.restore_last_viewed_date,
+                    toggled(t) => Msg::RestoreLastViewedDateToggled(t.is_active())
+                },
+                gtk::CheckButton {
+                    label: "Group related events happening close to each other",
+                    active: self.model.group_related_events,
+                    toggled(t) => Msg::GroupRelatedEventsToggled(t.is_active())
+                },
+                gtk::CheckButton {
+                    label: "Merge the same event reported by several event sources",
+                    active: self.model.merge_events_across_sources,
+                    toggled(t) => Msg::MergeEventsAcrossSourcesToggled(t.is_active())
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: "Refetch today's events if the cache is older than (minutes)",
+                    },
+                    gtk::SpinButton {
+                        adjustment: &gtk::Adjustment::new(
+                            self.model.cache_ttl_mins, 0.0, 1440.0, 1.0, 10.0, 0.0),
+                        value_changed(s) => Msg::CacheTtlChanged(s.value())
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: "Date format",
+                    },
+                    #[name="date_format_entry"]
+                    gtk::Entry {
+                        text: &self.model.date_display_format,
+                        tooltip_text: Some("For instance %Y-%m-%d (%a) or %A, %B %-d"),
+                        changed(e) => Msg::DateFormatChanged(e.text().to_string()),
+                    },
+                    gtk::Label {
+                        text: &self.model.date_format_error,
+                        visible: !self.model.date_format_error.is_empty(),
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: "Weeks start on",
+                    },
+                    #[name="week_starts_on_combo"]
+                    gtk::ComboBoxText {},
+                },
+                gtk::CheckButton {
+                    label: "Notify me of new events for today",
+                    active: self.model.notify_new_events,
+                    toggled(t) => Msg::NotifyNewEventsToggled(t.is_active())
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    sensitive: self.model.notify_new_events,
+                    gtk::Label {
+                        label: "Check for new events every (minutes)",
+                    },
+                    gtk::SpinButton {
+                        adjustment: &gtk::Adjustment::new(
+                            self.model.notify_poll_interval_mins, 1.0, 1440.0, 1.0, 10.0, 0.0),
+                        value_changed(s) => Msg::NotifyPollIntervalChanged(s.value())
+                    },
+                },
+                gtk::CheckButton {
+                    label: "On startup, fetch today's events in the background",
+                    active: self.model.warm_today_cache_on_startup,
+                    toggled(t) => Msg::WarmTodayCacheToggled(t.is_active())
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: "Event sources fetched at the same time (1 to fetch them one by one)",
+                    },
+                    gtk::SpinButton {
+                        adjustment: &gtk::Adjustment::new(
+                            self.model.max_concurrent_fetches, 1.0, 32.0, 1.0, 4.0, 0.0),
+                        value_changed(s) => Msg::MaxConcurrentFetchesChanged(s.value())
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: "On startup, remove cache files older than (days, 0 to keep them)",
+                    },
+                    gtk::SpinButton {
+                        adjustment: &gtk::Adjustment::new(
+                            self.model.cache_eviction_days, 0.0, 3650.0, 1.0, 30.0, 0.0),
+                        value_changed(s) => Msg::CacheEvictionDaysChanged(s.value())
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: &self.model.cache_size_desc,
+                    },
+                    gtk::Button {
+                        label: "Clear cache",
+                        clicked => Msg::ClearCache
+                    },
+                },
+                gtk::Box {
+                    orientation: gtk::Orientation::Horizontal,
+                    spacing: 6,
+                    gtk::Label {
+                        label: "Log level",
+                    },
+                    #[name="log_level_combo"]
+                    gtk::ComboBoxText {},
+                    gtk::Button {
+                        label: "Copy diagnostics",
+                        tooltip_text: Some("Copy the recent logs to the clipboard, for a bug report"),
+                        clicked => Msg::CopyDiagnostics
+                    },
+                },
             },
             key_press_event(_, key) => (Msg::KeyPress(key.clone()), Inhibit(false)), // just for the ESC key.. surely there's a better way..
         }
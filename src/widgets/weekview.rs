@@ -0,0 +1,196 @@
+use crate::config::Config;
+use crate::events::events::{Event, FetchOptions, FetchedEvents};
+use chrono::prelude::*;
+use gtk::prelude::*;
+use itertools::Itertools;
+use relm::{Channel, Widget};
+use relm_derive::{widget, Msg};
+
+#[derive(Msg)]
+pub enum Msg {
+    LoadWeek(Date<Local>),
+    GotWeek(Date<Local>, Vec<(Date<Local>, FetchedEvents)>),
+    ConfigUpdate(Box<Config>), // box to prevent large size difference between variants
+    DayClicked(Date<Local>),
+}
+
+pub struct Model {
+    relm: relm::Relm<WeekView>,
+    config: Config,
+    // first day of the week we display or are loading
+    week_start: Option<Date<Local>>,
+    loading: bool,
+}
+
+#[widget]
+impl Widget for WeekView {
+    fn init_view(&mut self) {}
+
+    fn model(relm: &relm::Relm<Self>, config: Config) -> Model {
+        Model {
+            relm: relm.clone(),
+            config,
+            week_start: None,
+            loading: false,
+        }
+    }
+
+    fn fetch_week(&self, week_start: Date<Local>) {
+        let stream = self.model.relm.stream().clone();
+        let (_channel, sender) = Channel::new(move |days| {
+            stream.emit(Msg::GotWeek(week_start, days));
+        });
+        let c = self.model.config.clone();
+        let days = (0..7)
+            .map(|i| week_start + chrono::Duration::days(i))
+            .collect();
+        std::thread::spawn(move || {
+            sender
+                .send(crate::events::events::get_all_events_for_days(
+                    c,
+                    &FetchOptions::default(),
+                    days,
+                ))
+                .unwrap_or_else(|err| println!("Thread communication error: {}", err));
+        });
+    }
+
+    // per provider, the number of events and the total duration
+    fn summarize_day(events: &[Event]) -> Vec<(&'static str, usize, chrono::Duration)> {
+        events
+            .iter()
+            .map(|e| e.event_type_desc)
+            .unique()
+            .map(|provider| {
+                let provider_events = events.iter().filter(|e| e.event_type_desc == provider);
+                (
+                    provider,
+                    provider_events.clone().count(),
+                    provider_events
+                        .filter_map(|e| e.event_duration)
+                        .fold(chrono::Duration::zero(), |a, b| a + b),
+                )
+            })
+            .collect()
+    }
+
+    fn display_week(&self, days: &[(Date<Local>, FetchedEvents)]) {
+        for child in self.widgets.days_box.children() {
+            self.widgets.days_box.remove(&child);
+        }
+        for (day, fetched) in days {
+            let vbox = gtk::BoxBuilder::new()
+                .orientation(gtk::Orientation::Vertical)
+                .spacing(3)
+                .build();
+            vbox.add(
+                &gtk::LabelBuilder::new()
+                    .label(&format!(
+                        "<b>{}</b>",
+                        glib::markup_escape_text(
+                            &day.format(&self.model.config.date_display_format)
+                                .to_string()
+                        )
+                    ))
+                    .use_markup(true)
+                    .build(),
+            );
+            for (provider, count, duration) in Self::summarize_day(&fetched.events) {
+                let label = if duration > chrono::Duration::zero() {
+                    format!(
+                        "{}: {} ({}h {}m)",
+                        provider,
+                        count,
+                        duration.num_hours(),
+                        duration.num_minutes() % 60
+                    )
+                } else {
+                    format!("{}: {}", provider, count)
+                };
+                vbox.add(&gtk::LabelBuilder::new().label(&label).xalign(0.0).build());
+            }
+            if !fetched.errors.is_empty() {
+                vbox.add(
+                    &gtk::LabelBuilder::new()
+                        .label(&format!("{} error(s)", fetched.errors.len()))
+                        .xalign(0.0)
+                        .tooltip_text(
+                            &fetched
+                                .errors
+                                .iter()
+                                .map(|e| e.to_string_with_hint())
+                                .collect::<Vec<_>>()
+                                .join("\n"),
+                        )
+                        .build(),
+                );
+            }
+            let btn = gtk::ButtonBuilder::new()
+                .relief(gtk::ReliefStyle::None)
+                .child(&vbox)
+                .valign(gtk::Align::Start)
+                .build();
+            let d = *day;
+            relm::connect!(
+                self.model.relm,
+                &btn,
+                connect_clicked(_),
+                Msg::DayClicked(d)
+            );
+            self.widgets.days_box.add(&btn);
+        }
+        self.widgets.days_box.show_all();
+    }
+
+    fn update(&mut self, event: Msg) {
+        match event {
+            Msg::LoadWeek(day) => {
+                let week_start = self.model.config.week_start(day);
+                if self.model.week_start != Some(week_start) {
+                    self.model.week_start = Some(week_start);
+                    self.model.loading = true;
+                    self.fetch_week(week_start);
+                }
+            }
+            Msg::GotWeek(week_start, days) => {
+                // ignore the results if the user moved to another week meanwhile
+                if self.model.week_start == Some(week_start) {
+                    self.model.loading = false;
+                    self.display_week(&days);
+                }
+            }
+            Msg::ConfigUpdate(config) => {
+                self.model.config = *config;
+                // force a refetch on the next LoadWeek
+                self.model.week_start = None;
+            }
+            Msg::DayClicked(_) => {
+                // meant for my parent, not for me
+            }
+        }
+    }
+
+    view! {
+        gtk::Box {
+            orientation: gtk::Orientation::Horizontal,
+            margin_start: 10,
+            margin_end: 10,
+            margin_bottom: 6,
+            spacing: 6,
+            gtk::Spinner {
+                active: self.model.loading,
+                visible: self.model.loading,
+            },
+            #[name="days_box"]
+            gtk::Box {
+                orientation: gtk::Orientation::Horizontal,
+                homogeneous: true,
+                spacing: 6,
+                child: {
+                    expand: true,
+                    fill: true,
+                },
+            },
+        }
+    }
+}
@@ -1,11 +1,12 @@
-use super::addeventsourcedlg::EventSourceEditModel;
-use super::addeventsourcedlg::Msg as AddEventSourceDialogMsg;
 use super::events::EventView;
+use super::events::Msg::FetchDone as EventsFetchDoneMsg;
+use super::events::Msg::GroupByHourChanged as EventsGroupByHourChangedMsg;
+use super::events::Msg::RowsLayoutChanged as EventsRowsLayoutChangedMsg;
 use super::eventsources::EventSources;
 use super::eventsources::Msg as EventSourcesMsg;
 use super::wintitlebar::Msg as WinTitleBarMsg;
 use super::wintitlebar::WinTitleBar;
-use crate::config::Config;
+use crate::config::{Config, EventRowsLayout};
 use crate::events::events::EventProvider;
 use glib::signal::Inhibit;
 use gtk::prelude::*;
@@ -21,10 +22,17 @@ pub enum Msg {
     Quit,
     AddConfig(&'static str, String, HashMap<&'static str, String>),
     EditConfig(String, &'static str, String, HashMap<&'static str, String>),
-    EditEventSource(&'static str, String),
+    EditEventSource(String),
     RemoveEventSource(&'static str, String),
+    MoveEventSource(String, bool),
+    SetEventSourceEnabled(&'static str, String, bool),
+    UndoRemoveEventSource,
     KeyPress(gdk::EventKey),
     ConfigUpdated(Box<Config>),
+    ForceRefresh,
+    RefreshDone,
+    RowsLayoutChanged(EventRowsLayout),
+    GroupByHourChanged(bool),
 }
 
 pub struct Model {
@@ -32,6 +40,16 @@ pub struct Model {
     config: Config,
     titlebar: Component<WinTitleBar>,
     accel_group: gtk::AccelGroup,
+    // the last removed event source, kept so the user can undo the removal
+    removed_source: Option<RemovedSource>,
+}
+
+struct RemovedSource {
+    provider_name: &'static str,
+    config_name: String,
+    config_values: HashMap<&'static str, String>,
+    order_position: Option<usize>,
+    disabled: bool,
 }
 
 #[widget]
@@ -48,13 +66,23 @@ impl Widget for Win {
         ));
         relm::connect!(titlebar@WinTitleBarMsg::AddConfig(providername, ref name, ref cfg),
                                self.model.relm, Msg::AddConfig(providername, name.clone(), cfg.clone()));
+        relm::connect!(titlebar@WinTitleBarMsg::EditConfig(ref configname, providername, ref name, ref cfg),
+                               self.model.relm, Msg::EditConfig(configname.clone(), providername, name.clone(), cfg.clone()));
         relm::connect!(titlebar@WinTitleBarMsg::ConfigUpdated(ref cfg),
                        self.model.relm, Msg::ConfigUpdated(cfg.clone()));
+        relm::connect!(titlebar@WinTitleBarMsg::ForceRefresh,
+                       self.model.relm, Msg::ForceRefresh);
         let event_sources = &self.components.event_sources;
         relm::connect!(event_sources@EventSourcesMsg::RemoveEventSource(providername, ref name),
                                self.model.relm, Msg::RemoveEventSource(providername, name.clone()));
-        relm::connect!(event_sources@EventSourcesMsg::EditEventSource(providername, ref name),
-                               self.model.relm, Msg::EditEventSource(providername, name.clone()));
+        relm::connect!(event_sources@EventSourcesMsg::EditEventSource(_, ref name),
+                               self.model.relm, Msg::EditEventSource(name.clone()));
+        relm::connect!(event_sources@EventSourcesMsg::MoveEventSource(ref name, up),
+                               self.model.relm, Msg::MoveEventSource(name.clone(), up));
+        relm::connect!(event_sources@EventSourcesMsg::SetEventSourceEnabled(providername, ref name, enabled),
+                               self.model.relm, Msg::SetEventSourceEnabled(providername, name.clone(), enabled));
+        relm::connect!(event_sources@EventSourcesMsg::UndoRemove,
+                               self.model.relm, Msg::UndoRemoveEventSource);
         self.update_event_sources_need_attention();
     }
 
@@ -63,6 +91,12 @@ impl Widget for Win {
             .unwrap()
             .add_resource_path("/icons");
         let config = Config::read_config();
+        crate::logging::set_app_level(config.log_level.level_filter());
+        match config.evict_old_cache_files() {
+            Ok(0) => {}
+            Ok(freed) => log::info!("Evicted {} bytes of old cache files", freed),
+            Err(e) => log::error!("Error evicting old cache files: {}", e),
+        }
         gtk::Settings::default()
             .unwrap()
             .set_gtk_application_prefer_dark_theme(config.prefer_dark_theme);
@@ -74,6 +108,7 @@ impl Widget for Win {
             config,
             titlebar,
             accel_group,
+            removed_source: None,
         }
     }
 
@@ -157,8 +192,10 @@ impl Widget for Win {
             }
             Msg::EditConfig(configname, providername, name, contents) => {
                 let ep = Win::get_event_provider_by_name(providers, providername);
-                ep.remove_config(&mut self.model.config, configname);
-                ep.add_config_values(&mut self.model.config, name, contents);
+                self.model
+                    .config
+                    .rename_event_source(providername, &configname, &name);
+                ep.update_config_values(&mut self.model.config, &configname, name, contents);
                 self.save_event_providers();
             }
             Msg::RemoveEventSource(ep_name, config_name) => {
@@ -180,32 +217,74 @@ impl Widget for Win {
                 dialog.close();
                 if r == gtk::ResponseType::Yes {
                     let ep = Win::get_event_provider_by_name(providers, ep_name);
-                    ep.remove_config(&mut self.model.config, config_name);
+                    // the values include the secrets from the keyring, which
+                    // remove_config deletes, so that undo can store them again.
+                    self.model.removed_source = Some(RemovedSource {
+                        provider_name: ep_name,
+                        config_name: config_name.clone(),
+                        config_values: ep.get_config_values(&self.model.config, &config_name),
+                        order_position: self
+                            .model
+                            .config
+                            .sources_order
+                            .iter()
+                            .position(|n| *n == config_name),
+                        disabled: !self.model.config.is_source_enabled(ep_name, &config_name),
+                    });
+                    self.model.config.forget_event_source(ep_name, &config_name);
+                    ep.remove_config(&mut self.model.config, config_name.clone());
                     self.save_event_providers();
+                    self.components
+                        .event_sources
+                        .emit(EventSourcesMsg::SourceRemoved(config_name));
                 }
             }
-            Msg::EditEventSource(ep_name, config_name) => {
-                let mut config_source_names = Win::config_source_names(&self.model.config);
-                config_source_names.remove(&config_name); // allow to use the current config name in the edit dialog
-                let ep = Win::get_event_provider_by_name(providers, ep_name);
-                let event_source_values = ep.get_config_values(&self.model.config, &config_name);
-                let (dialog, dialog_contents) = WinTitleBar::prepare_addedit_eventsource_dlg(
-                    &self.widgets.window,
-                    &config_source_names,
-                    Some(EventSourceEditModel {
-                        event_provider_name: ep_name,
-                        event_source_name: config_name,
-                        event_source_values,
-                    }),
-                );
-                relm::connect!(dialog_contents@AddEventSourceDialogMsg::EditConfig(ref configname, providername, ref name, ref cfg),
-                               self.model.relm, Msg::EditConfig(configname.clone(), providername, name.clone(), cfg.clone()));
-                let resp = dialog.run();
-                match resp {
-                    gtk::ResponseType::Cancel | gtk::ResponseType::DeleteEvent => dialog.close(),
-                    _ => {}
+            Msg::UndoRemoveEventSource => {
+                if let Some(removed) = self.model.removed_source.take() {
+                    if Self::config_source_names(&self.model.config).contains(&removed.config_name)
+                    {
+                        // the user created a new source with the same name meanwhile
+                        return;
+                    }
+                    if let Some(pos) = removed.order_position {
+                        let pos = pos.min(self.model.config.sources_order.len());
+                        self.model
+                            .config
+                            .sources_order
+                            .insert(pos, removed.config_name.clone());
+                    }
+                    if removed.disabled {
+                        self.model.config.set_source_enabled(
+                            removed.provider_name,
+                            &removed.config_name,
+                            false,
+                        );
+                    }
+                    let ep = Win::get_event_provider_by_name(providers, removed.provider_name);
+                    ep.add_config_values(
+                        &mut self.model.config,
+                        removed.config_name,
+                        removed.config_values,
+                    );
+                    self.save_event_providers();
                 }
             }
+            Msg::MoveEventSource(config_name, up) => {
+                crate::events::events::move_event_source(&mut self.model.config, &config_name, up);
+                self.save_event_providers();
+            }
+            Msg::SetEventSourceEnabled(provider_name, config_name, enabled) => {
+                self.model
+                    .config
+                    .set_source_enabled(provider_name, &config_name, enabled);
+                self.save_event_providers();
+            }
+            Msg::EditEventSource(config_name) => {
+                self.model
+                    .titlebar
+                    .stream()
+                    .emit(WinTitleBarMsg::EditEventSource(config_name));
+            }
             Msg::KeyPress(key) => {
                 if key.state().contains(gdk::ModifierType::CONTROL_MASK)
                     && key.state().contains(gdk::ModifierType::MOD1_MASK)
@@ -220,6 +299,27 @@ impl Widget for Win {
                 self.model.config = *cfg;
                 self.propagate_config_change();
             }
+            Msg::ForceRefresh => {
+                self.components
+                    .events
+                    .emit(super::events::Msg::ForceRefresh);
+            }
+            Msg::RefreshDone => {
+                self.model
+                    .titlebar
+                    .stream()
+                    .emit(WinTitleBarMsg::RefreshDone);
+            }
+            Msg::RowsLayoutChanged(layout) => {
+                // the events view already applied it, no need to propagate
+                self.model.config.event_rows_layout = layout;
+                self.model.config.save_config(&self.widgets.window);
+            }
+            Msg::GroupByHourChanged(group) => {
+                // the events view already applied it, no need to propagate
+                self.model.config.group_events_by_hour = group;
+                self.model.config.save_config(&self.widgets.window);
+            }
         }
     }
 
@@ -237,6 +337,9 @@ impl Widget for Win {
                         name: Some("events"),
                         icon_name: Some("view-list-symbolic")
                     },
+                    EventsFetchDoneMsg => Msg::RefreshDone,
+                    EventsRowsLayoutChangedMsg(layout) => Msg::RowsLayoutChanged(layout),
+                    EventsGroupByHourChangedMsg(group) => Msg::GroupByHourChanged(group),
                 },
                 #[name="event_sources"]
                 EventSources(self.model.config.clone()) {
@@ -18,12 +18,16 @@ pub enum Msg {
     ScreenChanged,
     MainWindowStackReady(gtk::Stack),
     NewEventSourceClick,
+    EditEventSource(String),
     AddConfig(&'static str, String, HashMap<&'static str, String>),
+    EditConfig(String, &'static str, String, HashMap<&'static str, String>),
     EventSourceNamesChanged(HashSet<String>),
     DisplayAbout,
     DisplayShortcuts,
     DisplayPreferences,
     ConfigUpdated(Box<Config>),
+    ForceRefresh,
+    RefreshDone,
 }
 
 pub struct Model {
@@ -33,6 +37,7 @@ pub struct Model {
     existing_source_names: HashSet<String>,
     menu_popover: gtk::Popover,
     prefs_win: Option<Component<Preferences>>,
+    refreshing: bool,
 }
 
 pub fn left_align_menu(menu: &gtk::ModelButton) {
@@ -96,6 +101,7 @@ impl Widget for WinTitleBar {
             existing_source_names,
             menu_popover: gtk::Popover::new(None::<&gtk::MenuButton>),
             prefs_win: None,
+            refreshing: false,
         }
     }
 
@@ -154,15 +160,32 @@ impl Widget for WinTitleBar {
             .unwrap()
     }
 
-    fn run_event_source_addedit_dlg(&self) {
+    fn get_edit_model(config_name: String) -> Option<EventSourceEditModel> {
+        let config = Config::read_config();
+        let ep = crate::events::events::get_event_providers()
+            .into_iter()
+            .find(|ep| ep.get_config_names(&config).contains(&&config_name))?;
+        let event_source_values = ep.get_config_values(&config, &config_name);
+        Some(EventSourceEditModel {
+            event_provider_name: ep.name(),
+            event_source_name: config_name,
+            event_source_values,
+        })
+    }
+
+    fn run_event_source_addedit_dlg(&self, edit_model: Option<EventSourceEditModel>) {
         let main_win = self.get_main_window();
-        let (dialog, dialog_contents) = Self::prepare_addedit_eventsource_dlg(
-            &main_win,
-            &self.model.existing_source_names,
-            None,
-        );
+        let mut existing_source_names = self.model.existing_source_names.clone();
+        if let Some(m) = &edit_model {
+            // allow to use the current config name in the edit dialog
+            existing_source_names.remove(&m.event_source_name);
+        }
+        let (dialog, dialog_contents) =
+            Self::prepare_addedit_eventsource_dlg(&main_win, &existing_source_names, edit_model);
         relm::connect!(dialog_contents@AddEventSourceDialogMsg::AddConfig(providername, ref name, ref cfg),
                                self.model.relm, Msg::AddConfig(providername, name.clone(), cfg.clone()));
+        relm::connect!(dialog_contents@AddEventSourceDialogMsg::EditConfig(ref configname, providername, ref name, ref cfg),
+                               self.model.relm, Msg::EditConfig(configname.clone(), providername, name.clone(), cfg.clone()));
         let resp = dialog.run();
         match resp {
             gtk::ResponseType::Cancel | gtk::ResponseType::DeleteEvent => dialog.close(),
@@ -170,18 +193,33 @@ impl Widget for WinTitleBar {
         }
     }
 
-    fn display_about() {
+    fn display_about(&self) {
         let dlg = gtk::AboutDialogBuilder::new()
             .name("Cigale")
             .version(env!("CARGO_PKG_VERSION"))
             .logo_icon_name(Icon::APP_ICON.name())
             .website("https://github.com/emmanueltouzery/cigale/")
             .comments("Review your past activity")
+            .system_information(&Self::providers_description(&Config::read_config()))
+            .transient_for(&self.get_main_window())
             .build();
         dlg.run();
         dlg.close();
     }
 
+    // useful in bug reports
+    fn providers_description(config: &Config) -> String {
+        let providers: Vec<String> = crate::events::events::event_providers_usage(config)
+            .into_iter()
+            .map(|(name, count)| match count {
+                0 => format!("{}: not configured", name),
+                1 => format!("{}: 1 event source", name),
+                _ => format!("{}: {} event sources", name, count),
+            })
+            .collect();
+        format!("Event providers:\n{}", providers.join("\n"))
+    }
+
     fn display_shortcuts(&self) {
         let win = gtk::Builder::from_string(SHORTCUTS_UI)
             .object::<gtk::Window>("shortcuts")
@@ -239,22 +277,40 @@ impl Widget for WinTitleBar {
                 self.widgets
                     .new_event_source_btn
                     .set_visible(self.model.displaying_event_sources);
+                self.widgets
+                    .refresh_btn
+                    .set_visible(!self.model.displaying_event_sources);
             }
             Msg::NewEventSourceClick => {
-                self.run_event_source_addedit_dlg();
+                self.run_event_source_addedit_dlg(None);
             }
+            Msg::EditEventSource(config_name) => match Self::get_edit_model(config_name) {
+                Some(edit_model) => self.run_event_source_addedit_dlg(Some(edit_model)),
+                None => log::error!("Can't find the event source to edit"),
+            },
             Msg::EventSourceNamesChanged(src) => {
                 self.model.existing_source_names = src;
             }
             Msg::AddConfig(_, _, _) => {
                 // this is meant for win... we emit here, not interested by it ourselves
             }
-            Msg::DisplayAbout => Self::display_about(),
+            Msg::EditConfig(_, _, _, _) => {
+                // this is meant for win... we emit here, not interested by it ourselves
+            }
+            Msg::DisplayAbout => self.display_about(),
             Msg::DisplayShortcuts => self.display_shortcuts(),
             Msg::DisplayPreferences => self.display_preferences(),
             Msg::ConfigUpdated(_) => {
                 // this is meant for win... we emit here, not interested by it ourselves
             }
+            Msg::ForceRefresh => {
+                // win will forward the refresh to the event view,
+                // we only display the spinner
+                self.model.refreshing = true;
+            }
+            Msg::RefreshDone => {
+                self.model.refreshing = false;
+            }
         }
     }
 
@@ -268,6 +324,16 @@ impl Widget for WinTitleBar {
                 visible:false,
                 clicked() => Msg::NewEventSourceClick,
             },
+            #[name="refresh_btn"]
+            gtk::Button {
+                image: Some(&gtk::Image::from_icon_name(Some("view-refresh-symbolic"), gtk::IconSize::Menu)),
+                tooltip_text: Some("Refresh (bypass the cache)"),
+                sensitive: !self.model.refreshing,
+                clicked() => Msg::ForceRefresh,
+            },
+            gtk::Spinner {
+                active: self.model.refreshing,
+            },
             show_close_button: true,
             title: Some("Cigale"),
             #[name="menu_button"]